@@ -316,6 +316,15 @@ mod tests {
     }
 
     impl Store for NullStore {
+        fn add_identity(&self, _alias: &commitmux_types::IdentityAlias) -> Result<()> {
+            unimplemented!()
+        }
+        fn list_identities(&self) -> Result<Vec<commitmux_types::IdentityAlias>> {
+            unimplemented!()
+        }
+        fn remove_identity(&self, _alias_email: &str) -> Result<()> {
+            unimplemented!()
+        }
         fn get_config(&self, key: &str) -> Result<Option<String>> {
             Ok(self.config.lock().unwrap().get(key).cloned())
         }
@@ -386,6 +395,9 @@ mod tests {
         fn upsert_patch(&self, _patch: &commitmux_types::CommitPatch) -> Result<()> {
             unimplemented!()
         }
+        fn write_batch(&self, _batch: &commitmux_types::WriteBatch) -> Result<()> {
+            unimplemented!()
+        }
         fn get_ingest_state(&self, _repo_id: i64) -> Result<Option<commitmux_types::IngestState>> {
             unimplemented!()
         }
@@ -409,6 +421,47 @@ mod tests {
         ) -> Result<Vec<commitmux_types::TouchResult>> {
             unimplemented!()
         }
+        fn find_by_ref(
+            &self,
+            _reference: &str,
+            _opts: &commitmux_types::RefOpts,
+        ) -> Result<Vec<commitmux_types::RefResult>> {
+            unimplemented!()
+        }
+        fn conventional_summary(
+            &self,
+            _opts: &commitmux_types::ConventionalSummaryOpts,
+        ) -> Result<commitmux_types::ConventionalSummary> {
+            unimplemented!()
+        }
+        fn list_tags(&self, _repo_id: i64) -> Result<Vec<commitmux_types::Tag>> {
+            unimplemented!()
+        }
+        fn replace_tags(&self, _repo_id: i64, _index: &commitmux_types::TagIndex) -> Result<()> {
+            unimplemented!()
+        }
+        fn reextract_refs(
+            &self,
+            _repo_id: i64,
+            _extract: &dyn Fn(&str) -> Vec<commitmux_types::CommitRef>,
+        ) -> Result<usize> {
+            unimplemented!()
+        }
+        fn changelog_commits(
+            &self,
+            _repo_id: i64,
+            _shas: &[String],
+        ) -> Result<Vec<commitmux_types::ChangelogCommit>> {
+            unimplemented!()
+        }
+        fn symbol_history(
+            &self,
+            _repo_id: i64,
+            _symbol: &str,
+            _opts: &commitmux_types::SymbolOpts,
+        ) -> Result<Vec<commitmux_types::SymbolChange>> {
+            unimplemented!()
+        }
         fn get_commit(
             &self,
             _repo_name: &str,
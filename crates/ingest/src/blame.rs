@@ -0,0 +1,101 @@
+use commitmux_types::{BlameHunk, BlameOpts, CommitmuxError, FileBlame, Repo, Result};
+use std::path::Path;
+
+use crate::walker::resolve_rev;
+
+/// Runs git blame for `opts.path` at `opts.rev` (default HEAD) over the
+/// requested line range, attaching the text of each blamed line.
+pub fn blame_file(git_repo: &git2::Repository, repo: &Repo, opts: &BlameOpts) -> Result<FileBlame> {
+    let rev = opts.rev.as_deref().unwrap_or("HEAD");
+    let rev_oid = resolve_rev(git_repo, repo, rev)?;
+    let commit = git_repo
+        .find_commit(rev_oid)
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    let tree = commit
+        .tree()
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    let path = Path::new(&opts.path);
+    let blob = tree
+        .get_path(path)
+        .and_then(|entry| entry.to_object(git_repo))
+        .map_err(|_| {
+            CommitmuxError::NotFound(format!(
+                "path '{}' at '{}' in repo '{}'",
+                opts.path, rev, repo.name
+            ))
+        })?
+        .into_blob()
+        .map_err(|_| CommitmuxError::Config(format!("'{}' is not a file", opts.path)))?;
+    if blob.is_binary() {
+        return Err(CommitmuxError::Config(format!(
+            "'{}' is a binary file",
+            opts.path
+        )));
+    }
+
+    let text = String::from_utf8_lossy(blob.content());
+    let lines: Vec<&str> = text.lines().collect();
+    let start = opts.start_line.unwrap_or(1);
+    let end = opts.end_line.unwrap_or(lines.len()).min(lines.len());
+    if lines.is_empty() && opts.start_line.is_none() {
+        return Ok(FileBlame {
+            rev_sha: rev_oid.to_string(),
+            ..Default::default()
+        });
+    }
+    if start == 0 || start > end {
+        return Err(CommitmuxError::Config(format!(
+            "invalid line range {}-{} for '{}' ({} lines)",
+            start,
+            opts.end_line.unwrap_or(lines.len()),
+            opts.path,
+            lines.len()
+        )));
+    }
+
+    let mut blame_opts = git2::BlameOptions::new();
+    blame_opts
+        .newest_commit(rev_oid)
+        .min_line(start)
+        .max_line(end)
+        .use_mailmap(true);
+    let blame = git_repo
+        .blame_file(path, Some(&mut blame_opts))
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+
+    let mut hunks = Vec::new();
+    for hunk in blame.iter() {
+        let hunk_start = hunk.final_start_line();
+        let hunk_end = hunk_start + hunk.lines_in_hunk() - 1;
+        // Hunks can extend past the requested range; keep only its lines.
+        let (first, last) = (hunk_start.max(start), hunk_end.min(end));
+        if first > last {
+            continue;
+        }
+        let signature = hunk.final_signature();
+        let orig_path = hunk
+            .path()
+            .filter(|p| *p != path)
+            .map(|p| p.to_string_lossy().into_owned());
+        hunks.push(BlameHunk {
+            sha: hunk.final_commit_id().to_string(),
+            start_line: first,
+            end_line: last,
+            orig_path,
+            orig_start_line: hunk.orig_start_line() + (first - hunk_start),
+            author: signature.name().unwrap_or("").to_string(),
+            date: signature.when().seconds(),
+            lines: lines[first - 1..last]
+                .iter()
+                .map(|l| l.to_string())
+                .collect(),
+        });
+    }
+
+    Ok(FileBlame {
+        rev_sha: rev_oid.to_string(),
+        start_line: start,
+        end_line: end,
+        hunks,
+    })
+}
@@ -0,0 +1,375 @@
+use commitmux_types::{CommitmuxError, RemoteAuth, Result};
+use std::collections::{BinaryHeap, HashSet};
+use std::path::Path;
+
+use crate::credentials;
+
+/// Depth of the first fetch for a `since` window without a depth; doubled
+/// until the history reaches back far enough.
+const SINCE_START_DEPTH: u32 = 64;
+/// Past this depth a `since` window fetches the full history instead.
+const MAX_DEEPEN_DEPTH: u32 = 1 << 20;
+/// libgit2's `GIT_FETCH_DEPTH_UNSHALLOW`.
+const UNSHALLOW: i32 = i32::MAX;
+/// Copy of a shallow clone's boundary kept in the git directory while a fetch
+/// runs, so that a fetch interrupted before the boundary was restored is
+/// repaired by the next one.
+const BOUNDARY_JOURNAL: &str = "commitmux-shallow";
+/// Commits a shallow walk keeps visiting once only hidden ones are queued, in
+/// case commit timestamps are out of order (git's revision walk does the same).
+const WALK_SLOP: usize = 5;
+
+fn fetch_options<'a>(
+    depth: Option<i32>,
+    auth: &RemoteAuth,
+    config: Option<git2::Config>,
+) -> git2::FetchOptions<'a> {
+    let mut fo = git2::FetchOptions::new();
+    fo.remote_callbacks(credentials::remote_callbacks(auth, config));
+    if let Some(depth) = depth {
+        fo.depth(depth);
+    }
+    fo
+}
+
+/// Clones `url` into `dest` for `add-repo --url`. With `depth` only that many
+/// commits of each branch are fetched, plus the boundary commit they are
+/// diffed against; with `since` the clone is deepened until every branch
+/// reaches back past that time.
+pub fn clone_repo(
+    url: &str,
+    dest: &Path,
+    depth: Option<u32>,
+    since: Option<i64>,
+    auth: &RemoteAuth,
+) -> Result<git2::Repository> {
+    let start = boundary_depth(depth).or(since.map(|_| SINCE_START_DEPTH));
+    if start.is_some() && !supports_shallow(url) {
+        return Err(CommitmuxError::Ingest(format!(
+            "cannot make a shallow clone of '{}': shallow fetches need an http(s) URL",
+            url
+        )));
+    }
+    let mut builder = git2::build::RepoBuilder::new();
+    builder.fetch_options(fetch_options(
+        start.map(|d| d as i32),
+        auth,
+        git2::Config::open_default().ok(),
+    ));
+    let git_repo = builder
+        .clone(url, dest)
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    if let (Some(start), Some(since)) = (start, since) {
+        deepen_since(&git_repo, "origin", start, since, auth)
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    }
+    Ok(git_repo)
+}
+
+/// Whether managed clones of `url` can be shallow. libgit2 only supports
+/// shallow fetches over smart HTTP: its local transport refuses them and it
+/// garbles the negotiation over ssh and git://.
+pub fn supports_shallow(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// Fetches `remote_name` before a sync, keeping a shallow clone within its
+/// depth and history window. A shallow clone whose window was removed is
+/// unshallowed.
+pub(crate) fn fetch_remote(
+    git_repo: &git2::Repository,
+    remote_name: &str,
+    depth: Option<u32>,
+    since: Option<i64>,
+    auth: &RemoteAuth,
+) -> std::result::Result<(), git2::Error> {
+    let fetch_depth = match (depth, since) {
+        (Some(_), _) => boundary_depth(depth).map(|d| d as i32),
+        (None, None) if git_repo.is_shallow() => Some(UNSHALLOW),
+        // New commits are fetched on top of the existing shallow boundary.
+        _ => None,
+    };
+    fetch(git_repo, remote_name, fetch_depth, auth)?;
+    if let Some(since) = since {
+        deepen_since(
+            git_repo,
+            remote_name,
+            boundary_depth(depth).unwrap_or(SINCE_START_DEPTH),
+            since,
+            auth,
+        )?;
+    }
+    Ok(())
+}
+
+/// Fetches `remote_name`, then restores the shallow boundary: libgit2 1.8
+/// drops the existing boundary commits from `.git/shallow` when a depth
+/// fetch does not move them, which would leave the clone looking complete.
+fn fetch(
+    git_repo: &git2::Repository,
+    remote_name: &str,
+    depth: Option<i32>,
+    auth: &RemoteAuth,
+) -> std::result::Result<(), git2::Error> {
+    let before: HashSet<git2::Oid> = shallow_roots(git_repo)
+        .union(&read_roots(git_repo, BOUNDARY_JOURNAL))
+        .copied()
+        .collect();
+    if !before.is_empty() {
+        write_roots(git_repo, BOUNDARY_JOURNAL, &before)?;
+    }
+    let mut options = fetch_options(depth, auth, git_repo.config().ok());
+    let fetched = git_repo
+        .find_remote(remote_name)
+        .and_then(|mut remote| remote.fetch::<&str>(&[], Some(&mut options), None));
+    if !before.is_empty() {
+        restore_boundary(git_repo, &before)?;
+    }
+    fetched
+}
+
+/// Rewrites `.git/shallow` as the boundary commits in `before` or written by
+/// the last fetch whose parents are still missing, then drops the journal.
+fn restore_boundary(
+    git_repo: &git2::Repository,
+    before: &HashSet<git2::Oid>,
+) -> std::result::Result<(), git2::Error> {
+    let odb = git_repo.odb()?;
+    let roots: HashSet<git2::Oid> = before
+        .union(&shallow_roots(git_repo))
+        .copied()
+        .filter(|oid| parents_fetched(git_repo, &odb, *oid) == Some(false))
+        .collect();
+    write_roots(git_repo, "shallow", &roots)?;
+    let journal = git_repo.path().join(BOUNDARY_JOURNAL);
+    remove_if_exists(&journal).map_err(|e| {
+        git2::Error::from_str(&format!("failed to remove {}: {}", journal.display(), e))
+    })
+}
+
+/// Reads a list of commit ids, one per line, from `name` in the git directory.
+fn read_roots(git_repo: &git2::Repository, name: &str) -> HashSet<git2::Oid> {
+    std::fs::read_to_string(git_repo.path().join(name))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| git2::Oid::from_str(line.trim()).ok())
+        .collect()
+}
+
+/// Replaces `name` in the git directory with `roots`, or removes it when
+/// there are none. The new contents go through `<name>.lock`, created
+/// exclusively as git and libgit2 do, and are renamed into place, so readers
+/// never see a partial file and a concurrent writer fails instead of racing.
+fn write_roots(
+    git_repo: &git2::Repository,
+    name: &str,
+    roots: &HashSet<git2::Oid>,
+) -> std::result::Result<(), git2::Error> {
+    use std::io::Write;
+
+    let path = git_repo.path().join(name);
+    let lock = git_repo.path().join(format!("{}.lock", name));
+    let fail = |e: std::io::Error| {
+        git2::Error::from_str(&format!("failed to write {}: {}", path.display(), e))
+    };
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&lock)
+        .map_err(fail)?;
+    let mut sorted: Vec<&git2::Oid> = roots.iter().collect();
+    sorted.sort();
+    let lines: String = sorted.iter().map(|oid| format!("{}\n", oid)).collect();
+    let written = file
+        .write_all(lines.as_bytes())
+        .and_then(|()| file.sync_all())
+        .and_then(|()| {
+            if roots.is_empty() {
+                remove_if_exists(&path).and_then(|()| std::fs::remove_file(&lock))
+            } else {
+                std::fs::rename(&lock, &path)
+            }
+        });
+    // Once renamed, the lock may already belong to another writer.
+    if written.is_err() {
+        let _ = std::fs::remove_file(&lock);
+    }
+    written.map_err(fail)
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Whether every parent of `oid` is in the object database, going by the
+/// commit's own parent headers rather than libgit2's shallow grafts. None if
+/// the commit itself is missing.
+fn parents_fetched(git_repo: &git2::Repository, odb: &git2::Odb, oid: git2::Oid) -> Option<bool> {
+    let commit = git_repo.find_commit(oid).ok()?;
+    let header = commit.raw_header()?;
+    Some(
+        header
+            .lines()
+            .filter_map(|line| line.strip_prefix("parent "))
+            .filter_map(|p| git2::Oid::from_str(p.trim()).ok())
+            .all(|p| odb.exists(p)),
+    )
+}
+
+/// The fetch depth that gives `depth` commits with a parent to diff against.
+fn boundary_depth(depth: Option<u32>) -> Option<u32> {
+    depth.map(|d| d.saturating_add(1))
+}
+
+/// Deepens a shallow clone, doubling the depth, until every boundary commit
+/// is older than `since`.
+fn deepen_since(
+    git_repo: &git2::Repository,
+    remote_name: &str,
+    mut depth: u32,
+    since: i64,
+    auth: &RemoteAuth,
+) -> std::result::Result<(), git2::Error> {
+    loop {
+        let roots = shallow_roots(git_repo);
+        let reached = roots.iter().all(|oid| {
+            git_repo
+                .find_commit(*oid)
+                .map(|c| c.time().seconds() < since)
+                .unwrap_or(true)
+        });
+        if reached {
+            return Ok(());
+        }
+        depth = depth.saturating_mul(2);
+        let fetch_depth = if depth > MAX_DEEPEN_DEPTH {
+            UNSHALLOW
+        } else {
+            depth as i32
+        };
+        fetch(git_repo, remote_name, Some(fetch_depth), auth)?;
+        // The whole history has been fetched.
+        if fetch_depth == UNSHALLOW || shallow_roots(git_repo) == roots {
+            return Ok(());
+        }
+    }
+}
+
+/// True if a fetch deepened the history behind `roots_before`, the boundary
+/// commits before it, so that older commits are now available.
+pub(crate) fn deepened(git_repo: &git2::Repository, roots_before: &HashSet<git2::Oid>) -> bool {
+    let Ok(odb) = git_repo.odb() else {
+        return false;
+    };
+    roots_before
+        .iter()
+        .any(|oid| parents_fetched(git_repo, &odb, *oid) == Some(true))
+}
+
+/// Commits reachable from `tip` but not from `hidden`, parents first, in a
+/// shallow clone. Commit parents, unlike the revwalk's, stop at the boundary.
+///
+/// Both sides are walked newest first, and the walk stops once only hidden
+/// commits are left to visit, so an incremental sync reads the new commits
+/// and the few indexed ones around them rather than the whole history.
+pub(crate) fn shallow_walk(
+    git_repo: &git2::Repository,
+    tip: git2::Oid,
+    hidden: &[git2::Oid],
+) -> std::result::Result<Vec<git2::Oid>, git2::Error> {
+    let mut queue: BinaryHeap<(i64, git2::Oid)> = BinaryHeap::new();
+    let mut queued: HashSet<git2::Oid> = HashSet::new();
+    let mut visited: HashSet<git2::Oid> = HashSet::new();
+    let mut uninteresting: HashSet<git2::Oid> = HashSet::new();
+    let mut enqueue = |queue: &mut BinaryHeap<_>, oid: git2::Oid| {
+        if queued.insert(oid) {
+            if let Ok(commit) = git_repo.find_commit(oid) {
+                queue.push((commit.time().seconds(), oid));
+            }
+        }
+    };
+    for &oid in hidden {
+        mark_uninteresting(git_repo, oid, &visited, &mut uninteresting);
+        enqueue(&mut queue, oid);
+    }
+    enqueue(&mut queue, tip);
+
+    let mut reached = Vec::new();
+    let mut slop = WALK_SLOP;
+    while let Some((_, oid)) = queue.pop() {
+        let commit = git_repo.find_commit(oid)?;
+        visited.insert(oid);
+        let is_hidden = uninteresting.contains(&oid);
+        if !is_hidden {
+            reached.push(oid);
+        }
+        for parent in commit.parent_ids() {
+            if is_hidden {
+                mark_uninteresting(git_repo, parent, &visited, &mut uninteresting);
+            }
+            enqueue(&mut queue, parent);
+        }
+        if queue.iter().all(|(_, o)| uninteresting.contains(o)) {
+            if slop == 0 {
+                break;
+            }
+            slop -= 1;
+        } else {
+            slop = WALK_SLOP;
+        }
+    }
+    let new: HashSet<git2::Oid> = reached
+        .into_iter()
+        .filter(|oid| !uninteresting.contains(oid))
+        .collect();
+
+    // Depth-first, emitting each commit after all of its parents
+    let mut oids = Vec::new();
+    let mut emitted: HashSet<git2::Oid> = HashSet::new();
+    let mut stack = vec![(tip, false)];
+    while let Some((oid, expanded)) = stack.pop() {
+        if expanded {
+            oids.push(oid);
+            continue;
+        }
+        if !new.contains(&oid) || !emitted.insert(oid) {
+            continue;
+        }
+        let commit = git_repo.find_commit(oid)?;
+        stack.push((oid, true));
+        stack.extend(
+            commit
+                .parent_ids()
+                .filter(|p| new.contains(p) && !emitted.contains(p))
+                .map(|p| (p, false)),
+        );
+    }
+    Ok(oids)
+}
+
+/// Marks `oid` as reachable from a hidden commit, along with the ancestors
+/// the walk has already visited through it; unvisited ones are marked when
+/// the walk reaches them.
+fn mark_uninteresting(
+    git_repo: &git2::Repository,
+    oid: git2::Oid,
+    visited: &HashSet<git2::Oid>,
+    uninteresting: &mut HashSet<git2::Oid>,
+) {
+    let mut stack = vec![oid];
+    while let Some(oid) = stack.pop() {
+        if uninteresting.insert(oid) && visited.contains(&oid) {
+            if let Ok(commit) = git_repo.find_commit(oid) {
+                stack.extend(commit.parent_ids());
+            }
+        }
+    }
+}
+
+/// Boundary commits of a shallow clone, whose parents were not fetched.
+pub(crate) fn shallow_roots(git_repo: &git2::Repository) -> HashSet<git2::Oid> {
+    read_roots(git_repo, "shallow")
+}
@@ -0,0 +1,140 @@
+use commitmux_types::{RemoteAuth, Repo, Store};
+use std::path::{Path, PathBuf};
+
+/// A step of the credential chain. Each is tried at most once per
+/// connection, so a rejected credential moves on to the next instead of
+/// being retried forever.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    SshKey,
+    SshAgent,
+    Token,
+    Helper,
+    AskPass,
+}
+
+/// The repo's credentials, with the configured token variable as the
+/// default.
+pub(crate) fn remote_auth(repo: &Repo, store: &dyn Store) -> RemoteAuth {
+    RemoteAuth {
+        ssh_key: repo.ssh_key.clone(),
+        token_env: repo.token_env.clone().or_else(|| {
+            store
+                .get_config(RemoteAuth::TOKEN_ENV_KEY)
+                .ok()
+                .flatten()
+                .filter(|v| !v.is_empty())
+        }),
+    }
+}
+
+/// Remote callbacks that answer credential requests from, in order: the
+/// repo's SSH key file and the SSH agent for SSH remotes; its token
+/// variable, git credential helpers and `GIT_ASKPASS` for HTTPS remotes.
+pub(crate) fn remote_callbacks<'a>(
+    auth: &RemoteAuth,
+    config: Option<git2::Config>,
+) -> git2::RemoteCallbacks<'a> {
+    let auth = auth.clone();
+    let mut tried: Vec<Step> = Vec::new();
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        if allowed.contains(git2::CredentialType::USERNAME) {
+            return git2::Cred::username(username.unwrap_or("git"));
+        }
+        let mut next = |step: Step| {
+            let first = !tried.contains(&step);
+            tried.push(step);
+            first
+        };
+        if allowed.contains(git2::CredentialType::SSH_KEY) {
+            let user = username.unwrap_or("git");
+            if let Some(key) = auth.ssh_key.as_deref().filter(|_| next(Step::SshKey)) {
+                return git2::Cred::ssh_key(user, None, key, None);
+            }
+            if next(Step::SshAgent) {
+                return git2::Cred::ssh_key_from_agent(user);
+            }
+        }
+        if allowed.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            if next(Step::Token) {
+                if let Some(token) = auth.token_env.as_deref().and_then(token_from_env) {
+                    return git2::Cred::userpass_plaintext(
+                        username.unwrap_or("x-access-token"),
+                        &token,
+                    );
+                }
+            }
+            if next(Step::Helper) {
+                if let Some(cred) = config
+                    .as_ref()
+                    .and_then(|c| git2::Cred::credential_helper(c, url, username).ok())
+                {
+                    return Ok(cred);
+                }
+            }
+            if next(Step::AskPass) {
+                if let Some((user, password)) = askpass(config.as_ref(), url, username) {
+                    return git2::Cred::userpass_plaintext(&user, &password);
+                }
+            }
+        }
+        Err(git2::Error::from_str(&format!(
+            "no credentials accepted for {}{}",
+            url,
+            hint(&auth)
+        )))
+    });
+    callbacks
+}
+
+fn token_from_env(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// What to configure when nothing worked.
+fn hint(auth: &RemoteAuth) -> String {
+    match (&auth.ssh_key, &auth.token_env) {
+        (None, None) => {
+            " (set --ssh-key or --token-env on the repo, or a git credential helper)".to_string()
+        }
+        (_, Some(name)) if token_from_env(name).is_none() => {
+            format!(" (token variable {} is not set)", name)
+        }
+        _ => String::new(),
+    }
+}
+
+/// Asks `GIT_ASKPASS`, or the `core.askPass` program, for a username and
+/// password the way git does: one run per prompt, the answer on stdout.
+fn askpass(
+    config: Option<&git2::Config>,
+    url: &str,
+    username: Option<&str>,
+) -> Option<(String, String)> {
+    let program: PathBuf = std::env::var_os("GIT_ASKPASS")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config?.get_path("core.askPass").ok())?;
+    let user = match username {
+        Some(user) => user.to_string(),
+        None => prompt(&program, &format!("Username for '{}': ", url))?,
+    };
+    let password = prompt(&program, &format!("Password for '{}': ", url))?;
+    Some((user, password))
+}
+
+fn prompt(program: &Path, text: &str) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .arg(text)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let answer = String::from_utf8(output.stdout).ok()?;
+    Some(answer.trim_end_matches(['\r', '\n']).to_string())
+}
@@ -0,0 +1,227 @@
+use commitmux_types::{
+    CommitmuxError, FileContent, FileOpts, Repo, Result, TreeEntry, TreeListing, TreeOpts,
+};
+use std::path::Path;
+
+use crate::walker::resolve_rev;
+
+/// Reads `opts.path` at `opts.rev`, limited to the requested line range or
+/// byte window.
+pub fn read_file(git_repo: &git2::Repository, repo: &Repo, opts: &FileOpts) -> Result<FileContent> {
+    let by_lines = opts.start_line.is_some() || opts.end_line.is_some();
+    if by_lines && opts.offset.is_some() {
+        return Err(CommitmuxError::Config(
+            "use either a line range or a byte offset, not both".to_string(),
+        ));
+    }
+    let max_bytes = opts.max_bytes.unwrap_or(FileContent::DEFAULT_MAX_BYTES);
+    if max_bytes == 0 {
+        return Err(CommitmuxError::Config(
+            "max_bytes must be greater than 0".to_string(),
+        ));
+    }
+
+    let rev_oid = resolve_rev(git_repo, repo, &opts.rev)?;
+    let tree = git_repo
+        .find_commit(rev_oid)
+        .and_then(|commit| commit.tree())
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    let entry = tree.get_path(Path::new(&opts.path)).map_err(|_| {
+        CommitmuxError::NotFound(format!(
+            "path '{}' at '{}' in repo '{}'",
+            opts.path, opts.rev, repo.name
+        ))
+    })?;
+    match entry.kind() {
+        Some(git2::ObjectType::Blob) => {}
+        Some(git2::ObjectType::Tree) => {
+            return Err(CommitmuxError::Config(format!(
+                "'{}' is a directory",
+                opts.path
+            )))
+        }
+        _ => {
+            return Err(CommitmuxError::Config(format!(
+                "'{}' is a submodule",
+                opts.path
+            )))
+        }
+    }
+    let blob = git_repo
+        .find_blob(entry.id())
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    let bytes = blob.content();
+
+    let mut file = FileContent {
+        repo: repo.name.clone(),
+        path: opts.path.clone(),
+        rev: opts.rev.clone(),
+        sha: rev_oid.to_string(),
+        size: bytes.len(),
+        binary: blob.is_binary(),
+        ..Default::default()
+    };
+    if file.binary {
+        return Ok(file);
+    }
+
+    let text = String::from_utf8_lossy(bytes);
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    file.total_lines = lines.len();
+
+    if by_lines {
+        let start = opts.start_line.unwrap_or(1);
+        let end = opts.end_line.unwrap_or(lines.len()).min(lines.len());
+        if start == 0 || start > end {
+            return Err(CommitmuxError::Config(format!(
+                "invalid line range {}-{} for '{}' ({} lines)",
+                start,
+                opts.end_line.unwrap_or(lines.len()),
+                opts.path,
+                lines.len()
+            )));
+        }
+        file.offset = lines[..start - 1].iter().map(|l| l.len()).sum();
+        // Whole lines only, unless even the first one does not fit.
+        let mut content = String::new();
+        let mut last = start - 1;
+        for line in &lines[start - 1..end] {
+            if content.len() + line.len() > max_bytes {
+                break;
+            }
+            content.push_str(line);
+            last += 1;
+        }
+        let mut cut_short = false;
+        if last < start {
+            let first = lines[start - 1];
+            let mut cut = max_bytes;
+            while !first.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            content.push_str(&first[..cut]);
+            last = start;
+            cut_short = true;
+        }
+        file.truncated = cut_short || last < end;
+        file.start_line = Some(start);
+        file.end_line = Some(last);
+        file.content = Some(content);
+    } else {
+        let offset = opts.offset.unwrap_or(0);
+        if offset > bytes.len() {
+            return Err(CommitmuxError::Config(format!(
+                "offset {} is past the end of '{}' ({} bytes)",
+                offset,
+                opts.path,
+                bytes.len()
+            )));
+        }
+        let end = bytes.len().min(offset.saturating_add(max_bytes));
+        file.offset = offset;
+        file.truncated = end < bytes.len();
+        file.content = Some(String::from_utf8_lossy(&bytes[offset..end]).into_owned());
+    }
+
+    Ok(file)
+}
+
+/// Lists the entries under `opts.path` at `opts.rev`, descending `opts.depth`
+/// levels, up to `TreeListing::MAX_ENTRIES` entries.
+pub fn list_tree(git_repo: &git2::Repository, repo: &Repo, opts: &TreeOpts) -> Result<TreeListing> {
+    let depth = opts.depth.unwrap_or(TreeListing::DEFAULT_DEPTH);
+    if depth == 0 {
+        return Err(CommitmuxError::Config(
+            "depth must be at least 1".to_string(),
+        ));
+    }
+
+    let rev_oid = resolve_rev(git_repo, repo, &opts.rev)?;
+    let root = git_repo
+        .find_commit(rev_oid)
+        .and_then(|commit| commit.tree())
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    let dir = opts.path.as_deref().unwrap_or("").trim_matches('/');
+    let tree = if dir.is_empty() {
+        root
+    } else {
+        let entry = root.get_path(Path::new(dir)).map_err(|_| {
+            CommitmuxError::NotFound(format!(
+                "path '{}' at '{}' in repo '{}'",
+                dir, opts.rev, repo.name
+            ))
+        })?;
+        if entry.kind() != Some(git2::ObjectType::Tree) {
+            return Err(CommitmuxError::Config(format!(
+                "'{}' is not a directory",
+                dir
+            )));
+        }
+        git_repo
+            .find_tree(entry.id())
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?
+    };
+
+    let odb = git_repo
+        .odb()
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    let mut listing = TreeListing {
+        repo: repo.name.clone(),
+        rev: opts.rev.clone(),
+        sha: rev_oid.to_string(),
+        path: dir.to_string(),
+        depth,
+        ..Default::default()
+    };
+    collect_entries(git_repo, &odb, &tree, dir, depth, &mut listing)?;
+    Ok(listing)
+}
+
+fn collect_entries(
+    git_repo: &git2::Repository,
+    odb: &git2::Odb,
+    tree: &git2::Tree,
+    prefix: &str,
+    depth: usize,
+    listing: &mut TreeListing,
+) -> Result<()> {
+    for entry in tree.iter() {
+        if listing.entries.len() >= TreeListing::MAX_ENTRIES {
+            listing.truncated = true;
+            return Ok(());
+        }
+        let Some(name) = entry.name() else {
+            continue;
+        };
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        let kind = entry.kind();
+        // Only the object header is read, so large blobs are not loaded.
+        let size = match kind {
+            Some(git2::ObjectType::Blob) => Some(
+                odb.read_header(entry.id())
+                    .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?
+                    .0 as u64,
+            ),
+            _ => None,
+        };
+        listing.entries.push(TreeEntry {
+            path: path.clone(),
+            kind: kind.map_or("unknown", |k| k.str()).to_string(),
+            mode: format!("{:06o}", entry.filemode()),
+            sha: entry.id().to_string(),
+            size,
+        });
+
+        if kind == Some(git2::ObjectType::Tree) && depth > 1 {
+            let subtree = git_repo
+                .find_tree(entry.id())
+                .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+            collect_entries(git_repo, odb, &subtree, &path, depth - 1, listing)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use commitmux_types::{AuthorMatcher, CommitmuxError, Result};
+use regex::{Regex, RegexBuilder};
+
+/// Compiled form of a repo's author filter.
+///
+/// A commit passes if any matcher accepts its author or its committer, either
+/// as recorded or as resolved through the repository's `.mailmap`. Email and
+/// name matches are exact and case-insensitive; regexes are case-insensitive
+/// and matched against `Name <email>`.
+pub struct AuthorFilter {
+    emails: Vec<String>,
+    names: Vec<String>,
+    regexes: Vec<Regex>,
+}
+
+impl AuthorFilter {
+    /// Returns `None` when there are no matchers (every commit passes), or an
+    /// error naming the first regex that fails to compile.
+    pub fn compile(matchers: &[AuthorMatcher]) -> Result<Option<AuthorFilter>> {
+        if matchers.is_empty() {
+            return Ok(None);
+        }
+        let mut filter = AuthorFilter {
+            emails: Vec::new(),
+            names: Vec::new(),
+            regexes: Vec::new(),
+        };
+        for m in matchers {
+            match m {
+                AuthorMatcher::Email(e) => filter.emails.push(e.to_lowercase()),
+                AuthorMatcher::Name(n) => filter.names.push(n.to_lowercase()),
+                AuthorMatcher::Regex(r) => {
+                    let re = RegexBuilder::new(r)
+                        .case_insensitive(true)
+                        .build()
+                        .map_err(|e| {
+                            CommitmuxError::Config(format!("invalid author regex '{}': {}", r, e))
+                        })?;
+                    filter.regexes.push(re);
+                }
+            }
+        }
+        Ok(Some(filter))
+    }
+
+    fn matches_identity(&self, name: &str, email: &str) -> bool {
+        let (name_lc, email_lc) = (name.to_lowercase(), email.to_lowercase());
+        if self.emails.contains(&email_lc) || self.names.contains(&name_lc) {
+            return true;
+        }
+        if self.regexes.is_empty() {
+            return false;
+        }
+        let ident = format!("{} <{}>", name, email);
+        self.regexes.iter().any(|re| re.is_match(&ident))
+    }
+
+    fn matches_signature(
+        &self,
+        sig: &git2::Signature<'_>,
+        mailmap: Option<&git2::Mailmap>,
+    ) -> bool {
+        let name = sig.name().unwrap_or("");
+        let email = sig.email().unwrap_or("");
+        if self.matches_identity(name, email) {
+            return true;
+        }
+        match mailmap.and_then(|m| m.resolve_signature(sig).ok()) {
+            Some(resolved) => self.matches_identity(
+                resolved.name().unwrap_or(""),
+                resolved.email().unwrap_or(""),
+            ),
+            None => false,
+        }
+    }
+
+    /// Returns true if the commit's author or committer is accepted.
+    pub fn matches_commit(
+        &self,
+        commit: &git2::Commit<'_>,
+        mailmap: Option<&git2::Mailmap>,
+    ) -> bool {
+        self.matches_signature(&commit.author(), mailmap)
+            || self.matches_signature(&commit.committer(), mailmap)
+    }
+}
@@ -1,15 +1,32 @@
+mod blame;
+mod clone;
+mod credentials;
+mod files;
+mod filter;
 mod patch;
+mod refs;
+mod structured;
+mod submodules;
+mod symbols;
+mod tags;
 mod walker;
 
+pub use clone::{clone_repo, supports_shallow};
+pub use filter::AuthorFilter;
+pub use refs::{reextract_refs, RefExtractor};
+pub use structured::structured_patch;
 pub use walker::Git2Ingester;
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use commitmux_types::{
-        CommitDetail, CommitFile, CommitPatch, EmbedCommit, IgnoreConfig, IngestState, Ingester,
-        PatchResult, Repo, RepoInput, RepoListEntry, RepoStats, RepoUpdate, Result, SearchOpts,
-        SearchResult, SemanticSearchOpts, Store, TouchOpts, TouchResult,
+        AuthorMatcher, BlameOpts, ChangelogCommit, CommitDetail, CommitFile, CommitPatch,
+        CommitSubmodule, CommitSymbol, ConventionalSummary, ConventionalSummaryOpts, DiffOpts,
+        EmbedCommit, FileKind, FileOpts, FileStatus, IgnoreConfig, IngestState, Ingester,
+        PatchResult, RefOpts, RefPattern, RefResult, Repo, RepoInput, RepoListEntry, RepoProject,
+        RepoStats, RepoUpdate, Result, SearchOpts, SearchResult, SemanticSearchOpts, Store,
+        SymbolChange, SymbolOpts, Tag, TagIndex, TouchOpts, TouchResult, TreeOpts,
     };
     use std::sync::Mutex;
 
@@ -19,7 +36,11 @@ mod tests {
         commits: Mutex<Vec<commitmux_types::Commit>>,
         files: Mutex<Vec<CommitFile>>,
         patches: Mutex<Vec<CommitPatch>>,
+        symbols: Mutex<Vec<CommitSymbol>>,
+        submodules: Mutex<Vec<CommitSubmodule>>,
         ingest_state: Mutex<Option<IngestState>>,
+        tags: Mutex<TagIndex>,
+        tag_replacements: Mutex<usize>,
     }
 
     impl MockStore {
@@ -28,7 +49,11 @@ mod tests {
                 commits: Mutex::new(Vec::new()),
                 files: Mutex::new(Vec::new()),
                 patches: Mutex::new(Vec::new()),
+                symbols: Mutex::new(Vec::new()),
+                submodules: Mutex::new(Vec::new()),
                 ingest_state: Mutex::new(None),
+                tags: Mutex::new(TagIndex::default()),
+                tag_replacements: Mutex::new(0),
             }
         }
     }
@@ -73,6 +98,25 @@ mod tests {
             Ok(())
         }
 
+        fn write_batch(&self, batch: &commitmux_types::WriteBatch) -> Result<()> {
+            for entry in &batch.commits {
+                self.upsert_commit(&entry.commit)?;
+                self.upsert_commit_files(&entry.files)?;
+                if let Some(ref patch) = entry.patch {
+                    self.upsert_patch(patch)?;
+                }
+                self.symbols
+                    .lock()
+                    .unwrap()
+                    .extend_from_slice(&entry.symbols);
+                self.submodules
+                    .lock()
+                    .unwrap()
+                    .extend_from_slice(&entry.submodules);
+            }
+            Ok(())
+        }
+
         fn get_ingest_state(&self, _repo_id: i64) -> Result<Option<IngestState>> {
             Ok(self.ingest_state.lock().unwrap().clone())
         }
@@ -87,6 +131,23 @@ mod tests {
             Ok(self.commits.lock().unwrap().iter().any(|c| c.sha == sha))
         }
 
+        fn list_tags(&self, _repo_id: i64) -> Result<Vec<Tag>> {
+            Ok(self.tags.lock().unwrap().tags.clone())
+        }
+
+        fn replace_tags(&self, _repo_id: i64, index: &TagIndex) -> Result<()> {
+            *self.tags.lock().unwrap() = index.clone();
+            *self.tag_replacements.lock().unwrap() += 1;
+            Ok(())
+        }
+        fn reextract_refs(
+            &self,
+            _repo_id: i64,
+            _extract: &dyn Fn(&str) -> Vec<commitmux_types::CommitRef>,
+        ) -> Result<usize> {
+            Ok(0)
+        }
+
         fn search(&self, _query: &str, _opts: &SearchOpts) -> Result<Vec<SearchResult>> {
             unimplemented!()
         }
@@ -95,6 +156,34 @@ mod tests {
             unimplemented!()
         }
 
+        fn find_by_ref(&self, _reference: &str, _opts: &RefOpts) -> Result<Vec<RefResult>> {
+            unimplemented!()
+        }
+
+        fn conventional_summary(
+            &self,
+            _opts: &ConventionalSummaryOpts,
+        ) -> Result<ConventionalSummary> {
+            unimplemented!()
+        }
+
+        fn changelog_commits(
+            &self,
+            _repo_id: i64,
+            _shas: &[String],
+        ) -> Result<Vec<ChangelogCommit>> {
+            unimplemented!()
+        }
+
+        fn symbol_history(
+            &self,
+            _repo_id: i64,
+            _symbol: &str,
+            _opts: &SymbolOpts,
+        ) -> Result<Vec<SymbolChange>> {
+            unimplemented!()
+        }
+
         fn get_commit(&self, _repo_name: &str, _sha_prefix: &str) -> Result<Option<CommitDetail>> {
             unimplemented!()
         }
@@ -113,13 +202,23 @@ mod tests {
         }
 
         fn count_commits_for_repo(&self, _repo_id: i64) -> Result<usize> {
-            Ok(0)
+            Ok(self.commits.lock().unwrap().len())
         }
 
         fn count_embeddings_for_repo(&self, _repo_id: i64) -> Result<usize> {
             Ok(0)
         }
 
+        fn add_identity(&self, _alias: &commitmux_types::IdentityAlias) -> Result<()> {
+            Ok(())
+        }
+        fn list_identities(&self) -> Result<Vec<commitmux_types::IdentityAlias>> {
+            Ok(vec![])
+        }
+        fn remove_identity(&self, _alias_email: &str) -> Result<()> {
+            Ok(())
+        }
+
         fn get_config(&self, _key: &str) -> Result<Option<String>> {
             Ok(None)
         }
@@ -206,16 +305,26 @@ mod tests {
             remote_url: None,
             default_branch: None,
             fork_of: None,
-            author_filter: None,
+            author_filters: vec![],
+            include_paths: vec![],
+            ref_patterns: vec![],
+            projects: vec![],
             exclude_prefixes: vec![],
             embed_enabled: false,
+            clone_depth: None,
+            clone_since: None,
+            ssh_key: None,
+            token_env: None,
         }
     }
 
     fn default_config() -> IgnoreConfig {
         IgnoreConfig {
             path_prefixes: vec!["node_modules/".into()],
+            include_prefixes: vec![],
             max_patch_bytes: 1_048_576,
+            rename_threshold: 50,
+            projects: vec![],
         }
     }
 
@@ -336,7 +445,10 @@ mod tests {
         let repo = make_repo(dir.path());
         let config = IgnoreConfig {
             path_prefixes: vec!["node_modules/".into()],
+            include_prefixes: vec![],
             max_patch_bytes: 1_048_576,
+            rename_threshold: 50,
+            projects: vec![],
         };
 
         let summary = Git2Ingester::new()
@@ -415,7 +527,7 @@ mod tests {
 
         let store = MockStore::new();
         let mut repo = make_repo(dir.path());
-        repo.author_filter = Some("alice@example.com".into());
+        repo.author_filters = vec![AuthorMatcher::Email("alice@example.com".into())];
         let config = default_config();
 
         let summary = Git2Ingester::new()
@@ -545,14 +657,15 @@ mod tests {
             "first run: 0 already-indexed"
         );
 
-        // Second run: both commits already in store, both skipped
+        // Second run: history behind the previous tip is hidden, so nothing
+        // is walked
         let summary2 = Git2Ingester::new()
             .sync_repo(&repo, &store, &config)
             .expect("sync_repo second run");
         assert_eq!(summary2.commits_indexed, 0, "second run: 0 indexed");
         assert_eq!(
-            summary2.commits_already_indexed, 2,
-            "second run: 2 already-indexed"
+            summary2.commits_already_indexed, 0,
+            "second run: hidden commits are not counted"
         );
     }
 
@@ -605,13 +718,15 @@ mod tests {
         );
         assert_eq!(summary1.commits_filtered, 0, "first sync: 0 filtered");
 
-        // Second sync: all commits already indexed
+        // Second sync without the walk state: every walked commit is found
+        // in the store
+        *store.ingest_state.lock().unwrap() = None;
         let summary2 = Git2Ingester::new()
             .sync_repo(&repo, &store, &config)
             .expect("second sync");
-        assert!(
-            summary2.commits_already_indexed > 0,
-            "second sync: some already-indexed"
+        assert_eq!(
+            summary2.commits_already_indexed, 2,
+            "second sync: both already-indexed"
         );
         assert_eq!(summary2.commits_filtered, 0, "second sync: 0 filtered");
     }
@@ -639,7 +754,7 @@ mod tests {
         let store = MockStore::new();
         let mut repo = make_repo(dir.path());
         // Filter for a non-matching email — no commits will match
-        repo.author_filter = Some("nobody@example.com".into());
+        repo.author_filters = vec![AuthorMatcher::Email("nobody@example.com".into())];
         let config = default_config();
 
         let summary = Git2Ingester::new()
@@ -653,4 +768,1488 @@ mod tests {
         );
         assert_eq!(summary.commits_indexed, 0, "should have 0 indexed");
     }
+
+    /// Writes `name` and commits it on top of HEAD (or as a root commit).
+    fn commit_file(
+        git_repo: &git2::Repository,
+        dir: &std::path::Path,
+        name: &str,
+        message: &str,
+    ) -> git2::Oid {
+        commit_file_as(git_repo, dir, name, message, ("Test", "test@example.com"))
+    }
+
+    /// Like `commit_file`, with the given author (and committer) identity.
+    fn commit_file_as(
+        git_repo: &git2::Repository,
+        dir: &std::path::Path,
+        name: &str,
+        message: &str,
+        (author_name, author_email): (&str, &str),
+    ) -> git2::Oid {
+        if let Some(parent) = std::path::Path::new(name).parent() {
+            std::fs::create_dir_all(dir.join(parent)).expect("create dirs");
+        }
+        std::fs::write(dir.join(name), format!("{}\n", name)).expect("write file");
+        let mut index = git_repo.index().expect("get index");
+        index
+            .add_path(std::path::Path::new(name))
+            .expect("add path");
+        index.write().expect("write index");
+        let tree_oid = index.write_tree().expect("write tree");
+        let tree = git_repo.find_tree(tree_oid).expect("find tree");
+        let sig = git2::Signature::now(author_name, author_email).expect("sig");
+        let parent = git_repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        git_repo
+            .commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .expect("commit")
+    }
+
+    #[test]
+    fn test_author_filter_multiple_identities() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        // .mailmap folds the old personal address into the work identity
+        std::fs::write(
+            dir.path().join(".mailmap"),
+            "Alice <alice@work.com> <alice@home.net>\n",
+        )
+        .expect("write .mailmap");
+
+        commit_file_as(
+            &git_repo,
+            dir.path(),
+            "a.txt",
+            "work",
+            ("Alice", "alice@work.com"),
+        );
+        commit_file_as(
+            &git_repo,
+            dir.path(),
+            "b.txt",
+            "home",
+            ("A. S.", "alice@home.net"),
+        );
+        commit_file_as(
+            &git_repo,
+            dir.path(),
+            "c.txt",
+            "web edit",
+            ("alice", "12345+alice@users.noreply.github.com"),
+        );
+        commit_file_as(
+            &git_repo,
+            dir.path(),
+            "d.txt",
+            "bob",
+            ("Bob", "bob@work.com"),
+        );
+
+        let store = MockStore::new();
+        let mut repo = make_repo(dir.path());
+        repo.author_filters = vec![
+            AuthorMatcher::parse("ALICE@work.com"),
+            AuthorMatcher::parse(r"re:\+alice@users\.noreply\.github\.com>$"),
+        ];
+
+        let summary = Git2Ingester::new()
+            .sync_repo(&repo, &store, &default_config())
+            .expect("sync_repo");
+
+        assert_eq!(summary.commits_indexed, 3, "all of Alice's identities");
+        assert_eq!(summary.commits_filtered, 1, "Bob is filtered");
+        let subjects: Vec<String> = store
+            .commits
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|c| c.subject.clone())
+            .collect();
+        assert_eq!(subjects, vec!["work", "home", "web edit"]);
+
+        // The raw identity is kept alongside the .mailmap-resolved one
+        let commits = store.commits.lock().unwrap();
+        assert_eq!(commits[1].author_email, "alice@home.net");
+        assert_eq!(commits[1].canonical_author_name, "Alice");
+        assert_eq!(commits[1].canonical_author_email, "alice@work.com");
+        assert_eq!(
+            commits[2].canonical_author_email,
+            "12345+alice@users.noreply.github.com"
+        );
+    }
+
+    #[test]
+    fn test_trailers_parsed() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        commit_file_as(
+            &git_repo,
+            dir.path(),
+            "a.txt",
+            "Pair on parser\n\nSplit the tokenizer out.\n\nFixes: #42\nCo-authored-by: Bob <bob@work.com>\n",
+            ("Alice", "alice@work.com"),
+        );
+        commit_file(&git_repo, dir.path(), "b.txt", "No trailers here");
+
+        let store = MockStore::new();
+        Git2Ingester::new()
+            .sync_repo(&make_repo(dir.path()), &store, &default_config())
+            .expect("sync_repo");
+
+        let commits = store.commits.lock().unwrap();
+        let keys: Vec<(&str, &str)> = commits[0]
+            .trailers
+            .iter()
+            .map(|t| (t.key.as_str(), t.value.as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![("Fixes", "#42"), ("Co-authored-by", "Bob <bob@work.com>")]
+        );
+        assert_eq!(
+            commits[0].body.as_deref().map(|b| b.contains("tokenizer")),
+            Some(true)
+        );
+        assert!(commits[1].trailers.is_empty());
+    }
+
+    #[test]
+    fn test_commit_range_between_refs() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let c1 = commit_file(&git_repo, dir.path(), "a.txt", "Initial");
+        let tagged = git_repo.find_object(c1, None).expect("c1");
+        git_repo
+            .tag_lightweight("v1.0.0", &tagged, false)
+            .expect("tag");
+        let c2 = commit_file(&git_repo, dir.path(), "b.txt", "Second");
+
+        // A side branch off c1, merged into the mainline after c2.
+        let sig = git2::Signature::now("Test", "test@example.com").expect("sig");
+        let base = git_repo.find_commit(c1).expect("c1");
+        let side = git_repo
+            .commit(
+                None,
+                &sig,
+                &sig,
+                "Side work",
+                &base.tree().unwrap(),
+                &[&base],
+            )
+            .expect("side commit");
+        let head = git_repo.find_commit(c2).expect("c2");
+        let side_commit = git_repo.find_commit(side).expect("side");
+        let merge = git_repo
+            .commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                "Merge side",
+                &head.tree().unwrap(),
+                &[&head, &side_commit],
+            )
+            .expect("merge commit");
+
+        let repo = make_repo(dir.path());
+        let ingester = Git2Ingester::new();
+        let range = ingester
+            .commit_range(&repo, Some("v1.0.0"), "HEAD", false)
+            .expect("range");
+        assert_eq!(range.from_sha, Some(c1.to_string()));
+        assert_eq!(range.to_sha, merge.to_string());
+        assert_eq!(range.shas.len(), 3);
+        assert!(range.shas.contains(&side.to_string()));
+
+        let range = ingester
+            .commit_range(&repo, Some("v1.0.0"), "HEAD", true)
+            .expect("first-parent range");
+        assert_eq!(range.shas, vec![merge.to_string(), c2.to_string()]);
+
+        let all = ingester
+            .commit_range(&repo, None, "HEAD", true)
+            .expect("full history");
+        assert_eq!(all.shas.len(), 3);
+
+        let err = ingester
+            .commit_range(&repo, Some("v9"), "HEAD", false)
+            .unwrap_err();
+        assert!(matches!(err, commitmux_types::CommitmuxError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_tags_indexed_with_containment() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let sig = git2::Signature::now("Rel Eng", "rel@example.com").expect("sig");
+        let c1 = commit_file(&git_repo, dir.path(), "a.txt", "First");
+        let obj = git_repo.find_object(c1, None).expect("c1");
+        git_repo
+            .tag("v1.0.0", &obj, &sig, "Release 1.0.0\n", false)
+            .expect("annotated tag");
+        let c2 = commit_file(&git_repo, dir.path(), "b.txt", "Second");
+        let obj = git_repo.find_object(c2, None).expect("c2");
+        git_repo
+            .tag_lightweight("v1.1.0", &obj, false)
+            .expect("lightweight tag");
+        commit_file(&git_repo, dir.path(), "c.txt", "Unreleased");
+
+        let store = MockStore::new();
+        let repo = make_repo(dir.path());
+        Git2Ingester::new()
+            .sync_repo(&repo, &store, &default_config())
+            .expect("sync_repo");
+
+        {
+            let index = store.tags.lock().unwrap();
+            let v1 = index.tags.iter().find(|t| t.name == "v1.0.0").expect("v1");
+            assert!(v1.annotated);
+            assert_eq!(v1.target_sha, c1.to_string());
+            assert_eq!(v1.tagger_email.as_deref(), Some("rel@example.com"));
+            assert_eq!(v1.message.as_deref(), Some("Release 1.0.0"));
+            let v11 = index
+                .tags
+                .iter()
+                .find(|t| t.name == "v1.1.0")
+                .expect("v1.1");
+            assert!(!v11.annotated);
+            assert_eq!(v11.tagger_name, None);
+
+            let introduced = |tag: &str| -> Vec<String> {
+                index
+                    .introduced
+                    .iter()
+                    .filter(|(t, _)| t == tag)
+                    .map(|(_, sha)| sha.clone())
+                    .collect()
+            };
+            assert_eq!(introduced("v1.0.0"), vec![c1.to_string()]);
+            assert_eq!(introduced("v1.1.0"), vec![c2.to_string()]);
+            assert_eq!(
+                index.ancestors,
+                vec![("v1.1.0".to_string(), "v1.0.0".to_string())]
+            );
+        }
+
+        // An unchanged tag set is not recomputed on the next sync.
+        Git2Ingester::new()
+            .sync_repo(&repo, &store, &default_config())
+            .expect("re-sync");
+        assert_eq!(*store.tag_replacements.lock().unwrap(), 1);
+    }
+
+    /// Commits `name` with the given full contents.
+    fn commit_contents(
+        git_repo: &git2::Repository,
+        dir: &std::path::Path,
+        name: &str,
+        contents: &str,
+        message: &str,
+    ) -> git2::Oid {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().expect("parent")).expect("create dirs");
+        std::fs::write(path, contents).expect("write file");
+        let mut index = git_repo.index().expect("get index");
+        index
+            .add_path(std::path::Path::new(name))
+            .expect("add path");
+        index.write().expect("write index");
+        let tree = git_repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now("Test", "test@example.com").expect("sig");
+        let parent = git_repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        git_repo
+            .commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .expect("commit")
+    }
+
+    #[test]
+    fn test_renames_detected_by_similarity() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let contents: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        commit_contents(&git_repo, dir.path(), "old.txt", &contents, "Add old.txt");
+
+        // Move the file and edit one line of it.
+        std::fs::remove_file(dir.path().join("old.txt")).expect("remove");
+        let mut index = git_repo.index().expect("get index");
+        index
+            .remove_path(std::path::Path::new("old.txt"))
+            .expect("remove path");
+        index.write().expect("write index");
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "new.txt",
+            &contents.replace("line 7\n", "line seven\n"),
+            "Rename old.txt",
+        );
+
+        let statuses = |config: &IgnoreConfig| -> Vec<(String, FileStatus, Option<String>)> {
+            let store = MockStore::new();
+            Git2Ingester::new()
+                .sync_repo(&make_repo(dir.path()), &store, config)
+                .expect("sync_repo");
+            let commits = store.commits.lock().unwrap();
+            let sha = &commits
+                .iter()
+                .find(|c| c.subject == "Rename old.txt")
+                .unwrap()
+                .sha;
+            let mut files: Vec<_> = store
+                .files
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|f| &f.sha == sha)
+                .map(|f| (f.path.clone(), f.status.clone(), f.old_path.clone()))
+                .collect();
+            files.sort_by(|a, b| a.0.cmp(&b.0));
+            files
+        };
+
+        assert_eq!(
+            statuses(&default_config()),
+            vec![(
+                "new.txt".to_string(),
+                FileStatus::Renamed,
+                Some("old.txt".to_string())
+            )]
+        );
+
+        let config = IgnoreConfig {
+            rename_threshold: 0,
+            ..default_config()
+        };
+        assert_eq!(
+            statuses(&config),
+            vec![
+                ("new.txt".to_string(), FileStatus::Added, None),
+                ("old.txt".to_string(), FileStatus::Deleted, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_binary_lfs_and_oversized_files_recorded() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        commit_contents(&git_repo, dir.path(), "README", "hello\n", "Initial");
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "logo.png",
+            "PNG\0\x01\x02",
+            "Add logo",
+        );
+        let pointer = "version https://git-lfs.github.com/spec/v1\n\
+                       oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+                       size 12345\n";
+        commit_contents(&git_repo, dir.path(), "model.bin", pointer, "Add model");
+        let big = "x".repeat(FileKind::MAX_TEXT_BYTES as usize + 1);
+        commit_contents(&git_repo, dir.path(), "dump.txt", &big, "Add dump");
+
+        let store = MockStore::new();
+        Git2Ingester::new()
+            .sync_repo(&make_repo(dir.path()), &store, &default_config())
+            .expect("sync_repo");
+
+        let files = store.files.lock().unwrap();
+        let file = |path: &str| files.iter().find(|f| f.path == path).unwrap().clone();
+        assert_eq!(file("README").kind, FileKind::Text);
+        let logo = file("logo.png");
+        assert_eq!(logo.kind, FileKind::Binary);
+        assert_eq!(logo.size, Some(6));
+        let model = file("model.bin");
+        assert_eq!(model.kind, FileKind::Lfs);
+        let lfs = model.lfs.expect("lfs pointer");
+        assert_eq!(lfs.size, 12345);
+        assert!(lfs.oid.starts_with("sha256:4d7a2146"));
+        let dump = file("dump.txt");
+        assert_eq!(dump.kind, FileKind::Oversized);
+        assert_eq!(dump.size, Some(big.len() as u64));
+
+        let commits = store.commits.lock().unwrap();
+        let patches = store.patches.lock().unwrap();
+        let patch_for = |subject: &str| {
+            let sha = &commits.iter().find(|c| c.subject == subject).unwrap().sha;
+            let patch = patches.iter().find(|p| &p.sha == sha).unwrap();
+            String::from_utf8(patch.patch_blob.clone()).unwrap()
+        };
+        assert!(patch_for("Add logo").contains("[binary file, 6 bytes]"));
+        assert!(patch_for("Add model").contains("[Git LFS object sha256:4d7a2146"));
+        let dump_patch = patch_for("Add dump");
+        assert!(dump_patch.contains("[oversized file, 1048577 bytes; diff omitted]"));
+        assert!(dump_patch.len() < 1000);
+    }
+
+    #[test]
+    fn test_files_tagged_with_repo_projects() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        for path in [
+            "services/billing/invoice.rs",
+            "services/billing/tax/vat.rs",
+            "README.md",
+        ] {
+            commit_contents(&git_repo, dir.path(), path, "x\n", path);
+        }
+
+        let mut repo = make_repo(dir.path());
+        repo.projects = vec![
+            RepoProject::parse("billing=services/billing/").unwrap(),
+            RepoProject::parse("tax=services/billing/tax/").unwrap(),
+        ];
+        let store = MockStore::new();
+        Git2Ingester::new()
+            .sync_repo(&repo, &store, &default_config())
+            .expect("sync_repo");
+
+        let files = store.files.lock().unwrap();
+        let project = |path: &str| {
+            files
+                .iter()
+                .find(|f| f.path == path)
+                .unwrap()
+                .project
+                .clone()
+        };
+        assert_eq!(
+            project("services/billing/invoice.rs").as_deref(),
+            Some("billing")
+        );
+        assert_eq!(
+            project("services/billing/tax/vat.rs").as_deref(),
+            Some("tax"),
+            "the longest matching prefix wins"
+        );
+        assert_eq!(project("README.md"), None);
+    }
+
+    #[test]
+    fn test_patch_truncation_recorded_and_full_patch() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let contents: String = (1..=200).map(|i| format!("line {}\n", i)).collect();
+        commit_contents(&git_repo, dir.path(), "big.txt", &contents, "Add big.txt");
+
+        let repo = make_repo(dir.path());
+        let config = IgnoreConfig {
+            max_patch_bytes: 256,
+            ..default_config()
+        };
+        let store = MockStore::new();
+        Git2Ingester::new()
+            .sync_repo(&repo, &store, &config)
+            .expect("sync_repo");
+
+        let patches = store.patches.lock().unwrap();
+        let stored = &patches[0];
+        assert!(stored.truncated);
+        assert!(stored.patch_blob.len() <= 256);
+        assert!(stored.original_size > contents.len() as u64);
+
+        let full = Git2Ingester::new()
+            .patch(&repo, &stored.sha, &config)
+            .expect("patch");
+        assert!(!full.truncated);
+        assert_eq!(full.original_size, Some(stored.original_size));
+        assert_eq!(full.patch_text.len() as u64, stored.original_size);
+        assert!(full.patch_text.ends_with("+line 200\n"));
+        assert!(full
+            .patch_text
+            .starts_with(std::str::from_utf8(&stored.patch_blob).unwrap()));
+    }
+
+    #[test]
+    fn test_submodule_bumps_recorded() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let sig = git2::Signature::now("Test", "test@example.com").expect("sig");
+        let gitmodules = git_repo
+            .blob(b"[submodule \"proto\"]\n\tpath = vendor/proto\n\turl = ../proto.git\n")
+            .expect("blob");
+        let pin = |n: u8| git2::Oid::from_bytes(&[n; 20]).expect("oid");
+        // Pins vendor/proto at `pin`, or removes it.
+        let commit = |pinned: Option<git2::Oid>, message: &str| {
+            let mut vendor = git_repo.treebuilder(None).expect("treebuilder");
+            let mut root = git_repo.treebuilder(None).expect("treebuilder");
+            if let Some(pinned) = pinned {
+                vendor.insert("proto", pinned, 0o160000).expect("gitlink");
+                let vendor = vendor.write().expect("write tree");
+                root.insert("vendor", vendor, 0o040000).expect("tree");
+                root.insert(".gitmodules", gitmodules, 0o100644)
+                    .expect("gitmodules");
+            }
+            let tree = git_repo
+                .find_tree(root.write().expect("write tree"))
+                .expect("find tree");
+            let parent = git_repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            git_repo
+                .commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                .expect("commit");
+        };
+        commit(Some(pin(1)), "Add proto");
+        commit(Some(pin(2)), "Bump proto");
+        commit(None, "Drop proto");
+
+        let repo = Repo {
+            remote_url: Some("git@github.com:acme/app.git".into()),
+            ..make_repo(dir.path())
+        };
+        let store = MockStore::new();
+        Git2Ingester::new()
+            .sync_repo(&repo, &store, &default_config())
+            .expect("sync_repo");
+
+        let commits = store.commits.lock().unwrap();
+        let submodules = store.submodules.lock().unwrap();
+        let bump = |subject: &str| {
+            let sha = &commits.iter().find(|c| c.subject == subject).unwrap().sha;
+            let s = submodules.iter().find(|s| &s.sha == sha).unwrap();
+            (s.old_sha.clone(), s.new_sha.clone())
+        };
+        let sha = |n: u8| Some(pin(n).to_string());
+        assert_eq!(bump("Add proto"), (None, sha(1)));
+        assert_eq!(bump("Bump proto"), (sha(1), sha(2)));
+        assert_eq!(bump("Drop proto"), (sha(2), None));
+        assert!(submodules.iter().all(|s| s.path == "vendor/proto"
+            && s.url.as_deref() == Some("git@github.com:acme/proto.git")));
+
+        let files = store.files.lock().unwrap();
+        assert!(files
+            .iter()
+            .filter(|f| f.path == "vendor/proto")
+            .all(|f| f.kind == FileKind::Submodule));
+    }
+
+    #[test]
+    fn test_symbols_recorded_per_language() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let rust = "struct Parser;\n\nimpl Parser {\n    fn new() -> Self {\n        Parser\n    }\n\n    fn parse(&self) -> u32 {\n        1\n    }\n}\n";
+        commit_contents(&git_repo, dir.path(), "lib.rs", rust, "Add parser");
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "cache.py",
+            "class Cache:\n    def get(self):\n        pass\n\ndef helper():\n    pass\n",
+            "Add cache",
+        );
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "server.go",
+            "package p\n\ntype Server struct{}\n\nfunc (s *Server) Serve() {}\n",
+            "Add server",
+        );
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "api.ts",
+            "export class Api {\n  fetch() {}\n}\nconst handler = () => 1;\n",
+            "Add api",
+        );
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "lib.rs",
+            &rust.replace("        1\n", "        2\n"),
+            "Bump parse result",
+        );
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "cache.py",
+            "class Cache:\n    def get(self):\n        pass\n",
+            "Drop helper",
+        );
+        std::fs::remove_file(dir.path().join("server.go")).expect("remove file");
+        git_repo
+            .index()
+            .and_then(|mut index| {
+                index.remove_path(std::path::Path::new("server.go"))?;
+                index.write()
+            })
+            .expect("remove from index");
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "net/server.go",
+            "package p\n\ntype Server struct{}\n\nfunc (s *Server) Serve() {}\n",
+            "Move server",
+        );
+
+        let store = MockStore::new();
+        Git2Ingester::new()
+            .sync_repo(&make_repo(dir.path()), &store, &default_config())
+            .expect("sync_repo");
+
+        let commits = store.commits.lock().unwrap();
+        let symbols = store.symbols.lock().unwrap();
+        let touched = |subject: &str| -> Vec<(String, String)> {
+            let sha = &commits.iter().find(|c| c.subject == subject).unwrap().sha;
+            symbols
+                .iter()
+                .filter(|s| &s.sha == sha)
+                .map(|s| (s.qualified.clone(), s.kind.clone()))
+                .collect()
+        };
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+
+        assert_eq!(
+            touched("Add parser"),
+            vec![
+                pair("Parser", "struct"),
+                pair("Parser::new", "method"),
+                pair("Parser::parse", "method")
+            ]
+        );
+        assert_eq!(
+            touched("Add server"),
+            vec![pair("Server", "struct"), pair("Server.Serve", "method")]
+        );
+        assert_eq!(
+            touched("Add api"),
+            vec![
+                pair("Api", "class"),
+                pair("Api.fetch", "method"),
+                pair("handler", "function")
+            ]
+        );
+        // Only the definition whose lines changed is recorded, and removed
+        // definitions are found on the old side of the diff.
+        assert_eq!(
+            touched("Bump parse result"),
+            vec![pair("Parser::parse", "method")]
+        );
+        assert_eq!(touched("Drop helper"), vec![pair("helper", "function")]);
+        // A rename without changed lines touches no definitions.
+        assert_eq!(touched("Move server"), vec![]);
+
+        let parse = symbols
+            .iter()
+            .find(|s| s.qualified == "Parser::parse" && s.excerpt.contains("+        2"))
+            .expect("parse change");
+        assert_eq!(parse.name, "parse");
+        assert_eq!(parse.path, "lib.rs");
+        assert!(parse.excerpt.starts_with("@@ "), "{}", parse.excerpt);
+        assert!(parse.excerpt.contains("-        1\n"));
+        // Context outside the definition is left out.
+        assert!(!parse.excerpt.contains("Parser\n"), "{}", parse.excerpt);
+    }
+
+    #[test]
+    fn test_blame_line_range() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let c1 = commit_contents(&git_repo, dir.path(), "f.rs", "one\ntwo\nthree\n", "Add f");
+        let c2 = commit_contents(&git_repo, dir.path(), "f.rs", "one\nTWO\nthree\n", "Shout");
+
+        let repo = make_repo(dir.path());
+        let ingester = Git2Ingester::new();
+        let opts = BlameOpts {
+            repo: repo.name.clone(),
+            path: "f.rs".into(),
+            ..Default::default()
+        };
+        let blame = ingester.blame(&repo, &opts).expect("blame");
+        assert_eq!(blame.rev_sha, c2.to_string());
+        assert_eq!((blame.start_line, blame.end_line), (1, 3));
+        let hunks: Vec<(usize, usize, String)> = blame
+            .hunks
+            .iter()
+            .map(|h| (h.start_line, h.end_line, h.sha.clone()))
+            .collect();
+        assert_eq!(
+            hunks,
+            vec![
+                (1, 1, c1.to_string()),
+                (2, 2, c2.to_string()),
+                (3, 3, c1.to_string())
+            ]
+        );
+        assert_eq!(blame.hunks[1].lines, vec!["TWO"]);
+
+        let blame = ingester
+            .blame(
+                &repo,
+                &BlameOpts {
+                    start_line: Some(2),
+                    end_line: Some(2),
+                    rev: Some(c1.to_string()),
+                    ..opts.clone()
+                },
+            )
+            .expect("blame at c1");
+        assert_eq!(blame.hunks.len(), 1);
+        assert_eq!(blame.hunks[0].sha, c1.to_string());
+        assert_eq!(blame.hunks[0].lines, vec!["two"]);
+
+        let err = ingester
+            .blame(
+                &repo,
+                &BlameOpts {
+                    start_line: Some(5),
+                    ..opts.clone()
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, commitmux_types::CommitmuxError::Config(_)));
+        let err = ingester
+            .blame(
+                &repo,
+                &BlameOpts {
+                    path: "missing.rs".into(),
+                    ..opts
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, commitmux_types::CommitmuxError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_read_file_line_and_byte_ranges() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let c1 = commit_contents(&git_repo, dir.path(), "f.txt", "one\ntwo\nthree\n", "Add f");
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "f.txt",
+            "one\nTWO\nthree\nfour\n",
+            "Grow",
+        );
+
+        let repo = make_repo(dir.path());
+        let ingester = Git2Ingester::new();
+        let opts = FileOpts {
+            repo: repo.name.clone(),
+            path: "f.txt".into(),
+            rev: c1.to_string(),
+            ..Default::default()
+        };
+        let file = ingester.read_file(&repo, &opts).expect("read at c1");
+        assert_eq!(file.content.as_deref(), Some("one\ntwo\nthree\n"));
+        assert_eq!((file.size, file.total_lines), (14, 3));
+        assert!(!file.truncated);
+
+        // Whole lines only, up to max_bytes.
+        let file = ingester
+            .read_file(
+                &repo,
+                &FileOpts {
+                    rev: "HEAD".into(),
+                    start_line: Some(2),
+                    max_bytes: Some(10),
+                    ..opts.clone()
+                },
+            )
+            .expect("read lines");
+        assert_eq!(file.content.as_deref(), Some("TWO\nthree\n"));
+        assert_eq!((file.start_line, file.end_line), (Some(2), Some(3)));
+        assert_eq!(file.offset, 4);
+        assert!(file.truncated);
+
+        let file = ingester
+            .read_file(
+                &repo,
+                &FileOpts {
+                    rev: "HEAD".into(),
+                    offset: Some(8),
+                    max_bytes: Some(5),
+                    ..opts.clone()
+                },
+            )
+            .expect("read bytes");
+        assert_eq!(file.content.as_deref(), Some("three"));
+        assert!(file.truncated);
+
+        let err = ingester
+            .read_file(
+                &repo,
+                &FileOpts {
+                    start_line: Some(1),
+                    offset: Some(0),
+                    ..opts.clone()
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, commitmux_types::CommitmuxError::Config(_)));
+        let err = ingester
+            .read_file(
+                &repo,
+                &FileOpts {
+                    path: "missing.txt".into(),
+                    ..opts.clone()
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, commitmux_types::CommitmuxError::NotFound(_)));
+
+        let gone = make_repo(&dir.path().join("moved"));
+        let err = ingester.read_file(&gone, &opts).unwrap_err();
+        assert!(err.to_string().contains("no longer exists"), "{err}");
+    }
+
+    #[test]
+    fn test_diff_between_revisions() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let c1 = commit_contents(&git_repo, dir.path(), "src/a.rs", "one\ntwo\n", "Add a");
+        commit_contents(&git_repo, dir.path(), "docs/x.md", "x\n", "Add docs");
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "node_modules/dep.js",
+            "dep\n",
+            "Vendor",
+        );
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "src/a.rs",
+            "one\nTWO\nthree\n",
+            "Edit a",
+        );
+
+        let repo = make_repo(dir.path());
+        let ingester = Git2Ingester::new();
+        let opts = DiffOpts {
+            repo: repo.name.clone(),
+            from: c1.to_string(),
+            to: "HEAD".into(),
+            ..Default::default()
+        };
+        let diff = ingester
+            .diff(&repo, &opts, &default_config())
+            .expect("diff");
+        let files: Vec<(&str, &str, usize, usize)> = diff
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.status.as_str(), f.additions, f.deletions))
+            .collect();
+        // node_modules/ is ignored by the test config.
+        assert_eq!(
+            files,
+            vec![("docs/x.md", "A", 1, 0), ("src/a.rs", "M", 2, 1)]
+        );
+        assert_eq!((diff.additions, diff.deletions), (3, 1));
+        let patch_text = diff.patch_text.expect("patch text");
+        assert!(patch_text.contains("-two\n+TWO\n+three\n"), "{patch_text}");
+        assert!(!diff.truncated);
+
+        let diff = ingester
+            .diff(
+                &repo,
+                &DiffOpts {
+                    paths: vec!["src/".into()],
+                    max_bytes: Some(20),
+                    ..opts.clone()
+                },
+                &default_config(),
+            )
+            .expect("filtered diff");
+        assert_eq!(diff.files.len(), 1);
+        assert_eq!(diff.patch_text.as_deref().map(str::len), Some(20));
+        assert!(diff.truncated);
+
+        let diff = ingester
+            .diff(
+                &repo,
+                &DiffOpts {
+                    stat_only: true,
+                    ..opts
+                },
+                &default_config(),
+            )
+            .expect("stat diff");
+        assert_eq!(diff.files.len(), 2);
+        assert!(diff.patch_text.is_none());
+    }
+
+    #[test]
+    fn test_list_tree_depth_and_sizes() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        commit_contents(&git_repo, dir.path(), "README.md", "hello\n", "Readme");
+        commit_contents(
+            &git_repo,
+            dir.path(),
+            "src/a/deep.rs",
+            "fn x() {}\n",
+            "Deep",
+        );
+        commit_contents(&git_repo, dir.path(), "src/lib.rs", "mod a;\n", "Lib");
+
+        let repo = make_repo(dir.path());
+        let ingester = Git2Ingester::new();
+        let opts = TreeOpts {
+            repo: repo.name.clone(),
+            rev: "HEAD".into(),
+            ..Default::default()
+        };
+        let listing = ingester.list_tree(&repo, &opts).expect("list root");
+        let entries: Vec<(&str, &str, &str, Option<u64>)> = listing
+            .entries
+            .iter()
+            .map(|e| (e.path.as_str(), e.kind.as_str(), e.mode.as_str(), e.size))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("README.md", "blob", "100644", Some(6)),
+                ("src", "tree", "040000", None),
+            ]
+        );
+
+        let listing = ingester
+            .list_tree(
+                &repo,
+                &TreeOpts {
+                    path: Some("src/".into()),
+                    depth: Some(2),
+                    ..opts.clone()
+                },
+            )
+            .expect("list src");
+        assert_eq!(listing.path, "src");
+        let paths: Vec<&str> = listing.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["src/a", "src/a/deep.rs", "src/lib.rs"]);
+
+        let err = ingester
+            .list_tree(
+                &repo,
+                &TreeOpts {
+                    path: Some("README.md".into()),
+                    ..opts.clone()
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, commitmux_types::CommitmuxError::Config(_)));
+        let err = ingester
+            .list_tree(
+                &repo,
+                &TreeOpts {
+                    path: Some("docs".into()),
+                    ..opts
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, commitmux_types::CommitmuxError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_conventional_commit_parsed() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        commit_file(
+            &git_repo,
+            dir.path(),
+            "a.txt",
+            "feat(api): drop v1 endpoints\n\nBREAKING CHANGE: clients must use /v2\n",
+        );
+        commit_file(&git_repo, dir.path(), "b.txt", "Update readme");
+
+        let store = MockStore::new();
+        Git2Ingester::new()
+            .sync_repo(&make_repo(dir.path()), &store, &default_config())
+            .expect("sync_repo");
+
+        let commits = store.commits.lock().unwrap();
+        let cc = commits[0].conventional.as_ref().expect("conventional");
+        assert_eq!(cc.cc_type, "feat");
+        assert_eq!(cc.scope.as_deref(), Some("api"));
+        assert!(cc.breaking);
+        assert_eq!(cc.description, "drop v1 endpoints");
+        assert!(commits[1].conventional.is_none());
+    }
+
+    #[test]
+    fn test_refs_extracted_with_origin_project() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        git_repo
+            .remote("origin", "git@github.com:Acme/API.git")
+            .expect("add origin");
+        commit_file(&git_repo, dir.path(), "a.txt", "Fix race (#1234)");
+        commit_file(
+            &git_repo,
+            dir.path(),
+            "b.txt",
+            "PROJ-881: port acme/web#7, see https://github.com/acme/cli/pull/9",
+        );
+
+        let store = MockStore::new();
+        let mut repo = make_repo(dir.path());
+        repo.ref_patterns = vec![RefPattern::Github, RefPattern::Jira(vec!["PROJ".into()])];
+        Git2Ingester::new()
+            .sync_repo(&repo, &store, &default_config())
+            .expect("sync_repo");
+
+        let commits = store.commits.lock().unwrap();
+        let refs =
+            |i: usize| -> Vec<String> { commits[i].refs.iter().map(|r| r.to_string()).collect() };
+        assert_eq!(refs(0), vec!["acme/api#1234"]);
+        assert_eq!(refs(1), vec!["acme/cli#9", "PROJ-881", "acme/web#7"]);
+    }
+
+    #[test]
+    fn test_ref_extractor_patterns() {
+        let defaults = RefExtractor::compile(&[], None).expect("defaults");
+        let found = |e: &RefExtractor, msg: &str| -> Vec<String> {
+            e.extract(msg).iter().map(|r| r.to_string()).collect()
+        };
+        assert_eq!(
+            found(
+                &defaults,
+                "Merge !12 and #3 (again #3); not C#4, &#39; or a/b/#5"
+            ),
+            vec!["!12", "#3"]
+        );
+        assert!(found(&defaults, "UTF-8 OPS-2").is_empty(), "Jira is opt-in");
+
+        let jira =
+            RefExtractor::compile(&[RefPattern::Jira(vec!["OPS".into()])], None).expect("jira");
+        assert_eq!(found(&jira, "PROJ-1 OPS-2 #3"), vec!["OPS-2"]);
+        assert!(
+            found(&jira, "UTF-8, SHA-256, ISO-8601 and CVE-2024-1234").is_empty(),
+            "only the configured project keys are Jira keys"
+        );
+        assert!(RefExtractor::compile(&[RefPattern::Jira(vec![])], None).is_err());
+
+        let custom = RefExtractor::compile(&[RefPattern::Regex(r"ticket (\d+)".into())], None)
+            .expect("custom");
+        assert_eq!(found(&custom, "closes ticket 42"), vec!["42"]);
+        assert!(RefExtractor::compile(&[RefPattern::Regex("(".into())], None).is_err());
+    }
+
+    #[test]
+    fn test_author_filter_invalid_regex_errors() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        commit_file(&git_repo, dir.path(), "a.txt", "A");
+
+        let mut repo = make_repo(dir.path());
+        repo.author_filters = vec![AuthorMatcher::Regex("(unclosed".into())];
+        let result = Git2Ingester::new().sync_repo(&repo, &MockStore::new(), &default_config());
+        assert!(result.is_err(), "invalid regex should fail the sync");
+    }
+
+    #[test]
+    fn test_include_paths_filter() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        commit_file(&git_repo, dir.path(), "services/api/main.rs", "api");
+        commit_file(&git_repo, dir.path(), "services/web/index.ts", "web");
+        commit_file(&git_repo, dir.path(), "README.md", "docs");
+
+        let store = MockStore::new();
+        let mut repo = make_repo(dir.path());
+        repo.include_paths = vec!["services/api/".into()];
+
+        let summary = Git2Ingester::new()
+            .sync_repo(&repo, &store, &default_config())
+            .expect("sync_repo");
+
+        assert_eq!(summary.commits_indexed, 1);
+        assert_eq!(summary.commits_filtered, 2);
+        let files = store.files.lock().unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["services/api/main.rs"]);
+    }
+
+    #[test]
+    fn test_cancel_saves_checkpoint_and_resumes() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let first = commit_file(&git_repo, dir.path(), "a.txt", "A");
+        commit_file(&git_repo, dir.path(), "b.txt", "B");
+        commit_file(&git_repo, dir.path(), "c.txt", "C");
+
+        let store = MockStore::new();
+        let repo = make_repo(dir.path());
+        let config = default_config();
+
+        // The first progress report arrives before the first commit is
+        // indexed; cancelling there stops the walk after exactly one commit.
+        let cancel = AtomicBool::new(false);
+        let on_progress = |_: &commitmux_types::SyncProgress| cancel.store(true, Ordering::Relaxed);
+        let control = commitmux_types::SyncControl {
+            on_progress: Some(&on_progress),
+            cancel: Some(&cancel),
+            full: false,
+        };
+        let summary = Git2Ingester::new()
+            .sync_repo_with(&repo, &store, &config, &control)
+            .expect("cancelled sync");
+        assert!(summary.cancelled, "sync should report cancellation");
+        assert_eq!(summary.commits_indexed, 1);
+
+        let state = store.ingest_state.lock().unwrap().clone().expect("state");
+        assert_eq!(state.checkpoint_sha, Some(first.to_string()));
+        assert!(
+            state.last_synced_sha.is_none(),
+            "an interrupted sync must not claim the tip"
+        );
+
+        // Resuming hides the checkpoint, so the first commit is not re-walked.
+        let summary = Git2Ingester::new()
+            .sync_repo(&repo, &store, &config)
+            .expect("resumed sync");
+        assert!(!summary.cancelled);
+        assert_eq!(summary.commits_indexed, 2);
+        assert_eq!(summary.commits_already_indexed, 0);
+
+        let state = store.ingest_state.lock().unwrap().clone().expect("state");
+        assert!(
+            state.checkpoint_sha.is_none(),
+            "checkpoint cleared on completion"
+        );
+        assert!(state.last_synced_sha.is_some());
+    }
+
+    #[test]
+    fn test_full_sync_rewrites_indexed_commits() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        commit_file(&git_repo, dir.path(), "a.txt", "A");
+        commit_file(&git_repo, dir.path(), "b.txt", "B");
+
+        let store = MockStore::new();
+        let repo = make_repo(dir.path());
+        let config = default_config();
+        let ingester = Git2Ingester::new();
+        ingester.sync_repo(&repo, &store, &config).expect("sync");
+        let summary = ingester.sync_repo(&repo, &store, &config).expect("resync");
+        assert_eq!(summary.commits_indexed, 0);
+
+        // Every commit is prepared and written again, not skipped as indexed.
+        let full = commitmux_types::SyncControl {
+            full: true,
+            ..Default::default()
+        };
+        let summary = ingester
+            .sync_repo_with(&repo, &store, &config, &full)
+            .expect("full sync");
+        assert_eq!(summary.commits_indexed, 2);
+        assert_eq!(summary.commits_already_indexed, 0);
+        assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+
+        // Later syncs are incremental again.
+        let summary = ingester.sync_repo(&repo, &store, &config).expect("resync");
+        assert_eq!(summary.commits_indexed, 0);
+    }
+
+    #[test]
+    fn test_sync_reports_final_progress() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        commit_file(&git_repo, dir.path(), "a.txt", "A");
+        commit_file(&git_repo, dir.path(), "b.txt", "B");
+
+        let store = MockStore::new();
+        let repo = make_repo(dir.path());
+        let reports = Mutex::new(Vec::new());
+        let on_progress =
+            |p: &commitmux_types::SyncProgress| reports.lock().unwrap().push(p.clone());
+        let control = commitmux_types::SyncControl {
+            on_progress: Some(&on_progress),
+            cancel: None,
+            full: false,
+        };
+        Git2Ingester::new()
+            .sync_repo_with(&repo, &store, &default_config(), &control)
+            .expect("sync");
+
+        let reports = reports.lock().unwrap();
+        let last = reports.last().expect("at least one report");
+        assert_eq!(last.commits_total, 2);
+        assert_eq!(last.commits_walked, 2);
+        assert_eq!(last.commits_indexed, 2);
+        assert_eq!(last.eta_secs, Some(0));
+    }
+
+    #[test]
+    fn test_rewritten_history_falls_back_to_full_walk() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let a = commit_file(&git_repo, dir.path(), "a.txt", "A");
+        commit_file(&git_repo, dir.path(), "b.txt", "B");
+
+        let store = MockStore::new();
+        let repo = make_repo(dir.path());
+        let config = default_config();
+        Git2Ingester::new()
+            .sync_repo(&repo, &store, &config)
+            .expect("first sync");
+
+        // "Force-push": move the branch back to A and commit something else.
+        let a_commit = git_repo.find_commit(a).expect("find A");
+        git_repo
+            .reset(a_commit.as_object(), git2::ResetType::Hard, None)
+            .expect("reset");
+        commit_file(&git_repo, dir.path(), "c.txt", "C");
+
+        let summary = Git2Ingester::new()
+            .sync_repo(&repo, &store, &config)
+            .expect("second sync");
+        assert_eq!(summary.commits_indexed, 1, "only the new commit is indexed");
+        assert_eq!(summary.commits_already_indexed, 1, "A found via full walk");
+        assert!(
+            summary
+                .errors
+                .iter()
+                .any(|e| e.contains("no longer an ancestor")),
+            "expected a rewrite warning: {:?}",
+            summary.errors
+        );
+    }
+
+    #[test]
+    fn test_earlier_indexed_tip_is_hidden() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("git init");
+        let a = commit_file(&git_repo, dir.path(), "a.txt", "A");
+        commit_file(&git_repo, dir.path(), "b.txt", "B on main");
+        let main_branch = git_repo
+            .head()
+            .expect("head")
+            .shorthand()
+            .expect("branch name")
+            .to_string();
+
+        // A second branch from A with its own commit.
+        let a_commit = git_repo.find_commit(a).expect("find A");
+        git_repo
+            .branch("dev", &a_commit, false)
+            .expect("branch dev");
+        git_repo.set_head("refs/heads/dev").expect("checkout dev");
+        git_repo
+            .checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .expect("checkout");
+        commit_file(&git_repo, dir.path(), "d.txt", "D on dev");
+
+        let store = MockStore::new();
+        let mut repo = make_repo(dir.path());
+        let config = default_config();
+
+        repo.default_branch = Some(main_branch.clone());
+        Git2Ingester::new()
+            .sync_repo(&repo, &store, &config)
+            .expect("sync main");
+        repo.default_branch = Some("dev".into());
+        Git2Ingester::new()
+            .sync_repo(&repo, &store, &config)
+            .expect("sync dev");
+
+        // Back on main with one new commit: the dev tip is not an ancestor,
+        // but the remembered main tip is, so no full walk is needed.
+        git_repo
+            .set_head(&format!("refs/heads/{}", main_branch))
+            .expect("checkout main");
+        git_repo
+            .checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .expect("checkout");
+        commit_file(&git_repo, dir.path(), "e.txt", "E on main");
+        repo.default_branch = Some(main_branch);
+        let summary = Git2Ingester::new()
+            .sync_repo(&repo, &store, &config)
+            .expect("sync main again");
+        assert_eq!(summary.commits_indexed, 1);
+        assert!(summary.errors.is_empty(), "errors: {:?}", summary.errors);
+
+        let state = store.ingest_state.lock().unwrap().clone().expect("state");
+        assert_eq!(state.indexed_tips.len(), 3);
+    }
+
+    #[test]
+    fn test_structured_patch_parse() {
+        use commitmux_types::{CommitmuxError, PatchLine, PatchLineKind};
+
+        let patch = PatchResult {
+            repo: "r".into(),
+            sha: "abc".into(),
+            patch_text: [
+                "diff --git a/src/lib.rs b/src/lib.rs",
+                "index 1111111..2222222 100644",
+                "--- a/src/lib.rs",
+                "+++ b/src/lib.rs",
+                "@@ -1,3 +1,3 @@ fn main() {",
+                " one",
+                "-two",
+                "+TWO",
+                " three",
+                "@@ -10 +10,2 @@",
+                " ten",
+                "+eleven",
+                "diff --git a/old.md b/docs/new.md",
+                "similarity index 90%",
+                "rename from old.md",
+                "rename to docs/new.md",
+                "@@ -1 +1 @@",
+                "-x",
+                "+y",
+                "\\ No newline at end of file",
+                "",
+            ]
+            .join("\n"),
+            truncated: false,
+            stored_truncated: false,
+            original_size: None,
+        };
+
+        let parsed = structured_patch(&patch, &[], None).expect("parse");
+        assert!(!parsed.truncated);
+        assert_eq!(parsed.files.len(), 2);
+        let lib = &parsed.files[0];
+        assert_eq!(
+            (lib.path.as_str(), lib.status.as_str()),
+            ("src/lib.rs", "M")
+        );
+        assert_eq!(lib.hunks.len(), 2);
+        assert_eq!(
+            lib.hunks[0].lines[1],
+            PatchLine {
+                kind: PatchLineKind::Delete,
+                old_line: Some(2),
+                new_line: None,
+                text: "two".into(),
+            }
+        );
+        assert_eq!(lib.hunks[0].lines[3].old_line, Some(3));
+        assert_eq!(lib.hunks[1].lines[1].new_line, Some(11));
+        let renamed = &parsed.files[1];
+        assert_eq!(renamed.status, "R");
+        assert_eq!(renamed.old_path.as_deref(), Some("old.md"));
+
+        // A path filter matches either side of a rename.
+        let parsed = structured_patch(&patch, &["old.md".into()], None).expect("parse");
+        assert_eq!(parsed.files.len(), 1);
+        assert_eq!(parsed.files[0].path, "docs/new.md");
+
+        // The budget is per file and whole hunks only.
+        let parsed = structured_patch(&patch, &["src/".into()], Some(60)).expect("parse");
+        assert!(parsed.truncated);
+        assert_eq!(parsed.files[0].hunks.len(), 1);
+        assert_eq!(parsed.files[0].omitted_hunks, 1);
+
+        // Old patches were stored without `+`/`-` markers.
+        let legacy = PatchResult {
+            patch_text: "diff --git a/f b/f\n@@ -1,2 +1,2 @@\nold\nnew\nsame\n".into(),
+            ..patch
+        };
+        assert!(matches!(
+            structured_patch(&legacy, &[], None),
+            Err(CommitmuxError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_interrupted_shallow_fetch_repaired() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let upstream = git2::Repository::init(dir.path().join("upstream")).expect("init");
+        commit_file(&upstream, &dir.path().join("upstream"), "a.txt", "upstream");
+        let clone_dir = dir.path().join("clone");
+        let git_repo = git2::Repository::init(&clone_dir).expect("init");
+        git_repo
+            .remote("origin", dir.path().join("upstream").to_str().unwrap())
+            .expect("remote");
+
+        // A boundary commit whose parent was never fetched, and a child of it.
+        let tree = git_repo.treebuilder(None).unwrap().write().unwrap();
+        let raw = format!(
+            "tree {}\nparent {}\nauthor Test <test@example.com> 0 +0000\n\
+             committer Test <test@example.com> 0 +0000\n\nboundary\n",
+            tree,
+            "1".repeat(40)
+        );
+        let boundary = git_repo
+            .odb()
+            .unwrap()
+            .write(git2::ObjectType::Commit, raw.as_bytes())
+            .unwrap();
+        let boundary_commit = git_repo.find_commit(boundary).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        git_repo
+            .commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                "tip",
+                &git_repo.find_tree(tree).unwrap(),
+                &[&boundary_commit],
+            )
+            .unwrap();
+        let git_dir = git_repo.path().to_path_buf();
+        std::fs::write(git_dir.join("shallow"), format!("{}\n", boundary)).unwrap();
+        let roots = std::collections::HashSet::from([boundary]);
+        assert_eq!(clone::shallow_roots(&git_repo), roots);
+
+        // A fetch that died after libgit2 dropped the boundary, before it
+        // was restored, leaves the clone looking complete...
+        std::fs::write(git_dir.join("commitmux-shallow"), format!("{}\n", boundary)).unwrap();
+        std::fs::remove_file(git_dir.join("shallow")).unwrap();
+        assert!(clone::shallow_roots(&git_repo).is_empty());
+
+        // ...until a fetch blocked by a concurrent writer's lock, which
+        // leaves the journal in place...
+        let auth = commitmux_types::RemoteAuth::default();
+        std::fs::write(git_dir.join("shallow.lock"), "").unwrap();
+        assert!(clone::fetch_remote(&git_repo, "origin", None, None, &auth).is_err());
+        assert!(git_dir.join("commitmux-shallow").exists());
+
+        // ...and the next one restores it from the journal.
+        std::fs::remove_file(git_dir.join("shallow.lock")).unwrap();
+        clone::fetch_remote(&git_repo, "origin", None, None, &auth).expect("fetch");
+        assert_eq!(clone::shallow_roots(&git_repo), roots);
+        assert!(!git_dir.join("commitmux-shallow").exists());
+        assert!(!git_dir.join("shallow.lock").exists());
+    }
+
+    #[test]
+    fn test_shallow_walk_stops_at_hidden_history() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let git_repo = git2::Repository::init(dir.path()).expect("init");
+        let tree = git_repo.treebuilder(None).unwrap().write().unwrap();
+        let tree = git_repo.find_tree(tree).unwrap();
+        let commit = |message: &str, time: i64, parents: &[git2::Oid]| {
+            let sig = git2::Signature::new("Test", "test@example.com", &git2::Time::new(time, 0))
+                .unwrap();
+            let parents: Vec<git2::Commit> = parents
+                .iter()
+                .map(|p| git_repo.find_commit(*p).unwrap())
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            git_repo
+                .commit(None, &sig, &sig, message, &tree, &parents)
+                .unwrap()
+        };
+        let a = commit("a", 10, &[]);
+        let b = commit("b", 20, &[a]);
+        let c = commit("c", 30, &[b]);
+        let d = commit("d", 40, &[c]);
+        // A side branch off indexed history, with a clock older than all of it
+        let side = commit("side", 5, &[b]);
+        let merge = commit("merge", 60, &[d, side]);
+
+        let walked = clone::shallow_walk(&git_repo, merge, &[c]).expect("walk");
+        assert_eq!(walked.len(), 3);
+        let position = |oid| walked.iter().position(|o| *o == oid).unwrap();
+        assert!(position(d) < position(merge));
+        assert!(position(side) < position(merge));
+
+        assert_eq!(
+            clone::shallow_walk(&git_repo, d, &[]).expect("walk"),
+            vec![a, b, c, d]
+        );
+        assert!(clone::shallow_walk(&git_repo, c, &[d])
+            .expect("walk")
+            .is_empty());
+    }
 }
@@ -1,18 +1,152 @@
-use commitmux_types::{CommitFile, CommitmuxError, FileStatus, IgnoreConfig, Result};
+use commitmux_types::{
+    CommitFile, CommitmuxError, DiffFileStat, DiffOpts, DiffResult, FileKind, FileStatus,
+    IgnoreConfig, LfsPointer, PatchResult, Repo, RepoProject, Result,
+};
 
-fn is_ignored(path: &str, config: &IgnoreConfig) -> bool {
+use crate::walker::resolve_rev;
+
+/// First line of a Git LFS pointer file.
+const LFS_POINTER_PREFIX: &[u8] = b"version https://git-lfs.github.com/spec/v1\n";
+/// LFS pointers are around 130 bytes; larger blobs are never pointers.
+const MAX_LFS_POINTER_BYTES: usize = 1024;
+
+pub(crate) fn is_ignored(path: &str, config: &IgnoreConfig) -> bool {
+    if !config.include_prefixes.is_empty()
+        && !config
+            .include_prefixes
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()))
+    {
+        return true;
+    }
     config
         .path_prefixes
         .iter()
         .any(|prefix| path.starts_with(prefix.as_str()))
 }
 
-pub fn get_commit_files(
-    repo: &git2::Repository,
+fn file_status(status: git2::Delta) -> FileStatus {
+    match status {
+        git2::Delta::Added | git2::Delta::Untracked => FileStatus::Added,
+        git2::Delta::Modified | git2::Delta::Typechange => FileStatus::Modified,
+        git2::Delta::Deleted => FileStatus::Deleted,
+        git2::Delta::Renamed => FileStatus::Renamed,
+        git2::Delta::Copied => FileStatus::Copied,
+        _ => FileStatus::Unknown,
+    }
+}
+
+/// A changed file's kind, blob size and LFS pointer.
+struct BlobInfo {
+    kind: FileKind,
+    size: Option<u64>,
+    lfs: Option<LfsPointer>,
+}
+
+/// Classifies the blob on the side of `delta` that exists (the old one for
+/// deletions). Blobs over `FileKind::MAX_TEXT_BYTES` are classified by size
+/// alone, without being loaded.
+fn blob_info(repo: &git2::Repository, odb: &git2::Odb, delta: &git2::DiffDelta) -> BlobInfo {
+    let file = match delta.status() {
+        git2::Delta::Deleted => delta.old_file(),
+        _ => delta.new_file(),
+    };
+    let mut info = BlobInfo {
+        kind: FileKind::Text,
+        size: None,
+        lfs: None,
+    };
+    if file.mode() == git2::FileMode::Commit {
+        info.kind = FileKind::Submodule;
+        return info;
+    }
+    let Ok((size, git2::ObjectType::Blob)) = odb.read_header(file.id()) else {
+        return info;
+    };
+    info.size = Some(size as u64);
+    if size as u64 > FileKind::MAX_TEXT_BYTES {
+        info.kind = FileKind::Oversized;
+        return info;
+    }
+    let Ok(blob) = repo.find_blob(file.id()) else {
+        return info;
+    };
+    if let Some(lfs) = parse_lfs_pointer(blob.content()) {
+        info.kind = FileKind::Lfs;
+        info.lfs = Some(lfs);
+    } else if blob.is_binary() || file.is_binary() {
+        info.kind = FileKind::Binary;
+    }
+    info
+}
+
+fn parse_lfs_pointer(content: &[u8]) -> Option<LfsPointer> {
+    if content.len() > MAX_LFS_POINTER_BYTES || !content.starts_with(LFS_POINTER_PREFIX) {
+        return None;
+    }
+    let text = std::str::from_utf8(content).ok()?;
+    let mut oid = None;
+    let mut size = None;
+    for line in text.lines() {
+        if let Some(value) = line.strip_prefix("oid ") {
+            oid = Some(value.to_string());
+        } else if let Some(value) = line.strip_prefix("size ") {
+            size = value.parse().ok();
+        }
+    }
+    Some(LfsPointer {
+        oid: oid?,
+        size: size?,
+    })
+}
+
+/// The line stored in place of the hunks of a file that is not diffed.
+fn placeholder(info: &BlobInfo) -> Option<String> {
+    let size = info.size.unwrap_or(0);
+    match (info.kind, &info.lfs) {
+        // Gitlinks keep libgit2's `Subproject commit <sha>` lines.
+        (FileKind::Text | FileKind::Submodule, _) => None,
+        (FileKind::Lfs, Some(lfs)) => Some(format!(
+            "[Git LFS object {}, {} bytes]\n",
+            lfs.oid, lfs.size
+        )),
+        (FileKind::Oversized, _) => {
+            Some(format!("[oversized file, {} bytes; diff omitted]\n", size))
+        }
+        _ => Some(format!("[binary file, {} bytes]\n", size)),
+    }
+}
+
+/// Options shared by every diff: files over `FileKind::MAX_TEXT_BYTES` are
+/// treated as binary so libgit2 does not compute their hunks.
+fn diff_options() -> git2::DiffOptions {
+    let mut opts = git2::DiffOptions::new();
+    opts.max_size(FileKind::MAX_TEXT_BYTES as i64);
+    opts
+}
+
+/// Detects renames and copies in `diff` at `config.rename_threshold`.
+fn find_renames(diff: &mut git2::Diff, config: &IgnoreConfig) -> Result<()> {
+    if config.rename_threshold > 0 {
+        let mut find = git2::DiffFindOptions::new();
+        find.renames(true)
+            .copies(true)
+            .rename_threshold(config.rename_threshold)
+            .copy_threshold(config.rename_threshold);
+        diff.find_similar(Some(&mut find))
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    }
+    Ok(())
+}
+
+/// The diff of a commit against its first parent (or the empty tree), with
+/// renames and copies detected at `config.rename_threshold`. Ingest computes
+/// it once per commit and hands it to every extractor.
+pub(crate) fn commit_diff<'r>(
+    repo: &'r git2::Repository,
     commit: &git2::Commit,
-    repo_id: i64,
     config: &IgnoreConfig,
-) -> Result<Vec<CommitFile>> {
+) -> Result<git2::Diff<'r>> {
     let parent_tree = if commit.parent_count() > 0 {
         Some(
             commit
@@ -29,19 +163,135 @@ pub fn get_commit_files(
         .tree()
         .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
 
-    let diff = repo
-        .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), None)
+    let mut diff = repo
+        .diff_tree_to_tree(
+            parent_tree.as_ref(),
+            Some(&commit_tree),
+            Some(&mut diff_options()),
+        )
         .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
 
-    let sha = commit.id().to_string();
-    let mut files = Vec::new();
+    find_renames(&mut diff, config)?;
+    Ok(diff)
+}
 
-    for delta in diff.deltas() {
-        // Skip binary files (check via new_file or old_file flags)
-        if delta.new_file().is_binary() || delta.old_file().is_binary() {
+/// Diffs the trees of `opts.from` and `opts.to`, leaving out ignored paths.
+/// The patch text is built file by file and stops at `opts.max_bytes`, cut
+/// at a UTF-8 character boundary; line counts cover every file.
+pub fn diff_revs(
+    git_repo: &git2::Repository,
+    repo: &Repo,
+    opts: &DiffOpts,
+    config: &IgnoreConfig,
+) -> Result<DiffResult> {
+    let from_oid = resolve_rev(git_repo, repo, &opts.from)?;
+    let to_oid = resolve_rev(git_repo, repo, &opts.to)?;
+    let tree = |oid| {
+        git_repo
+            .find_commit(oid)
+            .and_then(|commit| commit.tree())
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))
+    };
+    let (from_tree, to_tree) = (tree(from_oid)?, tree(to_oid)?);
+
+    let mut diff_opts = diff_options();
+    for path in &opts.paths {
+        diff_opts.pathspec(path.trim_end_matches('/'));
+    }
+    let mut diff = git_repo
+        .diff_tree_to_tree(Some(&from_tree), Some(&to_tree), Some(&mut diff_opts))
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    find_renames(&mut diff, config)?;
+
+    let mut result = DiffResult {
+        repo: repo.name.clone(),
+        from: opts.from.clone(),
+        to: opts.to.clone(),
+        from_sha: from_oid.to_string(),
+        to_sha: to_oid.to_string(),
+        ..Default::default()
+    };
+    let mut patch_text = String::new();
+    for idx in 0..diff.deltas().len() {
+        let Some(mut patch) = git2::Patch::from_diff(&diff, idx)
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?
+        else {
+            continue;
+        };
+        let delta = patch.delta();
+        let status = delta.status();
+        let path = match status {
+            git2::Delta::Deleted => delta.old_file().path(),
+            _ => delta.new_file().path().or_else(|| delta.old_file().path()),
+        };
+        let Some(path) = path.and_then(|p| p.to_str()).map(str::to_string) else {
+            continue;
+        };
+        if is_ignored(&path, config) {
             continue;
         }
+        let old_path = if matches!(status, git2::Delta::Renamed | git2::Delta::Copied) {
+            delta
+                .old_file()
+                .path()
+                .and_then(|p| p.to_str())
+                .map(str::to_string)
+        } else {
+            None
+        };
+        let binary = delta.flags().is_binary();
+        let (_, additions, deletions) = patch
+            .line_stats()
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+
+        if !opts.stat_only && !result.truncated {
+            let buf = patch
+                .to_buf()
+                .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+            patch_text.push_str(&String::from_utf8_lossy(&buf));
+            if let Some(max) = opts.max_bytes {
+                if patch_text.len() > max {
+                    let mut cut = max;
+                    while !patch_text.is_char_boundary(cut) {
+                        cut -= 1;
+                    }
+                    patch_text.truncate(cut);
+                    result.truncated = true;
+                }
+            }
+        }
+
+        result.additions += additions;
+        result.deletions += deletions;
+        result.files.push(DiffFileStat {
+            path,
+            status: file_status(status).as_str().to_string(),
+            old_path,
+            additions,
+            deletions,
+            binary,
+        });
+    }
+    if !opts.stat_only {
+        result.patch_text = Some(patch_text);
+    }
+    Ok(result)
+}
+
+pub fn get_commit_files(
+    repo: &git2::Repository,
+    commit: &git2::Commit,
+    diff: &git2::Diff,
+    repo_id: i64,
+    config: &IgnoreConfig,
+) -> Result<Vec<CommitFile>> {
+    let sha = commit.id().to_string();
+    let odb = repo
+        .odb()
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    let mut files = Vec::new();
 
+    for delta in diff.deltas() {
         let status = delta.status();
 
         // Determine the primary path (new file path, or old for deletes)
@@ -60,8 +310,8 @@ pub fn get_commit_files(
             continue;
         }
 
-        // Get old_path for renames
-        let old_path = if status == git2::Delta::Renamed {
+        // Get old_path for renames and copies
+        let old_path = if matches!(status, git2::Delta::Renamed | git2::Delta::Copied) {
             delta
                 .old_file()
                 .path()
@@ -71,62 +321,50 @@ pub fn get_commit_files(
             None
         };
 
-        let file_status = match status {
-            git2::Delta::Added | git2::Delta::Untracked => FileStatus::Added,
-            git2::Delta::Modified | git2::Delta::Typechange => FileStatus::Modified,
-            git2::Delta::Deleted => FileStatus::Deleted,
-            git2::Delta::Renamed => FileStatus::Renamed,
-            git2::Delta::Copied => FileStatus::Copied,
-            _ => FileStatus::Unknown,
-        };
-
+        let info = blob_info(repo, &odb, &delta);
+        let project = RepoProject::for_path(&config.projects, &path_str).map(|p| p.name.clone());
         files.push(CommitFile {
             repo_id,
             sha: sha.clone(),
             path: path_str,
-            status: file_status,
+            status: file_status(status),
             old_path,
+            kind: info.kind,
+            size: info.size,
+            lfs: info.lfs,
+            project,
         });
     }
 
     Ok(files)
 }
 
+/// A commit's unified diff, cut at `max_patch_bytes`.
+pub struct PatchText {
+    pub text: String,
+    /// Whether lines were dropped at `max_patch_bytes`.
+    pub truncated: bool,
+    /// Size in bytes of the whole diff.
+    pub original_size: usize,
+}
+
 pub fn get_patch_text(
     repo: &git2::Repository,
-    commit: &git2::Commit,
+    diff: &git2::Diff,
     config: &IgnoreConfig,
-) -> Result<Option<String>> {
-    let parent_tree = if commit.parent_count() > 0 {
-        Some(
-            commit
-                .parent(0)
-                .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?
-                .tree()
-                .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?,
-        )
-    } else {
-        None
-    };
-
-    let commit_tree = commit
-        .tree()
-        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
-
-    let diff = repo
-        .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), None)
+) -> Result<Option<PatchText>> {
+    let odb = repo
+        .odb()
         .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
-
     let max_bytes = config.max_patch_bytes;
     let mut patch_text = String::new();
     let mut truncated = false;
+    let mut original_size = 0;
+    // Binary, LFS and oversized files get one placeholder line after their
+    // file header instead of hunks.
+    let mut file_placeholder: Option<String> = None;
 
     diff.print(git2::DiffFormat::Patch, |delta, _hunk, line| {
-        // Skip binary deltas
-        if delta.new_file().is_binary() || delta.old_file().is_binary() {
-            return true;
-        }
-
         // Check path against ignore config
         let path_ignored = delta
             .new_file()
@@ -140,7 +378,9 @@ pub fn get_patch_text(
             return true;
         }
 
-        if truncated {
+        if line.origin() == 'F' {
+            file_placeholder = placeholder(&blob_info(repo, &odb, &delta));
+        } else if file_placeholder.is_some() {
             return true;
         }
 
@@ -149,19 +389,67 @@ pub fn get_patch_text(
             Err(_) => return true,
         };
 
-        if patch_text.len() + content.len() > max_bytes {
+        // Content lines carry their `+`/`-`/space marker in `origin`.
+        let marker = match line.origin() {
+            origin @ ('+' | '-' | ' ') => Some(origin),
+            _ => None,
+        };
+
+        let extra = match line.origin() {
+            'F' => file_placeholder.as_deref().unwrap_or(""),
+            _ => "",
+        };
+        // Past the cut, lines are still measured for `original_size`.
+        let len = marker.map_or(0, char::len_utf8) + content.len() + extra.len();
+        original_size += len;
+        if truncated || patch_text.len() + len > max_bytes {
             truncated = true;
             return true;
         }
 
+        if let Some(marker) = marker {
+            patch_text.push(marker);
+        }
         patch_text.push_str(content);
+        patch_text.push_str(extra);
         true
     })
     .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
 
-    if patch_text.is_empty() {
+    if original_size == 0 {
         Ok(None)
     } else {
-        Ok(Some(patch_text))
+        Ok(Some(PatchText {
+            text: patch_text,
+            truncated,
+            original_size,
+        }))
     }
 }
+
+/// Regenerates the patch of `sha` without the `max_patch_bytes` cut.
+pub fn full_patch(
+    git_repo: &git2::Repository,
+    repo: &Repo,
+    sha: &str,
+    config: &IgnoreConfig,
+) -> Result<PatchResult> {
+    let oid = resolve_rev(git_repo, repo, sha)?;
+    let commit = git_repo
+        .find_commit(oid)
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+    let config = IgnoreConfig {
+        max_patch_bytes: usize::MAX,
+        ..config.clone()
+    };
+    let diff = commit_diff(git_repo, &commit, &config)?;
+    let text = get_patch_text(git_repo, &diff, &config)?;
+    Ok(PatchResult {
+        repo: repo.name.clone(),
+        sha: oid.to_string(),
+        original_size: Some(text.as_ref().map_or(0, |t| t.original_size as u64)),
+        patch_text: text.map(|t| t.text).unwrap_or_default(),
+        truncated: false,
+        stored_truncated: false,
+    })
+}
@@ -0,0 +1,171 @@
+use commitmux_types::{
+    remote_project, CommitRef, CommitmuxError, RefKind, RefPattern, Repo, Result, Store,
+};
+use regex::Regex;
+
+/// Extracts issue and PR references from commit messages using a repo's
+/// configured [`RefPattern`]s.
+///
+/// Bare `#N` and `!N` references are attributed to the repo's own project
+/// (derived from its remote URL) so that `owner/repo#N` written in another
+/// repo and `#N` written in `owner/repo` resolve to the same reference.
+pub struct RefExtractor {
+    matchers: Vec<Matcher>,
+    project: Option<String>,
+}
+
+enum Matcher {
+    /// `[project]<sep>N`, where the separator is `#` or `!`.
+    Short(Regex, RefKind),
+    /// Web URL; captures the project, the path segment and the number.
+    Url(Regex),
+    Jira(Regex, Vec<String>),
+    Custom(Regex),
+}
+
+// The leading group stands in for a look-behind: a reference must not be
+// glued to a word, a path or an HTML entity.
+const ISSUE_RE: &str = r"(?:^|[^\w/#&!.-])((?:[\w.-]+/)+[\w.-]+)?#(\d+)\b";
+const MR_RE: &str = r"(?:^|[^\w/#&!.-])((?:[\w.-]+/)+[\w.-]+)?!(\d+)\b";
+const GITHUB_URL_RE: &str = r"https?://github\.com/([\w.-]+/[\w.-]+)/(issues|pull)/(\d+)";
+const GITLAB_URL_RE: &str =
+    r"https?://[\w.-]+/((?:[\w.-]+/)+[\w.-]+)/-/(issues|merge_requests)/(\d+)";
+const JIRA_RE: &str = r"\b([A-Z][A-Z0-9]+)-(\d+)\b";
+
+fn build(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern)
+        .map_err(|e| CommitmuxError::Config(format!("invalid ref pattern '{}': {}", pattern, e)))
+}
+
+impl RefExtractor {
+    /// Compiles `patterns` ([`RefPattern::defaults`] when empty). `remote_url`
+    /// is the repo's remote, used to qualify bare references.
+    pub fn compile(patterns: &[RefPattern], remote_url: Option<&str>) -> Result<RefExtractor> {
+        let defaults;
+        let patterns = if patterns.is_empty() {
+            defaults = RefPattern::defaults();
+            &defaults
+        } else {
+            patterns
+        };
+
+        let mut matchers = Vec::new();
+        let mut issues = false;
+        for pattern in patterns {
+            match pattern {
+                RefPattern::Github => {
+                    matchers.push(Matcher::Url(build(GITHUB_URL_RE)?));
+                    issues = true;
+                }
+                RefPattern::Gitlab => {
+                    matchers.push(Matcher::Url(build(GITLAB_URL_RE)?));
+                    matchers.push(Matcher::Short(build(MR_RE)?, RefKind::MergeRequest));
+                    issues = true;
+                }
+                // Any `UPPER-123` token would otherwise count: UTF-8,
+                // SHA-256, ISO-8601, CVE-2024-1234.
+                RefPattern::Jira(keys) if keys.is_empty() => {
+                    return Err(CommitmuxError::Config(
+                        "jira needs project keys, e.g. jira:PROJ,OPS".to_string(),
+                    ))
+                }
+                RefPattern::Jira(keys) => {
+                    matchers.push(Matcher::Jira(build(JIRA_RE)?, keys.clone()));
+                }
+                RefPattern::Regex(re) => matchers.push(Matcher::Custom(build(re)?)),
+            }
+        }
+        // GitHub and GitLab share the `#N` issue syntax.
+        if issues {
+            matchers.push(Matcher::Short(build(ISSUE_RE)?, RefKind::Issue));
+        }
+
+        Ok(RefExtractor {
+            matchers,
+            project: remote_url.and_then(remote_project),
+        })
+    }
+
+    /// The extractor for `repo`'s patterns. Local repos have no stored
+    /// remote URL, so bare references are qualified with their `origin`.
+    pub fn for_repo(repo: &Repo, git_repo: Option<&git2::Repository>) -> Result<RefExtractor> {
+        let origin_url = git_repo
+            .and_then(|g| g.find_remote("origin").ok())
+            .and_then(|r| r.url().map(str::to_string));
+        RefExtractor::compile(
+            &repo.ref_patterns,
+            repo.remote_url.as_deref().or(origin_url.as_deref()),
+        )
+    }
+
+    /// References in `message`, in matcher order and without duplicates.
+    pub fn extract(&self, message: &str) -> Vec<CommitRef> {
+        let mut refs: Vec<CommitRef> = Vec::new();
+        let mut push = |r: CommitRef| {
+            if !refs.contains(&r) {
+                refs.push(r);
+            }
+        };
+        for matcher in &self.matchers {
+            match matcher {
+                Matcher::Short(re, kind) => {
+                    for caps in re.captures_iter(message) {
+                        let project = caps
+                            .get(1)
+                            .map(|m| m.as_str().to_lowercase())
+                            .or_else(|| self.project.clone());
+                        push(CommitRef {
+                            kind: *kind,
+                            project,
+                            id: caps[2].to_string(),
+                        });
+                    }
+                }
+                Matcher::Url(re) => {
+                    for caps in re.captures_iter(message) {
+                        let kind = match &caps[2] {
+                            "pull" | "issues" => RefKind::Issue,
+                            _ => RefKind::MergeRequest,
+                        };
+                        push(CommitRef {
+                            kind,
+                            project: Some(caps[1].to_lowercase()),
+                            id: caps[3].to_string(),
+                        });
+                    }
+                }
+                Matcher::Jira(re, keys) => {
+                    for caps in re.captures_iter(message) {
+                        if keys.iter().any(|k| k == &caps[1]) {
+                            push(CommitRef {
+                                kind: RefKind::Jira,
+                                project: None,
+                                id: caps[0].to_string(),
+                            });
+                        }
+                    }
+                }
+                Matcher::Custom(re) => {
+                    for caps in re.captures_iter(message) {
+                        let m = caps.get(1).unwrap_or_else(|| caps.get(0).unwrap());
+                        push(CommitRef {
+                            kind: RefKind::Custom,
+                            project: None,
+                            id: m.as_str().to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        refs
+    }
+}
+
+/// Re-extracts the references of every commit of `repo` already indexed,
+/// after its ref patterns changed. Returns the number of commits with
+/// references.
+pub fn reextract_refs(repo: &Repo, store: &dyn Store) -> Result<usize> {
+    let git_repo = git2::Repository::open(&repo.local_path).ok();
+    let extractor = RefExtractor::for_repo(repo, git_repo.as_ref())?;
+    store.reextract_refs(repo.repo_id, &|message| extractor.extract(message))
+}
@@ -0,0 +1,180 @@
+use commitmux_types::{
+    CommitmuxError, FileStatus, PatchFile, PatchHunk, PatchLine, PatchLineKind, PatchResult,
+    Result, StructuredPatch,
+};
+
+/// Parses a patch's unified diff text. Only files at or under one of
+/// `paths` are kept (all files if empty). `max_bytes` applies to each
+/// file separately and only whole hunks are returned; hunks that do not
+/// fit are counted in `omitted_hunks`.
+pub fn structured_patch(
+    patch: &PatchResult,
+    paths: &[String],
+    max_bytes: Option<usize>,
+) -> Result<StructuredPatch> {
+    let wanted = |path: &str| {
+        paths.is_empty()
+            || paths.iter().any(|p| {
+                let p = p.trim_end_matches('/');
+                path == p
+                    || path
+                        .strip_prefix(p)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+    };
+
+    let mut files: Vec<PatchFile> = Vec::new();
+    let mut old_line = 0;
+    let mut new_line = 0;
+    let mut in_header = false;
+    for line in patch.patch_text.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let (old, new) = split_git_paths(rest);
+            files.push(PatchFile {
+                path: new.to_string(),
+                status: FileStatus::Modified.as_str().to_string(),
+                old_path: (old != new).then(|| old.to_string()),
+                hunks: Vec::new(),
+                omitted_hunks: 0,
+            });
+            in_header = true;
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if let Some(header) = line.strip_prefix("@@ ") {
+            let (old_start, old_lines, new_start, new_lines) = parse_hunk_range(header)
+                .ok_or_else(|| {
+                    CommitmuxError::Config(format!("malformed hunk header '{}'", line))
+                })?;
+            file.hunks.push(PatchHunk {
+                header: line.to_string(),
+                old_start,
+                old_lines,
+                new_start,
+                new_lines,
+                lines: Vec::new(),
+            });
+            old_line = old_start;
+            new_line = new_start;
+            in_header = false;
+            continue;
+        }
+        if in_header {
+            if line.starts_with("new file mode") {
+                file.status = FileStatus::Added.as_str().to_string();
+            } else if line.starts_with("deleted file mode") {
+                file.status = FileStatus::Deleted.as_str().to_string();
+            } else if line.starts_with("rename from ") {
+                file.status = FileStatus::Renamed.as_str().to_string();
+            } else if line.starts_with("copy from ") {
+                file.status = FileStatus::Copied.as_str().to_string();
+            }
+            continue;
+        }
+        let Some(hunk) = file.hunks.last_mut() else {
+            continue;
+        };
+        let (kind, text) = match line.as_bytes().first() {
+            Some(b'+') => (PatchLineKind::Add, &line[1..]),
+            Some(b'-') => (PatchLineKind::Delete, &line[1..]),
+            Some(b' ') => (PatchLineKind::Context, &line[1..]),
+            // "\ No newline at end of file"
+            Some(b'\\') => continue,
+            _ => (PatchLineKind::Context, line),
+        };
+        let old = (kind != PatchLineKind::Add).then_some(old_line);
+        let new = (kind != PatchLineKind::Delete).then_some(new_line);
+        old_line += old.is_some() as usize;
+        new_line += new.is_some() as usize;
+        hunk.lines.push(PatchLine {
+            kind,
+            old_line: old,
+            new_line: new,
+            text: text.to_string(),
+        });
+    }
+
+    let complete = |hunk: &PatchHunk| {
+        let old = hunk.lines.iter().filter(|l| l.old_line.is_some()).count();
+        let new = hunk.lines.iter().filter(|l| l.new_line.is_some()).count();
+        old == hunk.old_lines && new == hunk.new_lines
+    };
+    // A patch cut at ingest can end partway through its last hunk.
+    if patch.stored_truncated {
+        if let Some(file) = files.last_mut() {
+            if file.hunks.last().is_some_and(|h| !complete(h)) {
+                file.hunks.pop();
+                file.omitted_hunks += 1;
+            }
+        }
+    }
+
+    // Patches stored without `+`/`-` markers cannot be split reliably;
+    // their line counts do not add up to the hunk headers.
+    for hunk in files.iter().flat_map(|f| &f.hunks) {
+        if !complete(hunk) {
+            return Err(CommitmuxError::Config(format!(
+                "patch for {} was indexed without line markers and cannot be structured; \
+                 use the text format, or run `commitmux sync --full` to re-index the repo",
+                patch.sha
+            )));
+        }
+    }
+
+    files.retain(|f| wanted(&f.path) || f.old_path.as_deref().is_some_and(wanted));
+    let mut truncated = patch.stored_truncated;
+    if let Some(max) = max_bytes {
+        for file in &mut files {
+            let mut used = 0;
+            let mut kept = 0;
+            for hunk in &file.hunks {
+                let size = hunk.header.len()
+                    + 1
+                    + hunk.lines.iter().map(|l| l.text.len() + 2).sum::<usize>();
+                if used + size > max {
+                    break;
+                }
+                used += size;
+                kept += 1;
+            }
+            file.omitted_hunks += file.hunks.len() - kept;
+            file.hunks.truncate(kept);
+            truncated |= file.omitted_hunks > 0;
+        }
+    }
+
+    Ok(StructuredPatch {
+        repo: patch.repo.clone(),
+        sha: patch.sha.clone(),
+        files,
+        truncated,
+    })
+}
+
+/// Splits the `a/old b/new` part of a `diff --git` line.
+fn split_git_paths(rest: &str) -> (&str, &str) {
+    match rest.find(" b/") {
+        Some(i) => (
+            rest[..i].strip_prefix("a/").unwrap_or(&rest[..i]),
+            &rest[i + 3..],
+        ),
+        None => (rest, rest),
+    }
+}
+
+/// Parses `-a,b +c,d @@ ...` into `(a, b, c, d)`; a missing count is 1.
+fn parse_hunk_range(header: &str) -> Option<(usize, usize, usize, usize)> {
+    let mut parts = header.split_whitespace();
+    let range = |part: Option<&str>, sign: char| -> Option<(usize, usize)> {
+        let part = part?.strip_prefix(sign)?;
+        match part.split_once(',') {
+            Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+            None => Some((part.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_lines) = range(parts.next(), '-')?;
+    let (new_start, new_lines) = range(parts.next(), '+')?;
+    Some((old_start, old_lines, new_start, new_lines))
+}
@@ -0,0 +1,132 @@
+use commitmux_types::{CommitSubmodule, IgnoreConfig, Repo, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::patch::is_ignored;
+
+/// The gitlinks `commit` changed: submodules added, removed or moved to
+/// another commit.
+pub fn get_commit_submodules(
+    git_repo: &git2::Repository,
+    commit: &git2::Commit,
+    diff: &git2::Diff,
+    repo: &Repo,
+    config: &IgnoreConfig,
+) -> Result<Vec<CommitSubmodule>> {
+    let sha = commit.id().to_string();
+    // `.gitmodules` is only read for commits that change a gitlink.
+    let mut urls: Option<HashMap<String, String>> = None;
+    let mut submodules = Vec::new();
+
+    for delta in diff.deltas() {
+        let pinned = |file: git2::DiffFile| {
+            (file.mode() == git2::FileMode::Commit && !file.id().is_zero())
+                .then(|| file.id().to_string())
+        };
+        let old_sha = pinned(delta.old_file());
+        let new_sha = pinned(delta.new_file());
+        if old_sha.is_none() && new_sha.is_none() {
+            continue;
+        }
+        let Some(path) = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .and_then(|p| p.to_str())
+        else {
+            continue;
+        };
+        if is_ignored(path, config) {
+            continue;
+        }
+
+        let urls = urls.get_or_insert_with(|| gitmodule_urls(git_repo, commit));
+        let url = urls
+            .get(path)
+            .map(|url| resolve_url(url, repo.remote_url.as_deref()));
+        submodules.push(CommitSubmodule {
+            repo_id: repo.repo_id,
+            sha: sha.clone(),
+            path: path.to_string(),
+            url,
+            old_sha,
+            new_sha,
+        });
+    }
+
+    Ok(submodules)
+}
+
+/// Submodule URLs by path, from `.gitmodules` in `commit`, falling back to
+/// its first parent for commits that remove the submodule.
+fn gitmodule_urls(git_repo: &git2::Repository, commit: &git2::Commit) -> HashMap<String, String> {
+    let mut urls = HashMap::new();
+    let trees = std::iter::once(commit.tree().ok()).chain(std::iter::once(
+        commit.parent(0).ok().and_then(|p| p.tree().ok()),
+    ));
+    for tree in trees.flatten() {
+        let Ok(entry) = tree.get_path(Path::new(".gitmodules")) else {
+            continue;
+        };
+        let Ok(blob) = git_repo.find_blob(entry.id()) else {
+            continue;
+        };
+        for (path, url) in parse_gitmodules(&String::from_utf8_lossy(blob.content())) {
+            urls.entry(path).or_insert(url);
+        }
+    }
+    urls
+}
+
+/// `(path, url)` of each `[submodule "..."]` section of a `.gitmodules` file.
+fn parse_gitmodules(text: &str) -> Vec<(String, String)> {
+    let mut sections: Vec<(Option<String>, Option<String>)> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with("[submodule") {
+            sections.push((None, None));
+            continue;
+        }
+        let (Some(section), Some((key, value))) = (sections.last_mut(), line.split_once('='))
+        else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').to_string();
+        match key.trim() {
+            "path" => section.0 = Some(value),
+            "url" => section.1 = Some(value),
+            _ => {}
+        }
+    }
+    sections
+        .into_iter()
+        .filter_map(|(path, url)| Some((path?, url?)))
+        .collect()
+}
+
+/// Resolves a `./` or `../` submodule URL against the superproject's remote,
+/// as git does; other URLs are returned as they are.
+fn resolve_url(url: &str, remote: Option<&str>) -> String {
+    let Some(remote) = remote.filter(|_| url.starts_with("./") || url.starts_with("../")) else {
+        return url.to_string();
+    };
+    let mut base = remote.trim_end_matches('/').to_string();
+    // scp-like remotes keep their `host:` separator when cut back to it.
+    let mut separator = '/';
+    let mut rest = url;
+    loop {
+        if let Some(r) = rest.strip_prefix("./") {
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("../") {
+            rest = r;
+            let Some(i) = base.rfind(['/', ':']) else {
+                return url.to_string();
+            };
+            separator = if base[i..].starts_with(':') { ':' } else { '/' };
+            base.truncate(i);
+        } else {
+            break;
+        }
+    }
+    format!("{}{}{}", base, separator, rest)
+}
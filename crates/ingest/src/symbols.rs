@@ -0,0 +1,419 @@
+use commitmux_types::{CommitSymbol, CommitmuxError, IgnoreConfig, Result};
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use tree_sitter::{Node, Parser};
+
+use crate::patch::is_ignored;
+
+/// Files larger than this are not parsed.
+const MAX_SOURCE_BYTES: usize = 1024 * 1024;
+
+/// Excerpts are cut at a line boundary before this many bytes.
+const MAX_EXCERPT_BYTES: usize = 4000;
+
+#[derive(Clone, Copy)]
+enum Lang {
+    Rust,
+    Go,
+    Python,
+    TypeScript,
+    Tsx,
+}
+
+impl Lang {
+    fn from_path(path: &str) -> Option<Lang> {
+        match path.rsplit_once('.')?.1 {
+            "rs" => Some(Lang::Rust),
+            "go" => Some(Lang::Go),
+            "py" | "pyi" => Some(Lang::Python),
+            "ts" | "mts" | "cts" => Some(Lang::TypeScript),
+            "tsx" => Some(Lang::Tsx),
+            _ => None,
+        }
+    }
+
+    fn language(self) -> tree_sitter::Language {
+        match self {
+            Lang::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Lang::Go => tree_sitter_go::LANGUAGE.into(),
+            Lang::Python => tree_sitter_python::LANGUAGE.into(),
+            Lang::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Lang::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+        }
+    }
+
+    fn separator(self) -> &'static str {
+        match self {
+            Lang::Rust => "::",
+            _ => ".",
+        }
+    }
+}
+
+/// A definition in one version of a file. Lines are 1-based.
+struct Definition {
+    name: String,
+    qualified: String,
+    kind: &'static str,
+    lines: RangeInclusive<u32>,
+}
+
+/// What a syntax node defines. `kind` is `None` for nodes that only name a
+/// scope, like Rust `impl` blocks.
+struct Found {
+    kind: Option<&'static str>,
+    name: String,
+    /// Go method receiver type, qualifying the name without being a scope.
+    receiver: Option<String>,
+}
+
+/// Functions, methods and types defined in `source`, outermost first.
+fn definitions(lang: Lang, source: &[u8]) -> Vec<Definition> {
+    let mut parser = Parser::new();
+    if parser.set_language(&lang.language()).is_err() {
+        return Vec::new();
+    }
+    let Some(tree) = parser.parse(source, None) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    collect(lang, tree.root_node(), source, &mut Vec::new(), &mut out);
+    out
+}
+
+fn collect(
+    lang: Lang,
+    node: Node,
+    source: &[u8],
+    scope: &mut Vec<String>,
+    out: &mut Vec<Definition>,
+) {
+    let found = classify(lang, node, source);
+    if let Some(ref found) = found {
+        if let Some(kind) = found.kind {
+            let mut parts = scope.clone();
+            parts.extend(found.receiver.clone());
+            parts.push(found.name.clone());
+            out.push(Definition {
+                name: found.name.clone(),
+                qualified: parts.join(lang.separator()),
+                kind,
+                lines: node.start_position().row as u32 + 1..=node.end_position().row as u32 + 1,
+            });
+        }
+        scope.push(found.name.clone());
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect(lang, child, source, scope, out);
+    }
+    if found.is_some() {
+        scope.pop();
+    }
+}
+
+fn text(node: Node, source: &[u8]) -> Option<String> {
+    node.utf8_text(source).ok().map(str::to_string)
+}
+
+fn field_text(node: Node, field: &str, source: &[u8]) -> Option<String> {
+    text(node.child_by_field_name(field)?, source)
+}
+
+/// Whether `node` sits directly in the body of a node of kind `container`.
+fn in_body_of(node: Node, container: &str) -> bool {
+    node.parent()
+        .and_then(|body| body.parent())
+        .is_some_and(|p| p.kind() == container)
+}
+
+fn classify(lang: Lang, node: Node, source: &[u8]) -> Option<Found> {
+    let named = |kind: &'static str| {
+        Some(Found {
+            kind: Some(kind),
+            name: field_text(node, "name", source)?,
+            receiver: None,
+        })
+    };
+    match (lang, node.kind()) {
+        (Lang::Rust, "function_item" | "function_signature_item") => {
+            if in_body_of(node, "impl_item") || in_body_of(node, "trait_item") {
+                named("method")
+            } else {
+                named("function")
+            }
+        }
+        (Lang::Rust, "struct_item") => named("struct"),
+        (Lang::Rust, "enum_item") => named("enum"),
+        (Lang::Rust, "union_item") => named("union"),
+        (Lang::Rust, "trait_item") => named("trait"),
+        (Lang::Rust, "type_item") => named("type"),
+        (Lang::Rust, "mod_item") => named("module"),
+        (Lang::Rust, "macro_definition") => named("macro"),
+        (Lang::Rust, "impl_item") => {
+            // `impl<T> Trait for path::Type<T>` is scoped as `Type`.
+            let mut ty = node.child_by_field_name("type")?;
+            while let Some(inner) = ty
+                .child_by_field_name("type")
+                .or_else(|| ty.child_by_field_name("name"))
+            {
+                ty = inner;
+            }
+            Some(Found {
+                kind: None,
+                name: text(ty, source)?,
+                receiver: None,
+            })
+        }
+
+        (Lang::Go, "function_declaration") => named("function"),
+        (Lang::Go, "method_declaration") => {
+            let receiver = node
+                .child_by_field_name("receiver")
+                .and_then(|r| r.named_child(0))
+                .and_then(|p| field_text(p, "type", source))
+                .map(|t| {
+                    let t = t.trim_start_matches('*');
+                    t.split('[').next().unwrap_or(t).to_string()
+                });
+            Some(Found {
+                kind: Some("method"),
+                name: field_text(node, "name", source)?,
+                receiver,
+            })
+        }
+        (Lang::Go, "type_spec" | "type_alias") => {
+            match node.child_by_field_name("type").map(|t| t.kind()) {
+                Some("struct_type") => named("struct"),
+                Some("interface_type") => named("interface"),
+                _ => named("type"),
+            }
+        }
+
+        (Lang::Python, "function_definition") => {
+            if in_body_of(node, "class_definition") {
+                named("method")
+            } else {
+                named("function")
+            }
+        }
+        (Lang::Python, "class_definition") => named("class"),
+
+        (
+            Lang::TypeScript | Lang::Tsx,
+            "function_declaration" | "generator_function_declaration",
+        ) => named("function"),
+        (Lang::TypeScript | Lang::Tsx, "class_declaration" | "abstract_class_declaration") => {
+            named("class")
+        }
+        (Lang::TypeScript | Lang::Tsx, "method_definition") => named("method"),
+        (Lang::TypeScript | Lang::Tsx, "interface_declaration") => named("interface"),
+        (Lang::TypeScript | Lang::Tsx, "type_alias_declaration") => named("type"),
+        (Lang::TypeScript | Lang::Tsx, "enum_declaration") => named("enum"),
+        (Lang::TypeScript | Lang::Tsx, "internal_module") => named("namespace"),
+        (Lang::TypeScript | Lang::Tsx, "variable_declarator") => {
+            let value = node.child_by_field_name("value")?;
+            if matches!(value.kind(), "arrow_function" | "function_expression") {
+                named("function")
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Definitions in one side of a delta; empty if the side does not exist or
+/// is too large to parse.
+fn side_definitions(repo: &git2::Repository, file: git2::DiffFile, lang: Lang) -> Vec<Definition> {
+    if file.id().is_zero() {
+        return Vec::new();
+    }
+    match repo.find_blob(file.id()) {
+        Ok(blob) if blob.content().len() <= MAX_SOURCE_BYTES => definitions(lang, blob.content()),
+        _ => Vec::new(),
+    }
+}
+
+/// One diff line. `origin` is `+`, `-` or ` `.
+struct Line {
+    origin: char,
+    old: Option<u32>,
+    new: Option<u32>,
+    content: String,
+}
+
+struct Hunk {
+    header: String,
+    lines: Vec<Line>,
+}
+
+/// A definition as it was before and after the commit.
+#[derive(Default)]
+struct Sides {
+    name: String,
+    old: Option<RangeInclusive<u32>>,
+    new: Option<RangeInclusive<u32>>,
+}
+
+impl Sides {
+    fn contains(&self, line: &Line) -> bool {
+        let within = |range: &Option<RangeInclusive<u32>>, n: Option<u32>| matches!((range, n), (Some(r), Some(n)) if r.contains(&n));
+        within(&self.old, line.old) || within(&self.new, line.new)
+    }
+
+    fn changed_by(&self, line: &Line) -> bool {
+        line.origin != ' ' && self.contains(line)
+    }
+}
+
+/// Widens `range` to cover `other` (for names defined more than once).
+fn merge(range: &mut Option<RangeInclusive<u32>>, other: RangeInclusive<u32>) {
+    *range = Some(match range.take() {
+        Some(r) => *r.start().min(other.start())..=*r.end().max(other.end()),
+        None => other,
+    });
+}
+
+/// The functions, methods and types whose lines `commit` added, changed or
+/// removed, in files of a supported language. A side of a file is parsed
+/// only when the hunks change lines on it, so renames, mode changes and
+/// pure additions or deletions parse at most one version.
+pub fn get_commit_symbols(
+    repo: &git2::Repository,
+    commit: &git2::Commit,
+    diff: &git2::Diff,
+    repo_id: i64,
+    config: &IgnoreConfig,
+) -> Result<Vec<CommitSymbol>> {
+    let sha = commit.id().to_string();
+    let mut symbols = Vec::new();
+
+    for (idx, delta) in diff.deltas().enumerate() {
+        if delta.new_file().is_binary() || delta.old_file().is_binary() {
+            continue;
+        }
+        let path = match delta.status() {
+            git2::Delta::Deleted => delta.old_file().path(),
+            _ => delta.new_file().path().or_else(|| delta.old_file().path()),
+        };
+        let Some(path) = path.and_then(|p| p.to_str()) else {
+            continue;
+        };
+        if is_ignored(path, config) {
+            continue;
+        }
+        let Some(lang) = Lang::from_path(path) else {
+            continue;
+        };
+
+        let Some(patch) = git2::Patch::from_diff(diff, idx)
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?
+        else {
+            continue;
+        };
+        let hunks = read_hunks(&patch)?;
+        let changes = |origin| {
+            hunks
+                .iter()
+                .flat_map(|h| &h.lines)
+                .any(|l| l.origin == origin)
+        };
+        let (removes, adds) = (changes('-'), changes('+'));
+        if !removes && !adds {
+            continue;
+        }
+
+        let mut defs: BTreeMap<(String, &str), Sides> = BTreeMap::new();
+        if removes {
+            for def in side_definitions(repo, delta.old_file(), lang) {
+                let sides = defs.entry((def.qualified, def.kind)).or_default();
+                sides.name = def.name;
+                merge(&mut sides.old, def.lines);
+            }
+        }
+        if adds {
+            for def in side_definitions(repo, delta.new_file(), lang) {
+                let sides = defs.entry((def.qualified, def.kind)).or_default();
+                sides.name = def.name;
+                merge(&mut sides.new, def.lines);
+            }
+        }
+
+        for ((qualified, kind), sides) in defs {
+            let touched: Vec<&Hunk> = hunks
+                .iter()
+                .filter(|h| h.lines.iter().any(|l| sides.changed_by(l)))
+                .collect();
+            if touched.is_empty() {
+                continue;
+            }
+            symbols.push(CommitSymbol {
+                repo_id,
+                sha: sha.clone(),
+                path: path.to_string(),
+                name: sides.name.clone(),
+                qualified,
+                kind: kind.to_string(),
+                excerpt: excerpt(&touched, &sides),
+            });
+        }
+    }
+
+    Ok(symbols)
+}
+
+fn read_hunks(patch: &git2::Patch) -> Result<Vec<Hunk>> {
+    let mut hunks = Vec::new();
+    for h in 0..patch.num_hunks() {
+        let (hunk, count) = patch
+            .hunk(h)
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+        let mut lines = Vec::with_capacity(count);
+        for l in 0..count {
+            let line = patch
+                .line_in_hunk(h, l)
+                .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+            // Skip "\ No newline at end of file" markers.
+            if !matches!(line.origin(), '+' | '-' | ' ') {
+                continue;
+            }
+            lines.push(Line {
+                origin: line.origin(),
+                old: line.old_lineno(),
+                new: line.new_lineno(),
+                content: String::from_utf8_lossy(line.content()).into_owned(),
+            });
+        }
+        hunks.push(Hunk {
+            header: String::from_utf8_lossy(hunk.header()).into_owned(),
+            lines,
+        });
+    }
+    Ok(hunks)
+}
+
+/// The lines of `hunks` that fall inside the definition, each hunk under its
+/// `@@` header.
+fn excerpt(hunks: &[&Hunk], sides: &Sides) -> String {
+    let mut out = String::new();
+    for hunk in hunks {
+        out.push_str(&hunk.header);
+        if !hunk.header.ends_with('\n') {
+            out.push('\n');
+        }
+        for line in hunk.lines.iter().filter(|l| sides.contains(l)) {
+            out.push(line.origin);
+            out.push_str(&line.content);
+            if !line.content.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+    }
+    if out.len() > MAX_EXCERPT_BYTES {
+        let cut = out.floor_char_boundary(MAX_EXCERPT_BYTES);
+        let cut = out[..cut].rfind('\n').map_or(cut, |i| i + 1);
+        out.truncate(cut);
+    }
+    out
+}
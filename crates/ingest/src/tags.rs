@@ -0,0 +1,132 @@
+use commitmux_types::{CommitmuxError, Repo, Result, Store, Tag, TagIndex};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Re-indexes the repo's tags if any were added, removed or moved since the
+/// last sync. Tags can change without the branch tip moving, so this runs on
+/// every sync, but containment is only recomputed when needed.
+pub fn sync_tags(git_repo: &git2::Repository, repo: &Repo, store: &dyn Store) -> Result<()> {
+    let tags = read_tags(git_repo)?;
+    let key = |t: &Tag| (t.name.clone(), t.target_sha.clone());
+    let current: BTreeSet<_> = tags.iter().map(key).collect();
+    let stored: BTreeSet<_> = store.list_tags(repo.repo_id)?.iter().map(key).collect();
+    if current == stored {
+        return Ok(());
+    }
+    let index = build_index(git_repo, tags)?;
+    store.replace_tags(repo.repo_id, &index)
+}
+
+/// All tags under `refs/tags/` that peel to a commit.
+fn read_tags(git_repo: &git2::Repository) -> Result<Vec<Tag>> {
+    let refs = git_repo
+        .references_glob("refs/tags/*")
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+
+    let mut tags = Vec::new();
+    for reference in refs.flatten() {
+        let Some(name) = reference.shorthand().map(str::to_string) else {
+            continue;
+        };
+        // Tags of trees or blobs have no place in history.
+        let Ok(commit) = reference.peel_to_commit() else {
+            continue;
+        };
+        let tag = match reference.peel_to_tag() {
+            Ok(annotated) => {
+                let tagger = annotated.tagger();
+                Tag {
+                    name,
+                    target_sha: commit.id().to_string(),
+                    annotated: true,
+                    tagger_name: tagger.as_ref().and_then(|s| s.name().map(str::to_string)),
+                    tagger_email: tagger.as_ref().and_then(|s| s.email().map(str::to_string)),
+                    date: tagger
+                        .map(|s| s.when().seconds())
+                        .unwrap_or_else(|| commit.time().seconds()),
+                    message: annotated
+                        .message()
+                        .map(str::trim)
+                        .filter(|m| !m.is_empty())
+                        .map(str::to_string),
+                }
+            }
+            Err(_) => Tag {
+                name,
+                target_sha: commit.id().to_string(),
+                annotated: false,
+                tagger_name: None,
+                tagger_email: None,
+                date: commit.time().seconds(),
+                message: None,
+            },
+        };
+        tags.push(tag);
+    }
+    Ok(tags)
+}
+
+/// Computes which commits each tag introduces and which tags it contains.
+///
+/// Each tag's history is walked until it reaches a commit that another tag
+/// points at; those tags are its direct ancestors, and everything visited
+/// before them is introduced by the tag. A commit can be introduced by more
+/// than one tag (e.g. below a merge), which is harmless: a tag contains a
+/// commit iff it introduces it or contains a tag that does.
+fn build_index(git_repo: &git2::Repository, tags: Vec<Tag>) -> Result<TagIndex> {
+    let mut by_target: HashMap<git2::Oid, Vec<&str>> = HashMap::new();
+    for tag in &tags {
+        let oid = git2::Oid::from_str(&tag.target_sha)
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+        by_target.entry(oid).or_default().push(&tag.name);
+    }
+
+    let mut introduced = Vec::new();
+    let mut direct: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for (&target, names) in &by_target {
+        let mut parents: BTreeSet<&str> = BTreeSet::new();
+        let mut shas = Vec::new();
+        let mut seen: HashSet<git2::Oid> = HashSet::new();
+        let mut queue = vec![target];
+        while let Some(oid) = queue.pop() {
+            if !seen.insert(oid) {
+                continue;
+            }
+            if oid != target {
+                if let Some(others) = by_target.get(&oid) {
+                    parents.extend(others.iter().copied());
+                    continue;
+                }
+            }
+            shas.push(oid.to_string());
+            let commit = git_repo
+                .find_commit(oid)
+                .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+            queue.extend(commit.parent_ids());
+        }
+        for name in names {
+            introduced.extend(shas.iter().map(|sha| (name.to_string(), sha.clone())));
+            direct.insert(*name, parents.clone());
+        }
+    }
+
+    // Transitive closure of the direct ancestors.
+    let mut ancestors = Vec::new();
+    for (&tag, parents) in &direct {
+        let mut all: BTreeSet<&str> = BTreeSet::new();
+        let mut stack: Vec<&str> = parents.iter().copied().collect();
+        while let Some(a) = stack.pop() {
+            if all.insert(a) {
+                if let Some(more) = direct.get(a) {
+                    stack.extend(more.iter().copied());
+                }
+            }
+        }
+        ancestors.extend(all.into_iter().map(|a| (tag.to_string(), a.to_string())));
+    }
+
+    Ok(TagIndex {
+        tags,
+        introduced,
+        ancestors,
+    })
+}
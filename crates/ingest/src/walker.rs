@@ -1,10 +1,29 @@
 use commitmux_types::{
-    Commit, CommitPatch, CommitmuxError, IgnoreConfig, IngestState, Repo, Result, Store,
-    SyncSummary,
+    BatchCommit, BlameOpts, Commit, CommitPatch, CommitRange, CommitmuxError, ConventionalCommit,
+    DiffOpts, DiffResult, FileBlame, FileContent, FileOpts, IgnoreConfig, IngestState, PatchResult,
+    Repo, Result, Store, SyncControl, SyncProgress, SyncSummary, Trailer, TreeListing, TreeOpts,
+    WriteBatch,
 };
-use std::time::{SystemTime, UNIX_EPOCH};
-
+use std::collections::HashSet;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::blame;
+use crate::clone;
+use crate::credentials;
+use crate::files;
+use crate::filter::AuthorFilter;
 use crate::patch;
+use crate::refs::RefExtractor;
+use crate::submodules;
+use crate::symbols;
+use crate::tags;
+
+/// Commits walked per write transaction; a checkpoint is saved after each.
+const BATCH_SIZE: usize = 500;
+/// Number of completed-sync tips remembered in `ingest_state.indexed_tips`.
+const MAX_INDEXED_TIPS: usize = 16;
+/// Commits between progress reports.
+const PROGRESS_INTERVAL: usize = 100;
 
 pub struct Git2Ingester;
 
@@ -21,51 +40,38 @@ impl Default for Git2Ingester {
 }
 
 impl commitmux_types::Ingester for Git2Ingester {
-    fn sync_repo(
+    fn sync_repo_with(
         &self,
         repo: &Repo,
         store: &dyn Store,
         config: &IgnoreConfig,
+        control: &SyncControl<'_>,
     ) -> Result<SyncSummary> {
-        let mut summary = SyncSummary {
-            commits_indexed: 0,
-            commits_already_indexed: 0,
-            commits_filtered: 0,
-            errors: Vec::new(),
-        };
+        let mut summary = SyncSummary::default();
 
         // Open the git repository
-        let git_repo = git2::Repository::open(&repo.local_path)
-            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+        let git_repo = open_repo(repo)?;
 
-        // If this is a managed clone (has a remote_url), fetch all remotes to keep it up to date
+        // If this is a managed clone (has a remote_url), fetch all remotes to
+        // keep it up to date, within its depth and history window
+        let roots_before = clone::shallow_roots(&git_repo);
+        let auth = credentials::remote_auth(repo, store);
         if repo.remote_url.is_some() {
             match git_repo.remotes() {
                 Ok(remotes) => {
                     for remote_name in remotes.iter().flatten() {
-                        match git_repo.find_remote(remote_name) {
-                            Ok(mut remote) => {
-                                let mut callbacks = git2::RemoteCallbacks::new();
-                                callbacks.credentials(|_url, username, _allowed| {
-                                    git2::Cred::ssh_key_from_agent(username.unwrap_or("git"))
-                                });
-                                let mut fo = git2::FetchOptions::new();
-                                fo.remote_callbacks(callbacks);
-                                if let Err(e) = remote.fetch::<&str>(&[], Some(&mut fo), None) {
-                                    summary.errors.push(format!(
-                                        "Warning: failed to fetch remote '{}': {}",
-                                        remote_name,
-                                        e.message()
-                                    ));
-                                }
-                            }
-                            Err(e) => {
-                                summary.errors.push(format!(
-                                    "Warning: failed to open remote '{}': {}",
-                                    remote_name,
-                                    e.message()
-                                ));
-                            }
+                        if let Err(e) = clone::fetch_remote(
+                            &git_repo,
+                            remote_name,
+                            repo.clone_depth,
+                            repo.clone_since,
+                            &auth,
+                        ) {
+                            summary.errors.push(format!(
+                                "Warning: failed to fetch remote '{}': {}",
+                                remote_name,
+                                e.message()
+                            ));
                         }
                     }
                 }
@@ -77,17 +83,37 @@ impl commitmux_types::Ingester for Git2Ingester {
             }
         }
 
-        // Construct effective_config: merge persisted exclude_prefixes from Repo
-        let effective_config = if repo.exclude_prefixes.is_empty() {
-            config.clone()
-        } else {
-            let mut merged = config.clone();
-            for p in &repo.exclude_prefixes {
-                if !merged.path_prefixes.contains(p) {
-                    merged.path_prefixes.push(p.clone());
-                }
-            }
-            merged
+        // Construct effective_config: merge persisted exclude_prefixes and
+        // include paths from Repo
+        let mut effective_config = config.for_repo(repo);
+        match store.get_config(IgnoreConfig::RENAME_THRESHOLD_KEY) {
+            Ok(Some(value)) => match value.parse::<u16>() {
+                Ok(threshold) if threshold <= 100 => effective_config.rename_threshold = threshold,
+                _ => summary.errors.push(format!(
+                    "Warning: ignoring invalid {} '{}'",
+                    IgnoreConfig::RENAME_THRESHOLD_KEY,
+                    value
+                )),
+            },
+            Ok(None) => {}
+            Err(e) => summary.errors.push(format!(
+                "Warning: failed to read {}: {}",
+                IgnoreConfig::RENAME_THRESHOLD_KEY,
+                e
+            )),
+        }
+
+        let authors = AuthorFilter::compile(&repo.author_filters)?;
+        // A missing or unreadable .mailmap just means no identities are merged.
+        let mailmap = git_repo.mailmap().ok();
+        let refs = RefExtractor::for_repo(repo, Some(&git_repo))?;
+        let filters = Filters {
+            config: effective_config,
+            authors,
+            mailmap,
+            refs,
+            since: repo.clone_since,
+            shallow_roots: clone::shallow_roots(&git_repo),
         };
 
         // Resolve the tip commit
@@ -106,6 +132,8 @@ impl commitmux_types::Ingester for Git2Ingester {
         revwalk
             .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)
             .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+        // Hidden commits, for the walk of a shallow clone
+        let mut hidden = Vec::new();
 
         // Fork-of upstream exclusion: hide commits reachable from upstream
         if let Some(ref upstream_url) = repo.fork_of {
@@ -137,20 +165,18 @@ impl commitmux_types::Ingester for Git2Ingester {
                 }
             }
 
-            // Step 2: fetch upstream (non-fatal)
-            if let Ok(mut remote) = git_repo.find_remote("upstream") {
-                let mut callbacks = git2::RemoteCallbacks::new();
-                callbacks.credentials(|_url, username, _allowed| {
-                    git2::Cred::ssh_key_from_agent(username.unwrap_or("git"))
-                });
-                let mut fo = git2::FetchOptions::new();
-                fo.remote_callbacks(callbacks);
-                if let Err(e) = remote.fetch::<&str>(&[], Some(&mut fo), None) {
-                    summary.errors.push(format!(
-                        "Warning: failed to fetch upstream: {}",
-                        e.message()
-                    ));
-                }
+            // Step 2: fetch upstream (non-fatal), no deeper than the clone
+            if let Err(e) = clone::fetch_remote(
+                &git_repo,
+                "upstream",
+                repo.clone_depth,
+                repo.clone_since,
+                &auth,
+            ) {
+                summary.errors.push(format!(
+                    "Warning: failed to fetch upstream: {}",
+                    e.message()
+                ));
             }
 
             // Step 3: resolve upstream tip (try HEAD, main, master)
@@ -170,14 +196,22 @@ impl commitmux_types::Ingester for Git2Ingester {
             if let Some(upstream_commit) = upstream_tip {
                 // Step 4: find merge base and hide upstream commits from walk
                 match git_repo.merge_base(tip_oid, upstream_commit.id()) {
-                    Ok(base_oid) => {
-                        if let Err(e) = revwalk.hide(base_oid) {
-                            summary.errors.push(format!(
-                                "Warning: failed to hide upstream commits: {}",
-                                e.message()
-                            ));
-                        }
-                    }
+                    Ok(base_oid) => match revwalk.hide(base_oid) {
+                        Ok(()) => hidden.push(base_oid),
+                        Err(e) => summary.errors.push(format!(
+                            "Warning: failed to hide upstream commits: {}",
+                            e.message()
+                        )),
+                    },
+                    // Shallow histories may stop before the fork point; every
+                    // fetched upstream commit is still hidden.
+                    Err(_) if git_repo.is_shallow() => match revwalk.hide(upstream_commit.id()) {
+                        Ok(()) => hidden.push(upstream_commit.id()),
+                        Err(e) => summary.errors.push(format!(
+                            "Warning: failed to hide upstream commits: {}",
+                            e.message()
+                        )),
+                    },
                     Err(e) => {
                         summary.errors.push(format!(
                             "Warning: no merge base with upstream ({}): {}",
@@ -194,147 +228,165 @@ impl commitmux_types::Ingester for Git2Ingester {
             }
         }
 
-        // Walk commits
-        for oid_result in revwalk {
-            let oid = match oid_result {
-                Ok(oid) => oid,
-                Err(e) => {
-                    summary
-                        .errors
-                        .push(format!("Failed to get oid in revwalk: {}", e.message()));
-                    continue;
-                }
-            };
-
-            let git_commit = match git_repo.find_commit(oid) {
-                Ok(c) => c,
-                Err(e) => {
-                    summary
+        // Hide already-ingested history. Every tip of a completed sync that is
+        // still an ancestor of the current tip covers history that is already
+        // indexed; a checkpoint does the same for an interrupted sync. History
+        // walked with other filters may hold commits that now match, so after
+        // a settings change the earlier tips are forgotten and the full
+        // history is walked again.
+        let config_hash = config_hash(repo, &filters.config);
+        let mut prev_state = store
+            .get_ingest_state(repo.repo_id)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| IngestState {
+                repo_id: repo.repo_id,
+                ..IngestState::default()
+            });
+        if control.full || prev_state.config_hash.as_deref() != Some(config_hash.as_str()) {
+            prev_state.last_synced_sha = None;
+            prev_state.checkpoint_sha = None;
+            prev_state.indexed_tips.clear();
+            prev_state.config_hash = Some(config_hash.clone());
+        }
+        let existing_count = store.count_commits_for_repo(repo.repo_id).unwrap_or(0);
+        let previous_tips = previous_tips(&prev_state);
+        // A deepened clone has older history behind the previous tips that
+        // was never indexed, so nothing is hidden.
+        let deepened = clone::deepened(&git_repo, &roots_before);
+        let mut hid_previous_tip = false;
+        for sha in previous_tips.iter().filter(|_| !deepened) {
+            hid_previous_tip |= hide_if_reachable(
+                &git_repo,
+                &mut revwalk,
+                &mut hidden,
+                tip_oid,
+                Some(sha),
+                &mut summary,
+            );
+        }
+        // A shallow fetch can move the boundary past the previous tip; that
+        // is expected, not a rewrite.
+        if !hid_previous_tip && !previous_tips.is_empty() && !git_repo.is_shallow() && !deepened {
+            summary.errors.push(format!(
+                "Warning: previously synced tip {} is no longer an ancestor of {} (history rewritten?); walking full history",
+                previous_tips[0], tip_oid
+            ));
+        }
+        let hid_checkpoint = hide_if_reachable(
+            &git_repo,
+            &mut revwalk,
+            &mut hidden,
+            tip_oid,
+            prev_state.checkpoint_sha.as_deref().filter(|_| !deepened),
+            &mut summary,
+        );
+
+        // With a previous tip hidden every walked commit is new. Otherwise
+        // (first sync after an interruption, or rewritten history) fall back
+        // to asking the store about each commit — unless it has none.
+        let check_existing =
+            !control.full && (hid_checkpoint || (!hid_previous_tip && existing_count > 0));
+
+        let mut oids = Vec::new();
+        if git_repo.is_shallow() {
+            // libgit2's revwalk fails at the boundary of a shallow clone
+            match clone::shallow_walk(&git_repo, tip_oid, &hidden) {
+                Ok(walked) => oids = walked,
+                Err(e) => summary
+                    .errors
+                    .push(format!("Failed to walk shallow history: {}", e.message())),
+            }
+        } else {
+            for oid_result in revwalk {
+                match oid_result {
+                    Ok(oid) => oids.push(oid),
+                    Err(e) => summary
                         .errors
-                        .push(format!("Failed to find commit {}: {}", oid, e.message()));
-                    continue;
-                }
-            };
-
-            let sha = oid.to_string();
-
-            // Skip commits already in the store (incremental skip)
-            match store.commit_exists(repo.repo_id, &sha) {
-                Ok(true) => {
-                    summary.commits_already_indexed += 1;
-                    continue;
-                }
-                Ok(false) => { /* proceed */ }
-                Err(e) => {
-                    summary.errors.push(format!(
-                        "Warning: failed to check commit existence for {}: {}",
-                        sha, e
-                    ));
-                    // Proceed to index it anyway (conservative)
+                        .push(format!("Failed to get oid in revwalk: {}", e.message())),
                 }
             }
+        }
 
-            // Extract commit metadata
-            let author = git_commit.author();
-            let committer = git_commit.committer();
-
-            let message = git_commit.message().unwrap_or("").to_string();
-            let mut lines = message.lines();
-            let subject = lines.next().unwrap_or("").trim().to_string();
-            let body_lines: Vec<&str> = lines.skip_while(|l| l.trim().is_empty()).collect();
-            let body = if body_lines.is_empty() {
-                None
-            } else {
-                Some(body_lines.join("\n"))
-            };
-
-            let commit = Commit {
-                repo_id: repo.repo_id,
-                sha: sha.clone(),
-                author_name: author.name().unwrap_or("").to_string(),
-                author_email: author.email().unwrap_or("").to_string(),
-                committer_name: committer.name().unwrap_or("").to_string(),
-                committer_email: committer.email().unwrap_or("").to_string(),
-                author_time: author.when().seconds(),
-                commit_time: git_commit.time().seconds(),
-                subject,
-                body,
-                parent_count: git_commit.parent_count() as u32,
-            };
-
-            // Author filter: skip commits not matching the configured author email
-            if let Some(ref filter_email) = repo.author_filter {
-                if !commit.author_email.eq_ignore_ascii_case(filter_email) {
-                    summary.commits_filtered += 1;
-                    continue;
-                }
+        let started = Instant::now();
+        let mut progress = SyncProgress {
+            commits_total: oids.len(),
+            ..SyncProgress::default()
+        };
+        let mut batch = WriteBatch::default();
+        // `walked_through` is the last commit whose ancestors have all been
+        // prepared; it becomes the durable checkpoint once its batch commits.
+        // After any failure it stops advancing so the failed commits are
+        // retried by the next sync.
+        let mut walked_through: Option<String> = None;
+        let mut durable: Option<String> = None;
+        let mut stuck = false;
+
+        for (walked, oid) in oids.into_iter().enumerate() {
+            if control.is_cancelled() {
+                summary.cancelled = true;
+                break;
             }
-
-            // Upsert commit
-            if let Err(e) = store.upsert_commit(&commit) {
-                summary
-                    .errors
-                    .push(format!("Failed to upsert commit {}: {}", sha, e));
-                continue;
+            if walked > 0 && walked % BATCH_SIZE == 0 {
+                flush_batch(
+                    store,
+                    repo.repo_id,
+                    &prev_state,
+                    &mut batch,
+                    &mut walked_through,
+                    &mut durable,
+                    &mut stuck,
+                    &mut summary,
+                );
             }
-
-            // Get changed files
-            match patch::get_commit_files(&git_repo, &git_commit, repo.repo_id, &effective_config) {
-                Ok(files) => {
-                    if let Err(e) = store.upsert_commit_files(&files) {
-                        summary
-                            .errors
-                            .push(format!("Failed to upsert files for commit {}: {}", sha, e));
-                    }
-                }
-                Err(e) => {
-                    summary
-                        .errors
-                        .push(format!("Failed to get files for commit {}: {}", sha, e));
-                }
+            if walked % PROGRESS_INTERVAL == 0 {
+                progress.commits_walked = walked;
+                progress.commits_indexed = summary.commits_indexed;
+                progress.current_sha = Some(oid.to_string());
+                progress.eta_secs = estimate_eta(started, walked, progress.commits_total);
+                control.report(&progress);
             }
 
-            // Get and store patch text
-            match patch::get_patch_text(&git_repo, &git_commit, &effective_config) {
-                Ok(Some(text)) => {
-                    let preview_len = text.floor_char_boundary(500);
-                    let patch_preview = text[..preview_len].to_string();
-
-                    match zstd::encode_all(text.as_bytes(), 3) {
-                        Ok(patch_blob) => {
-                            let cp = CommitPatch {
-                                repo_id: repo.repo_id,
-                                sha: sha.clone(),
-                                patch_blob,
-                                patch_preview,
-                            };
-                            if let Err(e) = store.upsert_patch(&cp) {
-                                summary.errors.push(format!(
-                                    "Failed to upsert patch for commit {}: {}",
-                                    sha, e
-                                ));
-                            }
-                        }
-                        Err(e) => {
-                            summary.errors.push(format!(
-                                "Failed to compress patch for commit {}: {}",
-                                sha, e
-                            ));
-                        }
-                    }
-                }
-                Ok(None) => {
-                    // No patch text (e.g., all binary) — that's fine
-                }
-                Err(e) => {
-                    summary.errors.push(format!(
-                        "Failed to get patch text for commit {}: {}",
-                        sha, e
-                    ));
-                }
+            match prepare_commit(
+                &git_repo,
+                oid,
+                repo,
+                store,
+                &filters,
+                check_existing,
+                &mut summary,
+            ) {
+                Prepared::Write(entry) => batch.commits.push(*entry),
+                Prepared::Skip => {}
+                Prepared::Failed => stuck = true,
             }
+            if !stuck {
+                walked_through = Some(oid.to_string());
+            }
+        }
 
-            summary.commits_indexed += 1;
+        flush_batch(
+            store,
+            repo.repo_id,
+            &prev_state,
+            &mut batch,
+            &mut walked_through,
+            &mut durable,
+            &mut stuck,
+            &mut summary,
+        );
+
+        if summary.cancelled {
+            return Ok(summary);
+        }
+
+        progress.commits_walked = progress.commits_total;
+        progress.commits_indexed = summary.commits_indexed;
+        progress.eta_secs = Some(0);
+        control.report(&progress);
+
+        if let Err(e) = tags::sync_tags(&git_repo, repo, store) {
+            summary.errors.push(format!("Failed to index tags: {}", e));
         }
 
         // Update ingest state
@@ -343,11 +395,19 @@ impl commitmux_types::Ingester for Git2Ingester {
             .map(|d| d.as_secs() as i64)
             .unwrap_or(0);
 
+        let tip_sha = tip_oid.to_string();
+        let mut indexed_tips = vec![tip_sha.clone()];
+        indexed_tips.extend(previous_tips.into_iter().filter(|t| *t != tip_sha));
+        indexed_tips.truncate(MAX_INDEXED_TIPS);
+
         let ingest_state = IngestState {
             repo_id: repo.repo_id,
             last_synced_at: now,
-            last_synced_sha: Some(tip_oid.to_string()),
+            last_synced_sha: Some(tip_sha),
             last_error: summary.errors.last().cloned(),
+            checkpoint_sha: None,
+            indexed_tips,
+            config_hash: Some(config_hash),
         };
 
         // Best-effort — don't fail the whole sync if state update fails
@@ -359,6 +419,479 @@ impl commitmux_types::Ingester for Git2Ingester {
 
         Ok(summary)
     }
+
+    fn commit_range(
+        &self,
+        repo: &Repo,
+        from: Option<&str>,
+        to: &str,
+        first_parent: bool,
+    ) -> Result<CommitRange> {
+        let git_repo = open_repo(repo)?;
+        let to_oid = resolve_rev(&git_repo, repo, to)?;
+        let from_oid = from.map(|f| resolve_rev(&git_repo, repo, f)).transpose()?;
+
+        let mut revwalk = git_repo
+            .revwalk()
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+        revwalk
+            .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+        if first_parent {
+            revwalk
+                .simplify_first_parent()
+                .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+        }
+        revwalk
+            .push(to_oid)
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+        if let Some(from_oid) = from_oid {
+            revwalk
+                .hide(from_oid)
+                .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+        }
+
+        let shas = revwalk
+            .map(|oid| oid.map(|o| o.to_string()))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))?;
+        Ok(CommitRange {
+            from_sha: from_oid.map(|o| o.to_string()),
+            to_sha: to_oid.to_string(),
+            shas,
+        })
+    }
+
+    fn blame(&self, repo: &Repo, opts: &BlameOpts) -> Result<FileBlame> {
+        let git_repo = open_repo(repo)?;
+        blame::blame_file(&git_repo, repo, opts)
+    }
+
+    fn read_file(&self, repo: &Repo, opts: &FileOpts) -> Result<FileContent> {
+        let git_repo = open_repo(repo)?;
+        files::read_file(&git_repo, repo, opts)
+    }
+
+    fn list_tree(&self, repo: &Repo, opts: &TreeOpts) -> Result<TreeListing> {
+        let git_repo = open_repo(repo)?;
+        files::list_tree(&git_repo, repo, opts)
+    }
+
+    fn diff(&self, repo: &Repo, opts: &DiffOpts, config: &IgnoreConfig) -> Result<DiffResult> {
+        let git_repo = open_repo(repo)?;
+        patch::diff_revs(&git_repo, repo, opts, config)
+    }
+
+    fn patch(&self, repo: &Repo, sha: &str, config: &IgnoreConfig) -> Result<PatchResult> {
+        let git_repo = open_repo(repo)?;
+        patch::full_patch(&git_repo, repo, sha, config)
+    }
+}
+
+/// Opens the repo's local clone, reporting a missing path distinctly from
+/// other git errors.
+pub(crate) fn open_repo(repo: &Repo) -> Result<git2::Repository> {
+    if !repo.local_path.exists() {
+        return Err(CommitmuxError::NotFound(format!(
+            "local path {} for repo '{}' no longer exists; remove and re-add the repo",
+            repo.local_path.display(),
+            repo.name
+        )));
+    }
+    git2::Repository::open(&repo.local_path)
+        .map_err(|e| CommitmuxError::Ingest(e.message().to_string()))
+}
+
+/// Resolves a branch, tag or SHA to a commit. Managed clones only have
+/// remote-tracking branches, so `origin/<rev>` is tried as well.
+pub(crate) fn resolve_rev(
+    git_repo: &git2::Repository,
+    repo: &Repo,
+    rev: &str,
+) -> Result<git2::Oid> {
+    let commit = git_repo
+        .revparse_single(rev)
+        .or_else(|e| {
+            git_repo
+                .revparse_single(&format!("origin/{}", rev))
+                .map_err(|_| e)
+        })
+        .and_then(|obj| obj.peel_to_commit());
+    match commit {
+        Ok(commit) => Ok(commit.id()),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Err(CommitmuxError::NotFound(format!(
+            "revision '{}' in repo '{}'",
+            rev, repo.name
+        ))),
+        Err(e) => Err(CommitmuxError::Ingest(e.message().to_string())),
+    }
+}
+
+/// Outcome of preparing one walked commit.
+enum Prepared {
+    /// New commit to write in the current batch.
+    Write(Box<BatchCommit>),
+    /// Already indexed or filtered out; nothing to write.
+    Skip,
+    /// Could not be read; must not be covered by a checkpoint.
+    Failed,
+}
+
+/// Per-sync path and author filters and reference patterns, resolved once
+/// before the walk.
+struct Filters {
+    config: IgnoreConfig,
+    authors: Option<AuthorFilter>,
+    mailmap: Option<git2::Mailmap>,
+    refs: RefExtractor,
+    /// Commits before this time are outside the repo's history window.
+    since: Option<i64>,
+    /// Boundary commits of a shallow clone.
+    shallow_roots: HashSet<git2::Oid>,
+}
+
+/// Reads a commit, its changed files and its patch from git.
+fn prepare_commit(
+    git_repo: &git2::Repository,
+    oid: git2::Oid,
+    repo: &Repo,
+    store: &dyn Store,
+    filters: &Filters,
+    check_existing: bool,
+    summary: &mut SyncSummary,
+) -> Prepared {
+    let git_commit = match git_repo.find_commit(oid) {
+        Ok(c) => c,
+        Err(e) => {
+            summary
+                .errors
+                .push(format!("Failed to find commit {}: {}", oid, e.message()));
+            return Prepared::Failed;
+        }
+    };
+
+    let sha = oid.to_string();
+
+    // A shallow clone's boundary commits have no parent to diff against,
+    // and older commits fetched while deepening are outside the window.
+    if filters.shallow_roots.contains(&oid)
+        || filters
+            .since
+            .is_some_and(|since| git_commit.time().seconds() < since)
+    {
+        summary.commits_filtered += 1;
+        return Prepared::Skip;
+    }
+
+    // Skip commits already in the store (only needed when the walk could
+    // not be narrowed to new history)
+    if check_existing {
+        match store.commit_exists(repo.repo_id, &sha) {
+            Ok(true) => {
+                summary.commits_already_indexed += 1;
+                return Prepared::Skip;
+            }
+            Ok(false) => { /* proceed */ }
+            Err(e) => {
+                summary.errors.push(format!(
+                    "Warning: failed to check commit existence for {}: {}",
+                    sha, e
+                ));
+                // Proceed to index it anyway (conservative)
+            }
+        }
+    }
+
+    // Author filter: skip commits by anyone outside the configured identities,
+    // checked on the raw signatures before the message is parsed
+    if let Some(ref authors) = filters.authors {
+        if !authors.matches_commit(&git_commit, filters.mailmap.as_ref()) {
+            summary.commits_filtered += 1;
+            return Prepared::Skip;
+        }
+    }
+
+    // Extract commit metadata
+    let author = git_commit.author();
+    let committer = git_commit.committer();
+
+    let message = git_commit.message().unwrap_or("").to_string();
+    let mut lines = message.lines();
+    let subject = lines.next().unwrap_or("").trim().to_string();
+    let body_lines: Vec<&str> = lines.skip_while(|l| l.trim().is_empty()).collect();
+    let body = if body_lines.is_empty() {
+        None
+    } else {
+        Some(body_lines.join("\n"))
+    };
+
+    let conventional = ConventionalCommit::parse(&subject, body.as_deref());
+
+    let canonical = filters
+        .mailmap
+        .as_ref()
+        .and_then(|m| m.resolve_signature(&author).ok());
+    let canonical = canonical.as_ref().unwrap_or(&author);
+
+    let commit = Commit {
+        repo_id: repo.repo_id,
+        sha: sha.clone(),
+        author_name: author.name().unwrap_or("").to_string(),
+        author_email: author.email().unwrap_or("").to_string(),
+        canonical_author_name: canonical.name().unwrap_or("").to_string(),
+        canonical_author_email: canonical.email().unwrap_or("").to_string(),
+        committer_name: committer.name().unwrap_or("").to_string(),
+        committer_email: committer.email().unwrap_or("").to_string(),
+        author_time: author.when().seconds(),
+        commit_time: git_commit.time().seconds(),
+        subject,
+        body,
+        parent_count: git_commit.parent_count() as u32,
+        trailers: parse_trailers(&message),
+        refs: filters.refs.extract(&message),
+        conventional,
+    };
+
+    let config = &filters.config;
+
+    // One diff feeds the files, patch, symbols and submodules below
+    let diff = match patch::commit_diff(git_repo, &git_commit, config) {
+        Ok(diff) => diff,
+        Err(e) => {
+            summary
+                .errors
+                .push(format!("Failed to diff commit {}: {}", sha, e));
+            return Prepared::Failed;
+        }
+    };
+
+    // Get changed files
+    let files = match patch::get_commit_files(git_repo, &git_commit, &diff, repo.repo_id, config) {
+        Ok(files) => files,
+        Err(e) => {
+            summary
+                .errors
+                .push(format!("Failed to get files for commit {}: {}", sha, e));
+            Vec::new()
+        }
+    };
+
+    // Include paths: skip commits that touch nothing under them
+    if !config.include_prefixes.is_empty() && files.is_empty() {
+        summary.commits_filtered += 1;
+        return Prepared::Skip;
+    }
+
+    // Get patch text. The store compresses the blob, so it is passed raw.
+    let patch = match patch::get_patch_text(git_repo, &diff, config) {
+        Ok(Some(patch)) => {
+            let preview_len = patch.text.floor_char_boundary(500);
+            let patch_preview = patch.text[..preview_len].to_string();
+            Some(CommitPatch {
+                repo_id: repo.repo_id,
+                sha: sha.clone(),
+                patch_blob: patch.text.into_bytes(),
+                patch_preview,
+                truncated: patch.truncated,
+                original_size: patch.original_size as u64,
+            })
+        }
+        Ok(None) => {
+            // No patch text (e.g., all binary) — that's fine
+            None
+        }
+        Err(e) => {
+            summary.errors.push(format!(
+                "Failed to get patch text for commit {}: {}",
+                sha, e
+            ));
+            None
+        }
+    };
+
+    // Functions and types the diff touched, for symbol history
+    let symbols =
+        match symbols::get_commit_symbols(git_repo, &git_commit, &diff, repo.repo_id, config) {
+            Ok(symbols) => symbols,
+            Err(e) => {
+                summary
+                    .errors
+                    .push(format!("Failed to get symbols for commit {}: {}", sha, e));
+                Vec::new()
+            }
+        };
+
+    // Submodule pointer changes
+    let submodules =
+        match submodules::get_commit_submodules(git_repo, &git_commit, &diff, repo, config) {
+            Ok(submodules) => submodules,
+            Err(e) => {
+                summary.errors.push(format!(
+                    "Failed to get submodules for commit {}: {}",
+                    sha, e
+                ));
+                Vec::new()
+            }
+        };
+
+    Prepared::Write(Box::new(BatchCommit {
+        commit,
+        files,
+        patch,
+        symbols,
+        submodules,
+    }))
+}
+
+/// Trailers from the final paragraph of a commit message, as git itself
+/// recognises them. A message git cannot parse yields no trailers.
+fn parse_trailers(message: &str) -> Vec<Trailer> {
+    match git2::message_trailers_strs(message) {
+        Ok(trailers) => trailers
+            .iter()
+            .map(|(key, value)| Trailer {
+                key: key.to_string(),
+                value: value.to_string(),
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Writes the pending batch in one transaction and, on success, records the
+/// walk position as the resume checkpoint.
+#[allow(clippy::too_many_arguments)]
+fn flush_batch(
+    store: &dyn Store,
+    repo_id: i64,
+    prev_state: &IngestState,
+    batch: &mut WriteBatch,
+    walked_through: &mut Option<String>,
+    durable: &mut Option<String>,
+    stuck: &mut bool,
+    summary: &mut SyncSummary,
+) {
+    if !batch.is_empty() {
+        match store.write_batch(batch) {
+            Ok(()) => summary.commits_indexed += batch.len(),
+            Err(e) => {
+                summary.errors.push(format!(
+                    "Failed to write batch of {} commits: {}",
+                    batch.len(),
+                    e
+                ));
+                *stuck = true;
+                *walked_through = durable.clone();
+            }
+        }
+        batch.commits.clear();
+    }
+
+    if *walked_through != *durable {
+        if let Some(ref sha) = walked_through {
+            save_checkpoint(store, repo_id, prev_state, sha, summary);
+        }
+        *durable = walked_through.clone();
+    }
+}
+
+/// Identifies the settings that decide which commits and files are indexed:
+/// the git blob id of a canonical listing, so it is stable across builds.
+fn config_hash(repo: &Repo, config: &IgnoreConfig) -> String {
+    let mut authors: Vec<String> = repo.author_filters.iter().map(|m| m.to_string()).collect();
+    authors.sort();
+    let canonical = format!(
+        "authors={:?}\nfork_of={:?}\ninclude={:?}\nexclude={:?}\nrename_threshold={}\nmax_patch_bytes={}\n",
+        authors,
+        repo.fork_of,
+        config.include_prefixes,
+        config.path_prefixes,
+        config.rename_threshold,
+        config.max_patch_bytes,
+    );
+    git2::Oid::hash_object(git2::ObjectType::Blob, canonical.as_bytes())
+        .map(|oid| oid.to_string())
+        .unwrap_or_default()
+}
+
+/// Tips of earlier completed syncs, newest first. `last_synced_sha` is
+/// included for databases written before `indexed_tips` existed.
+fn previous_tips(state: &IngestState) -> Vec<String> {
+    let mut tips = Vec::new();
+    for sha in state.last_synced_sha.iter().chain(&state.indexed_tips) {
+        if !tips.contains(sha) {
+            tips.push(sha.clone());
+        }
+    }
+    tips
+}
+
+/// Hides `sha` in the revwalk if it is the tip or one of its ancestors.
+/// Returns true if it was hidden.
+fn hide_if_reachable(
+    git_repo: &git2::Repository,
+    revwalk: &mut git2::Revwalk<'_>,
+    hidden: &mut Vec<git2::Oid>,
+    tip: git2::Oid,
+    sha: Option<&str>,
+    summary: &mut SyncSummary,
+) -> bool {
+    let Some(oid) = sha.and_then(|sha| reachable_from_tip(git_repo, tip, sha)) else {
+        return false;
+    };
+    match revwalk.hide(oid) {
+        Ok(()) => {
+            hidden.push(oid);
+            true
+        }
+        Err(e) => {
+            summary.errors.push(format!(
+                "Warning: failed to hide already-indexed commit {}: {}",
+                oid,
+                e.message()
+            ));
+            false
+        }
+    }
+}
+
+/// Records `sha` as the resume point without touching the completed-sync
+/// fields, so `serve` still sees the repo as stale until the sync finishes.
+fn save_checkpoint(
+    store: &dyn Store,
+    repo_id: i64,
+    prev_state: &IngestState,
+    sha: &str,
+    summary: &mut SyncSummary,
+) {
+    let state = IngestState {
+        repo_id,
+        checkpoint_sha: Some(sha.to_string()),
+        ..prev_state.clone()
+    };
+    if let Err(e) = store.update_ingest_state(&state) {
+        summary
+            .errors
+            .push(format!("Failed to save sync checkpoint: {}", e));
+    }
+}
+
+/// Parses `sha` and returns it if it is the tip or one of its ancestors.
+fn reachable_from_tip(git_repo: &git2::Repository, tip: git2::Oid, sha: &str) -> Option<git2::Oid> {
+    let oid = git2::Oid::from_str(sha).ok()?;
+    if oid == tip || git_repo.graph_descendant_of(tip, oid).unwrap_or(false) {
+        Some(oid)
+    } else {
+        None
+    }
+}
+
+fn estimate_eta(started: Instant, walked: usize, total: usize) -> Option<u64> {
+    if walked == 0 {
+        return None;
+    }
+    let per_commit = started.elapsed().as_secs_f64() / walked as f64;
+    Some((per_commit * total.saturating_sub(walked) as f64).round() as u64)
 }
 
 fn resolve_tip<'repo>(
@@ -5,16 +5,22 @@
 //! tokio/async which adds significant complexity. The protocol is straightforward enough
 //! that a manual implementation is simpler and more reliable.
 
+pub mod service;
 pub mod tools;
 
 use std::io::{BufRead, Write};
 use std::sync::Arc;
 
-use commitmux_types::{MemoryFtsSearchOpts, MemorySearchOpts, SearchOpts, Store, TouchOpts};
+use commitmux_types::{
+    BlameOpts, ChangelogGroupBy, ChangelogOpts, ConventionalSummaryOpts, DiffOpts, FileOpts,
+    Ingester, MemoryFtsSearchOpts, MemorySearchOpts, RefOpts, SearchOpts, Store, SymbolOpts,
+    TouchOpts, TreeOpts,
+};
 use serde_json::{json, Value};
 use tools::{
-    GetCommitInput, GetPatchInput, SearchInput, SearchMemoryInput, SearchSawInput,
-    SemanticSearchInput, TouchesInput,
+    BlameInput, ChangelogInput, ConventionalSummaryInput, DiffInput, FindByRefInput,
+    GetCommitInput, GetFileInput, GetPatchInput, ListTreeInput, SearchInput, SearchMemoryInput,
+    SearchSawInput, SemanticSearchInput, SymbolHistoryInput, TouchesInput,
 };
 // ListReposInput is defined in tools.rs for API consistency but has no fields to parse
 #[allow(unused_imports)]
@@ -23,19 +29,24 @@ use tools::ListReposInput;
 /// Run the MCP server, blocking until stdin is closed.
 ///
 /// Reads newline-delimited JSON-RPC messages from stdin, dispatches tool calls
-/// to the provided store, and writes JSON-RPC responses to stdout.
-pub fn run_mcp_server(store: Arc<dyn Store + 'static>) -> anyhow::Result<()> {
-    let server = McpServer::new(store);
+/// to the provided store, and writes JSON-RPC responses to stdout. The
+/// ingester resolves git revisions for tools that take a commit range.
+pub fn run_mcp_server(
+    store: Arc<dyn Store + 'static>,
+    ingester: Arc<dyn Ingester + 'static>,
+) -> anyhow::Result<()> {
+    let server = McpServer::new(store, ingester);
     server.run_stdio()
 }
 
 struct McpServer {
     store: Arc<dyn Store + 'static>,
+    ingester: Arc<dyn Ingester + 'static>,
 }
 
 impl McpServer {
-    fn new(store: Arc<dyn Store + 'static>) -> Self {
-        Self { store }
+    fn new(store: Arc<dyn Store + 'static>, ingester: Arc<dyn Ingester + 'static>) -> Self {
+        Self { store, ingester }
     }
 
     fn run_stdio(&self) -> anyhow::Result<()> {
@@ -131,6 +142,12 @@ impl McpServer {
                                 "since": { "type": "integer", "description": "Unix timestamp lower bound" },
                                 "repos": { "type": "array", "items": { "type": "string" }, "description": "Filter by repo names" },
                                 "paths": { "type": "array", "items": { "type": "string" }, "description": "Filter by path substrings" },
+                                "authors": { "type": "array", "items": { "type": "string" }, "description": "Filter by author name or email; any alias (raw, .mailmap or identity map) matches all commits by that person, including as a Co-authored-by trailer" },
+                                "trailer": { "type": "string", "description": "Only commits with this trailer key (e.g. \"Fixes\"), or \"Key:value\" to also match the value as a substring" },
+                                "cc_types": { "type": "array", "items": { "type": "string" }, "description": "Conventional Commit types to include (e.g. [\"feat\", \"fix\"])" },
+                                "cc_scopes": { "type": "array", "items": { "type": "string" }, "description": "Conventional Commit scopes to include" },
+                                "breaking": { "type": "boolean", "description": "true for breaking changes only, false to exclude them" },
+                                "projects": { "type": "array", "items": { "type": "string" }, "description": "Only commits touching one of these repo projects (monorepo sub-projects defined with --project)" },
                                 "limit": { "type": "integer", "description": "Max results (default 20)" }
                             },
                             "required": ["query"]
@@ -145,14 +162,91 @@ impl McpServer {
                                 "path_glob": { "type": "string", "description": "Path substring to match (e.g. 'src/' or '.rs'). Note: glob patterns like '**/*.rs' are NOT supported — use plain substrings." },
                                 "since": { "type": "integer" },
                                 "repos": { "type": "array", "items": { "type": "string" } },
-                                "limit": { "type": "integer" }
+                                "authors": { "type": "array", "items": { "type": "string" }, "description": "Filter by author name or email" },
+                                "projects": { "type": "array", "items": { "type": "string" }, "description": "Only files in one of these repo projects" },
+                                "limit": { "type": "integer" },
+                                "follow_renames": { "type": "boolean", "description": "Also return the history of matching files under their earlier names, following renames and copies (default false)" }
                             },
                             "required": ["path_glob"]
                         }
                     },
+                    {
+                        "name": "commitmux_find_by_ref",
+                        "description": "Find every commit, across all indexed repos, whose message mentions an issue, PR or ticket reference. A bare '#1234' matches that number in any project; 'owner/repo#1234' matches only that project, including bare '#1234' mentions inside owner/repo itself.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "ref": { "type": "string", "description": "Reference such as '#1234', 'acme/api#1234', '!45', 'group/project!45' or 'PROJ-881'" },
+                                "repos": { "type": "array", "items": { "type": "string" }, "description": "Optional list of repo names to search within" },
+                                "limit": { "type": "integer", "description": "Max results (default 50)" }
+                            },
+                            "required": ["ref"]
+                        }
+                    },
+                    {
+                        "name": "commitmux_conventional_summary",
+                        "description": "Changelog-style summary of Conventional Commits (type(scope)!: description): breaking changes first, then commits grouped by type. Use for questions like 'all breaking changes in repo X since <date>'. Commits whose subject is not in Conventional Commits form are not included.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "repos": { "type": "array", "items": { "type": "string" }, "description": "Optional list of repo names" },
+                                "since": { "type": "integer", "description": "Unix timestamp lower bound on author date" },
+                                "until": { "type": "integer", "description": "Unix timestamp upper bound (exclusive) on author date" },
+                                "cc_types": { "type": "array", "items": { "type": "string" }, "description": "Only these types (e.g. [\"feat\", \"fix\"])" },
+                                "cc_scopes": { "type": "array", "items": { "type": "string" }, "description": "Only these scopes" },
+                                "breaking": { "type": "boolean", "description": "true for breaking changes only" },
+                                "limit": { "type": "integer", "description": "Max commits to summarise (default 500)" }
+                            }
+                        }
+                    },
+                    {
+                        "name": "commitmux_changelog",
+                        "description": "Release notes for the commits between two git revisions of one repo, e.g. 'what changed between v1.4.0 and v1.5.0'. Revisions are resolved in the repo's git history (branches, tags or SHAs); only indexed commits are listed, and the number of unindexed commits in the range is reported.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "repo": { "type": "string", "description": "Repo name" },
+                                "from": { "type": "string", "description": "Start revision (exclusive). Omit for all history up to 'to'" },
+                                "to": { "type": "string", "description": "End revision (inclusive), e.g. a tag, branch or SHA" },
+                                "first_parent": { "type": "boolean", "description": "Follow only first parents, so merged branches appear as their merge commit (default false)" },
+                                "group_by": { "type": "string", "enum": ["type", "directory", "author"], "description": "Group by Conventional Commit type (default), top-level directory or author" },
+                                "format": { "type": "string", "enum": ["json", "markdown"], "description": "Output format (default json)" }
+                            },
+                            "required": ["repo", "to"]
+                        }
+                    },
+                    {
+                        "name": "commitmux_blame",
+                        "description": "Line-level blame: for a file and line range at a revision, the commit that last changed each line, with the indexed details (message, trailers, releases) of those commits. Use to answer 'why is this line like this?'.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "repo": { "type": "string", "description": "Repo name" },
+                                "path": { "type": "string", "description": "File path relative to the repo root" },
+                                "rev": { "type": "string", "description": "Revision to blame at: branch, tag or SHA (default HEAD)" },
+                                "start_line": { "type": "integer", "description": "First line, 1-based (default 1)" },
+                                "end_line": { "type": "integer", "description": "Last line, inclusive (default end of file)" }
+                            },
+                            "required": ["repo", "path"]
+                        }
+                    },
+                    {
+                        "name": "commitmux_symbol_history",
+                        "description": "History of one function, method or type: the commits that changed its lines, newest first, each with the diff hunks inside the definition. Like 'git log -L :symbol:file'. Works for Rust, Go, Python and TypeScript files. Pass a bare name ('parse') to match every definition with that name, or qualify it ('Parser::parse', 'Parser.parse').",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "repo": { "type": "string", "description": "Repo name" },
+                                "symbol": { "type": "string", "description": "Function, method or type name, optionally qualified by its enclosing type or module" },
+                                "path": { "type": "string", "description": "Only changes in this file, or in files under this directory" },
+                                "limit": { "type": "integer", "description": "Max results (default 50)" }
+                            },
+                            "required": ["repo", "symbol"]
+                        }
+                    },
                     {
                         "name": "commitmux_get_commit",
-                        "description": "Get full details for a specific commit",
+                        "description": "Get full details for a specific commit, including its trailers and the releases (tags) that contain it: 'first_release' is the earliest tag containing the commit, 'contained_in' lists every such tag",
                         "inputSchema": {
                             "type": "object",
                             "properties": {
@@ -164,13 +258,63 @@ impl McpServer {
                     },
                     {
                         "name": "commitmux_get_patch",
-                        "description": "Get the patch (diff) for a specific commit",
+                        "description": "Get the patch (diff) for a specific commit. The default text format returns one unified diff string. The structured format returns files -> hunks -> lines with old and new line numbers, can be limited to some paths, and applies max_bytes per file without splitting hunks. Patches over the ingest size limit are stored cut short and reported with stored_truncated and their original_size; truncated means max_bytes cut the result; set full to read the whole patch from the repo's local clone.",
                         "inputSchema": {
                             "type": "object",
                             "properties": {
                                 "repo": { "type": "string" },
                                 "sha": { "type": "string" },
-                                "max_bytes": { "type": "integer", "description": "Truncate patch to this many bytes" }
+                                "max_bytes": { "type": "integer", "description": "Text: truncate patch to this many bytes. Structured: most bytes of whole hunks per file" },
+                                "format": { "type": "string", "enum": ["text", "structured"], "description": "Output format (default text)" },
+                                "paths": { "type": "array", "items": { "type": "string" }, "description": "Structured format only: only files at or under these paths" },
+                                "full": { "type": "boolean", "description": "If the stored patch was truncated at ingest, regenerate it whole from git (default false)" }
+                            },
+                            "required": ["repo", "sha"]
+                        }
+                    },
+                    {
+                        "name": "commitmux_diff",
+                        "description": "Diff any two revisions of a repo, computed live from its local clone: per-file status and line counts, plus the unified patch unless stat_only is set. Paths the repo excludes from indexing are left out. Use for 'what changed between v1.2 and v1.3 in src/auth?'.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "repo": { "type": "string", "description": "Repo name" },
+                                "from": { "type": "string", "description": "Base revision: branch, tag or SHA" },
+                                "to": { "type": "string", "description": "Target revision: branch, tag or SHA" },
+                                "paths": { "type": "array", "items": { "type": "string" }, "description": "Only files at or under these paths" },
+                                "stat_only": { "type": "boolean", "description": "Return per-file line counts without the patch text (default false)" },
+                                "max_bytes": { "type": "integer", "description": "Truncate patch text to this many bytes" }
+                            },
+                            "required": ["repo", "from", "to"]
+                        }
+                    },
+                    {
+                        "name": "commitmux_get_file",
+                        "description": "Read a file as it was at a commit, from the repo's local clone. Pass a line range or a byte offset to read part of a large file; 'truncated' is true when max_bytes cut the read short. Binary files return metadata only ('binary': true, no 'content').",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "repo": { "type": "string", "description": "Repo name" },
+                                "sha": { "type": "string", "description": "Commit to read at: a SHA, or any branch or tag" },
+                                "path": { "type": "string", "description": "File path relative to the repo root" },
+                                "start_line": { "type": "integer", "description": "First line, 1-based (default 1)" },
+                                "end_line": { "type": "integer", "description": "Last line, inclusive (default end of file)" },
+                                "offset": { "type": "integer", "description": "Byte offset to start at; cannot be combined with a line range" },
+                                "max_bytes": { "type": "integer", "description": "Most bytes of content to return (default 100000)" }
+                            },
+                            "required": ["repo", "sha", "path"]
+                        }
+                    },
+                    {
+                        "name": "commitmux_list_tree",
+                        "description": "List the files and directories of a repo as they were at a commit, with git modes, object ids and blob sizes. Use with commitmux_get_file to navigate a historical state without a checkout. Lists one level by default; raise 'depth' to descend.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "repo": { "type": "string", "description": "Repo name" },
+                                "sha": { "type": "string", "description": "Commit to list at: a SHA, or any branch or tag" },
+                                "path": { "type": "string", "description": "Directory to list, relative to the repo root (default the root)" },
+                                "depth": { "type": "integer", "description": "Levels to descend; 1 lists only the directory's own entries (default 1)" }
                             },
                             "required": ["repo", "sha"]
                         }
@@ -192,6 +336,8 @@ impl McpServer {
                                 "query": { "type": "string", "description": "Natural language description of what you're looking for" },
                                 "repos": { "type": "array", "items": { "type": "string" }, "description": "Optional list of repo names to search within" },
                                 "since": { "type": "integer", "description": "Optional Unix timestamp lower bound" },
+                                "authors": { "type": "array", "items": { "type": "string" }, "description": "Optional list of author names or emails" },
+                                "projects": { "type": "array", "items": { "type": "string" }, "description": "Only commits touching one of these repo projects" },
                                 "limit": { "type": "integer", "description": "Max results (default 10)" }
                             },
                             "required": ["query"]
@@ -236,8 +382,16 @@ impl McpServer {
         let result = match name {
             "commitmux_search" => self.call_search(&arguments),
             "commitmux_touches" => self.call_touches(&arguments),
+            "commitmux_find_by_ref" => self.call_find_by_ref(&arguments),
+            "commitmux_conventional_summary" => self.call_conventional_summary(&arguments),
+            "commitmux_changelog" => self.call_changelog(&arguments),
+            "commitmux_blame" => self.call_blame(&arguments),
+            "commitmux_symbol_history" => self.call_symbol_history(&arguments),
             "commitmux_get_commit" => self.call_get_commit(&arguments),
             "commitmux_get_patch" => self.call_get_patch(&arguments),
+            "commitmux_diff" => self.call_diff(&arguments),
+            "commitmux_get_file" => self.call_get_file(&arguments),
+            "commitmux_list_tree" => self.call_list_tree(&arguments),
             "commitmux_list_repos" => self.call_list_repos(&arguments),
             "commitmux_search_semantic" => self.call_search_semantic(&arguments),
             "commitmux_search_memory" => self.call_search_memory(&arguments),
@@ -273,6 +427,12 @@ impl McpServer {
             since: input.since,
             repos: input.repos,
             paths: input.paths,
+            authors: input.authors,
+            trailer: input.trailer,
+            cc_types: input.cc_types,
+            cc_scopes: input.cc_scopes,
+            breaking: input.breaking,
+            projects: input.projects,
             limit: input.limit,
         };
 
@@ -289,7 +449,10 @@ impl McpServer {
         let opts = TouchOpts {
             since: input.since,
             repos: input.repos,
+            authors: input.authors,
+            projects: input.projects,
             limit: input.limit,
+            follow_renames: input.follow_renames.unwrap_or(false),
         };
 
         self.store
@@ -298,12 +461,123 @@ impl McpServer {
             .and_then(|results| serde_json::to_string(&results).map_err(|e| e.to_string()))
     }
 
+    fn call_find_by_ref(&self, arguments: &Value) -> Result<String, String> {
+        let input: FindByRefInput = serde_json::from_value(arguments.clone())
+            .map_err(|e| format!("Invalid arguments for commitmux_find_by_ref: {e}"))?;
+
+        if input.reference.trim().is_empty() {
+            return Err("ref must not be empty".to_string());
+        }
+        let opts = RefOpts {
+            repos: input.repos,
+            limit: input.limit,
+        };
+
+        self.store
+            .find_by_ref(&input.reference, &opts)
+            .map_err(|e| e.to_string())
+            .and_then(|results| serde_json::to_string(&results).map_err(|e| e.to_string()))
+    }
+
+    fn call_conventional_summary(&self, arguments: &Value) -> Result<String, String> {
+        let input: ConventionalSummaryInput = serde_json::from_value(arguments.clone())
+            .map_err(|e| format!("Invalid arguments for commitmux_conventional_summary: {e}"))?;
+
+        if input.limit == Some(0) {
+            return Err("limit must be greater than 0".to_string());
+        }
+        let opts = ConventionalSummaryOpts {
+            repos: input.repos,
+            since: input.since,
+            until: input.until,
+            cc_types: input.cc_types,
+            cc_scopes: input.cc_scopes,
+            breaking: input.breaking,
+            limit: input.limit,
+        };
+
+        self.store
+            .conventional_summary(&opts)
+            .map_err(|e| e.to_string())
+            .and_then(|summary| serde_json::to_string(&summary).map_err(|e| e.to_string()))
+    }
+
+    fn call_changelog(&self, arguments: &Value) -> Result<String, String> {
+        let input: ChangelogInput = serde_json::from_value(arguments.clone())
+            .map_err(|e| format!("Invalid arguments for commitmux_changelog: {e}"))?;
+
+        let group_by = match input.group_by.as_deref() {
+            None => ChangelogGroupBy::default(),
+            Some(g) => ChangelogGroupBy::from_str(g).ok_or_else(|| {
+                format!("group_by must be 'type', 'directory' or 'author', got '{g}'")
+            })?,
+        };
+        let markdown = match input.format.as_deref() {
+            None | Some("json") => false,
+            Some("markdown") => true,
+            Some(f) => return Err(format!("format must be 'json' or 'markdown', got '{f}'")),
+        };
+        let opts = ChangelogOpts {
+            repo: input.repo,
+            from: input.from.filter(|f| !f.trim().is_empty()),
+            to: input.to,
+            first_parent: input.first_parent.unwrap_or(false),
+            group_by,
+        };
+
+        let changelog =
+            service::changelog(&*self.store, &*self.ingester, &opts).map_err(|e| e.to_string())?;
+        if markdown {
+            Ok(service::changelog_markdown(&changelog))
+        } else {
+            serde_json::to_string(&changelog).map_err(|e| e.to_string())
+        }
+    }
+
+    fn call_blame(&self, arguments: &Value) -> Result<String, String> {
+        let input: BlameInput = serde_json::from_value(arguments.clone())
+            .map_err(|e| format!("Invalid arguments for commitmux_blame: {e}"))?;
+
+        let opts = BlameOpts {
+            repo: input.repo,
+            path: input.path,
+            rev: input.rev,
+            start_line: input.start_line,
+            end_line: input.end_line,
+        };
+        service::blame(&*self.store, &*self.ingester, &opts)
+            .map_err(|e| e.to_string())
+            .and_then(|blame| serde_json::to_string(&blame).map_err(|e| e.to_string()))
+    }
+
+    fn call_symbol_history(&self, arguments: &Value) -> Result<String, String> {
+        let input: SymbolHistoryInput = serde_json::from_value(arguments.clone())
+            .map_err(|e| format!("Invalid arguments for commitmux_symbol_history: {e}"))?;
+
+        if input.symbol.trim().is_empty() {
+            return Err("symbol must not be empty".to_string());
+        }
+        let repo = self
+            .store
+            .get_repo_by_name(&input.repo)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Repo '{}' not found", input.repo))?;
+        let opts = SymbolOpts {
+            path: input.path,
+            limit: input.limit,
+        };
+
+        self.store
+            .symbol_history(repo.repo_id, input.symbol.trim(), &opts)
+            .map_err(|e| e.to_string())
+            .and_then(|changes| serde_json::to_string(&changes).map_err(|e| e.to_string()))
+    }
+
     fn call_get_commit(&self, arguments: &Value) -> Result<String, String> {
         let input: GetCommitInput = serde_json::from_value(arguments.clone())
             .map_err(|e| format!("Invalid arguments for commitmux_get_commit: {e}"))?;
 
-        self.store
-            .get_commit(&input.repo, &input.sha)
+        service::commit_detail(&*self.store, &*self.ingester, &input.repo, &input.sha)
             .map_err(|e| e.to_string())
             .and_then(|opt| {
                 let result =
@@ -323,14 +597,89 @@ impl McpServer {
         let input: GetPatchInput = serde_json::from_value(arguments.clone())
             .map_err(|e| format!("Invalid arguments for commitmux_get_patch: {e}"))?;
 
-        self.store
-            .get_patch(&input.repo, &input.sha, input.max_bytes)
+        let structured = match input.format.as_deref() {
+            None | Some("text") => false,
+            Some("structured") => true,
+            Some(other) => {
+                return Err(format!(
+                    "Invalid format '{other}': expected 'text' or 'structured'"
+                ))
+            }
+        };
+        if !structured && input.paths.is_some() {
+            return Err("paths requires format 'structured'".to_string());
+        }
+
+        // Structured output budgets max_bytes per file, so it needs the whole patch.
+        let max_bytes = if structured { None } else { input.max_bytes };
+        let result = service::patch(
+            &*self.store,
+            &*self.ingester,
+            &input.repo,
+            &input.sha,
+            max_bytes,
+            input.full.unwrap_or(false),
+        )
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Patch {}:{} not found", input.repo, input.sha))?;
+        if structured {
+            let paths = input.paths.unwrap_or_default();
+            let patch = commitmux_ingest::structured_patch(&result, &paths, input.max_bytes)
+                .map_err(|e| e.to_string())?;
+            serde_json::to_string(&patch).map_err(|e| e.to_string())
+        } else {
+            serde_json::to_string(&result).map_err(|e| e.to_string())
+        }
+    }
+
+    fn call_diff(&self, arguments: &Value) -> Result<String, String> {
+        let input: DiffInput = serde_json::from_value(arguments.clone())
+            .map_err(|e| format!("Invalid arguments for commitmux_diff: {e}"))?;
+
+        let opts = DiffOpts {
+            repo: input.repo,
+            from: input.from,
+            to: input.to,
+            paths: input.paths.unwrap_or_default(),
+            stat_only: input.stat_only.unwrap_or(false),
+            max_bytes: input.max_bytes,
+        };
+        service::diff(&*self.store, &*self.ingester, &opts)
             .map_err(|e| e.to_string())
-            .and_then(|opt| {
-                let result =
-                    opt.ok_or_else(|| format!("Patch {}:{} not found", input.repo, input.sha))?;
-                serde_json::to_string(&result).map_err(|e| e.to_string())
-            })
+            .and_then(|diff| serde_json::to_string(&diff).map_err(|e| e.to_string()))
+    }
+
+    fn call_get_file(&self, arguments: &Value) -> Result<String, String> {
+        let input: GetFileInput = serde_json::from_value(arguments.clone())
+            .map_err(|e| format!("Invalid arguments for commitmux_get_file: {e}"))?;
+
+        let opts = FileOpts {
+            repo: input.repo,
+            path: input.path,
+            rev: input.sha,
+            start_line: input.start_line,
+            end_line: input.end_line,
+            offset: input.offset,
+            max_bytes: input.max_bytes,
+        };
+        service::read_file(&*self.store, &*self.ingester, &opts)
+            .map_err(|e| e.to_string())
+            .and_then(|file| serde_json::to_string(&file).map_err(|e| e.to_string()))
+    }
+
+    fn call_list_tree(&self, arguments: &Value) -> Result<String, String> {
+        let input: ListTreeInput = serde_json::from_value(arguments.clone())
+            .map_err(|e| format!("Invalid arguments for commitmux_list_tree: {e}"))?;
+
+        let opts = TreeOpts {
+            repo: input.repo,
+            rev: input.sha,
+            path: input.path,
+            depth: input.depth,
+        };
+        service::list_tree(&*self.store, &*self.ingester, &opts)
+            .map_err(|e| e.to_string())
+            .and_then(|listing| serde_json::to_string(&listing).map_err(|e| e.to_string()))
     }
 
     fn call_search_semantic(&self, arguments: &Value) -> Result<String, String> {
@@ -382,6 +731,8 @@ impl McpServer {
         let opts = SemanticSearchOpts {
             repos: input.repos,
             since: input.since,
+            authors: input.authors,
+            projects: input.projects,
             limit: input.limit,
         };
         let results = self
@@ -470,6 +821,12 @@ impl McpServer {
             since: None,
             repos: None,
             paths: None,
+            authors: None,
+            trailer: None,
+            cc_types: None,
+            cc_scopes: None,
+            breaking: None,
+            projects: None,
             limit: input.limit.or(Some(20)),
         };
 
@@ -484,11 +841,14 @@ impl McpServer {
 mod tests {
     use super::*;
     use commitmux_types::{
-        Commit, CommitFile, CommitPatch, EmbedCommit, IngestState, Repo, RepoInput, RepoListEntry,
-        RepoStats, RepoUpdate, SearchOpts, SemanticSearchOpts, TouchOpts,
+        BlameHunk, ChangelogCommit, CommitDetail, CommitRange, ConventionalCommit,
+        ConventionalEntry, ConventionalSummary, DiffFileStat, DiffResult, FileBlame, FileContent,
+        FileOpts, IgnoreConfig, PatchResult, RefResult, Result as StoreResult, SearchResult, Store,
+        SymbolChange, SyncControl, SyncSummary, Tag, TagIndex, TouchResult, TreeEntry, TreeListing,
     };
     use commitmux_types::{
-        CommitDetail, PatchResult, Result as StoreResult, SearchResult, Store, TouchResult,
+        Commit, CommitFile, CommitPatch, EmbedCommit, IngestState, Repo, RepoInput, RepoListEntry,
+        RepoStats, RepoUpdate, SearchOpts, SemanticSearchOpts, TouchOpts,
     };
 
     /// A minimal in-memory stub store for testing.
@@ -502,8 +862,25 @@ mod tests {
             // Return empty list for validation tests
             Ok(vec![])
         }
-        fn get_repo_by_name(&self, _name: &str) -> StoreResult<Option<Repo>> {
-            unimplemented!()
+        fn get_repo_by_name(&self, name: &str) -> StoreResult<Option<Repo>> {
+            Ok((name == "testrepo").then(|| Repo {
+                repo_id: 1,
+                name: name.into(),
+                local_path: "/tmp/testrepo".into(),
+                remote_url: None,
+                default_branch: None,
+                fork_of: None,
+                author_filters: vec![],
+                include_paths: vec![],
+                ref_patterns: vec![],
+                projects: vec![],
+                exclude_prefixes: vec![],
+                embed_enabled: false,
+                clone_depth: None,
+                clone_since: None,
+                ssh_key: None,
+                token_env: None,
+            }))
         }
         fn remove_repo(&self, _name: &str) -> StoreResult<()> {
             unimplemented!()
@@ -523,6 +900,9 @@ mod tests {
         fn upsert_patch(&self, _patch: &CommitPatch) -> StoreResult<()> {
             unimplemented!()
         }
+        fn write_batch(&self, _batch: &commitmux_types::WriteBatch) -> StoreResult<()> {
+            unimplemented!()
+        }
         fn get_ingest_state(&self, _repo_id: i64) -> StoreResult<Option<IngestState>> {
             unimplemented!()
         }
@@ -532,6 +912,20 @@ mod tests {
         fn commit_exists(&self, _repo_id: i64, _sha: &str) -> StoreResult<bool> {
             unimplemented!()
         }
+        fn list_tags(&self, _repo_id: i64) -> StoreResult<Vec<Tag>> {
+            unimplemented!()
+        }
+        fn replace_tags(&self, _repo_id: i64, _index: &TagIndex) -> StoreResult<()> {
+            unimplemented!()
+        }
+
+        fn reextract_refs(
+            &self,
+            _repo_id: i64,
+            _extract: &dyn Fn(&str) -> Vec<commitmux_types::CommitRef>,
+        ) -> StoreResult<usize> {
+            unimplemented!()
+        }
 
         fn search(&self, query: &str, _opts: &SearchOpts) -> StoreResult<Vec<SearchResult>> {
             Ok(vec![SearchResult {
@@ -554,9 +948,88 @@ mod tests {
                 date: 1700000001,
                 path: path_glob.to_string(),
                 status: "M".into(),
+                old_path: None,
+                kind: Default::default(),
+                size: None,
+            }])
+        }
+
+        fn find_by_ref(&self, reference: &str, _opts: &RefOpts) -> StoreResult<Vec<RefResult>> {
+            Ok(vec![RefResult {
+                repo: "testrepo".into(),
+                sha: "abc123".into(),
+                subject: format!("Fix race ({})", reference),
+                author: "Alice".into(),
+                date: 1700000000,
+                reference: reference.into(),
             }])
         }
 
+        fn conventional_summary(
+            &self,
+            opts: &ConventionalSummaryOpts,
+        ) -> StoreResult<ConventionalSummary> {
+            let entry = ConventionalEntry {
+                repo: "testrepo".into(),
+                sha: "abc123".into(),
+                scope: Some("api".into()),
+                breaking: true,
+                description: "drop v1".into(),
+                author: "Alice".into(),
+                date: 1700000000,
+            };
+            let entries = if opts.breaking == Some(false) {
+                vec![]
+            } else {
+                vec![("feat".to_string(), entry)]
+            };
+            Ok(ConventionalSummary::group(entries, false))
+        }
+
+        fn changelog_commits(&self, _: i64, shas: &[String]) -> StoreResult<Vec<ChangelogCommit>> {
+            // Only `abc123` is indexed.
+            Ok(shas
+                .iter()
+                .filter(|sha| *sha == "abc123")
+                .map(|sha| ChangelogCommit {
+                    sha: sha.clone(),
+                    subject: "fix(api): retry on 503".into(),
+                    author: "Alice".into(),
+                    date: 1700000000,
+                    conventional: ConventionalCommit::parse("fix(api): retry on 503", None),
+                    paths: vec!["src/api.rs".into()],
+                })
+                .collect())
+        }
+
+        fn symbol_history(
+            &self,
+            _: i64,
+            symbol: &str,
+            opts: &SymbolOpts,
+        ) -> StoreResult<Vec<SymbolChange>> {
+            if symbol != "parse" && symbol != "Parser::parse" {
+                return Ok(vec![]);
+            }
+            let change = SymbolChange {
+                sha: "abc123".into(),
+                subject: "fix(api): retry on 503".into(),
+                author: "Alice".into(),
+                date: 1700000000,
+                path: "src/parser.rs".into(),
+                symbol: "Parser::parse".into(),
+                kind: "method".into(),
+                excerpt:
+                    "@@ -10,3 +10,4 @@ impl Parser {\n-        self.next()\n+        self.retry()\n"
+                        .into(),
+            };
+            let matches = opts
+                .path
+                .as_deref()
+                .is_none_or(|p| change.path.starts_with(p));
+            Ok(if matches { vec![change] } else { vec![] })
+        }
+
         fn get_commit(
             &self,
             repo_name: &str,
@@ -571,6 +1044,13 @@ mod tests {
                     author: "Alice".into(),
                     date: "2023-11-14T22:13:20Z".into(),
                     changed_files: vec![],
+                    trailers: Default::default(),
+                    first_release: Some("v1.2.0".into()),
+                    contained_in: vec!["v1.2.0".into(), "v1.3.0".into()],
+                    patch_truncated: false,
+                    patch_size: Some(96),
+                    submodules: vec![],
+                    projects: vec![],
                 }))
             } else {
                 Ok(None)
@@ -587,7 +1067,20 @@ mod tests {
                 Ok(Some(PatchResult {
                     repo: repo_name.into(),
                     sha: sha.into(),
-                    patch_text: "diff --git a/src/lib.rs b/src/lib.rs\n".into(),
+                    patch_text: "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n-old\n+new\n same\n".into(),
+                    truncated: false,
+                    stored_truncated: false,
+                    original_size: Some(96),
+                }))
+            } else if repo_name == "testrepo" && sha == "def456" {
+                // Cut at ingest partway through its only hunk.
+                Ok(Some(PatchResult {
+                    repo: repo_name.into(),
+                    sha: sha.into(),
+                    patch_text: "diff --git a/big.txt b/big.txt\n--- a/big.txt\n+++ b/big.txt\n@@ -1,3 +1,3 @@\n-a\n".into(),
+                    truncated: false,
+                    stored_truncated: true,
+                    original_size: Some(80),
                 }))
             } else {
                 Ok(None)
@@ -606,6 +1099,15 @@ mod tests {
             Ok(0)
         }
 
+        fn add_identity(&self, _alias: &commitmux_types::IdentityAlias) -> StoreResult<()> {
+            Ok(())
+        }
+        fn list_identities(&self) -> StoreResult<Vec<commitmux_types::IdentityAlias>> {
+            Ok(vec![])
+        }
+        fn remove_identity(&self, _alias_email: &str) -> StoreResult<()> {
+            Ok(())
+        }
         fn get_config(&self, _key: &str) -> StoreResult<Option<String>> {
             Ok(None)
         }
@@ -680,8 +1182,135 @@ mod tests {
         }
     }
 
+    /// Resolves every range to two fixed commits, `abc123` and `def456`,
+    /// and blames every file to them.
+    struct StubIngester;
+
+    impl Ingester for StubIngester {
+        fn sync_repo_with(
+            &self,
+            _: &Repo,
+            _: &dyn Store,
+            _: &IgnoreConfig,
+            _: &SyncControl<'_>,
+        ) -> StoreResult<SyncSummary> {
+            unimplemented!()
+        }
+        fn commit_range(
+            &self,
+            _: &Repo,
+            from: Option<&str>,
+            to: &str,
+            _: bool,
+        ) -> StoreResult<CommitRange> {
+            Ok(CommitRange {
+                from_sha: from.map(|f| format!("{f}-sha")),
+                to_sha: format!("{to}-sha"),
+                shas: vec!["def456".into(), "abc123".into()],
+            })
+        }
+        fn blame(&self, _: &Repo, opts: &BlameOpts) -> StoreResult<FileBlame> {
+            let hunk = |sha: &str, line: usize, text: &str| BlameHunk {
+                sha: sha.into(),
+                start_line: line,
+                end_line: line,
+                orig_path: None,
+                orig_start_line: line,
+                author: "Alice".into(),
+                date: 1700000000,
+                lines: vec![text.into()],
+            };
+            Ok(FileBlame {
+                rev_sha: format!("{}-sha", opts.rev.as_deref().unwrap_or("HEAD")),
+                start_line: 1,
+                end_line: 2,
+                hunks: vec![hunk("abc123", 1, "fn main() {"), hunk("def456", 2, "}")],
+            })
+        }
+        fn read_file(&self, repo: &Repo, opts: &FileOpts) -> StoreResult<FileContent> {
+            Ok(FileContent {
+                repo: repo.name.clone(),
+                path: opts.path.clone(),
+                rev: opts.rev.clone(),
+                sha: format!("{}-sha", opts.rev),
+                size: 13,
+                total_lines: 2,
+                content: Some("fn main() {\n}".into()),
+                ..Default::default()
+            })
+        }
+        fn diff(
+            &self,
+            repo: &Repo,
+            opts: &DiffOpts,
+            config: &IgnoreConfig,
+        ) -> StoreResult<DiffResult> {
+            let files: Vec<DiffFileStat> = ["src/main.rs", "vendor/lib.rs"]
+                .into_iter()
+                .filter(|p| {
+                    !config
+                        .path_prefixes
+                        .iter()
+                        .any(|x| p.starts_with(x.as_str()))
+                })
+                .map(|p| DiffFileStat {
+                    path: p.into(),
+                    status: "M".into(),
+                    old_path: None,
+                    additions: 1,
+                    deletions: 1,
+                    binary: false,
+                })
+                .collect();
+            Ok(DiffResult {
+                repo: repo.name.clone(),
+                from: opts.from.clone(),
+                to: opts.to.clone(),
+                from_sha: format!("{}-sha", opts.from),
+                to_sha: format!("{}-sha", opts.to),
+                additions: files.len(),
+                deletions: files.len(),
+                files,
+                patch_text: (!opts.stat_only).then(|| "-old\n+new\n".into()),
+                truncated: false,
+            })
+        }
+        fn patch(
+            &self,
+            repo: &Repo,
+            sha: &str,
+            _config: &IgnoreConfig,
+        ) -> StoreResult<PatchResult> {
+            Ok(PatchResult {
+                repo: repo.name.clone(),
+                sha: sha.into(),
+                patch_text: "diff --git a/big.txt b/big.txt\n--- a/big.txt\n+++ b/big.txt\n@@ -1,3 +1,3 @@\n-a\n+b\n c\n d\n".into(),
+                truncated: false,
+                stored_truncated: false,
+                original_size: Some(80),
+            })
+        }
+        fn list_tree(&self, repo: &Repo, opts: &TreeOpts) -> StoreResult<TreeListing> {
+            Ok(TreeListing {
+                repo: repo.name.clone(),
+                rev: opts.rev.clone(),
+                sha: format!("{}-sha", opts.rev),
+                path: opts.path.clone().unwrap_or_default(),
+                depth: opts.depth.unwrap_or(1),
+                entries: vec![TreeEntry {
+                    path: "src/main.rs".into(),
+                    kind: "blob".into(),
+                    mode: "100644".into(),
+                    sha: "blob-sha".into(),
+                    size: Some(13),
+                }],
+                truncated: false,
+            })
+        }
+    }
+
     fn make_server() -> McpServer {
-        McpServer::new(Arc::new(StubStore))
+        McpServer::new(Arc::new(StubStore), Arc::new(StubIngester))
     }
 
     struct StubStoreWithRepos;
@@ -713,9 +1342,16 @@ mod tests {
                     remote_url: None,
                     default_branch: None,
                     fork_of: None,
-                    author_filter: None,
+                    author_filters: vec![],
+                    include_paths: vec![],
+                    ref_patterns: vec![],
+                    projects: vec![],
                     exclude_prefixes: vec![],
                     embed_enabled: false,
+                    clone_depth: None,
+                    clone_since: None,
+                    ssh_key: None,
+                    token_env: None,
                 },
                 Repo {
                     repo_id: 2,
@@ -724,9 +1360,16 @@ mod tests {
                     remote_url: None,
                     default_branch: None,
                     fork_of: None,
-                    author_filter: None,
+                    author_filters: vec![],
+                    include_paths: vec![],
+                    ref_patterns: vec![],
+                    projects: vec![],
                     exclude_prefixes: vec![],
                     embed_enabled: false,
+                    clone_depth: None,
+                    clone_since: None,
+                    ssh_key: None,
+                    token_env: None,
                 },
             ])
         }
@@ -739,6 +1382,19 @@ mod tests {
         fn commit_exists(&self, _: i64, _: &str) -> StoreResult<bool> {
             unimplemented!()
         }
+        fn list_tags(&self, _: i64) -> StoreResult<Vec<Tag>> {
+            unimplemented!()
+        }
+        fn replace_tags(&self, _: i64, _: &TagIndex) -> StoreResult<()> {
+            unimplemented!()
+        }
+        fn reextract_refs(
+            &self,
+            _: i64,
+            _: &dyn Fn(&str) -> Vec<commitmux_types::CommitRef>,
+        ) -> StoreResult<usize> {
+            unimplemented!()
+        }
         fn update_repo(&self, _: i64, _: &RepoUpdate) -> StoreResult<Repo> {
             unimplemented!()
         }
@@ -751,6 +1407,9 @@ mod tests {
         fn upsert_patch(&self, _: &CommitPatch) -> StoreResult<()> {
             unimplemented!()
         }
+        fn write_batch(&self, _: &commitmux_types::WriteBatch) -> StoreResult<()> {
+            unimplemented!()
+        }
         fn get_ingest_state(&self, _: i64) -> StoreResult<Option<IngestState>> {
             unimplemented!()
         }
@@ -763,6 +1422,26 @@ mod tests {
         fn touches(&self, _: &str, _: &TouchOpts) -> StoreResult<Vec<TouchResult>> {
             unimplemented!()
         }
+        fn find_by_ref(&self, _: &str, _: &RefOpts) -> StoreResult<Vec<RefResult>> {
+            unimplemented!()
+        }
+        fn conventional_summary(
+            &self,
+            _: &ConventionalSummaryOpts,
+        ) -> StoreResult<ConventionalSummary> {
+            unimplemented!()
+        }
+        fn changelog_commits(&self, _: i64, _: &[String]) -> StoreResult<Vec<ChangelogCommit>> {
+            unimplemented!()
+        }
+        fn symbol_history(
+            &self,
+            _: i64,
+            _: &str,
+            _: &SymbolOpts,
+        ) -> StoreResult<Vec<SymbolChange>> {
+            unimplemented!()
+        }
         fn get_commit(&self, _: &str, _: &str) -> StoreResult<Option<CommitDetail>> {
             unimplemented!()
         }
@@ -783,6 +1462,15 @@ mod tests {
         fn count_embeddings_for_repo(&self, _: i64) -> StoreResult<usize> {
             Ok(0)
         }
+        fn add_identity(&self, _alias: &commitmux_types::IdentityAlias) -> StoreResult<()> {
+            Ok(())
+        }
+        fn list_identities(&self) -> StoreResult<Vec<commitmux_types::IdentityAlias>> {
+            Ok(vec![])
+        }
+        fn remove_identity(&self, _alias_email: &str) -> StoreResult<()> {
+            Ok(())
+        }
         fn get_config(&self, _key: &str) -> StoreResult<Option<String>> {
             Ok(None)
         }
@@ -889,7 +1577,39 @@ mod tests {
             tool_names.contains(&"commitmux_get_patch"),
             "missing commitmux_get_patch"
         );
-        assert_eq!(tool_names.len(), 8, "must have exactly 8 tools");
+        assert!(
+            tool_names.contains(&"commitmux_find_by_ref"),
+            "missing commitmux_find_by_ref"
+        );
+        assert!(
+            tool_names.contains(&"commitmux_conventional_summary"),
+            "missing commitmux_conventional_summary"
+        );
+        assert!(
+            tool_names.contains(&"commitmux_changelog"),
+            "missing commitmux_changelog"
+        );
+        assert!(
+            tool_names.contains(&"commitmux_blame"),
+            "missing commitmux_blame"
+        );
+        assert!(
+            tool_names.contains(&"commitmux_symbol_history"),
+            "missing commitmux_symbol_history"
+        );
+        assert!(
+            tool_names.contains(&"commitmux_get_file"),
+            "missing commitmux_get_file"
+        );
+        assert!(
+            tool_names.contains(&"commitmux_list_tree"),
+            "missing commitmux_list_tree"
+        );
+        assert!(
+            tool_names.contains(&"commitmux_diff"),
+            "missing commitmux_diff"
+        );
+        assert_eq!(tool_names.len(), 16, "must have exactly 16 tools");
     }
 
     #[test]
@@ -951,6 +1671,380 @@ mod tests {
         assert!(results.as_array().is_some());
     }
 
+    #[test]
+    fn test_tools_call_find_by_ref() {
+        let server = make_server();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 4,
+            "method": "tools/call",
+            "params": {
+                "name": "commitmux_find_by_ref",
+                "arguments": { "ref": "acme/api#1234" }
+            }
+        })
+        .to_string();
+
+        let response_str = server
+            .handle_message(&request)
+            .expect("tools/call must produce a response");
+        let response: Value = serde_json::from_str(&response_str).expect("valid JSON");
+
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("text field");
+        let results: Value = serde_json::from_str(text).expect("results must be JSON");
+        assert_eq!(results[0]["reference"], "acme/api#1234");
+    }
+
+    #[test]
+    fn test_tools_call_changelog() {
+        let server = make_server();
+        let call = |arguments: Value| -> Value {
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": 6,
+                "method": "tools/call",
+                "params": { "name": "commitmux_changelog", "arguments": arguments }
+            })
+            .to_string();
+            let response_str = server
+                .handle_message(&request)
+                .expect("tools/call must produce a response");
+            serde_json::from_str(&response_str).expect("valid JSON")
+        };
+
+        let response = call(json!({ "repo": "testrepo", "from": "v1.4.0", "to": "v1.5.0" }));
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("text field");
+        let log: Value = serde_json::from_str(text).expect("changelog must be JSON");
+        assert_eq!(log["to_sha"], "v1.5.0-sha");
+        assert_eq!(log["total"], 1);
+        assert_eq!(log["unindexed"], 1);
+        assert_eq!(log["groups"][0]["name"], "fix");
+
+        let response = call(json!({ "repo": "testrepo", "to": "main", "format": "markdown" }));
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("text field");
+        assert!(text.contains("### Bug fixes"), "{text}");
+        assert!(text.contains("**api:** retry on 503"), "{text}");
+
+        let response = call(json!({ "repo": "nope", "to": "main" }));
+        assert_eq!(response["result"]["isError"], true);
+        let response = call(json!({ "repo": "testrepo", "to": "main", "group_by": "size" }));
+        assert_eq!(response["result"]["isError"], true);
+    }
+
+    #[test]
+    fn test_tools_call_conventional_summary() {
+        let server = make_server();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 5,
+            "method": "tools/call",
+            "params": {
+                "name": "commitmux_conventional_summary",
+                "arguments": { "repos": ["testrepo"], "breaking": true }
+            }
+        })
+        .to_string();
+
+        let response_str = server
+            .handle_message(&request)
+            .expect("tools/call must produce a response");
+        let response: Value = serde_json::from_str(&response_str).expect("valid JSON");
+
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("text field");
+        let summary: Value = serde_json::from_str(text).expect("summary must be JSON");
+        assert_eq!(summary["breaking"][0]["description"], "drop v1");
+        assert_eq!(summary["sections"][0]["cc_type"], "feat");
+    }
+
+    #[test]
+    fn test_tools_call_blame() {
+        let server = make_server();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "tools/call",
+            "params": {
+                "name": "commitmux_blame",
+                "arguments": { "repo": "testrepo", "path": "src/main.rs", "rev": "v1.0.0" }
+            }
+        })
+        .to_string();
+
+        let response_str = server
+            .handle_message(&request)
+            .expect("tools/call must produce a response");
+        let response: Value = serde_json::from_str(&response_str).expect("valid JSON");
+
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("text field");
+        let blame: Value = serde_json::from_str(text).expect("blame must be JSON");
+        assert_eq!(blame["rev"], "v1.0.0");
+        assert_eq!(blame["rev_sha"], "v1.0.0-sha");
+        assert_eq!(blame["hunks"][1]["sha"], "def456");
+        // Only abc123 is indexed in the stub store.
+        assert_eq!(blame["commits"]["abc123"]["subject"], "test commit");
+        assert!(blame["commits"].get("def456").is_none());
+    }
+
+    #[test]
+    fn test_tools_call_get_patch_structured() {
+        let server = make_server();
+        let call = |arguments: Value| -> Value {
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": 11,
+                "method": "tools/call",
+                "params": { "name": "commitmux_get_patch", "arguments": arguments }
+            })
+            .to_string();
+            let response_str = server
+                .handle_message(&request)
+                .expect("tools/call must produce a response");
+            serde_json::from_str(&response_str).expect("valid JSON")
+        };
+
+        let response = call(json!({ "repo": "testrepo", "sha": "abc123", "format": "structured" }));
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("text field");
+        let patch: Value = serde_json::from_str(text).expect("patch must be JSON");
+        let lines = &patch["files"][0]["hunks"][0]["lines"];
+        assert_eq!(lines[0]["kind"], "delete");
+        assert_eq!(lines[0]["old_line"], 1);
+        assert_eq!(lines[1]["kind"], "add");
+        assert_eq!(lines[1]["new_line"], 1);
+        assert_eq!(lines[2]["text"], "same");
+
+        let response = call(json!({ "repo": "testrepo", "sha": "abc123", "paths": ["src"] }));
+        assert_eq!(response["result"]["isError"], true);
+    }
+
+    #[test]
+    fn test_tools_call_get_patch_truncated_and_full() {
+        let server = make_server();
+        let call = |arguments: Value| -> Value {
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": 12,
+                "method": "tools/call",
+                "params": { "name": "commitmux_get_patch", "arguments": arguments }
+            })
+            .to_string();
+            let response_str = server
+                .handle_message(&request)
+                .expect("tools/call must produce a response");
+            let response: Value = serde_json::from_str(&response_str).expect("valid JSON");
+            assert_eq!(response["result"]["isError"], false, "{response}");
+            let text = response["result"]["content"][0]["text"]
+                .as_str()
+                .expect("text field");
+            serde_json::from_str(text).expect("patch must be JSON")
+        };
+
+        let stored = call(json!({ "repo": "testrepo", "sha": "def456" }));
+        assert_eq!(stored["stored_truncated"], true);
+        assert_eq!(stored["truncated"], false);
+        assert_eq!(stored["original_size"], 80);
+        assert!(stored["patch_text"].as_str().unwrap().ends_with("-a\n"));
+
+        let full = call(json!({ "repo": "testrepo", "sha": "def456", "full": true }));
+        assert_eq!(full["stored_truncated"], false);
+        assert_eq!(full["truncated"], false);
+        assert!(full["patch_text"].as_str().unwrap().ends_with(" d\n"));
+
+        // `max_bytes` applied to the regenerated patch sets only `truncated`.
+        let cut =
+            call(json!({ "repo": "testrepo", "sha": "def456", "full": true, "max_bytes": 20 }));
+        assert_eq!(cut["truncated"], true);
+        assert_eq!(cut["stored_truncated"], false);
+        assert_eq!(cut["patch_text"].as_str().unwrap().len(), 20);
+
+        // The partial last hunk is dropped rather than rejected.
+        let structured =
+            call(json!({ "repo": "testrepo", "sha": "def456", "format": "structured" }));
+        assert_eq!(structured["truncated"], true);
+        assert_eq!(structured["files"][0]["omitted_hunks"], 1);
+        assert_eq!(structured["files"][0]["hunks"].as_array().unwrap().len(), 0);
+
+        // An untruncated patch is read from the store even with full.
+        let stored = call(json!({ "repo": "testrepo", "sha": "abc123", "full": true }));
+        assert_eq!(stored["truncated"], false);
+        assert_eq!(stored["stored_truncated"], false);
+        assert!(stored["patch_text"].as_str().unwrap().contains("+new"));
+    }
+
+    #[test]
+    fn test_tools_call_get_file() {
+        let server = make_server();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 8,
+            "method": "tools/call",
+            "params": {
+                "name": "commitmux_get_file",
+                "arguments": { "repo": "testrepo", "sha": "main", "path": "src/main.rs" }
+            }
+        })
+        .to_string();
+
+        let response_str = server
+            .handle_message(&request)
+            .expect("tools/call must produce a response");
+        let response: Value = serde_json::from_str(&response_str).expect("valid JSON");
+
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("text field");
+        let file: Value = serde_json::from_str(text).expect("file must be JSON");
+        assert_eq!(file["repo"], "testrepo");
+        assert_eq!(file["sha"], "main-sha");
+        assert_eq!(file["content"], "fn main() {\n}");
+        assert!(file.get("start_line").is_none());
+    }
+
+    #[test]
+    fn test_tools_call_diff() {
+        let server = make_server();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 10,
+            "method": "tools/call",
+            "params": {
+                "name": "commitmux_diff",
+                "arguments": { "repo": "testrepo", "from": "v1.0.0", "to": "main", "stat_only": true }
+            }
+        })
+        .to_string();
+
+        let response_str = server
+            .handle_message(&request)
+            .expect("tools/call must produce a response");
+        let response: Value = serde_json::from_str(&response_str).expect("valid JSON");
+
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("text field");
+        let diff: Value = serde_json::from_str(text).expect("diff must be JSON");
+        assert_eq!(diff["from_sha"], "v1.0.0-sha");
+        assert_eq!(diff["to_sha"], "main-sha");
+        // The default ignore rules drop vendor/.
+        assert_eq!(diff["files"].as_array().map(|f| f.len()), Some(1));
+        assert_eq!(diff["files"][0]["path"], "src/main.rs");
+        assert!(diff.get("patch_text").is_none());
+    }
+
+    #[test]
+    fn test_tools_call_list_tree() {
+        let server = make_server();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 9,
+            "method": "tools/call",
+            "params": {
+                "name": "commitmux_list_tree",
+                "arguments": { "repo": "testrepo", "sha": "v1.0.0", "path": "src", "depth": 2 }
+            }
+        })
+        .to_string();
+
+        let response_str = server
+            .handle_message(&request)
+            .expect("tools/call must produce a response");
+        let response: Value = serde_json::from_str(&response_str).expect("valid JSON");
+
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("text field");
+        let listing: Value = serde_json::from_str(text).expect("listing must be JSON");
+        assert_eq!(listing["sha"], "v1.0.0-sha");
+        assert_eq!(listing["path"], "src");
+        assert_eq!(listing["depth"], 2);
+        assert_eq!(listing["entries"][0]["mode"], "100644");
+        assert_eq!(listing["entries"][0]["size"], 13);
+    }
+
+    #[test]
+    fn test_tools_call_symbol_history() {
+        let server = make_server();
+        let call = |arguments: Value| -> Value {
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": 8,
+                "method": "tools/call",
+                "params": { "name": "commitmux_symbol_history", "arguments": arguments }
+            })
+            .to_string();
+            let response_str = server
+                .handle_message(&request)
+                .expect("tools/call must produce a response");
+            serde_json::from_str(&response_str).expect("valid JSON")
+        };
+
+        let response = call(json!({ "repo": "testrepo", "symbol": "Parser::parse" }));
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("text field");
+        let changes: Value = serde_json::from_str(text).expect("changes must be JSON");
+        assert_eq!(changes[0]["sha"], "abc123");
+        assert_eq!(changes[0]["kind"], "method");
+        assert!(changes[0]["excerpt"]
+            .as_str()
+            .unwrap()
+            .contains("+        self.retry()"));
+
+        let response = call(json!({ "repo": "testrepo", "symbol": "parse", "path": "docs/" }));
+        assert_eq!(response["result"]["content"][0]["text"], "[]");
+
+        let response = call(json!({ "repo": "testrepo", "symbol": "  " }));
+        assert_eq!(response["result"]["isError"], true);
+    }
+
+    #[test]
+    fn test_tools_call_get_commit_includes_releases() {
+        let server = make_server();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "tools/call",
+            "params": {
+                "name": "commitmux_get_commit",
+                "arguments": { "repo": "testrepo", "sha": "abc123" }
+            }
+        })
+        .to_string();
+
+        let response_str = server
+            .handle_message(&request)
+            .expect("tools/call must produce a response");
+        let response: Value = serde_json::from_str(&response_str).expect("valid JSON");
+
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("text field");
+        let detail: Value = serde_json::from_str(text).expect("detail must be JSON");
+        assert_eq!(detail["first_release"], "v1.2.0");
+        assert_eq!(detail["contained_in"], json!(["v1.2.0", "v1.3.0"]));
+    }
+
     #[test]
     fn test_tools_call_get_commit_not_found() {
         let server = make_server();
@@ -998,7 +2092,7 @@ mod tests {
 
     #[test]
     fn test_tools_call_list_repos() {
-        let server = McpServer::new(Arc::new(StubStoreWithRepos));
+        let server = McpServer::new(Arc::new(StubStoreWithRepos), Arc::new(StubIngester));
         let request = json!({
             "jsonrpc": "2.0",
             "id": 4,
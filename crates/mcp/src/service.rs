@@ -0,0 +1,449 @@
+//! Operations shared by the MCP tools and the CLI that combine the index
+//! with the repos' local clones: the store supplies indexed commits, the
+//! ingester resolves revisions and reads git.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use commitmux_types::{
+    remote_project, Blame, BlameOpts, Changelog, ChangelogCommit, ChangelogEntry, ChangelogGroup,
+    ChangelogGroupBy, ChangelogOpts, CommitDetail, CommitRange, CommitSubmoduleDetail,
+    CommitmuxError, ConventionalCommit, ConventionalSummary, DiffOpts, DiffResult, FileContent,
+    FileOpts, IgnoreConfig, Ingester, PatchResult, Repo, Result, Store, SubmoduleCommit,
+    TreeListing, TreeOpts,
+};
+
+fn find_repo(store: &dyn Store, name: &str) -> Result<Repo> {
+    store
+        .get_repo_by_name(name)?
+        .ok_or_else(|| CommitmuxError::NotFound(format!("repo '{}'", name)))
+}
+
+/// The ignore rules `repo` is indexed with, and the configured rename
+/// threshold, for git operations outside a sync.
+fn repo_ingest_config(store: &dyn Store, repo: &Repo) -> Result<IgnoreConfig> {
+    let mut config = IgnoreConfig::default().for_repo(repo);
+    if let Some(threshold) = store
+        .get_config(IgnoreConfig::RENAME_THRESHOLD_KEY)?
+        .and_then(|value| value.parse::<u16>().ok())
+        .filter(|threshold| *threshold <= 100)
+    {
+        config.rename_threshold = threshold;
+    }
+    Ok(config)
+}
+
+/// Resolves the range with `ingester`, then groups the indexed commits in it.
+pub fn changelog(
+    store: &dyn Store,
+    ingester: &dyn Ingester,
+    opts: &ChangelogOpts,
+) -> Result<Changelog> {
+    let repo = find_repo(store, &opts.repo)?;
+    let range = ingester.commit_range(&repo, opts.from.as_deref(), &opts.to, opts.first_parent)?;
+    let commits = store.changelog_commits(repo.repo_id, &range.shas)?;
+    Ok(build_changelog(opts, &range, commits))
+}
+
+/// Groups `commits` (newest first) by `opts.group_by`. Order within a group
+/// follows `commits`.
+pub fn build_changelog(
+    opts: &ChangelogOpts,
+    range: &CommitRange,
+    commits: Vec<ChangelogCommit>,
+) -> Changelog {
+    let mut log = Changelog {
+        repo: opts.repo.clone(),
+        from: opts.from.clone(),
+        to: opts.to.clone(),
+        from_sha: range.from_sha.clone(),
+        to_sha: range.to_sha.clone(),
+        group_by: opts.group_by.as_str().to_string(),
+        total: commits.len(),
+        unindexed: range.shas.len().saturating_sub(commits.len()),
+        breaking: Vec::new(),
+        groups: Vec::new(),
+    };
+
+    let push = |groups: &mut Vec<ChangelogGroup>, name: &str, entry: ChangelogEntry| match groups
+        .iter_mut()
+        .find(|g| g.name == name)
+    {
+        Some(group) => group.commits.push(entry),
+        None => groups.push(ChangelogGroup {
+            name: name.to_string(),
+            commits: vec![entry],
+        }),
+    };
+    for commit in commits {
+        let cc = commit.conventional.as_ref();
+        let entry = ChangelogEntry {
+            sha: commit.sha.clone(),
+            subject: commit.subject.clone(),
+            author: commit.author.clone(),
+            date: commit.date,
+            cc_type: cc.map(|c| c.cc_type.clone()),
+            scope: cc.and_then(|c| c.scope.clone()),
+            breaking: cc.is_some_and(|c| c.breaking),
+        };
+        if entry.breaking {
+            log.breaking.push(entry.clone());
+        }
+        match opts.group_by {
+            ChangelogGroupBy::Type => {
+                let name = cc.map_or("other", |c| c.cc_type.as_str());
+                push(&mut log.groups, name, entry);
+            }
+            ChangelogGroupBy::Author => push(&mut log.groups, &commit.author, entry),
+            ChangelogGroupBy::Directory => {
+                let mut dirs: Vec<&str> = commit
+                    .paths
+                    .iter()
+                    .map(|p| match p.split_once('/') {
+                        Some((dir, _)) => dir,
+                        None => "/",
+                    })
+                    .collect();
+                dirs.sort_unstable();
+                dirs.dedup();
+                for dir in dirs {
+                    push(&mut log.groups, dir, entry.clone());
+                }
+            }
+        }
+    }
+
+    match opts.group_by {
+        ChangelogGroupBy::Type => {
+            let rank = |t: &str| match t {
+                "other" => ConventionalSummary::TYPE_ORDER.len() + 1,
+                t => ConventionalSummary::TYPE_ORDER
+                    .iter()
+                    .position(|k| *k == t)
+                    .unwrap_or(ConventionalSummary::TYPE_ORDER.len()),
+            };
+            log.groups.sort_by(|a, b| {
+                rank(&a.name)
+                    .cmp(&rank(&b.name))
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+        }
+        ChangelogGroupBy::Author => log.groups.sort_by(|a, b| {
+            b.commits
+                .len()
+                .cmp(&a.commits.len())
+                .then_with(|| a.name.cmp(&b.name))
+        }),
+        ChangelogGroupBy::Directory => log.groups.sort_by(|a, b| a.name.cmp(&b.name)),
+    }
+    log
+}
+
+/// Renders a changelog as Markdown release notes.
+pub fn changelog_markdown(log: &Changelog) -> String {
+    fn line(out: &mut String, entry: &ChangelogEntry, conventional: bool) {
+        let sha = &entry.sha[..entry.sha.len().min(12)];
+        let parsed = conventional
+            .then(|| ConventionalCommit::parse(&entry.subject, None))
+            .flatten();
+        let text = match (parsed, &entry.scope) {
+            (Some(cc), Some(scope)) => format!("**{}:** {}", scope, cc.description),
+            (Some(cc), None) => cc.description,
+            (None, _) => entry.subject.clone(),
+        };
+        out.push_str(&format!("- {} ({}, {})\n", text, sha, entry.author));
+    }
+
+    let by_type = log.group_by == ChangelogGroupBy::Type.as_str();
+    let mut out = format!(
+        "## {} {}..{}\n",
+        log.repo,
+        log.from.as_deref().unwrap_or(""),
+        log.to
+    );
+    if !log.breaking.is_empty() {
+        out.push_str("\n### Breaking changes\n\n");
+        for entry in &log.breaking {
+            line(&mut out, entry, by_type);
+        }
+    }
+    for group in &log.groups {
+        let title = if by_type {
+            match group.name.as_str() {
+                "feat" => "Features",
+                "fix" => "Bug fixes",
+                "perf" => "Performance",
+                "revert" => "Reverts",
+                "refactor" => "Refactoring",
+                "docs" => "Documentation",
+                "other" => "Other changes",
+                other => other,
+            }
+        } else {
+            group.name.as_str()
+        };
+        out.push_str(&format!("\n### {}\n\n", title));
+        for entry in &group.commits {
+            line(&mut out, entry, by_type);
+        }
+    }
+    if log.total == 0 {
+        out.push_str("\nNo indexed commits in this range.\n");
+    }
+    if log.unindexed > 0 {
+        out.push_str(&format!(
+            "\n_{} commit(s) in the range are not indexed._\n",
+            log.unindexed
+        ));
+    }
+    out
+}
+
+/// Blames the range with `ingester`, then looks up each originating commit
+/// in `store`.
+pub fn blame(store: &dyn Store, ingester: &dyn Ingester, opts: &BlameOpts) -> Result<Blame> {
+    let repo = find_repo(store, &opts.repo)?;
+    let blame = ingester.blame(&repo, opts)?;
+
+    let mut commits = BTreeMap::new();
+    for hunk in &blame.hunks {
+        if commits.contains_key(&hunk.sha) {
+            continue;
+        }
+        if let Some(detail) = store.get_commit(&repo.name, &hunk.sha)? {
+            commits.insert(hunk.sha.clone(), detail);
+        }
+    }
+
+    Ok(Blame {
+        repo: repo.name,
+        path: opts.path.clone(),
+        rev: opts.rev.clone().unwrap_or_else(|| "HEAD".to_string()),
+        rev_sha: blame.rev_sha,
+        start_line: blame.start_line,
+        end_line: blame.end_line,
+        hunks: blame.hunks,
+        commits,
+    })
+}
+
+/// Looks up the repo in `store` and reads the file from its local clone.
+pub fn read_file(
+    store: &dyn Store,
+    ingester: &dyn Ingester,
+    opts: &FileOpts,
+) -> Result<FileContent> {
+    let repo = find_repo(store, &opts.repo)?;
+    ingester.read_file(&repo, opts)
+}
+
+/// Looks up the repo in `store` and diffs its local clone, skipping the
+/// paths the repo's ignore rules exclude from the index.
+pub fn diff(store: &dyn Store, ingester: &dyn Ingester, opts: &DiffOpts) -> Result<DiffResult> {
+    let repo = find_repo(store, &opts.repo)?;
+    let config = repo_ingest_config(store, &repo)?;
+    ingester.diff(&repo, opts, &config)
+}
+
+/// Looks up the repo in `store` and lists the tree from its local clone.
+pub fn list_tree(
+    store: &dyn Store,
+    ingester: &dyn Ingester,
+    opts: &TreeOpts,
+) -> Result<TreeListing> {
+    let repo = find_repo(store, &opts.repo)?;
+    ingester.list_tree(&repo, opts)
+}
+
+/// Reads a commit from `store`. Each submodule bump whose submodule is
+/// itself a registered repo is linked to the commits it pulled in, listed
+/// from that repo's local clone.
+pub fn commit_detail(
+    store: &dyn Store,
+    ingester: &dyn Ingester,
+    repo_name: &str,
+    sha_prefix: &str,
+) -> Result<Option<CommitDetail>> {
+    let Some(mut detail) = store.get_commit(repo_name, sha_prefix)? else {
+        return Ok(None);
+    };
+    if detail.submodules.is_empty() {
+        return Ok(Some(detail));
+    }
+    let repos = store.list_repos()?;
+    let parent = repos.iter().find(|r| r.name == detail.repo);
+    for submodule in &mut detail.submodules {
+        let Some(target) = submodule_repo(&repos, parent, submodule) else {
+            continue;
+        };
+        submodule.repo = Some(target.name.clone());
+        let Some(new_sha) = submodule.new_sha.as_deref() else {
+            continue;
+        };
+        let range =
+            match ingester.commit_range(target, submodule.old_sha.as_deref(), new_sha, false) {
+                Ok(range) => range,
+                Err(e) => {
+                    submodule.link_error = Some(e.to_string());
+                    continue;
+                }
+            };
+        let listed = &range.shas[..range.shas.len().min(CommitSubmoduleDetail::MAX_COMMITS)];
+        let subjects: HashMap<String, String> = store
+            .changelog_commits(target.repo_id, listed)?
+            .into_iter()
+            .map(|c| (c.sha, c.subject))
+            .collect();
+        submodule.commit_count = Some(range.shas.len());
+        submodule.commits = listed
+            .iter()
+            .map(|sha| SubmoduleCommit {
+                sha: sha.clone(),
+                subject: subjects.get(sha).cloned(),
+            })
+            .collect();
+    }
+    Ok(Some(detail))
+}
+
+/// The registered repo, other than `parent`, that a submodule of `parent`
+/// refers to: same remote project as its URL, or checked out at its path.
+fn submodule_repo<'a>(
+    repos: &'a [Repo],
+    parent: Option<&Repo>,
+    submodule: &CommitSubmoduleDetail,
+) -> Option<&'a Repo> {
+    let url = submodule.url.as_deref();
+    let project = url.and_then(remote_project);
+    let url_path = url.map(|u| PathBuf::from(u.strip_prefix("file://").unwrap_or(u)));
+    let checkout = parent.map(|p| p.local_path.join(&submodule.path));
+    repos
+        .iter()
+        .filter(|r| parent.is_none_or(|p| p.repo_id != r.repo_id))
+        .find(|r| {
+            (project.is_some() && r.remote_url.as_deref().and_then(remote_project) == project)
+                || url_path.as_ref() == Some(&r.local_path)
+                || checkout.as_ref() == Some(&r.local_path)
+        })
+}
+
+/// Reads the stored patch for `sha`. With `full`, a patch that was cut at
+/// ingest is regenerated whole from the repo's local clone instead.
+/// `max_bytes` applies to whichever copy is returned.
+pub fn patch(
+    store: &dyn Store,
+    ingester: &dyn Ingester,
+    repo_name: &str,
+    sha: &str,
+    max_bytes: Option<usize>,
+    full: bool,
+) -> Result<Option<PatchResult>> {
+    if !full {
+        return store.get_patch(repo_name, sha, max_bytes);
+    }
+    let Some(mut patch) = store.get_patch(repo_name, sha, None)? else {
+        return Ok(None);
+    };
+    if patch.stored_truncated {
+        let repo = find_repo(store, repo_name)?;
+        let config = repo_ingest_config(store, &repo)?;
+        patch = ingester.patch(&repo, &patch.sha, &config)?;
+    }
+    if let Some(max) = max_bytes {
+        truncate_patch(&mut patch, max);
+    }
+    Ok(Some(patch))
+}
+
+/// Cuts `patch_text` to at most `max_bytes`, at a UTF-8 character boundary,
+/// and marks the result truncated if anything was dropped.
+pub fn truncate_patch(patch: &mut PatchResult, max_bytes: usize) {
+    if patch.patch_text.len() > max_bytes {
+        let cut = patch.patch_text.floor_char_boundary(max_bytes);
+        patch.patch_text.truncate(cut);
+        patch.truncated = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_changelog_grouping() {
+        let commit = |sha: &str, subject: &str, author: &str, paths: &[&str]| ChangelogCommit {
+            sha: sha.into(),
+            subject: subject.into(),
+            author: author.into(),
+            date: 0,
+            conventional: ConventionalCommit::parse(subject, None),
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+        };
+        let commits = vec![
+            commit("c3", "Update readme", "Bob", &["README.md"]),
+            commit("c2", "fix(api): handle 503", "Alice", &["src/api.rs"]),
+            commit(
+                "c1",
+                "feat!: new config",
+                "Alice",
+                &["src/config.rs", "docs/config.md"],
+            ),
+        ];
+        let range = CommitRange {
+            from_sha: Some("c0".into()),
+            to_sha: "c3".into(),
+            shas: vec!["c3".into(), "c2".into(), "c1".into(), "cx".into()],
+        };
+        let mut opts = ChangelogOpts {
+            repo: "r".into(),
+            from: Some("v1".into()),
+            to: "v2".into(),
+            ..Default::default()
+        };
+
+        let log = build_changelog(&opts, &range, commits.clone());
+        assert_eq!((log.total, log.unindexed), (3, 1));
+        let names: Vec<&str> = log.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["feat", "fix", "other"]);
+        assert_eq!(log.breaking.len(), 1);
+        let md = changelog_markdown(&log);
+        assert!(
+            md.contains("### Bug fixes\n\n- **api:** handle 503 (c2, Alice)"),
+            "{md}"
+        );
+        assert!(md.contains("1 commit(s) in the range are not indexed"));
+
+        opts.group_by = ChangelogGroupBy::Directory;
+        let log = build_changelog(&opts, &range, commits.clone());
+        let names: Vec<&str> = log.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["/", "docs", "src"]);
+        assert_eq!(log.groups[2].commits.len(), 2);
+
+        opts.group_by = ChangelogGroupBy::Author;
+        let log = build_changelog(&opts, &range, commits);
+        assert_eq!(log.groups[0].name, "Alice");
+        assert_eq!(log.groups[0].commits.len(), 2);
+    }
+
+    #[test]
+    fn test_truncate_patch() {
+        let mut patch = PatchResult {
+            repo: "r".into(),
+            sha: "abc".into(),
+            patch_text: "+héllo\n".into(),
+            truncated: false,
+            stored_truncated: true,
+            original_size: Some(8),
+        };
+        truncate_patch(&mut patch, 100);
+        assert!(!patch.truncated);
+
+        // `é` is two bytes; the cut falls back to the character boundary.
+        truncate_patch(&mut patch, 3);
+        assert_eq!(patch.patch_text, "+h");
+        assert!(patch.truncated);
+        // The ingest cut is reported on its own.
+        assert!(patch.stored_truncated);
+        assert_eq!(patch.original_size, Some(8));
+    }
+}
@@ -7,6 +7,12 @@ pub struct SearchInput {
     pub since: Option<i64>,
     pub repos: Option<Vec<String>>,
     pub paths: Option<Vec<String>>,
+    pub authors: Option<Vec<String>>,
+    pub trailer: Option<String>,
+    pub cc_types: Option<Vec<String>>,
+    pub cc_scopes: Option<Vec<String>>,
+    pub breaking: Option<bool>,
+    pub projects: Option<Vec<String>>,
     pub limit: Option<usize>,
 }
 
@@ -16,6 +22,62 @@ pub struct TouchesInput {
     pub path_glob: String,
     pub since: Option<i64>,
     pub repos: Option<Vec<String>>,
+    pub authors: Option<Vec<String>>,
+    pub projects: Option<Vec<String>>,
+    pub limit: Option<usize>,
+    pub follow_renames: Option<bool>,
+}
+
+/// Input type for the `commitmux_find_by_ref` tool.
+#[derive(Debug, Deserialize)]
+pub struct FindByRefInput {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub repos: Option<Vec<String>>,
+    pub limit: Option<usize>,
+}
+
+/// Input type for the `commitmux_changelog` tool.
+#[derive(Debug, Deserialize)]
+pub struct ChangelogInput {
+    pub repo: String,
+    pub from: Option<String>,
+    pub to: String,
+    pub first_parent: Option<bool>,
+    /// `type` (default), `directory` or `author`.
+    pub group_by: Option<String>,
+    /// `json` (default) or `markdown`.
+    pub format: Option<String>,
+}
+
+/// Input type for the `commitmux_blame` tool.
+#[derive(Debug, Deserialize)]
+pub struct BlameInput {
+    pub repo: String,
+    pub path: String,
+    pub rev: Option<String>,
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+}
+
+/// Input type for the `commitmux_symbol_history` tool.
+#[derive(Debug, Deserialize)]
+pub struct SymbolHistoryInput {
+    pub repo: String,
+    pub symbol: String,
+    pub path: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Input type for the `commitmux_conventional_summary` tool.
+#[derive(Debug, Deserialize)]
+pub struct ConventionalSummaryInput {
+    pub repos: Option<Vec<String>>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub cc_types: Option<Vec<String>>,
+    pub cc_scopes: Option<Vec<String>>,
+    pub breaking: Option<bool>,
     pub limit: Option<usize>,
 }
 
@@ -32,6 +94,46 @@ pub struct GetPatchInput {
     pub repo: String,
     pub sha: String,
     pub max_bytes: Option<usize>,
+    /// `text` (default) or `structured`.
+    pub format: Option<String>,
+    /// Only files at or under these paths; structured format only.
+    pub paths: Option<Vec<String>>,
+    /// Regenerate the patch from git when the stored copy was cut at ingest.
+    pub full: Option<bool>,
+}
+
+/// Input type for the `commitmux_diff` tool.
+#[derive(Debug, Deserialize)]
+pub struct DiffInput {
+    pub repo: String,
+    pub from: String,
+    pub to: String,
+    pub paths: Option<Vec<String>>,
+    pub stat_only: Option<bool>,
+    pub max_bytes: Option<usize>,
+}
+
+/// Input type for the `commitmux_get_file` tool.
+#[derive(Debug, Deserialize)]
+pub struct GetFileInput {
+    pub repo: String,
+    /// Commit to read at: a SHA, or any branch or tag.
+    pub sha: String,
+    pub path: String,
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+    pub offset: Option<usize>,
+    pub max_bytes: Option<usize>,
+}
+
+/// Input type for the `commitmux_list_tree` tool.
+#[derive(Debug, Deserialize)]
+pub struct ListTreeInput {
+    pub repo: String,
+    /// Commit to list at: a SHA, or any branch or tag.
+    pub sha: String,
+    pub path: Option<String>,
+    pub depth: Option<usize>,
 }
 
 /// Input type for the `commitmux_list_repos` tool (no required fields).
@@ -44,6 +146,8 @@ pub struct SemanticSearchInput {
     pub query: String,
     pub repos: Option<Vec<String>>,
     pub since: Option<i64>,
+    pub authors: Option<Vec<String>>,
+    pub projects: Option<Vec<String>>,
     pub limit: Option<usize>,
 }
 
@@ -77,6 +181,7 @@ mod tests {
         assert!(input.since.is_none());
         assert!(input.repos.is_none());
         assert!(input.paths.is_none());
+        assert!(input.authors.is_none());
     }
 
     #[test]
@@ -87,5 +192,6 @@ mod tests {
         assert!(input.since.is_none());
         assert!(input.repos.is_none());
         assert!(input.limit.is_none());
+        assert!(input.follow_renames.is_none());
     }
 }
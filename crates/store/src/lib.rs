@@ -49,36 +49,44 @@ impl SqliteStore {
     fn init(&self) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute_batch(schema::SCHEMA_SQL)?;
-        // Apply column migrations one at a time; ignore "duplicate column name" errors
-        // so that init() remains idempotent on databases that already have the columns.
-        for &sql in schema::REPO_MIGRATIONS {
-            match conn.execute_batch(sql) {
-                Ok(()) => {}
-                Err(rusqlite::Error::SqliteFailure(_, Some(ref msg)))
-                    if msg.contains("duplicate column name") => {}
-                Err(e) => return Err(e.into()),
-            }
-        }
-        for &sql in schema::EMBED_MIGRATIONS {
-            match conn.execute_batch(sql) {
-                Ok(()) => {}
-                Err(rusqlite::Error::SqliteFailure(_, Some(ref msg)))
-                    if msg.contains("duplicate column name") => {}
-                Err(e) => return Err(e.into()),
-            }
-        }
+        apply_migrations(&conn, schema::REPO_MIGRATIONS)?;
+        conn.execute_batch(schema::AUTHOR_FILTER_MIGRATION)?;
+        apply_migrations(&conn, schema::EMBED_MIGRATIONS)?;
+        apply_migrations(&conn, schema::COMMIT_MIGRATIONS)?;
+        apply_migrations(&conn, schema::COMMIT_FILE_MIGRATIONS)?;
+        apply_migrations(&conn, schema::COMMIT_PATCH_MIGRATIONS)?;
+        apply_migrations(&conn, schema::INGEST_STATE_MIGRATIONS)?;
+        queries::backfill_conventional(&conn)?;
         Ok(())
     }
 }
 
+/// Runs column migrations one at a time. "duplicate column name" errors are
+/// ignored, so a migration is a no-op on a database that already has the
+/// column (every fresh one, and any that ran it before).
+fn apply_migrations(conn: &rusqlite::Connection, migrations: &[&str]) -> Result<()> {
+    for &sql in migrations {
+        match conn.execute_batch(sql) {
+            Ok(()) => {}
+            Err(rusqlite::Error::SqliteFailure(_, Some(ref msg)))
+                if msg.contains("duplicate column name") => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
 // ── Tests ─────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use commitmux_types::{
-        Commit, CommitFile, CommitPatch, FileStatus, MemoryDocInput, MemorySourceType, RepoInput,
-        RepoUpdate, SearchOpts, Store,
+        AuthorMatcher, BatchCommit, Commit, CommitFile, CommitPatch, CommitRef, CommitSymbol,
+        ConventionalCommit, ConventionalSummaryOpts, FileKind, FileStatus, IdentityAlias,
+        LfsPointer, MemoryDocInput, MemorySourceType, RefKind, RefOpts, RefPattern, RepoInput,
+        RepoProject, RepoUpdate, SearchOpts, Store, SymbolOpts, Tag, TagIndex, TouchOpts, Trailer,
+        WriteBatch,
     };
     use std::path::PathBuf;
 
@@ -86,6 +94,28 @@ mod tests {
         SqliteStore::open_in_memory().expect("open in-memory store")
     }
 
+    #[test]
+    fn test_fresh_schema_has_every_migrated_column() {
+        let store = make_store();
+        let conn = store.conn.lock().unwrap();
+        for migrations in [
+            schema::REPO_MIGRATIONS,
+            schema::EMBED_MIGRATIONS,
+            schema::COMMIT_MIGRATIONS,
+            schema::COMMIT_FILE_MIGRATIONS,
+            schema::COMMIT_PATCH_MIGRATIONS,
+            schema::INGEST_STATE_MIGRATIONS,
+        ] {
+            for sql in migrations.iter().filter(|sql| sql.contains("ADD COLUMN")) {
+                match conn.execute_batch(sql) {
+                    Err(rusqlite::Error::SqliteFailure(_, Some(msg)))
+                        if msg.contains("duplicate column name") => {}
+                    other => panic!("{} is missing from SCHEMA_SQL: {:?}", sql, other),
+                }
+            }
+        }
+    }
+
     fn make_repo_input(name: &str) -> RepoInput {
         RepoInput {
             name: name.to_string(),
@@ -93,9 +123,16 @@ mod tests {
             remote_url: None,
             default_branch: Some("main".to_string()),
             fork_of: None,
-            author_filter: None,
+            author_filters: vec![],
+            include_paths: vec![],
+            ref_patterns: vec![],
+            projects: vec![],
             exclude_prefixes: vec![],
             embed_enabled: false,
+            clone_depth: None,
+            clone_since: None,
+            ssh_key: None,
+            token_env: None,
         }
     }
 
@@ -107,11 +144,16 @@ mod tests {
             author_email: "alice@example.com".to_string(),
             committer_name: "Alice".to_string(),
             committer_email: "alice@example.com".to_string(),
+            canonical_author_name: "Alice".to_string(),
+            canonical_author_email: "alice@example.com".to_string(),
             author_time: 1700000000,
             commit_time: 1700000000,
             subject: subject.to_string(),
             body: None,
             parent_count: 0,
+            trailers: vec![],
+            refs: vec![],
+            conventional: None,
         }
     }
 
@@ -174,6 +216,8 @@ mod tests {
             sha: "cafebabe".to_string(),
             patch_blob: raw_patch.to_vec(),
             patch_preview: "xyzzy_unique_token preview".to_string(),
+            truncated: false,
+            original_size: raw_patch.len() as u64,
         };
         store.upsert_patch(&patch).expect("upsert patch");
 
@@ -200,6 +244,10 @@ mod tests {
             path: "src/main.rs".to_string(),
             status: FileStatus::Added,
             old_path: None,
+            kind: FileKind::Text,
+            size: None,
+            lfs: None,
+            project: None,
         }];
         store.upsert_commit_files(&files).expect("upsert files");
 
@@ -209,6 +257,8 @@ mod tests {
             sha: "deadbeef01234567".to_string(),
             patch_blob: raw_patch.to_vec(),
             patch_preview: "preview".to_string(),
+            truncated: false,
+            original_size: raw_patch.len() as u64,
         };
         store.upsert_patch(&patch).expect("upsert patch");
 
@@ -266,37 +316,91 @@ mod tests {
     }
 
     #[test]
-    fn test_update_repo_author_filter() {
+    fn test_update_repo_author_filters() {
         let store = make_store();
         let repo = store
             .add_repo(&make_repo_input("updaterepo"))
             .expect("add repo");
 
-        // Set author_filter
+        // Add two identities and an include path
         let update = RepoUpdate {
-            author_filter: Some(Some("alice@example.com".into())),
+            add_authors: vec![
+                AuthorMatcher::Email("alice@example.com".into()),
+                AuthorMatcher::Regex("^alice@users\\.noreply".into()),
+            ],
+            include_paths: Some(vec!["src/".into()]),
             ..RepoUpdate::default()
         };
         let updated = store
             .update_repo(repo.repo_id, &update)
-            .expect("update_repo set");
+            .expect("update_repo add");
+        assert_eq!(updated.author_filters.len(), 2);
+        assert_eq!(updated.include_paths, vec!["src/".to_string()]);
+
+        // Re-adding is a no-op; removing drops only the named matcher
+        let update = RepoUpdate {
+            add_authors: vec![AuthorMatcher::Email("alice@example.com".into())],
+            remove_authors: vec![AuthorMatcher::Regex("^alice@users\\.noreply".into())],
+            ..RepoUpdate::default()
+        };
+        store
+            .update_repo(repo.repo_id, &update)
+            .expect("update_repo remove");
+        let fetched = store
+            .get_repo_by_name("updaterepo")
+            .expect("get_repo_by_name")
+            .expect("repo exists");
         assert_eq!(
-            updated.author_filter,
-            Some("alice@example.com".to_string()),
-            "expected author_filter to be set"
+            fetched.author_filters,
+            vec![AuthorMatcher::Email("alice@example.com".into())]
         );
+        assert_eq!(fetched.include_paths, vec!["src/".to_string()]);
 
-        // Clear author_filter
+        // Clearing include paths leaves author filters alone
         let clear = RepoUpdate {
-            author_filter: Some(None),
+            include_paths: Some(vec![]),
             ..RepoUpdate::default()
         };
         let cleared = store
             .update_repo(repo.repo_id, &clear)
             .expect("update_repo clear");
-        assert!(
-            cleared.author_filter.is_none(),
-            "expected author_filter to be cleared"
+        assert!(cleared.include_paths.is_empty());
+        assert_eq!(cleared.author_filters.len(), 1);
+
+        store.remove_repo("updaterepo").expect("remove repo");
+        let conn = store.conn.lock().unwrap();
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM repo_filters", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 0, "expected filters removed with repo");
+    }
+
+    #[test]
+    fn test_legacy_author_filter_migrated() {
+        let store = make_store();
+        let repo = store
+            .add_repo(&make_repo_input("legacy"))
+            .expect("add repo");
+        store
+            .conn
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE repos SET author_filter = 'bob@example.com' WHERE repo_id = ?1",
+                rusqlite::params![repo.repo_id],
+            )
+            .unwrap();
+
+        store.init().expect("re-run init");
+        store.init().expect("init stays idempotent");
+
+        let migrated = store
+            .get_repo_by_name("legacy")
+            .expect("get_repo_by_name")
+            .expect("repo exists");
+        assert_eq!(
+            migrated.author_filters,
+            vec![AuthorMatcher::Email("bob@example.com".into())]
         );
     }
 
@@ -405,9 +509,16 @@ mod tests {
             remote_url: None,
             default_branch: Some("main".to_string()),
             fork_of: None,
-            author_filter: None,
+            author_filters: vec![],
+            include_paths: vec![],
+            ref_patterns: vec![],
+            projects: vec![],
             exclude_prefixes: vec!["dist/".into(), "vendor/".into()],
             embed_enabled: false,
+            clone_depth: None,
+            clone_since: None,
+            ssh_key: None,
+            token_env: None,
         };
         store.add_repo(&input).expect("add repo");
 
@@ -550,9 +661,16 @@ mod tests {
             remote_url: None,
             default_branch: Some("main".to_string()),
             fork_of: None,
-            author_filter: None,
+            author_filters: vec![],
+            include_paths: vec![],
+            ref_patterns: vec![],
+            projects: vec![],
             exclude_prefixes: vec![],
             embed_enabled: true,
+            clone_depth: None,
+            clone_since: None,
+            ssh_key: None,
+            token_env: None,
         };
         store.add_repo(&input).expect("add repo with embed_enabled");
         let repo = store
@@ -586,6 +704,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_clone_window_roundtrip() {
+        let store = make_store();
+        let repo = store
+            .add_repo(&RepoInput {
+                remote_url: Some("https://example.com/big.git".into()),
+                clone_depth: Some(50),
+                clone_since: Some(1700000000),
+                ..make_repo_input("bigrepo")
+            })
+            .expect("add repo");
+        assert_eq!(repo.clone_depth, Some(50));
+        let listed = store.get_repo_by_name("bigrepo").unwrap().unwrap();
+        assert_eq!(listed.clone_depth, Some(50));
+        assert_eq!(listed.clone_since, Some(1700000000));
+
+        let update = RepoUpdate {
+            clone_depth: Some(None),
+            ..RepoUpdate::default()
+        };
+        let updated = store.update_repo(repo.repo_id, &update).unwrap();
+        assert_eq!(updated.clone_depth, None);
+        assert_eq!(updated.clone_since, Some(1700000000));
+    }
+
+    #[test]
+    fn test_remote_auth_roundtrip() {
+        let store = make_store();
+        let repo = store
+            .add_repo(&RepoInput {
+                remote_url: Some("git@example.com:org/private.git".into()),
+                ssh_key: Some(PathBuf::from("/home/me/.ssh/deploy_key")),
+                token_env: Some("GITHUB_TOKEN".into()),
+                ..make_repo_input("private")
+            })
+            .expect("add repo");
+        let listed = store.get_repo_by_name("private").unwrap().unwrap();
+        assert_eq!(
+            listed.ssh_key,
+            Some(PathBuf::from("/home/me/.ssh/deploy_key"))
+        );
+        assert_eq!(listed.token_env.as_deref(), Some("GITHUB_TOKEN"));
+
+        let update = RepoUpdate {
+            ssh_key: Some(None),
+            ..RepoUpdate::default()
+        };
+        let updated = store.update_repo(repo.repo_id, &update).unwrap();
+        assert_eq!(updated.ssh_key, None);
+        assert_eq!(updated.token_env.as_deref(), Some("GITHUB_TOKEN"));
+    }
+
     #[test]
     fn test_get_patch_roundtrip() {
         let store = make_store();
@@ -604,6 +774,8 @@ mod tests {
             sha: "1234abcd".to_string(),
             patch_blob: original_text.as_bytes().to_vec(),
             patch_preview: original_text.chars().take(500).collect(),
+            truncated: false,
+            original_size: original_text.len() as u64,
         };
         store.upsert_patch(&patch).expect("upsert patch");
 
@@ -615,6 +787,153 @@ mod tests {
         assert_eq!(result.patch_text, original_text);
         assert_eq!(result.repo, "patchrepo");
         assert_eq!(result.sha, "1234abcd");
+        assert!(!result.truncated);
+        assert!(!result.stored_truncated);
+        assert_eq!(result.original_size, Some(original_text.len() as u64));
+    }
+
+    #[test]
+    fn test_truncated_patch_reported() {
+        let store = make_store();
+        let repo = store.add_repo(&make_repo_input("cut")).expect("add repo");
+        store
+            .upsert_commit(&make_commit(repo.repo_id, "feed0001", "big change"))
+            .expect("upsert commit");
+        let stored = "diff --git a/big.txt b/big.txt\n@@ -1,400 +1,400 @@\n-a\n";
+        store
+            .upsert_patch(&CommitPatch {
+                repo_id: repo.repo_id,
+                sha: "feed0001".to_string(),
+                patch_blob: stored.as_bytes().to_vec(),
+                patch_preview: stored.to_string(),
+                truncated: true,
+                original_size: 9000,
+            })
+            .expect("upsert patch");
+
+        let patch = store
+            .get_patch("cut", "feed", None)
+            .expect("get_patch")
+            .unwrap();
+        assert!(patch.stored_truncated);
+        assert!(!patch.truncated);
+        assert_eq!(patch.original_size, Some(9000));
+        assert_eq!(patch.patch_text, stored);
+
+        let detail = store
+            .get_commit("cut", "feed0001")
+            .expect("get_commit")
+            .unwrap();
+        assert!(detail.patch_truncated);
+        assert_eq!(detail.patch_size, Some(9000));
+
+        // Commits without a stored patch report neither.
+        store
+            .upsert_commit(&make_commit(repo.repo_id, "feed0002", "empty"))
+            .expect("upsert commit");
+        let detail = store
+            .get_commit("cut", "feed0002")
+            .expect("get_commit")
+            .unwrap();
+        assert!(!detail.patch_truncated);
+        assert_eq!(detail.patch_size, None);
+
+        // `max_bytes` cuts the stored copy further.
+        let patch = store
+            .get_patch("cut", "feed0001", Some(10))
+            .expect("get_patch")
+            .unwrap();
+        assert_eq!(patch.patch_text.len(), 10);
+        assert!(patch.truncated);
+        assert!(patch.stored_truncated);
+    }
+
+    #[test]
+    fn test_write_batch() {
+        let store = make_store();
+        let repo = store
+            .add_repo(&make_repo_input("batchrepo"))
+            .expect("add repo");
+
+        let patch_text = "diff --git a/lib.rs b/lib.rs\n+fn batched() {}\n";
+        let batch = commitmux_types::WriteBatch {
+            commits: vec![
+                commitmux_types::BatchCommit {
+                    commit: make_commit(repo.repo_id, "aaaa1111", "first batched commit"),
+                    files: vec![CommitFile {
+                        repo_id: repo.repo_id,
+                        sha: "aaaa1111".to_string(),
+                        path: "lib.rs".to_string(),
+                        status: FileStatus::Added,
+                        old_path: None,
+                        kind: FileKind::Text,
+                        size: None,
+                        lfs: None,
+                        project: None,
+                    }],
+                    patch: Some(CommitPatch {
+                        repo_id: repo.repo_id,
+                        sha: "aaaa1111".to_string(),
+                        patch_blob: patch_text.as_bytes().to_vec(),
+                        patch_preview: patch_text.to_string(),
+                        truncated: false,
+                        original_size: patch_text.len() as u64,
+                    }),
+                    symbols: vec![],
+                    submodules: vec![],
+                },
+                commitmux_types::BatchCommit {
+                    commit: make_commit(repo.repo_id, "bbbb2222", "second batched commit"),
+                    files: vec![],
+                    patch: None,
+                    symbols: vec![],
+                    submodules: vec![],
+                },
+            ],
+        };
+        store.write_batch(&batch).expect("write batch");
+        // Rewriting the same batch must not duplicate FTS or file rows.
+        store.write_batch(&batch).expect("rewrite batch");
+
+        assert_eq!(store.count_commits_for_repo(repo.repo_id).unwrap(), 2);
+
+        let results = store
+            .search("batched", &SearchOpts::default())
+            .expect("search");
+        assert_eq!(results.len(), 2);
+
+        // patch_preview is indexed in the same write
+        let results = store
+            .search("fn batched", &SearchOpts::default())
+            .expect("search preview");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].sha, "aaaa1111");
+
+        let detail = store
+            .get_commit("batchrepo", "aaaa1111")
+            .expect("get_commit")
+            .expect("some");
+        assert_eq!(detail.changed_files.len(), 1);
+
+        let patch = store
+            .get_patch("batchrepo", "aaaa1111", None)
+            .expect("get_patch")
+            .expect("some");
+        assert_eq!(patch.patch_text, patch_text);
+
+        // Re-indexed without a patch (e.g. its files are now excluded), the
+        // stored patch is dropped rather than served stale.
+        let mut reindexed = batch.clone();
+        reindexed.commits.truncate(1);
+        reindexed.commits[0].files.clear();
+        reindexed.commits[0].patch = None;
+        store
+            .write_batch(&reindexed)
+            .expect("rewrite without patch");
+        assert!(store
+            .get_patch("batchrepo", "aaaa1111", None)
+            .expect("get_patch")
+            .is_none());
     }
 
     // ── Memory document tests ─────────────────────────────────────────────
@@ -737,4 +1056,844 @@ mod tests {
             .store_memory_embedding(doc.doc_id, &embedding)
             .expect("store_memory_embedding second (idempotent)");
     }
+
+    #[test]
+    fn test_ingest_state_checkpoint_roundtrip() {
+        let store = make_store();
+        let repo = store.add_repo(&make_repo_input("ckpt")).expect("add repo");
+
+        store
+            .update_ingest_state(&commitmux_types::IngestState {
+                repo_id: repo.repo_id,
+                last_synced_at: 0,
+                last_synced_sha: None,
+                last_error: None,
+                checkpoint_sha: Some("abc123".into()),
+                indexed_tips: vec!["def456".into()],
+                config_hash: Some("0123abcd".into()),
+            })
+            .expect("update ingest state");
+
+        let state = store
+            .get_ingest_state(repo.repo_id)
+            .expect("get ingest state")
+            .expect("state exists");
+        assert_eq!(state.checkpoint_sha.as_deref(), Some("abc123"));
+        assert!(state.last_synced_sha.is_none());
+        assert_eq!(state.indexed_tips, vec!["def456".to_string()]);
+        assert_eq!(state.config_hash.as_deref(), Some("0123abcd"));
+    }
+
+    #[test]
+    fn test_identities_and_authors_filter() {
+        let store = make_store();
+        let repo = store
+            .add_repo(&make_repo_input("people"))
+            .expect("add repo");
+
+        // Work address; personal address folded in by .mailmap at ingest;
+        // GitHub noreply address left raw; and someone else.
+        let commits = [
+            ("a1", "Alice", "alice@work.com", "Alice", "alice@work.com"),
+            ("a2", "A. S.", "alice@home.net", "Alice", "alice@work.com"),
+            (
+                "a3",
+                "alice",
+                "1+alice@users.noreply.github.com",
+                "alice",
+                "1+alice@users.noreply.github.com",
+            ),
+            ("b1", "Bob", "bob@work.com", "Bob", "bob@work.com"),
+        ];
+        for (sha, name, email, cname, cemail) in commits {
+            let mut c = make_commit(repo.repo_id, sha, &format!("identity work {}", sha));
+            c.author_name = name.into();
+            c.author_email = email.into();
+            c.canonical_author_name = cname.into();
+            c.canonical_author_email = cemail.into();
+            store.upsert_commit(&c).expect("upsert");
+        }
+
+        let search_authors = |authors: &[&str]| -> Vec<String> {
+            let opts = SearchOpts {
+                authors: Some(authors.iter().map(|a| a.to_string()).collect()),
+                ..SearchOpts::default()
+            };
+            let mut shas: Vec<String> = store
+                .search("identity", &opts)
+                .expect("search")
+                .into_iter()
+                .map(|r| r.sha)
+                .collect();
+            shas.sort();
+            shas
+        };
+
+        // The raw personal address finds every commit by the mailmapped person
+        assert_eq!(search_authors(&["ALICE@home.net"]), vec!["a1", "a2"]);
+        assert_eq!(search_authors(&["Alice"]), vec!["a1", "a2", "a3"]);
+
+        // The global identity map folds the noreply address in as well
+        store
+            .add_identity(&IdentityAlias {
+                alias_email: "1+Alice@users.noreply.github.com".into(),
+                canonical_name: "Alice".into(),
+                canonical_email: "alice@work.com".into(),
+            })
+            .expect("add identity");
+        assert_eq!(search_authors(&["alice@work.com"]), vec!["a1", "a2", "a3"]);
+        let detail = store
+            .get_commit("people", "a3")
+            .expect("get_commit")
+            .expect("exists");
+        assert_eq!(detail.author, "Alice", "canonical name is displayed");
+
+        let touches = store
+            .touches(
+                "",
+                &TouchOpts {
+                    authors: Some(vec!["bob@work.com".into()]),
+                    ..TouchOpts::default()
+                },
+            )
+            .expect("touches");
+        assert!(touches.is_empty(), "no files recorded for bob");
+
+        let identities = store.list_identities().expect("list");
+        assert_eq!(identities.len(), 1);
+        assert_eq!(
+            identities[0].alias_email,
+            "1+alice@users.noreply.github.com"
+        );
+        store
+            .remove_identity("1+ALICE@users.noreply.github.com")
+            .expect("remove identity");
+        assert!(store.remove_identity("nobody@example.com").is_err());
+        assert_eq!(search_authors(&["alice@work.com"]), vec!["a1", "a2"]);
+    }
+
+    #[test]
+    fn test_trailers_stored_and_filtered() {
+        let store = make_store();
+        let repo = store
+            .add_repo(&make_repo_input("trailers"))
+            .expect("add repo");
+
+        let trailer = |key: &str, value: &str| Trailer {
+            key: key.into(),
+            value: value.into(),
+        };
+        let mut pair = make_commit(repo.repo_id, "t1", "pairing work");
+        pair.trailers = vec![
+            trailer("Co-authored-by", "Bob <bob@work.com>"),
+            trailer("Fixes", "#123"),
+            trailer("Fixes", "#124"),
+        ];
+        store.upsert_commit(&pair).expect("upsert t1");
+        let mut solo = make_commit(repo.repo_id, "t2", "solo work");
+        solo.trailers = vec![trailer("Signed-off-by", "Alice <alice@example.com>")];
+        store.upsert_commit(&solo).expect("upsert t2");
+
+        // Re-upserting does not duplicate trailers
+        store.upsert_commit(&pair).expect("re-upsert t1");
+        let detail = store
+            .get_commit("trailers", "t1")
+            .expect("get_commit")
+            .expect("exists");
+        assert_eq!(detail.trailers["Fixes"], vec!["#123", "#124"]);
+        assert_eq!(
+            detail.trailers["Co-authored-by"],
+            vec!["Bob <bob@work.com>"]
+        );
+
+        let search = |opts: SearchOpts| -> Vec<String> {
+            let mut shas: Vec<String> = store
+                .search("work", &opts)
+                .expect("search")
+                .into_iter()
+                .map(|r| r.sha)
+                .collect();
+            shas.sort();
+            shas
+        };
+        let by_trailer = |t: &str| SearchOpts {
+            trailer: Some(t.into()),
+            ..SearchOpts::default()
+        };
+        assert_eq!(search(by_trailer("fixes")), vec!["t1"]);
+        assert_eq!(search(by_trailer("Fixes:124")), vec!["t1"]);
+        assert!(search(by_trailer("Fixes:999")).is_empty());
+        // LIKE wildcards in the value match only themselves
+        assert!(search(by_trailer("Fixes:12_")).is_empty());
+        assert!(search(by_trailer("Fixes:1%4")).is_empty());
+        assert_eq!(search(by_trailer("Co-authored-by:bob@work")), vec!["t1"]);
+        assert_eq!(search(by_trailer("Signed-off-by")), vec!["t2"]);
+
+        // A co-author counts as an author, by email or by name
+        let by_author = |a: &str| SearchOpts {
+            authors: Some(vec![a.into()]),
+            ..SearchOpts::default()
+        };
+        assert_eq!(search(by_author("bob@work.com")), vec!["t1"]);
+        assert_eq!(search(by_author("bob")), vec!["t1"]);
+        assert_eq!(search(by_author("alice@example.com")), vec!["t1", "t2"]);
+    }
+
+    #[test]
+    fn test_reextract_refs_rewrites_commit_refs() {
+        let store = make_store();
+        let repo = store.add_repo(&make_repo_input("api")).expect("add api");
+        let mut commit = make_commit(repo.repo_id, "a1", "Fix crash");
+        commit.body = Some("Refs PROJ-12".into());
+        commit.refs = vec![CommitRef {
+            kind: RefKind::Issue,
+            project: Some("acme/api".into()),
+            id: "7".into(),
+        }];
+        store.upsert_commit(&commit).expect("upsert");
+        store
+            .upsert_commit(&make_commit(repo.repo_id, "a2", "Tidy up"))
+            .expect("upsert");
+
+        let jira = |message: &str| -> Vec<CommitRef> {
+            message
+                .contains("PROJ-12")
+                .then(|| CommitRef {
+                    kind: RefKind::Jira,
+                    project: None,
+                    id: "PROJ-12".into(),
+                })
+                .into_iter()
+                .collect()
+        };
+        let with_refs = store
+            .reextract_refs(repo.repo_id, &jira)
+            .expect("reextract");
+        assert_eq!(with_refs, 1);
+        let found = store
+            .find_by_ref("PROJ-12", &RefOpts::default())
+            .expect("find");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].sha, "a1");
+        // References the new patterns no longer recognise are dropped.
+        assert!(store
+            .find_by_ref("acme/api#7", &RefOpts::default())
+            .expect("find")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_find_by_ref_across_repos() {
+        let store = make_store();
+        let api = store.add_repo(&make_repo_input("api")).expect("add api");
+        let web = store.add_repo(&make_repo_input("web")).expect("add web");
+
+        let issue = |project: Option<&str>, id: &str| CommitRef {
+            kind: RefKind::Issue,
+            project: project.map(String::from),
+            id: id.into(),
+        };
+        // `#1234` inside acme/api, and a cross-repo mention from web
+        let mut fix = make_commit(api.repo_id, "a1", "Fix race (#1234)");
+        fix.refs = vec![issue(Some("acme/api"), "1234")];
+        fix.author_time = 1700000100;
+        store.upsert_commit(&fix).expect("upsert a1");
+        let mut follow = make_commit(web.repo_id, "w1", "Adapt to acme/api#1234");
+        follow.refs = vec![issue(Some("acme/api"), "1234")];
+        store.upsert_commit(&follow).expect("upsert w1");
+        let mut other = make_commit(web.repo_id, "w2", "Unrelated #1234 in another project");
+        other.refs = vec![
+            issue(Some("acme/web"), "1234"),
+            CommitRef {
+                kind: RefKind::Jira,
+                project: None,
+                id: "PROJ-881".into(),
+            },
+        ];
+        store.upsert_commit(&other).expect("upsert w2");
+
+        let shas = |reference: &str, opts: &RefOpts| -> Vec<String> {
+            store
+                .find_by_ref(reference, opts)
+                .expect("find_by_ref")
+                .into_iter()
+                .map(|r| r.sha)
+                .collect()
+        };
+        let all = RefOpts::default();
+        assert_eq!(shas("acme/api#1234", &all), vec!["a1", "w1"]);
+        assert_eq!(shas("#1234", &all), vec!["a1", "w1", "w2"]);
+        assert_eq!(shas("proj-881", &all), vec!["w2"]);
+        assert!(shas("!1234", &all).is_empty());
+        let web_only = RefOpts {
+            repos: Some(vec!["web".into()]),
+            ..RefOpts::default()
+        };
+        assert_eq!(shas("#1234", &web_only), vec!["w1", "w2"]);
+
+        let results = store.find_by_ref("ACME/API#1234", &all).expect("find");
+        assert_eq!(results[0].reference, "acme/api#1234");
+        assert_eq!(results[0].repo, "api");
+
+        // Reference patterns round-trip through repo_filters
+        let updated = store
+            .update_repo(
+                web.repo_id,
+                &RepoUpdate {
+                    ref_patterns: Some(vec![
+                        RefPattern::Github,
+                        RefPattern::Jira(vec!["PROJ".into()]),
+                    ]),
+                    ..RepoUpdate::default()
+                },
+            )
+            .expect("update");
+        assert_eq!(
+            updated.ref_patterns,
+            vec![RefPattern::Github, RefPattern::Jira(vec!["PROJ".into()])]
+        );
+
+        store.remove_repo("web").expect("remove web");
+        assert_eq!(shas("#1234", &all), vec!["a1"]);
+    }
+
+    #[test]
+    fn test_conventional_filters_and_summary() {
+        let store = make_store();
+        let repo = store.add_repo(&make_repo_input("cc")).expect("add repo");
+
+        let subjects = [
+            ("c1", "feat(api)!: remove v1 routes", 1700000400),
+            ("c2", "fix(api): retry on timeout", 1700000300),
+            ("c3", "docs: explain retries", 1700000200),
+            ("c4", "Update readme", 1700000100),
+        ];
+        for (sha, subject, time) in subjects {
+            let mut c = make_commit(repo.repo_id, sha, subject);
+            c.author_time = time;
+            c.conventional = ConventionalCommit::parse(subject, None);
+            store.upsert_commit(&c).expect("upsert");
+        }
+
+        let search = |opts: SearchOpts| -> Vec<String> {
+            store
+                .search("api OR retries OR readme", &opts)
+                .expect("search")
+                .into_iter()
+                .map(|r| r.sha)
+                .collect()
+        };
+        assert_eq!(
+            search(SearchOpts {
+                cc_types: Some(vec!["FIX".into(), "docs".into()]),
+                ..SearchOpts::default()
+            }),
+            vec!["c2", "c3"]
+        );
+        assert_eq!(
+            search(SearchOpts {
+                cc_scopes: Some(vec!["api".into()]),
+                breaking: Some(false),
+                ..SearchOpts::default()
+            }),
+            vec!["c2"]
+        );
+
+        let summary = store
+            .conventional_summary(&ConventionalSummaryOpts::default())
+            .expect("summary");
+        assert_eq!(summary.total, 3, "non-conventional commits are left out");
+        assert!(!summary.truncated);
+        assert_eq!(summary.breaking.len(), 1);
+        assert_eq!(summary.breaking[0].description, "remove v1 routes");
+        let types: Vec<&str> = summary
+            .sections
+            .iter()
+            .map(|s| s.cc_type.as_str())
+            .collect();
+        assert_eq!(types, vec!["feat", "fix", "docs"]);
+
+        let breaking_since = store
+            .conventional_summary(&ConventionalSummaryOpts {
+                repos: Some(vec!["cc".into()]),
+                since: Some(1700000150),
+                breaking: Some(true),
+                ..ConventionalSummaryOpts::default()
+            })
+            .expect("summary");
+        assert_eq!(breaking_since.total, 1);
+        let limited = store
+            .conventional_summary(&ConventionalSummaryOpts {
+                limit: Some(2),
+                ..ConventionalSummaryOpts::default()
+            })
+            .expect("summary");
+        assert_eq!(limited.total, 2);
+        assert!(limited.truncated);
+    }
+
+    #[test]
+    fn test_changelog_commits_in_range_order() {
+        let store = make_store();
+        let repo = store.add_repo(&make_repo_input("log")).expect("add repo");
+        for (sha, subject) in [("c1", "feat(cli)!: new flags"), ("c2", "Tidy up")] {
+            let mut c = make_commit(repo.repo_id, sha, subject);
+            c.conventional = ConventionalCommit::parse(subject, None);
+            store.upsert_commit(&c).expect("upsert");
+        }
+        store
+            .upsert_commit_files(&[CommitFile {
+                repo_id: repo.repo_id,
+                sha: "c1".to_string(),
+                path: "src/cli.rs".to_string(),
+                status: FileStatus::Modified,
+                old_path: None,
+                kind: FileKind::Text,
+                size: None,
+                lfs: None,
+                project: None,
+            }])
+            .expect("upsert files");
+
+        let shas: Vec<String> = ["c2", "missing", "c1"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let commits = store
+            .changelog_commits(repo.repo_id, &shas)
+            .expect("changelog_commits");
+        let got: Vec<&str> = commits.iter().map(|c| c.sha.as_str()).collect();
+        assert_eq!(
+            got,
+            vec!["c2", "c1"],
+            "range order kept, unknown SHAs skipped"
+        );
+        assert!(commits[0].conventional.is_none());
+        let cc = commits[1].conventional.as_ref().expect("conventional");
+        assert_eq!(
+            (cc.cc_type.as_str(), cc.scope.as_deref(), cc.breaking),
+            ("feat", Some("cli"), true)
+        );
+        assert_eq!(commits[1].paths, vec!["src/cli.rs"]);
+    }
+
+    #[test]
+    fn test_touches_follow_renames() {
+        let store = make_store();
+        let repo = store.add_repo(&make_repo_input("mv")).expect("add repo");
+        // (sha, time, path, status, old_path)
+        let history = [
+            ("c1", 100, "old.rs", FileStatus::Added, None),
+            ("c2", 200, "old.rs", FileStatus::Modified, None),
+            ("c3", 300, "new.rs", FileStatus::Renamed, Some("old.rs")),
+            // An unrelated file later reuses the old name.
+            ("c4", 400, "old.rs", FileStatus::Added, None),
+            ("c5", 500, "new.rs", FileStatus::Modified, None),
+            (
+                "c6",
+                600,
+                "lib/final.rs",
+                FileStatus::Renamed,
+                Some("new.rs"),
+            ),
+        ];
+        for (sha, time, path, status, old_path) in history {
+            let mut commit = make_commit(repo.repo_id, sha, sha);
+            commit.author_time = time;
+            store.upsert_commit(&commit).expect("upsert");
+            store
+                .upsert_commit_files(&[CommitFile {
+                    repo_id: repo.repo_id,
+                    sha: sha.to_string(),
+                    path: path.to_string(),
+                    status,
+                    old_path: old_path.map(str::to_string),
+                    kind: FileKind::Text,
+                    size: None,
+                    lfs: None,
+                    project: None,
+                }])
+                .expect("upsert files");
+        }
+
+        let shas = |glob: &str, follow_renames: bool| -> Vec<String> {
+            let opts = TouchOpts {
+                follow_renames,
+                ..TouchOpts::default()
+            };
+            store
+                .touches(glob, &opts)
+                .expect("touches")
+                .into_iter()
+                .map(|t| t.sha)
+                .collect()
+        };
+
+        assert_eq!(shas("new.rs", false), vec!["c5", "c3"]);
+        assert_eq!(shas("new.rs", true), vec!["c5", "c3", "c2", "c1"]);
+        assert_eq!(
+            shas("lib/final.rs", true),
+            vec!["c6", "c5", "c3", "c2", "c1"],
+            "renames are followed transitively"
+        );
+
+        let touches = store
+            .touches(
+                "lib/final.rs",
+                &TouchOpts {
+                    follow_renames: true,
+                    ..TouchOpts::default()
+                },
+            )
+            .expect("touches");
+        assert_eq!(touches[0].old_path.as_deref(), Some("new.rs"));
+        assert_eq!(touches[0].status, "R");
+        assert_eq!(touches[4].path, "old.rs");
+    }
+
+    #[test]
+    fn test_binary_and_lfs_files_in_touches_and_get_commit() {
+        let store = make_store();
+        let repo = store.add_repo(&make_repo_input("bin")).expect("add repo");
+        store
+            .upsert_commit(&make_commit(repo.repo_id, "b1", "Add assets"))
+            .expect("upsert");
+        let file =
+            |path: &str, kind: FileKind, size: Option<u64>, lfs: Option<LfsPointer>| CommitFile {
+                repo_id: repo.repo_id,
+                sha: "b1".to_string(),
+                path: path.to_string(),
+                status: FileStatus::Added,
+                old_path: None,
+                kind,
+                size,
+                lfs,
+                project: None,
+            };
+        let pointer = LfsPointer {
+            oid: "sha256:abc123".to_string(),
+            size: 5_000_000,
+        };
+        store
+            .upsert_commit_files(&[
+                file("assets/logo.png", FileKind::Binary, Some(2048), None),
+                file(
+                    "models/weights.bin",
+                    FileKind::Lfs,
+                    Some(130),
+                    Some(pointer.clone()),
+                ),
+                file("src/main.rs", FileKind::Text, Some(12), None),
+            ])
+            .expect("upsert files");
+
+        let touches = store
+            .touches("*.png", &TouchOpts::default())
+            .expect("touches");
+        assert_eq!(touches.len(), 1);
+        assert_eq!(touches[0].path, "assets/logo.png");
+        assert_eq!(touches[0].kind, FileKind::Binary);
+        assert_eq!(touches[0].size, Some(2048));
+
+        let detail = store.get_commit("bin", "b1").expect("get_commit").unwrap();
+        let weights = detail
+            .changed_files
+            .iter()
+            .find(|f| f.path == "models/weights.bin")
+            .unwrap();
+        assert_eq!(weights.kind, FileKind::Lfs);
+        assert_eq!(weights.lfs, Some(pointer));
+        let main = detail
+            .changed_files
+            .iter()
+            .find(|f| f.path == "src/main.rs")
+            .unwrap();
+        assert_eq!(main.kind, FileKind::Text);
+        assert_eq!(main.size, Some(12));
+    }
+
+    #[test]
+    fn test_projects_tagged_filtered_and_counted() {
+        let store = make_store();
+        let mut input = make_repo_input("mono");
+        input.projects = vec![
+            RepoProject::parse("billing=services/billing/").unwrap(),
+            RepoProject::parse("web=apps/web/").unwrap(),
+        ];
+        let repo = store.add_repo(&input).expect("add repo");
+        assert_eq!(repo.projects, input.projects);
+
+        // (sha, time, [(path, project)])
+        let history = [
+            (
+                "p1",
+                100,
+                vec![("services/billing/invoice.rs", Some("billing"))],
+            ),
+            ("p2", 200, vec![("apps/web/button.tsx", Some("web"))]),
+            (
+                "p3",
+                300,
+                vec![
+                    ("services/billing/tax.rs", Some("billing")),
+                    ("apps/web/cart.tsx", Some("web")),
+                    ("README.md", None),
+                ],
+            ),
+        ];
+        for (sha, time, files) in history {
+            let mut commit = make_commit(repo.repo_id, sha, "Fix rounding");
+            commit.author_time = time;
+            store.upsert_commit(&commit).expect("upsert");
+            let files: Vec<CommitFile> = files
+                .into_iter()
+                .map(|(path, project)| CommitFile {
+                    repo_id: repo.repo_id,
+                    sha: sha.to_string(),
+                    path: path.to_string(),
+                    status: FileStatus::Modified,
+                    old_path: None,
+                    kind: FileKind::Text,
+                    size: None,
+                    lfs: None,
+                    project: project.map(str::to_string),
+                })
+                .collect();
+            store.upsert_commit_files(&files).expect("upsert files");
+        }
+
+        let search = |project: &str| -> Vec<String> {
+            let opts = SearchOpts {
+                projects: Some(vec![project.to_string()]),
+                ..SearchOpts::default()
+            };
+            store
+                .search("rounding", &opts)
+                .expect("search")
+                .into_iter()
+                .map(|r| r.sha)
+                .collect()
+        };
+        assert_eq!(search("billing"), vec!["p3", "p1"]);
+        assert_eq!(search("web"), vec!["p3", "p2"]);
+
+        let touches = store
+            .touches(
+                "*.tsx",
+                &TouchOpts {
+                    projects: Some(vec!["web".to_string()]),
+                    ..TouchOpts::default()
+                },
+            )
+            .expect("touches");
+        let paths: Vec<&str> = touches.iter().map(|t| t.path.as_str()).collect();
+        assert_eq!(paths, vec!["apps/web/cart.tsx", "apps/web/button.tsx"]);
+
+        let detail = store.get_commit("mono", "p3").expect("get_commit").unwrap();
+        assert_eq!(detail.projects, vec!["billing", "web"]);
+
+        let stats = store.repo_stats(repo.repo_id).expect("stats");
+        let counts: Vec<(&str, usize, Option<i64>)> = stats
+            .projects
+            .iter()
+            .map(|p| (p.name.as_str(), p.commit_count, p.last_commit_at))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![("billing", 2, Some(300)), ("web", 2, Some(300))]
+        );
+
+        // Redefining the projects re-tags what is already indexed.
+        store
+            .update_repo(
+                repo.repo_id,
+                &RepoUpdate {
+                    projects: Some(vec![
+                        RepoProject::parse("services=services/").unwrap(),
+                        RepoProject::parse("docs=README.md").unwrap(),
+                    ]),
+                    ..RepoUpdate::default()
+                },
+            )
+            .expect("update");
+        assert!(search("billing").is_empty());
+        assert!(search("web").is_empty());
+        assert_eq!(search("services"), vec!["p3", "p1"]);
+        let detail = store.get_commit("mono", "p3").expect("get_commit").unwrap();
+        assert_eq!(detail.projects, vec!["docs", "services"]);
+    }
+
+    #[test]
+    fn test_symbol_history_bare_and_qualified() {
+        let store = make_store();
+        let repo = store.add_repo(&make_repo_input("sym")).expect("add repo");
+        let symbol = |sha: &str, path: &str, qualified: &str| CommitSymbol {
+            repo_id: repo.repo_id,
+            sha: sha.to_string(),
+            path: path.to_string(),
+            name: qualified.rsplit("::").next().unwrap().to_string(),
+            qualified: qualified.to_string(),
+            kind: "method".to_string(),
+            excerpt: format!("@@ -1 +1 @@\n+{}\n", qualified),
+        };
+        let entry = |sha: &str, time: i64, symbols: Vec<CommitSymbol>| {
+            let mut commit = make_commit(repo.repo_id, sha, sha);
+            commit.author_time = time;
+            BatchCommit {
+                commit,
+                files: vec![],
+                patch: None,
+                symbols,
+                submodules: vec![],
+            }
+        };
+        let batch = WriteBatch {
+            commits: vec![
+                entry("s1", 100, vec![symbol("s1", "src/a.rs", "Parser::parse")]),
+                entry(
+                    "s2",
+                    200,
+                    vec![
+                        symbol("s2", "src/a.rs", "Parser::parse"),
+                        symbol("s2", "src/b/c.rs", "Lexer::parse"),
+                    ],
+                ),
+                entry("s3", 300, vec![symbol("s3", "src/a.rs", "Parser::new")]),
+            ],
+        };
+        store.write_batch(&batch).expect("write batch");
+        // Rewriting must not duplicate symbol rows.
+        store.write_batch(&batch).expect("rewrite batch");
+
+        let history = |symbol: &str, path: Option<&str>| -> Vec<(String, String)> {
+            let opts = SymbolOpts {
+                path: path.map(str::to_string),
+                limit: None,
+            };
+            store
+                .symbol_history(repo.repo_id, symbol, &opts)
+                .expect("symbol_history")
+                .into_iter()
+                .map(|c| (c.sha, c.symbol))
+                .collect()
+        };
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+
+        assert_eq!(
+            history("parse", None),
+            vec![
+                pair("s2", "Parser::parse"),
+                pair("s2", "Lexer::parse"),
+                pair("s1", "Parser::parse")
+            ]
+        );
+        assert_eq!(
+            history("Parser::parse", None),
+            vec![pair("s2", "Parser::parse"), pair("s1", "Parser::parse")]
+        );
+        assert_eq!(history("Lexer.parse", None), vec![]);
+        assert_eq!(
+            history("parse", Some("src/b/")),
+            vec![pair("s2", "Lexer::parse")]
+        );
+        assert_eq!(history("parse", Some("src/b/c")), vec![]);
+
+        let change = &store
+            .symbol_history(repo.repo_id, "new", &SymbolOpts::default())
+            .expect("symbol_history")[0];
+        assert_eq!(change.path, "src/a.rs");
+        assert_eq!(change.kind, "method");
+        assert_eq!(change.excerpt, "@@ -1 +1 @@\n+Parser::new\n");
+        assert_eq!(change.date, 300);
+    }
+
+    #[test]
+    fn test_tags_first_release_and_contained_in() {
+        let store = make_store();
+        let repo = store.add_repo(&make_repo_input("rel")).expect("add repo");
+        for sha in ["c1", "c2", "c3", "c4"] {
+            store
+                .upsert_commit(&make_commit(repo.repo_id, sha, sha))
+                .expect("upsert");
+        }
+
+        let tag = |name: &str, target: &str, date: i64| Tag {
+            name: name.into(),
+            target_sha: target.into(),
+            annotated: name != "v2",
+            tagger_name: Some("Rel Eng".into()),
+            tagger_email: Some("rel@example.com".into()),
+            date,
+            message: Some(format!("Release {name}")),
+        };
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        let index = TagIndex {
+            tags: vec![
+                tag("v3", "c3", 300),
+                tag("v1", "c1", 100),
+                tag("v2", "c2", 200),
+            ],
+            introduced: vec![pair("v1", "c1"), pair("v2", "c2"), pair("v3", "c3")],
+            ancestors: vec![pair("v2", "v1"), pair("v3", "v1"), pair("v3", "v2")],
+        };
+        store
+            .replace_tags(repo.repo_id, &index)
+            .expect("replace_tags");
+
+        let names: Vec<String> = store
+            .list_tags(repo.repo_id)
+            .expect("list_tags")
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, vec!["v1", "v2", "v3"]);
+
+        let c1 = store.get_commit("rel", "c1").unwrap().expect("c1");
+        assert_eq!(c1.first_release.as_deref(), Some("v1"));
+        assert_eq!(c1.contained_in, vec!["v1", "v2", "v3"]);
+        let c2 = store.get_commit("rel", "c2").unwrap().expect("c2");
+        assert_eq!(c2.contained_in, vec!["v2", "v3"]);
+        let c4 = store.get_commit("rel", "c4").unwrap().expect("c4");
+        assert_eq!(c4.first_release, None);
+        assert!(c4.contained_in.is_empty());
+
+        // Replacing drops tags that no longer exist.
+        let index = TagIndex {
+            tags: vec![tag("v3", "c3", 300)],
+            introduced: vec![pair("v3", "c1"), pair("v3", "c2"), pair("v3", "c3")],
+            ancestors: vec![],
+        };
+        store
+            .replace_tags(repo.repo_id, &index)
+            .expect("replace_tags");
+        let c1 = store.get_commit("rel", "c1").unwrap().expect("c1");
+        assert_eq!(c1.first_release.as_deref(), Some("v3"));
+        assert_eq!(c1.contained_in, vec!["v3"]);
+    }
+
+    #[test]
+    fn test_conventional_backfill_for_existing_commits() {
+        let store = make_store();
+        let repo = store.add_repo(&make_repo_input("old")).expect("add repo");
+        let mut c = make_commit(repo.repo_id, "o1", "perf(db): batch inserts");
+        c.body = Some("BREAKING CHANGE: needs SQLite 3.35".into());
+        store.upsert_commit(&c).expect("upsert");
+        {
+            // As if indexed before the columns existed
+            let conn = store.conn.lock().unwrap();
+            conn.execute_batch(
+                "UPDATE commits SET cc_type = NULL, cc_scope = NULL, breaking = 0;
+                 DELETE FROM config WHERE key = 'migration.conventional_commits';",
+            )
+            .unwrap();
+        }
+
+        store.init().expect("re-run init");
+        let summary = store
+            .conventional_summary(&ConventionalSummaryOpts::default())
+            .expect("summary");
+        assert_eq!(summary.sections[0].cc_type, "perf");
+        assert_eq!(summary.sections[0].commits[0].scope.as_deref(), Some("db"));
+        assert!(summary.sections[0].commits[0].breaking);
+    }
 }
@@ -1,18 +1,329 @@
 use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
 use std::sync::MutexGuard;
 
 use commitmux_types::{
-    Commit, CommitDetail, CommitFile, CommitFileDetail, CommitPatch, CommitmuxError, EmbedCommit,
-    IngestState, MemoryDoc, MemoryDocInput, MemoryFtsSearchOpts, MemoryMatch, MemorySearchOpts,
-    MemorySourceType, PatchResult, Repo, RepoInput, RepoListEntry, RepoStats, RepoUpdate, Result,
-    SearchOpts, SearchResult, SemanticSearchOpts, Store, TouchOpts, TouchResult,
+    AuthorMatcher, ChangelogCommit, Commit, CommitDetail, CommitFile, CommitFileDetail,
+    CommitPatch, CommitRef, CommitSubmodule, CommitSubmoduleDetail, CommitSymbol, CommitmuxError,
+    ConventionalCommit, ConventionalEntry, ConventionalSummary, ConventionalSummaryOpts,
+    EmbedCommit, FileKind, IdentityAlias, IngestState, LfsPointer, MemoryDoc, MemoryDocInput,
+    MemoryFtsSearchOpts, MemoryMatch, MemorySearchOpts, MemorySourceType, PatchResult,
+    ProjectStats, RefKind, RefOpts, RefPattern, RefResult, Repo, RepoInput, RepoListEntry,
+    RepoProject, RepoStats, RepoUpdate, Result, SearchOpts, SearchResult, SemanticSearchOpts,
+    Store, SymbolChange, SymbolOpts, Tag, TagIndex, TouchOpts, TouchResult, WriteBatch,
 };
 
 use crate::SqliteStore;
 
 // ── Helpers ───────────────────────────────────────────────────────────────
 
-fn parse_exclude_prefixes(s: Option<String>) -> Vec<String> {
+/// Removes the FTS entry for an existing commit row, if there is one.
+/// Must run before the row is replaced so the external-content index stays in sync.
+fn delete_commit_fts(conn: &Connection, repo_id: i64, sha: &str) -> Result<()> {
+    let existing: Option<(i64, String, Option<String>, Option<String>)> = conn
+        .prepare_cached(
+            "SELECT rowid, COALESCE(subject,''), body, patch_preview
+             FROM commits WHERE repo_id = ?1 AND sha = ?2",
+        )?
+        .query_row(params![repo_id, sha], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .optional()?;
+
+    if let Some((rowid, subject, body, preview)) = existing {
+        conn.prepare_cached(
+            "INSERT INTO commits_fts(commits_fts, rowid, subject, body, patch_preview)
+             VALUES('delete', ?1, ?2, ?3, ?4)",
+        )?
+        .execute(params![rowid, subject, body, preview])?;
+    }
+    Ok(())
+}
+
+/// Inserts (or replaces) a commit row and its FTS entry.
+fn insert_commit(conn: &Connection, commit: &Commit, patch_preview: &str) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO commits
+            (repo_id, sha, author_name, author_email, committer_name, committer_email,
+             canonical_author_name, canonical_author_email,
+             author_time, commit_time, subject, body, parent_count, patch_preview,
+             cc_type, cc_scope, breaking)
+         VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16,?17)",
+    )?
+    .execute(params![
+        commit.repo_id,
+        commit.sha,
+        commit.author_name,
+        commit.author_email,
+        commit.committer_name,
+        commit.committer_email,
+        commit.canonical_author_name,
+        commit.canonical_author_email,
+        commit.author_time,
+        commit.commit_time,
+        commit.subject,
+        commit.body,
+        commit.parent_count,
+        patch_preview,
+        commit.conventional.as_ref().map(|cc| cc.cc_type.as_str()),
+        commit
+            .conventional
+            .as_ref()
+            .and_then(|cc| cc.scope.as_deref()),
+        commit.conventional.as_ref().is_some_and(|cc| cc.breaking),
+    ])?;
+
+    let new_rowid = conn.last_insert_rowid();
+    conn.prepare_cached(
+        "INSERT INTO commits_fts(rowid, subject, body, patch_preview)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?
+    .execute(params![
+        new_rowid,
+        commit.subject,
+        commit.body,
+        patch_preview
+    ])?;
+
+    conn.prepare_cached("DELETE FROM commit_trailers WHERE repo_id = ?1 AND sha = ?2")?
+        .execute(params![commit.repo_id, commit.sha])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO commit_trailers (repo_id, sha, key, value, ident_name, ident_email)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for trailer in &commit.trailers {
+        let ident = trailer.identity();
+        stmt.execute(params![
+            commit.repo_id,
+            commit.sha,
+            trailer.key,
+            trailer.value,
+            ident.map(|(name, _)| name),
+            ident.map(|(_, email)| email),
+        ])?;
+    }
+
+    conn.prepare_cached("DELETE FROM commit_refs WHERE repo_id = ?1 AND sha = ?2")?
+        .execute(params![commit.repo_id, commit.sha])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO commit_refs (repo_id, sha, kind, project, ref_id)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for r in &commit.refs {
+        stmt.execute(params![
+            commit.repo_id,
+            commit.sha,
+            r.kind.as_str(),
+            r.project,
+            r.id
+        ])?;
+    }
+    Ok(())
+}
+
+/// Fills the Conventional Commit columns of commits indexed before they
+/// existed, once per database.
+pub(crate) fn backfill_conventional(conn: &Connection) -> Result<()> {
+    let done: Option<String> = conn
+        .query_row(
+            "SELECT value FROM config WHERE key = ?1",
+            params![crate::schema::CONVENTIONAL_BACKFILL_KEY],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if done.is_some() {
+        return Ok(());
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut select = tx.prepare("SELECT rowid, subject, body FROM commits")?;
+        let mut update = tx.prepare(
+            "UPDATE commits SET cc_type = ?1, cc_scope = ?2, breaking = ?3 WHERE rowid = ?4",
+        )?;
+        let rows = select.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (rowid, subject, body) = row?;
+            if let Some(cc) = ConventionalCommit::parse(&subject, body.as_deref()) {
+                update.execute(params![cc.cc_type, cc.scope, cc.breaking, rowid])?;
+            }
+        }
+    }
+    tx.execute(
+        "INSERT INTO config (key, value) VALUES (?1, '1')",
+        params![crate::schema::CONVENTIONAL_BACKFILL_KEY],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// `commit_files.kind`; rows written before the column existed are text.
+fn file_kind(kind: Option<String>) -> FileKind {
+    kind.as_deref()
+        .and_then(FileKind::from_str)
+        .unwrap_or_default()
+}
+
+/// Replaces the changed-file rows for one commit, and the projects it
+/// touches.
+fn replace_commit_files(
+    conn: &Connection,
+    repo_id: i64,
+    sha: &str,
+    files: &[CommitFile],
+) -> Result<()> {
+    conn.prepare_cached("DELETE FROM commit_files WHERE repo_id = ?1 AND sha = ?2")?
+        .execute(params![repo_id, sha])?;
+    conn.prepare_cached("DELETE FROM commit_projects WHERE repo_id = ?1 AND sha = ?2")?
+        .execute(params![repo_id, sha])?;
+
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO commit_files (repo_id, sha, path, status, old_path, kind, size, lfs_oid, lfs_size, project)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+    let mut project_stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO commit_projects (repo_id, sha, project) VALUES (?1, ?2, ?3)",
+    )?;
+    for file in files {
+        stmt.execute(params![
+            file.repo_id,
+            file.sha,
+            file.path,
+            file.status.as_str(),
+            file.old_path,
+            file.kind.as_str(),
+            file.size.map(|s| s as i64),
+            file.lfs.as_ref().map(|l| l.oid.as_str()),
+            file.lfs.as_ref().map(|l| l.size as i64),
+            file.project,
+        ])?;
+        if let Some(ref project) = file.project {
+            project_stmt.execute(params![file.repo_id, file.sha, project])?;
+        }
+    }
+    Ok(())
+}
+
+/// Re-tags a repo's indexed files and commits after its projects changed.
+fn retag_projects(conn: &Connection, repo_id: i64, projects: &[RepoProject]) -> Result<()> {
+    conn.execute(
+        "UPDATE commit_files SET project = NULL WHERE repo_id = ?1",
+        params![repo_id],
+    )?;
+    // Shorter prefixes first, so nested projects overwrite the enclosing one
+    // as in `RepoProject::for_path`.
+    let mut projects: Vec<&RepoProject> = projects.iter().collect();
+    projects.sort_by_key(|p| p.prefix.len());
+    for p in projects {
+        conn.execute(
+            "UPDATE commit_files SET project = ?2
+             WHERE repo_id = ?1 AND substr(path, 1, length(?3)) = ?3",
+            params![repo_id, p.name, p.prefix],
+        )?;
+    }
+    conn.execute(
+        "DELETE FROM commit_projects WHERE repo_id = ?1",
+        params![repo_id],
+    )?;
+    conn.execute(
+        "INSERT OR IGNORE INTO commit_projects (repo_id, sha, project)
+         SELECT repo_id, sha, project FROM commit_files
+         WHERE repo_id = ?1 AND project IS NOT NULL",
+        params![repo_id],
+    )?;
+    Ok(())
+}
+
+/// Replaces the changed-symbol rows for one commit.
+fn replace_commit_symbols(
+    conn: &Connection,
+    repo_id: i64,
+    sha: &str,
+    symbols: &[CommitSymbol],
+) -> Result<()> {
+    conn.prepare_cached("DELETE FROM commit_symbols WHERE repo_id = ?1 AND sha = ?2")?
+        .execute(params![repo_id, sha])?;
+
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO commit_symbols (repo_id, sha, path, name, qualified, kind, excerpt)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for symbol in symbols {
+        stmt.execute(params![
+            symbol.repo_id,
+            symbol.sha,
+            symbol.path,
+            symbol.name,
+            symbol.qualified,
+            symbol.kind,
+            symbol.excerpt,
+        ])?;
+    }
+    Ok(())
+}
+
+/// Replaces the submodule rows for one commit.
+fn replace_commit_submodules(
+    conn: &Connection,
+    repo_id: i64,
+    sha: &str,
+    submodules: &[CommitSubmodule],
+) -> Result<()> {
+    conn.prepare_cached("DELETE FROM commit_submodules WHERE repo_id = ?1 AND sha = ?2")?
+        .execute(params![repo_id, sha])?;
+
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO commit_submodules (repo_id, sha, path, url, old_sha, new_sha)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for submodule in submodules {
+        stmt.execute(params![
+            submodule.repo_id,
+            submodule.sha,
+            submodule.path,
+            submodule.url,
+            submodule.old_sha,
+            submodule.new_sha,
+        ])?;
+    }
+    Ok(())
+}
+
+/// The last component of a possibly qualified symbol name:
+/// `Parser::parse` and `Parser.parse` both give `parse`.
+fn bare_symbol_name(symbol: &str) -> &str {
+    let last = symbol.rsplit("::").next().unwrap_or(symbol);
+    last.rsplit('.').next().unwrap_or(last)
+}
+
+/// Compresses and stores a patch blob. Does not touch `patch_preview`.
+fn insert_patch_blob(conn: &Connection, patch: &CommitPatch) -> Result<()> {
+    // Compress the blob with zstd level 3.
+    let compressed =
+        zstd::encode_all(patch.patch_blob.as_slice(), 3).map_err(CommitmuxError::Io)?;
+
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO commit_patches
+             (repo_id, sha, patch_blob, truncated, original_size)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?
+    .execute(params![
+        patch.repo_id,
+        patch.sha,
+        compressed,
+        patch.truncated,
+        patch.original_size as i64,
+    ])?;
+    Ok(())
+}
+
+fn parse_string_list(s: Option<String>) -> Vec<String> {
     match s {
         None => vec![],
         Some(j) => serde_json::from_str::<Vec<String>>(&j).unwrap_or_default(),
@@ -47,6 +358,186 @@ fn format_iso_date(ts: i64) -> String {
     )
 }
 
+/// Most earlier names followed for one `touches` query.
+const MAX_RENAME_SEGMENTS: usize = 256;
+
+/// Earlier names of the files matching `like_pat`, as
+/// `(repo_id, old_path, renamed_at, renaming_sha)`: `old_path` belongs to the
+/// history up to (and excluding) the commit that renamed or copied it.
+/// Followed transitively through older renames.
+fn rename_segments(
+    conn: &Connection,
+    like_pat: &str,
+    repos: Option<&Vec<String>>,
+) -> Result<Vec<(i64, String, i64, String)>> {
+    let repos_json = repos.map(|r| serde_json::to_string(r).unwrap_or_else(|_| "[]".into()));
+    let mut stmt = conn.prepare(
+        "SELECT c.repo_id, cf.old_path, c.author_time, c.sha
+         FROM commit_files cf
+         JOIN commits c ON cf.repo_id = c.repo_id AND cf.sha = c.sha
+         JOIN repos r ON r.repo_id = c.repo_id
+         WHERE cf.path LIKE ?1 AND cf.old_path IS NOT NULL
+           AND (?2 IS NULL OR r.name IN (SELECT value FROM json_each(?2)))",
+    )?;
+    let mut queue: Vec<(i64, String, i64, String)> = stmt
+        .query_map(params![like_pat, repos_json], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut older = conn.prepare_cached(
+        "SELECT cf.old_path, c.author_time, c.sha
+         FROM commit_files cf
+         JOIN commits c ON cf.repo_id = c.repo_id AND cf.sha = c.sha
+         WHERE cf.repo_id = ?1 AND cf.path = ?2 AND cf.old_path IS NOT NULL
+           AND c.author_time <= ?3 AND c.sha != ?4",
+    )?;
+    let mut segments = Vec::new();
+    while let Some(segment) = queue.pop() {
+        if segments.len() >= MAX_RENAME_SEGMENTS {
+            break;
+        }
+        if segments.contains(&segment) {
+            continue;
+        }
+        let (repo_id, ref path, until, ref sha) = segment;
+        let found: Vec<(String, i64, String)> = older
+            .query_map(params![repo_id, path, until, sha], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        queue.extend(
+            found
+                .into_iter()
+                .map(|(old, time, sha)| (repo_id, old, time, sha)),
+        );
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+/// SQL expression for the display name of commit alias `c`: the global
+/// identity map first, then the `.mailmap`-resolved name, then the raw name.
+fn author_name_sql(c: &str) -> String {
+    format!(
+        "COALESCE((SELECT i.canonical_name FROM identities i \
+          WHERE i.alias_email = LOWER(COALESCE({c}.canonical_author_email, {c}.author_email))), \
+          {c}.canonical_author_name, {c}.author_name)"
+    )
+}
+
+/// Like [`author_name_sql`], for the canonical email.
+fn author_email_sql(c: &str) -> String {
+    format!(
+        "COALESCE((SELECT i.canonical_email FROM identities i \
+          WHERE i.alias_email = LOWER(COALESCE({c}.canonical_author_email, {c}.author_email))), \
+          {c}.canonical_author_email, {c}.author_email)"
+    )
+}
+
+/// WHERE condition restricting commit alias `c` to the authors in the JSON
+/// array bound at `?{idx}`. A value matches a commit's canonical or raw name
+/// or email (case-insensitively); every commit sharing that canonical email
+/// is then included, so any alias of a person finds all their work. Commits
+/// where the person appears in a `Co-authored-by` trailer match as well.
+fn authors_condition(c: &str, idx: usize) -> String {
+    let keys = format!("(SELECT LOWER(value) FROM json_each(?{idx}))");
+    // Canonical emails of everyone the filter values refer to.
+    let emails = format!(
+        "(SELECT LOWER({a_email}) FROM commits a \
+            WHERE LOWER(a.author_email) IN {keys} OR LOWER(a.author_name) IN {keys} \
+               OR LOWER(a.canonical_author_email) IN {keys} \
+               OR LOWER(a.canonical_author_name) IN {keys} \
+          UNION SELECT LOWER(canonical_email) FROM identities WHERE alias_email IN {keys} \
+          UNION SELECT * FROM {keys})",
+        a_email = author_email_sql("a"),
+    );
+    format!(
+        "(LOWER({email}) IN {emails} OR LOWER({name}) IN {keys} \
+          OR EXISTS (SELECT 1 FROM commit_trailers t \
+             WHERE t.repo_id = {c}.repo_id AND t.sha = {c}.sha \
+               AND t.key = 'Co-authored-by' COLLATE NOCASE \
+               AND (LOWER(t.ident_name) IN {keys} \
+                    OR LOWER(COALESCE((SELECT i.canonical_email FROM identities i \
+                         WHERE i.alias_email = LOWER(t.ident_email)), t.ident_email)) IN {emails})))",
+        email = author_email_sql(c),
+        name = author_name_sql(c),
+    )
+}
+
+/// Appends the Conventional Commit type, scope and breaking filters for
+/// commit alias `c`, binding values from `?{param_idx}` onwards.
+fn push_conventional_conditions(
+    c: &str,
+    cc_types: Option<&Vec<String>>,
+    cc_scopes: Option<&Vec<String>>,
+    breaking: Option<bool>,
+    conditions: &mut String,
+    bind_vals: &mut Vec<Box<dyn rusqlite::types::ToSql>>,
+    param_idx: &mut usize,
+) {
+    if let Some(types) = cc_types {
+        conditions.push_str(&format!(
+            " AND {c}.cc_type IN (SELECT LOWER(value) FROM json_each(?{}))",
+            param_idx
+        ));
+        bind_vals.push(Box::new(
+            serde_json::to_string(types).unwrap_or_else(|_| "[]".into()),
+        ));
+        *param_idx += 1;
+    }
+    if let Some(scopes) = cc_scopes {
+        conditions.push_str(&format!(
+            " AND LOWER({c}.cc_scope) IN (SELECT LOWER(value) FROM json_each(?{}))",
+            param_idx
+        ));
+        bind_vals.push(Box::new(
+            serde_json::to_string(scopes).unwrap_or_else(|_| "[]".into()),
+        ));
+        *param_idx += 1;
+    }
+    if let Some(breaking) = breaking {
+        conditions.push_str(&format!(" AND {c}.breaking = ?{}", param_idx));
+        bind_vals.push(Box::new(breaking));
+        *param_idx += 1;
+    }
+}
+
+/// WHERE condition restricting commit alias `c` to commits touching one of
+/// the repo projects in the JSON array bound at `?{idx}`.
+fn projects_condition(c: &str, idx: usize) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM commit_projects cp \
+           WHERE cp.repo_id = {c}.repo_id AND cp.sha = {c}.sha \
+             AND cp.project IN (SELECT value FROM json_each(?{idx})))"
+    )
+}
+
+/// WHERE condition for a `Key` or `Key:value` trailer filter on commit
+/// alias `c`, binding the key at `?{idx}` and the value, escaped with
+/// [`escape_like`], at `?{idx + 1}`.
+fn trailer_condition(c: &str, idx: usize) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM commit_trailers t \
+           WHERE t.repo_id = {c}.repo_id AND t.sha = {c}.sha \
+             AND t.key = ?{idx} COLLATE NOCASE \
+             AND t.value LIKE '%' || ?{} || '%' ESCAPE '\\')",
+        idx + 1
+    )
+}
+
+/// Escapes `%`, `_` and `\` so `s` matches literally in a `LIKE ... ESCAPE '\'`.
+fn escape_like(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for ch in s.chars() {
+        if matches!(ch, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
 fn row_to_repo(row: &rusqlite::Row<'_>) -> rusqlite::Result<Repo> {
     Ok(Repo {
         repo_id: row.get(0)?,
@@ -55,12 +546,62 @@ fn row_to_repo(row: &rusqlite::Row<'_>) -> rusqlite::Result<Repo> {
         remote_url: row.get(3)?,
         default_branch: row.get(4)?,
         fork_of: row.get(5)?,
-        author_filter: row.get(6)?,
-        exclude_prefixes: parse_exclude_prefixes(row.get(7)?),
-        embed_enabled: row.get::<_, i64>(8).unwrap_or(0) != 0,
+        author_filters: Vec::new(),
+        include_paths: Vec::new(),
+        ref_patterns: Vec::new(),
+        projects: Vec::new(),
+        exclude_prefixes: parse_string_list(row.get(6)?),
+        embed_enabled: row.get::<_, i64>(7).unwrap_or(0) != 0,
+        clone_depth: row.get(8)?,
+        clone_since: row.get(9)?,
+        ssh_key: row
+            .get::<_, Option<String>>(10)?
+            .map(std::path::PathBuf::from),
+        token_env: row.get(11)?,
     })
 }
 
+/// Fills in `author_filters`, `include_paths`, `ref_patterns` and
+/// `projects` from `repo_filters`.
+fn load_repo_filters(conn: &Connection, repo: &mut Repo) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT kind, pattern FROM repo_filters WHERE repo_id = ?1 ORDER BY rowid",
+    )?;
+    let rows = stmt.query_map(params![repo.repo_id], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    repo.author_filters.clear();
+    repo.include_paths.clear();
+    repo.ref_patterns.clear();
+    repo.projects.clear();
+    for row in rows {
+        let (kind, pattern) = row?;
+        if kind == "include_path" {
+            repo.include_paths.push(pattern);
+        } else if kind == "ref_pattern" {
+            repo.ref_patterns.extend(RefPattern::parse(&pattern));
+        } else if kind == "project" {
+            repo.projects.extend(RepoProject::parse(&pattern));
+        } else if let Some(m) = AuthorMatcher::from_parts(&kind, &pattern) {
+            repo.author_filters.push(m);
+        }
+    }
+    Ok(())
+}
+
+fn insert_repo_filter(
+    conn: &Connection,
+    repo_id: i64,
+    kind: &str,
+    pattern: &str,
+) -> rusqlite::Result<()> {
+    conn.prepare_cached(
+        "INSERT OR IGNORE INTO repo_filters (repo_id, kind, pattern) VALUES (?1, ?2, ?3)",
+    )?
+    .execute(params![repo_id, kind, pattern])?;
+    Ok(())
+}
+
 // ── impl Store ────────────────────────────────────────────────────────────
 
 impl Store for SqliteStore {
@@ -71,20 +612,38 @@ impl Store for SqliteStore {
         let exclude_json =
             serde_json::to_string(&input.exclude_prefixes).unwrap_or_else(|_| "[]".to_string());
         conn.execute(
-            "INSERT INTO repos (name, local_path, remote_url, default_branch, fork_of, author_filter, exclude_prefixes, embed_enabled)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT INTO repos (name, local_path, remote_url, default_branch, fork_of, exclude_prefixes, embed_enabled, clone_depth, clone_since, ssh_key, token_env)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             params![
                 input.name,
                 input.local_path.to_string_lossy().as_ref(),
                 input.remote_url,
                 input.default_branch,
                 input.fork_of,
-                input.author_filter,
                 exclude_json,
                 input.embed_enabled as i64,
+                input.clone_depth,
+                input.clone_since,
+                input
+                    .ssh_key
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().into_owned()),
+                input.token_env,
             ],
         )?;
         let repo_id = conn.last_insert_rowid();
+        for m in &input.author_filters {
+            insert_repo_filter(&conn, repo_id, m.kind(), m.pattern())?;
+        }
+        for path in &input.include_paths {
+            insert_repo_filter(&conn, repo_id, "include_path", path)?;
+        }
+        for p in &input.ref_patterns {
+            insert_repo_filter(&conn, repo_id, "ref_pattern", &p.to_string())?;
+        }
+        for p in &input.projects {
+            insert_repo_filter(&conn, repo_id, "project", &p.to_string())?;
+        }
         Ok(Repo {
             repo_id,
             name: input.name.clone(),
@@ -92,30 +651,45 @@ impl Store for SqliteStore {
             remote_url: input.remote_url.clone(),
             default_branch: input.default_branch.clone(),
             fork_of: input.fork_of.clone(),
-            author_filter: input.author_filter.clone(),
+            author_filters: input.author_filters.clone(),
+            include_paths: input.include_paths.clone(),
+            ref_patterns: input.ref_patterns.clone(),
+            projects: input.projects.clone(),
             exclude_prefixes: input.exclude_prefixes.clone(),
             embed_enabled: input.embed_enabled,
+            clone_depth: input.clone_depth,
+            clone_since: input.clone_since,
+            ssh_key: input.ssh_key.clone(),
+            token_env: input.token_env.clone(),
         })
     }
 
     fn list_repos(&self) -> Result<Vec<Repo>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT repo_id, name, local_path, remote_url, default_branch, fork_of, author_filter, exclude_prefixes, embed_enabled FROM repos ORDER BY repo_id",
+            "SELECT repo_id, name, local_path, remote_url, default_branch, fork_of, exclude_prefixes, embed_enabled, clone_depth, clone_since, ssh_key, token_env FROM repos ORDER BY repo_id",
         )?;
-        let repos: rusqlite::Result<Vec<Repo>> = stmt.query_map([], row_to_repo)?.collect();
-        Ok(repos?)
+        let mut repos: Vec<Repo> = stmt
+            .query_map([], row_to_repo)?
+            .collect::<rusqlite::Result<_>>()?;
+        for repo in &mut repos {
+            load_repo_filters(&conn, repo)?;
+        }
+        Ok(repos)
     }
 
     fn get_repo_by_name(&self, name: &str) -> Result<Option<Repo>> {
         let conn = self.conn.lock().unwrap();
-        let result = conn
+        let mut result = conn
             .query_row(
-                "SELECT repo_id, name, local_path, remote_url, default_branch, fork_of, author_filter, exclude_prefixes, embed_enabled FROM repos WHERE name = ?1",
+                "SELECT repo_id, name, local_path, remote_url, default_branch, fork_of, exclude_prefixes, embed_enabled, clone_depth, clone_since, ssh_key, token_env FROM repos WHERE name = ?1",
                 params![name],
                 row_to_repo,
             )
             .optional()?;
+        if let Some(ref mut repo) = result {
+            load_repo_filters(&conn, repo)?;
+        }
         Ok(result)
     }
 
@@ -123,114 +697,23 @@ impl Store for SqliteStore {
 
     fn upsert_commit(&self, commit: &Commit) -> Result<()> {
         let conn = self.conn.lock().unwrap();
-
-        // First, determine the rowid of any existing row so we can delete from FTS.
-        let existing_rowid: Option<i64> = conn
-            .query_row(
-                "SELECT rowid FROM commits WHERE repo_id = ?1 AND sha = ?2",
-                params![commit.repo_id, commit.sha],
-                |row| row.get(0),
-            )
-            .optional()?;
-
-        // If replacing, remove old FTS entry first.
-        if let Some(old_rowid) = existing_rowid {
-            let old_subject: String = conn.query_row(
-                "SELECT COALESCE(subject,'') FROM commits WHERE rowid = ?1",
-                params![old_rowid],
-                |r| r.get(0),
-            )?;
-            let old_body: Option<String> = conn.query_row(
-                "SELECT body FROM commits WHERE rowid = ?1",
-                params![old_rowid],
-                |r| r.get(0),
-            )?;
-            let old_preview: Option<String> = conn.query_row(
-                "SELECT patch_preview FROM commits WHERE rowid = ?1",
-                params![old_rowid],
-                |r| r.get(0),
-            )?;
-            conn.execute(
-                "INSERT INTO commits_fts(commits_fts, rowid, subject, body, patch_preview)
-                 VALUES('delete', ?1, ?2, ?3, ?4)",
-                params![old_rowid, old_subject, old_body, old_preview],
-            )?;
-        }
-
-        conn.execute(
-            "INSERT OR REPLACE INTO commits
-                (repo_id, sha, author_name, author_email, committer_name, committer_email,
-                 author_time, commit_time, subject, body, parent_count, patch_preview)
-             VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,'')",
-            params![
-                commit.repo_id,
-                commit.sha,
-                commit.author_name,
-                commit.author_email,
-                commit.committer_name,
-                commit.committer_email,
-                commit.author_time,
-                commit.commit_time,
-                commit.subject,
-                commit.body,
-                commit.parent_count,
-            ],
-        )?;
-
-        let new_rowid = conn.last_insert_rowid();
-        conn.execute(
-            "INSERT INTO commits_fts(rowid, subject, body, patch_preview)
-             VALUES (?1, ?2, ?3, '')",
-            params![new_rowid, commit.subject, commit.body],
-        )?;
-
-        Ok(())
+        delete_commit_fts(&conn, commit.repo_id, &commit.sha)?;
+        insert_commit(&conn, commit, "")
     }
 
     fn upsert_commit_files(&self, files: &[CommitFile]) -> Result<()> {
         let conn = self.conn.lock().unwrap();
 
-        if files.is_empty() {
-            return Ok(());
-        }
-
-        // Determine the unique (repo_id, sha) pairs and delete existing entries.
-        // Using a simple approach: delete by sha for each unique pair.
-        if let Some(first) = files.first() {
-            conn.execute(
-                "DELETE FROM commit_files WHERE repo_id = ?1 AND sha = ?2",
-                params![first.repo_id, first.sha],
-            )?;
+        // All files belong to the same commit; replace that commit's rows.
+        match files.first() {
+            Some(first) => replace_commit_files(&conn, first.repo_id, &first.sha, files),
+            None => Ok(()),
         }
-
-        for file in files {
-            conn.execute(
-                "INSERT INTO commit_files (repo_id, sha, path, status, old_path)
-                 VALUES (?1, ?2, ?3, ?4, ?5)",
-                params![
-                    file.repo_id,
-                    file.sha,
-                    file.path,
-                    file.status.as_str(),
-                    file.old_path,
-                ],
-            )?;
-        }
-        Ok(())
     }
 
     fn upsert_patch(&self, patch: &CommitPatch) -> Result<()> {
         let conn = self.conn.lock().unwrap();
-
-        // Compress the blob with zstd level 3.
-        let compressed = zstd::encode_all(patch.patch_blob.as_slice(), 3)
-            .map_err(commitmux_types::CommitmuxError::Io)?;
-
-        conn.execute(
-            "INSERT OR REPLACE INTO commit_patches (repo_id, sha, patch_blob)
-             VALUES (?1, ?2, ?3)",
-            params![patch.repo_id, patch.sha, compressed],
-        )?;
+        insert_patch_blob(&conn, patch)?;
 
         // Update patch_preview in commits (first 2000 chars of preview).
         let preview: String = patch.patch_preview.chars().take(2000).collect();
@@ -279,11 +762,94 @@ impl Store for SqliteStore {
         Ok(())
     }
 
+    fn write_batch(&self, batch: &WriteBatch) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for entry in &batch.commits {
+            let commit = &entry.commit;
+            // Same 2000-char cap as upsert_patch.
+            let preview: String = entry
+                .patch
+                .as_ref()
+                .map(|p| p.patch_preview.chars().take(2000).collect())
+                .unwrap_or_default();
+
+            delete_commit_fts(&tx, commit.repo_id, &commit.sha)?;
+            insert_commit(&tx, commit, &preview)?;
+            replace_commit_files(&tx, commit.repo_id, &commit.sha, &entry.files)?;
+            replace_commit_symbols(&tx, commit.repo_id, &commit.sha, &entry.symbols)?;
+            replace_commit_submodules(&tx, commit.repo_id, &commit.sha, &entry.submodules)?;
+            match entry.patch {
+                Some(ref patch) => insert_patch_blob(&tx, patch)?,
+                // A re-index that no longer produces a patch must not leave
+                // the old one to be served.
+                None => {
+                    tx.prepare_cached(
+                        "DELETE FROM commit_patches WHERE repo_id = ?1 AND sha = ?2",
+                    )?
+                    .execute(params![commit.repo_id, commit.sha])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn reextract_refs(
+        &self,
+        repo_id: i64,
+        extract: &dyn Fn(&str) -> Vec<CommitRef>,
+    ) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let messages: Vec<(String, String, Option<String>)> = tx
+            .prepare("SELECT sha, subject, body FROM commits WHERE repo_id = ?1")?
+            .query_map(params![repo_id], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    row.get(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        tx.execute(
+            "DELETE FROM commit_refs WHERE repo_id = ?1",
+            params![repo_id],
+        )?;
+        let mut with_refs = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO commit_refs (repo_id, sha, kind, project, ref_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (sha, subject, body) in &messages {
+                // The message as ingest saw it: subject, blank line, body.
+                let message = match body {
+                    Some(body) => format!("{}\n\n{}", subject, body),
+                    None => subject.clone(),
+                };
+                let refs = extract(&message);
+                if !refs.is_empty() {
+                    with_refs += 1;
+                }
+                for r in &refs {
+                    stmt.execute(params![repo_id, sha, r.kind.as_str(), r.project, r.id])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(with_refs)
+    }
+
     fn get_ingest_state(&self, repo_id: i64) -> Result<Option<IngestState>> {
         let conn = self.conn.lock().unwrap();
         let result = conn
             .query_row(
-                "SELECT repo_id, last_synced_at, last_synced_sha, last_error
+                "SELECT repo_id, last_synced_at, last_synced_sha, last_error, checkpoint_sha,
+                        indexed_tips, config_hash
                  FROM ingest_state WHERE repo_id = ?1",
                 params![repo_id],
                 |row| {
@@ -292,6 +858,9 @@ impl Store for SqliteStore {
                         last_synced_at: row.get(1)?,
                         last_synced_sha: row.get(2)?,
                         last_error: row.get(3)?,
+                        checkpoint_sha: row.get(4)?,
+                        indexed_tips: parse_string_list(row.get(5)?),
+                        config_hash: row.get(6)?,
                     })
                 },
             )
@@ -303,13 +872,17 @@ impl Store for SqliteStore {
         let conn = self.conn.lock().unwrap();
         conn.execute(
             "INSERT OR REPLACE INTO ingest_state
-                (repo_id, last_synced_at, last_synced_sha, last_error)
-             VALUES (?1, ?2, ?3, ?4)",
+                (repo_id, last_synced_at, last_synced_sha, last_error, checkpoint_sha,
+                 indexed_tips, config_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
                 state.repo_id,
                 state.last_synced_at,
                 state.last_synced_sha,
                 state.last_error,
+                state.checkpoint_sha,
+                serde_json::to_string(&state.indexed_tips).unwrap_or_else(|_| "[]".to_string()),
+                state.config_hash,
             ],
         )?;
         Ok(())
@@ -336,6 +909,40 @@ impl Store for SqliteStore {
             param_idx += 1;
         }
 
+        if let Some(ref authors) = opts.authors {
+            extra_conditions.push_str(&format!(" AND {}", authors_condition("c", param_idx)));
+            bind_vals.push(Box::new(
+                serde_json::to_string(authors).unwrap_or_else(|_| "[]".into()),
+            ));
+            param_idx += 1;
+        }
+
+        if let Some(ref trailer) = opts.trailer {
+            let (key, value) = trailer.split_once(':').unwrap_or((trailer.as_str(), ""));
+            extra_conditions.push_str(&format!(" AND {}", trailer_condition("c", param_idx)));
+            bind_vals.push(Box::new(key.trim().to_string()));
+            bind_vals.push(Box::new(escape_like(value.trim())));
+            param_idx += 2;
+        }
+
+        push_conventional_conditions(
+            "c",
+            opts.cc_types.as_ref(),
+            opts.cc_scopes.as_ref(),
+            opts.breaking,
+            &mut extra_conditions,
+            &mut bind_vals,
+            &mut param_idx,
+        );
+
+        if let Some(ref projects) = opts.projects {
+            extra_conditions.push_str(&format!(" AND {}", projects_condition("c", param_idx)));
+            bind_vals.push(Box::new(
+                serde_json::to_string(projects).unwrap_or_else(|_| "[]".into()),
+            ));
+            param_idx += 1;
+        }
+
         // repos filter
         let repo_placeholders: Option<String> = opts.repos.as_ref().map(|repos| {
             let ph: String = repos
@@ -357,13 +964,16 @@ impl Store for SqliteStore {
         }
 
         let sql = format!(
-            "SELECT c.repo_id, c.sha, c.subject, c.author_name, c.author_time, c.patch_preview, r.name
+            "SELECT c.repo_id, c.sha, c.subject, {}, c.author_time, c.patch_preview, r.name
              FROM commits_fts
              JOIN commits c ON c.rowid = commits_fts.rowid
              JOIN repos r ON r.repo_id = c.repo_id
              WHERE commits_fts MATCH ?1{}
              ORDER BY c.author_time DESC
-             LIMIT ?{}", extra_conditions, param_idx
+             LIMIT ?{}",
+            author_name_sql("c"),
+            extra_conditions,
+            param_idx
         );
 
         bind_vals.push(Box::new(limit));
@@ -446,12 +1056,31 @@ impl Store for SqliteStore {
         let conn = self.conn.lock().unwrap();
 
         let limit = opts.limit.unwrap_or(50) as i64;
-        let like_pat = format!("%{}%", path_glob);
+        // `*` is accepted as a wildcard, so `*.png` works as well as `.png`.
+        let like_pat = format!("%{}%", path_glob.replace('*', "%"));
 
         let mut extra_conditions = String::new();
         let mut bind_vals: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
         let mut param_idx = 2usize; // ?1 = like_pat, limit appended at param_idx end
 
+        // Rows under an earlier name count up to the commit that renamed it.
+        let mut renamed_condition = String::new();
+        if opts.follow_renames {
+            let segments = rename_segments(&conn, &like_pat, opts.repos.as_ref())?;
+            if !segments.is_empty() {
+                renamed_condition = format!(
+                    " OR EXISTS (SELECT 1 FROM json_each(?{}) s
+                                 WHERE s.value ->> 0 = cf.repo_id AND s.value ->> 1 = cf.path
+                                   AND c.author_time <= s.value ->> 2 AND c.sha != s.value ->> 3)",
+                    param_idx
+                );
+                bind_vals.push(Box::new(
+                    serde_json::to_string(&segments).unwrap_or_else(|_| "[]".into()),
+                ));
+                param_idx += 1;
+            }
+        }
+
         if let Some(since) = opts.since {
             extra_conditions.push_str(&format!(" AND c.author_time >= ?{}", param_idx));
             bind_vals.push(Box::new(since));
@@ -472,15 +1101,35 @@ impl Store for SqliteStore {
             }
         }
 
+        if let Some(ref authors) = opts.authors {
+            extra_conditions.push_str(&format!(" AND {}", authors_condition("c", param_idx)));
+            bind_vals.push(Box::new(
+                serde_json::to_string(authors).unwrap_or_else(|_| "[]".into()),
+            ));
+            param_idx += 1;
+        }
+
+        if let Some(ref projects) = opts.projects {
+            extra_conditions.push_str(&format!(
+                " AND cf.project IN (SELECT value FROM json_each(?{}))",
+                param_idx
+            ));
+            bind_vals.push(Box::new(
+                serde_json::to_string(projects).unwrap_or_else(|_| "[]".into()),
+            ));
+            param_idx += 1;
+        }
+
         let sql = format!(
-            "SELECT cf.path, cf.status, c.sha, c.subject, c.author_time, r.name
+            "SELECT cf.path, cf.status, c.sha, c.subject, c.author_time, r.name, cf.old_path,
+                    cf.kind, cf.size
              FROM commit_files cf
              JOIN commits c ON cf.repo_id = c.repo_id AND cf.sha = c.sha
              JOIN repos r ON r.repo_id = c.repo_id
-             WHERE cf.path LIKE ?1{}
+             WHERE (cf.path LIKE ?1{}){}
              ORDER BY c.author_time DESC
              LIMIT ?{}",
-            extra_conditions, param_idx
+            renamed_condition, extra_conditions, param_idx
         );
 
         bind_vals.push(Box::new(limit));
@@ -501,10 +1150,273 @@ impl Store for SqliteStore {
                     subject: row.get(3)?,
                     date: row.get(4)?,
                     repo: row.get(5)?,
+                    old_path: row.get(6)?,
+                    kind: file_kind(row.get(7)?),
+                    size: row.get::<_, Option<i64>>(8)?.map(|s| s as u64),
+                })
+            })?
+            .collect();
+
+        Ok(rows?)
+    }
+
+    fn find_by_ref(&self, reference: &str, opts: &RefOpts) -> Result<Vec<RefResult>> {
+        let conn = self.conn.lock().unwrap();
+        let wanted = CommitRef::parse(reference);
+
+        let mut conditions = String::new();
+        let mut bind_vals: Vec<Box<dyn rusqlite::types::ToSql>> =
+            vec![Box::new(wanted.kind.as_str()), Box::new(wanted.id.clone())];
+        let mut param_idx = 3usize;
+
+        // A bare `#123` matches that number in every project. Key-like
+        // references may have been captured by either a Jira or a custom
+        // pattern.
+        if let Some(ref project) = wanted.project {
+            conditions.push_str(&format!(" AND cr.project = ?{}", param_idx));
+            bind_vals.push(Box::new(project.clone()));
+            param_idx += 1;
+        }
+        if let Some(ref repos) = opts.repos {
+            conditions.push_str(&format!(
+                " AND r.name IN (SELECT value FROM json_each(?{}))",
+                param_idx
+            ));
+            bind_vals.push(Box::new(
+                serde_json::to_string(repos).unwrap_or_else(|_| "[]".into()),
+            ));
+            param_idx += 1;
+        }
+
+        let sql = format!(
+            "SELECT r.name, c.sha, c.subject, {}, c.author_time, cr.kind, cr.project, cr.ref_id
+             FROM commit_refs cr
+             JOIN commits c ON c.repo_id = cr.repo_id AND c.sha = cr.sha
+             JOIN repos r ON r.repo_id = c.repo_id
+             WHERE (cr.kind = ?1 OR (?1 IN ('jira', 'custom') AND cr.kind IN ('jira', 'custom')))
+               AND cr.ref_id = ?2 COLLATE NOCASE{}
+             GROUP BY c.repo_id, c.sha
+             ORDER BY c.author_time DESC
+             LIMIT ?{}",
+            author_name_sql("c"),
+            conditions,
+            param_idx
+        );
+        bind_vals.push(Box::new(opts.limit.unwrap_or(50) as i64));
+
+        let mut stmt = conn.prepare(&sql)?;
+        let params: Vec<&dyn rusqlite::types::ToSql> =
+            bind_vals.iter().map(|b| b.as_ref()).collect();
+        let rows: rusqlite::Result<Vec<RefResult>> = stmt
+            .query_map(params.as_slice(), |row| {
+                let found = CommitRef {
+                    kind: RefKind::from_str(&row.get::<_, String>(5)?).unwrap_or(wanted.kind),
+                    project: row.get(6)?,
+                    id: row.get(7)?,
+                };
+                Ok(RefResult {
+                    repo: row.get(0)?,
+                    sha: row.get(1)?,
+                    subject: row.get(2)?,
+                    author: row.get(3)?,
+                    date: row.get(4)?,
+                    reference: found.to_string(),
+                })
+            })?
+            .collect();
+        Ok(rows?)
+    }
+
+    fn conventional_summary(&self, opts: &ConventionalSummaryOpts) -> Result<ConventionalSummary> {
+        let conn = self.conn.lock().unwrap();
+        let limit = opts.limit.unwrap_or(500);
+
+        let mut conditions = String::new();
+        let mut bind_vals: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+        let mut param_idx = 1usize;
+
+        if let Some(ref repos) = opts.repos {
+            conditions.push_str(&format!(
+                " AND r.name IN (SELECT value FROM json_each(?{}))",
+                param_idx
+            ));
+            bind_vals.push(Box::new(
+                serde_json::to_string(repos).unwrap_or_else(|_| "[]".into()),
+            ));
+            param_idx += 1;
+        }
+        if let Some(since) = opts.since {
+            conditions.push_str(&format!(" AND c.author_time >= ?{}", param_idx));
+            bind_vals.push(Box::new(since));
+            param_idx += 1;
+        }
+        if let Some(until) = opts.until {
+            conditions.push_str(&format!(" AND c.author_time < ?{}", param_idx));
+            bind_vals.push(Box::new(until));
+            param_idx += 1;
+        }
+        push_conventional_conditions(
+            "c",
+            opts.cc_types.as_ref(),
+            opts.cc_scopes.as_ref(),
+            opts.breaking,
+            &mut conditions,
+            &mut bind_vals,
+            &mut param_idx,
+        );
+
+        // One extra row tells us whether the result was truncated.
+        let sql = format!(
+            "SELECT r.name, c.sha, c.cc_type, c.cc_scope, c.breaking, c.subject, {}, c.author_time
+             FROM commits c
+             JOIN repos r ON r.repo_id = c.repo_id
+             WHERE c.cc_type IS NOT NULL{}
+             ORDER BY c.author_time DESC
+             LIMIT ?{}",
+            author_name_sql("c"),
+            conditions,
+            param_idx
+        );
+        bind_vals.push(Box::new(limit as i64 + 1));
+
+        let mut stmt = conn.prepare(&sql)?;
+        let params: Vec<&dyn rusqlite::types::ToSql> =
+            bind_vals.iter().map(|b| b.as_ref()).collect();
+        let rows: rusqlite::Result<Vec<(String, ConventionalEntry)>> = stmt
+            .query_map(params.as_slice(), |row| {
+                let subject: String = row.get(5)?;
+                let description = subject
+                    .split_once(": ")
+                    .map(|(_, d)| d.trim().to_string())
+                    .unwrap_or(subject);
+                Ok((
+                    row.get(2)?,
+                    ConventionalEntry {
+                        repo: row.get(0)?,
+                        sha: row.get(1)?,
+                        scope: row.get(3)?,
+                        breaking: row.get(4)?,
+                        description,
+                        author: row.get(6)?,
+                        date: row.get(7)?,
+                    },
+                ))
+            })?
+            .collect();
+        let mut rows = rows?;
+        let truncated = rows.len() > limit;
+        rows.truncate(limit);
+        Ok(ConventionalSummary::group(rows, truncated))
+    }
+
+    fn changelog_commits(&self, repo_id: i64, shas: &[String]) -> Result<Vec<ChangelogCommit>> {
+        let conn = self.conn.lock().unwrap();
+        let shas_json = serde_json::to_string(shas).unwrap_or_else(|_| "[]".into());
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT c.sha, c.subject, {}, c.author_time, c.cc_type, c.cc_scope, c.breaking
+             FROM json_each(?2) j
+             JOIN commits c ON c.repo_id = ?1 AND c.sha = j.value
+             ORDER BY j.key",
+            author_name_sql("c")
+        ))?;
+        let rows: rusqlite::Result<Vec<ChangelogCommit>> = stmt
+            .query_map(params![repo_id, shas_json], |row| {
+                let subject: String = row.get(1)?;
+                let cc_type: Option<String> = row.get(4)?;
+                let conventional = cc_type.map(|cc_type| ConventionalCommit {
+                    cc_type,
+                    scope: row.get(5).ok().flatten(),
+                    breaking: row.get(6).unwrap_or(false),
+                    description: subject
+                        .split_once(": ")
+                        .map(|(_, d)| d.trim().to_string())
+                        .unwrap_or_else(|| subject.clone()),
+                });
+                Ok(ChangelogCommit {
+                    sha: row.get(0)?,
+                    subject,
+                    author: row.get(2)?,
+                    date: row.get(3)?,
+                    conventional,
+                    paths: Vec::new(),
                 })
             })?
             .collect();
+        let mut commits = rows?;
+
+        let mut paths_stmt = conn.prepare_cached(
+            "SELECT path FROM commit_files WHERE repo_id = ?1 AND sha = ?2 ORDER BY path",
+        )?;
+        for commit in &mut commits {
+            let paths: rusqlite::Result<Vec<String>> = paths_stmt
+                .query_map(params![repo_id, commit.sha], |row| row.get(0))?
+                .collect();
+            commit.paths = paths?;
+        }
+        Ok(commits)
+    }
 
+    fn symbol_history(
+        &self,
+        repo_id: i64,
+        symbol: &str,
+        opts: &SymbolOpts,
+    ) -> Result<Vec<SymbolChange>> {
+        let conn = self.conn.lock().unwrap();
+        let name = bare_symbol_name(symbol);
+
+        // A bare name matches every definition with that name; a qualified
+        // one must match in full.
+        let mut conditions = String::new();
+        let mut bind_vals: Vec<Box<dyn rusqlite::types::ToSql>> =
+            vec![Box::new(repo_id), Box::new(name.to_string())];
+        let mut param_idx = 3usize;
+        if name != symbol {
+            conditions.push_str(&format!(" AND s.qualified = ?{}", param_idx));
+            bind_vals.push(Box::new(symbol.to_string()));
+            param_idx += 1;
+        }
+        if let Some(ref path) = opts.path {
+            let path = path.trim_end_matches('/');
+            conditions.push_str(&format!(
+                " AND (s.path = ?{0} OR s.path LIKE ?{0} || '/%')",
+                param_idx
+            ));
+            bind_vals.push(Box::new(path.to_string()));
+            param_idx += 1;
+        }
+
+        let sql = format!(
+            "SELECT c.sha, c.subject, {}, c.author_time, s.path, s.qualified, s.kind, s.excerpt
+             FROM commit_symbols s
+             JOIN commits c ON c.repo_id = s.repo_id AND c.sha = s.sha
+             WHERE s.repo_id = ?1 AND s.name = ?2{}
+             ORDER BY c.author_time DESC, c.commit_time DESC, s.path, s.qualified
+             LIMIT ?{}",
+            author_name_sql("c"),
+            conditions,
+            param_idx
+        );
+        bind_vals.push(Box::new(opts.limit.unwrap_or(50) as i64));
+
+        let mut stmt = conn.prepare(&sql)?;
+        let params: Vec<&dyn rusqlite::types::ToSql> =
+            bind_vals.iter().map(|b| b.as_ref()).collect();
+        let rows: rusqlite::Result<Vec<SymbolChange>> = stmt
+            .query_map(params.as_slice(), |row| {
+                Ok(SymbolChange {
+                    sha: row.get(0)?,
+                    subject: row.get(1)?,
+                    author: row.get(2)?,
+                    date: row.get(3)?,
+                    path: row.get(4)?,
+                    symbol: row.get(5)?,
+                    kind: row.get(6)?,
+                    excerpt: row.get(7)?,
+                })
+            })?
+            .collect();
         Ok(rows?)
     }
 
@@ -513,11 +1425,14 @@ impl Store for SqliteStore {
 
         let detail: Option<(i64, String, String, Option<String>, String, i64)> = conn
             .query_row(
-                "SELECT c.repo_id, c.sha, c.subject, c.body, c.author_name, c.author_time
-                 FROM commits c
-                 JOIN repos r ON r.repo_id = c.repo_id
-                 WHERE r.name = ?1 AND c.sha LIKE ?2 || '%'
-                 ORDER BY c.author_time DESC",
+                &format!(
+                    "SELECT c.repo_id, c.sha, c.subject, c.body, {}, c.author_time
+                     FROM commits c
+                     JOIN repos r ON r.repo_id = c.repo_id
+                     WHERE r.name = ?1 AND c.sha LIKE ?2 || '%'
+                     ORDER BY c.author_time DESC",
+                    author_name_sql("c")
+                ),
                 params![repo_name, sha_prefix],
                 |row| {
                     Ok((
@@ -537,20 +1452,98 @@ impl Store for SqliteStore {
             Some((repo_id, commit_sha, subject, body, author, raw_date)) => {
                 let date = format_iso_date(raw_date);
                 let mut fstmt = conn.prepare(
-                    "SELECT path, status, old_path FROM commit_files
+                    "SELECT path, status, old_path, kind, size, lfs_oid, lfs_size FROM commit_files
                      WHERE repo_id = ?1 AND sha = ?2 ORDER BY path",
                 )?;
                 let files: rusqlite::Result<Vec<CommitFileDetail>> = fstmt
                     .query_map(params![repo_id, commit_sha], |row| {
+                        let lfs_oid: Option<String> = row.get(5)?;
+                        let lfs_size: Option<i64> = row.get(6)?;
                         Ok(CommitFileDetail {
                             path: row.get(0)?,
                             status: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
                             old_path: row.get(2)?,
+                            kind: file_kind(row.get(3)?),
+                            size: row.get::<_, Option<i64>>(4)?.map(|s| s as u64),
+                            lfs: lfs_oid.zip(lfs_size).map(|(oid, size)| LfsPointer {
+                                oid,
+                                size: size as u64,
+                            }),
                         })
                     })?
                     .collect();
                 let changed_files = files?;
 
+                let mut trailers: BTreeMap<String, Vec<String>> = BTreeMap::new();
+                let mut tstmt = conn.prepare(
+                    "SELECT key, value FROM commit_trailers
+                     WHERE repo_id = ?1 AND sha = ?2 ORDER BY rowid",
+                )?;
+                let rows = tstmt.query_map(params![repo_id, commit_sha], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?;
+                for row in rows {
+                    let (key, value) = row?;
+                    trailers.entry(key).or_default().push(value);
+                }
+
+                // Tags introducing the commit, plus every tag containing one
+                // of those.
+                let mut gstmt = conn.prepare(
+                    "SELECT t.name FROM tags t
+                     WHERE t.repo_id = ?1 AND t.name IN (
+                         SELECT tag FROM tag_commits WHERE repo_id = ?1 AND sha = ?2
+                         UNION
+                         SELECT a.tag FROM tag_ancestors a
+                         JOIN tag_commits tc ON tc.repo_id = a.repo_id AND tc.tag = a.ancestor
+                         WHERE a.repo_id = ?1 AND tc.sha = ?2
+                     )
+                     ORDER BY t.tag_time, t.name",
+                )?;
+                let contained_in: rusqlite::Result<Vec<String>> = gstmt
+                    .query_map(params![repo_id, commit_sha], |row| row.get(0))?
+                    .collect();
+                let contained_in = contained_in?;
+
+                let (patch_truncated, patch_size): (bool, Option<i64>) = conn
+                    .query_row(
+                        "SELECT truncated, original_size FROM commit_patches
+                         WHERE repo_id = ?1 AND sha = ?2",
+                        params![repo_id, commit_sha],
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )
+                    .optional()?
+                    .unwrap_or_default();
+
+                let mut sstmt = conn.prepare(
+                    "SELECT path, url, old_sha, new_sha FROM commit_submodules
+                     WHERE repo_id = ?1 AND sha = ?2 ORDER BY path",
+                )?;
+                let submodules: rusqlite::Result<Vec<CommitSubmoduleDetail>> = sstmt
+                    .query_map(params![repo_id, commit_sha], |row| {
+                        Ok(CommitSubmoduleDetail {
+                            path: row.get(0)?,
+                            url: row.get(1)?,
+                            old_sha: row.get(2)?,
+                            new_sha: row.get(3)?,
+                            repo: None,
+                            commits: Vec::new(),
+                            commit_count: None,
+                            link_error: None,
+                        })
+                    })?
+                    .collect();
+                let submodules = submodules?;
+
+                let mut pstmt = conn.prepare(
+                    "SELECT project FROM commit_projects
+                     WHERE repo_id = ?1 AND sha = ?2 ORDER BY project",
+                )?;
+                let projects: rusqlite::Result<Vec<String>> = pstmt
+                    .query_map(params![repo_id, commit_sha], |row| row.get(0))?
+                    .collect();
+                let projects = projects?;
+
                 Ok(Some(CommitDetail {
                     repo: repo_name.to_string(),
                     sha: commit_sha,
@@ -559,6 +1552,13 @@ impl Store for SqliteStore {
                     author,
                     date,
                     changed_files,
+                    trailers,
+                    first_release: contained_in.first().cloned(),
+                    contained_in,
+                    patch_truncated,
+                    patch_size: patch_size.map(|s| s as u64),
+                    submodules,
+                    projects,
                 }))
             }
         }
@@ -589,6 +1589,32 @@ impl Store for SqliteStore {
             "DELETE FROM commit_files WHERE repo_id = ?1",
             params![repo_id],
         )?;
+        conn.execute(
+            "DELETE FROM commit_trailers WHERE repo_id = ?1",
+            params![repo_id],
+        )?;
+        conn.execute(
+            "DELETE FROM commit_refs WHERE repo_id = ?1",
+            params![repo_id],
+        )?;
+        conn.execute(
+            "DELETE FROM commit_symbols WHERE repo_id = ?1",
+            params![repo_id],
+        )?;
+        conn.execute(
+            "DELETE FROM commit_submodules WHERE repo_id = ?1",
+            params![repo_id],
+        )?;
+        conn.execute(
+            "DELETE FROM commit_projects WHERE repo_id = ?1",
+            params![repo_id],
+        )?;
+        for table in ["tags", "tag_commits", "tag_ancestors"] {
+            conn.execute(
+                &format!("DELETE FROM {} WHERE repo_id = ?1", table),
+                params![repo_id],
+            )?;
+        }
 
         // 4. Delete ingest state
         conn.execute(
@@ -602,7 +1628,11 @@ impl Store for SqliteStore {
         // 6. Rebuild FTS to reflect deleted commits
         conn.execute("INSERT INTO commits_fts(commits_fts) VALUES('rebuild')", [])?;
 
-        // 7. Delete repo
+        // 7. Delete repo and its filters
+        conn.execute(
+            "DELETE FROM repo_filters WHERE repo_id = ?1",
+            params![repo_id],
+        )?;
         conn.execute("DELETE FROM repos WHERE repo_id = ?1", params![repo_id])?;
 
         Ok(())
@@ -618,6 +1648,72 @@ impl Store for SqliteStore {
         Ok(count > 0)
     }
 
+    fn list_tags(&self, repo_id: i64) -> Result<Vec<Tag>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT name, target_sha, annotated, tagger_name, tagger_email, tag_time, message
+             FROM tags WHERE repo_id = ?1 ORDER BY tag_time, name",
+        )?;
+        let tags: rusqlite::Result<Vec<Tag>> = stmt
+            .query_map(params![repo_id], |row| {
+                Ok(Tag {
+                    name: row.get(0)?,
+                    target_sha: row.get(1)?,
+                    annotated: row.get(2)?,
+                    tagger_name: row.get(3)?,
+                    tagger_email: row.get(4)?,
+                    date: row.get::<_, Option<i64>>(5)?.unwrap_or(0),
+                    message: row.get(6)?,
+                })
+            })?
+            .collect();
+        Ok(tags?)
+    }
+
+    fn replace_tags(&self, repo_id: i64, index: &TagIndex) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for table in ["tags", "tag_commits", "tag_ancestors"] {
+            tx.execute(
+                &format!("DELETE FROM {} WHERE repo_id = ?1", table),
+                params![repo_id],
+            )?;
+        }
+        {
+            let mut insert_tag = tx.prepare(
+                "INSERT OR REPLACE INTO tags
+                 (repo_id, name, target_sha, annotated, tagger_name, tagger_email, tag_time, message)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for tag in &index.tags {
+                insert_tag.execute(params![
+                    repo_id,
+                    tag.name,
+                    tag.target_sha,
+                    tag.annotated,
+                    tag.tagger_name,
+                    tag.tagger_email,
+                    tag.date,
+                    tag.message,
+                ])?;
+            }
+            let mut insert_commit = tx.prepare(
+                "INSERT OR IGNORE INTO tag_commits (repo_id, tag, sha) VALUES (?1, ?2, ?3)",
+            )?;
+            for (tag, sha) in &index.introduced {
+                insert_commit.execute(params![repo_id, tag, sha])?;
+            }
+            let mut insert_ancestor = tx.prepare(
+                "INSERT OR IGNORE INTO tag_ancestors (repo_id, tag, ancestor) VALUES (?1, ?2, ?3)",
+            )?;
+            for (tag, ancestor) in &index.ancestors {
+                insert_ancestor.execute(params![repo_id, tag, ancestor])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
     fn update_repo(&self, repo_id: i64, update: &RepoUpdate) -> Result<Repo> {
         let conn = self.conn.lock().unwrap();
 
@@ -633,14 +1729,6 @@ impl Store for SqliteStore {
             });
             idx += 1;
         }
-        if let Some(ref v) = update.author_filter {
-            set_clauses.push(format!("author_filter = ?{}", idx));
-            bind_vals.push(match v {
-                Some(s) => Box::new(s.clone()),
-                None => Box::new(rusqlite::types::Null),
-            });
-            idx += 1;
-        }
         if let Some(ref v) = update.exclude_prefixes {
             let json = serde_json::to_string(v).unwrap_or_else(|_| "[]".to_string());
             set_clauses.push(format!("exclude_prefixes = ?{}", idx));
@@ -660,6 +1748,28 @@ impl Store for SqliteStore {
             bind_vals.push(Box::new(v as i64));
             idx += 1;
         }
+        if let Some(v) = update.clone_depth {
+            set_clauses.push(format!("clone_depth = ?{}", idx));
+            bind_vals.push(Box::new(v));
+            idx += 1;
+        }
+        if let Some(v) = update.clone_since {
+            set_clauses.push(format!("clone_since = ?{}", idx));
+            bind_vals.push(Box::new(v));
+            idx += 1;
+        }
+        if let Some(ref v) = update.ssh_key {
+            set_clauses.push(format!("ssh_key = ?{}", idx));
+            bind_vals.push(Box::new(
+                v.as_ref().map(|p| p.to_string_lossy().into_owned()),
+            ));
+            idx += 1;
+        }
+        if let Some(ref v) = update.token_env {
+            set_clauses.push(format!("token_env = ?{}", idx));
+            bind_vals.push(Box::new(v.clone()));
+            idx += 1;
+        }
 
         if !set_clauses.is_empty() {
             let sql = format!(
@@ -673,12 +1783,51 @@ impl Store for SqliteStore {
             conn.execute(&sql, params.as_slice())?;
         }
 
+        for m in &update.add_authors {
+            insert_repo_filter(&conn, repo_id, m.kind(), m.pattern())?;
+        }
+        for m in &update.remove_authors {
+            conn.execute(
+                "DELETE FROM repo_filters WHERE repo_id = ?1 AND kind = ?2 AND pattern = ?3",
+                params![repo_id, m.kind(), m.pattern()],
+            )?;
+        }
+        if let Some(ref paths) = update.include_paths {
+            conn.execute(
+                "DELETE FROM repo_filters WHERE repo_id = ?1 AND kind = 'include_path'",
+                params![repo_id],
+            )?;
+            for path in paths {
+                insert_repo_filter(&conn, repo_id, "include_path", path)?;
+            }
+        }
+        if let Some(ref patterns) = update.ref_patterns {
+            conn.execute(
+                "DELETE FROM repo_filters WHERE repo_id = ?1 AND kind = 'ref_pattern'",
+                params![repo_id],
+            )?;
+            for p in patterns {
+                insert_repo_filter(&conn, repo_id, "ref_pattern", &p.to_string())?;
+            }
+        }
+        if let Some(ref projects) = update.projects {
+            conn.execute(
+                "DELETE FROM repo_filters WHERE repo_id = ?1 AND kind = 'project'",
+                params![repo_id],
+            )?;
+            for p in projects {
+                insert_repo_filter(&conn, repo_id, "project", &p.to_string())?;
+            }
+            retag_projects(&conn, repo_id, projects)?;
+        }
+
         // Re-fetch
-        let repo = conn.query_row(
-            "SELECT repo_id, name, local_path, remote_url, default_branch, fork_of, author_filter, exclude_prefixes, embed_enabled FROM repos WHERE repo_id = ?1",
+        let mut repo = conn.query_row(
+            "SELECT repo_id, name, local_path, remote_url, default_branch, fork_of, exclude_prefixes, embed_enabled, clone_depth, clone_since, ssh_key, token_env FROM repos WHERE repo_id = ?1",
             params![repo_id],
             row_to_repo,
         )?;
+        load_repo_filters(&conn, &mut repo)?;
 
         Ok(repo)
     }
@@ -713,38 +1862,38 @@ impl Store for SqliteStore {
     ) -> Result<Option<PatchResult>> {
         let conn = self.conn.lock().unwrap();
 
-        let row: Option<(Vec<u8>, String)> = conn
+        let row: Option<(Vec<u8>, String, bool, Option<i64>)> = conn
             .query_row(
-                "SELECT cp.patch_blob, cp.sha
+                "SELECT cp.patch_blob, cp.sha, cp.truncated, cp.original_size
                  FROM commit_patches cp
                  JOIN repos r ON r.repo_id = cp.repo_id
                  WHERE r.name = ?1 AND cp.sha LIKE ?2 || '%'",
                 params![repo_name, sha],
-                |row| Ok((row.get(0)?, row.get(1)?)),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
             )
             .optional()?;
 
         match row {
             None => Ok(None),
-            Some((compressed, full_sha)) => {
+            Some((compressed, full_sha, stored_truncated, original_size)) => {
                 let decompressed = zstd::decode_all(compressed.as_slice())
                     .map_err(commitmux_types::CommitmuxError::Io)?;
 
-                let mut patch_text = String::from_utf8_lossy(&decompressed).into_owned();
-
-                if let Some(max) = max_bytes {
-                    if patch_text.len() > max {
-                        // Truncate at a valid UTF-8 boundary.
-                        let truncated: String = patch_text.chars().take(max).collect();
-                        patch_text = truncated;
-                    }
-                }
-
-                Ok(Some(PatchResult {
+                let mut patch = PatchResult {
                     repo: repo_name.to_string(),
                     sha: full_sha,
-                    patch_text,
-                }))
+                    patch_text: String::from_utf8_lossy(&decompressed).into_owned(),
+                    truncated: false,
+                    stored_truncated,
+                    original_size: original_size.map(|s| s as u64),
+                };
+                if let Some(max) = max_bytes.filter(|max| patch.patch_text.len() > *max) {
+                    // Truncate at a valid UTF-8 boundary.
+                    let cut = patch.patch_text.floor_char_boundary(max);
+                    patch.patch_text.truncate(cut);
+                    patch.truncated = true;
+                }
+                Ok(Some(patch))
             }
         }
     }
@@ -752,11 +1901,12 @@ impl Store for SqliteStore {
     fn repo_stats(&self, repo_id: i64) -> Result<RepoStats> {
         let conn = self.conn.lock().unwrap();
 
-        let repo_name: String = conn.query_row(
-            "SELECT name FROM repos WHERE repo_id = ?1",
+        let mut repo = conn.query_row(
+            "SELECT repo_id, name, local_path, remote_url, default_branch, fork_of, exclude_prefixes, embed_enabled, clone_depth, clone_since, ssh_key, token_env FROM repos WHERE repo_id = ?1",
             params![repo_id],
-            |row| row.get(0),
+            row_to_repo,
         )?;
+        load_repo_filters(&conn, &mut repo)?;
 
         let commit_count: i64 = conn.query_row(
             "SELECT COUNT(*) FROM commits WHERE repo_id = ?1",
@@ -777,12 +1927,36 @@ impl Store for SqliteStore {
             None => (None, None, None),
         };
 
+        let mut pstmt = conn.prepare_cached(
+            "SELECT COUNT(*), MAX(c.author_time) FROM commit_projects cp
+             JOIN commits c ON c.repo_id = cp.repo_id AND c.sha = cp.sha
+             WHERE cp.repo_id = ?1 AND cp.project = ?2",
+        )?;
+        let mut projects: Vec<ProjectStats> = Vec::new();
+        for p in repo.projects {
+            if let Some(stats) = projects.iter_mut().find(|s| s.name == p.name) {
+                stats.prefixes.push(p.prefix);
+                continue;
+            }
+            let (count, last_commit_at): (i64, Option<i64>) = pstmt
+                .query_row(params![repo_id, p.name], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?;
+            projects.push(ProjectStats {
+                name: p.name,
+                prefixes: vec![p.prefix],
+                commit_count: count as usize,
+                last_commit_at,
+            });
+        }
+
         Ok(RepoStats {
-            repo_name,
+            repo_name: repo.name,
             commit_count: commit_count as usize,
             last_synced_at,
             last_synced_sha,
             last_error,
+            projects,
         })
     }
 
@@ -834,6 +2008,58 @@ impl Store for SqliteStore {
 
     // ── Embedding support ─────────────────────────────────────────────────
 
+    // ── Identities ────────────────────────────────────────────────────────
+
+    fn add_identity(&self, alias: &IdentityAlias) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO identities (alias_email, canonical_name, canonical_email)
+             VALUES (LOWER(?1), ?2, ?3)
+             ON CONFLICT(alias_email) DO UPDATE SET
+                 canonical_name = excluded.canonical_name,
+                 canonical_email = excluded.canonical_email",
+            params![
+                alias.alias_email,
+                alias.canonical_name,
+                alias.canonical_email
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn list_identities(&self) -> Result<Vec<IdentityAlias>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT alias_email, canonical_name, canonical_email FROM identities
+             ORDER BY canonical_email, alias_email",
+        )?;
+        let rows: rusqlite::Result<Vec<IdentityAlias>> = stmt
+            .query_map([], |row| {
+                Ok(IdentityAlias {
+                    alias_email: row.get(0)?,
+                    canonical_name: row.get(1)?,
+                    canonical_email: row.get(2)?,
+                })
+            })?
+            .collect();
+        Ok(rows?)
+    }
+
+    fn remove_identity(&self, alias_email: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute(
+            "DELETE FROM identities WHERE alias_email = LOWER(?1)",
+            params![alias_email],
+        )?;
+        if removed == 0 {
+            return Err(CommitmuxError::NotFound(format!(
+                "identity '{}' not found",
+                alias_email
+            )));
+        }
+        Ok(())
+    }
+
     fn get_config(&self, key: &str) -> Result<Option<String>> {
         let conn = self.conn.lock().unwrap();
         let result = conn
@@ -862,9 +2088,9 @@ impl Store for SqliteStore {
         limit: usize,
     ) -> Result<Vec<EmbedCommit>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare(&format!(
             "SELECT c.sha, c.subject, c.body, c.patch_preview,
-                    c.author_name, c.author_time, r.name
+                    {}, c.author_time, r.name
              FROM commits c
              JOIN repos r ON r.repo_id = c.repo_id
              LEFT JOIN commit_embed_map m ON m.repo_id = c.repo_id AND m.sha = c.sha
@@ -872,7 +2098,8 @@ impl Store for SqliteStore {
                AND m.embed_id IS NULL
              ORDER BY c.author_time DESC
              LIMIT ?2",
-        )?;
+            author_name_sql("c")
+        ))?;
         let result: rusqlite::Result<Vec<EmbedCommit>> = stmt
             .query_map(params![repo_id, limit as i64], |row| {
                 Ok(EmbedCommit {
@@ -946,10 +2173,20 @@ impl Store for SqliteStore {
             .map(|r| serde_json::to_string(r).unwrap_or_else(|_| "[]".into()))
             .unwrap_or_else(|| "[]".into());
         let since = opts.since.unwrap_or(0);
+        let authors_json = opts
+            .authors
+            .as_ref()
+            .map(|a| serde_json::to_string(a).unwrap_or_else(|_| "[]".into()))
+            .unwrap_or_else(|| "[]".into());
+        let projects_json = opts
+            .projects
+            .as_ref()
+            .map(|p| serde_json::to_string(p).unwrap_or_else(|_| "[]".into()))
+            .unwrap_or_else(|| "[]".into());
 
         // For sqlite-vec, k must be specified in WHERE clause with MATCH
         // Filters are applied post-kNN by wrapping in a subquery
-        let sql =
+        let sql = format!(
             "SELECT repo_name, sha, subject, author_name, author_time, patch_excerpt, distance
              FROM (
                SELECT ce.repo_name, ce.sha, ce.subject, ce.author_name, ce.author_time,
@@ -958,14 +2195,30 @@ impl Store for SqliteStore {
                WHERE ce.embedding MATCH ?1
                  AND k = ?2
                ORDER BY distance
-             )
+             ) hits
              WHERE ('[]' = ?3 OR repo_name IN (SELECT value FROM json_each(?3)))
-               AND (?4 = 0 OR author_time >= ?4)";
+               AND (?4 = 0 OR author_time >= ?4)
+               AND ('[]' = ?5 OR EXISTS (
+                 SELECT 1 FROM commits c JOIN repos r ON r.repo_id = c.repo_id
+                 WHERE r.name = hits.repo_name AND c.sha = hits.sha AND {}))
+               AND ('[]' = ?6 OR EXISTS (
+                 SELECT 1 FROM commits c JOIN repos r ON r.repo_id = c.repo_id
+                 WHERE r.name = hits.repo_name AND c.sha = hits.sha AND {}))",
+            authors_condition("c", 5),
+            projects_condition("c", 6)
+        );
 
-        let mut stmt = conn.prepare(sql)?;
+        let mut stmt = conn.prepare(&sql)?;
         let results: rusqlite::Result<Vec<SearchResult>> = stmt
             .query_map(
-                params![embedding_bytes, limit as i64, repos_json, since],
+                params![
+                    embedding_bytes,
+                    limit as i64,
+                    repos_json,
+                    since,
+                    authors_json,
+                    projects_json
+                ],
                 |row| {
                     Ok(SearchResult {
                         repo: row.get(0)?,
@@ -981,6 +2234,9 @@ impl Store for SqliteStore {
             )?
             .collect();
         let results = results?;
+        // The checks below go through other Store methods, which take the lock.
+        drop(stmt);
+        drop(conn);
 
         // If no results, check if any repos have embeddings enabled but not generated
         if results.is_empty() {
@@ -1284,9 +2540,16 @@ mod tests {
             remote_url: None,
             default_branch: Some("main".to_string()),
             fork_of: None,
-            author_filter: None,
+            author_filters: vec![],
+            include_paths: vec![],
+            ref_patterns: vec![],
+            projects: vec![],
             exclude_prefixes: vec![],
             embed_enabled: false,
+            clone_depth: None,
+            clone_since: None,
+            ssh_key: None,
+            token_env: None,
         }
     }
 
@@ -1298,11 +2561,16 @@ mod tests {
             author_email: "test@example.com".to_string(),
             committer_name: "Test Author".to_string(),
             committer_email: "test@example.com".to_string(),
+            canonical_author_name: "Test Author".to_string(),
+            canonical_author_email: "test@example.com".to_string(),
             author_time,
             commit_time: author_time,
             subject: subject.to_string(),
             body: None,
             parent_count: 0,
+            trailers: vec![],
+            refs: vec![],
+            conventional: None,
         }
     }
 
@@ -1461,6 +2729,8 @@ mod tests {
             limit: Some(10),
             repos: None,
             since: None,
+            authors: None,
+            projects: None,
         };
         let results = store
             .search_semantic(&query_embedding, &opts)
@@ -1472,6 +2742,19 @@ mod tests {
         );
         assert_eq!(results[0].sha, "abc123def456");
         assert_eq!(results[0].repo, "semantic-test-repo");
+
+        let by_author = |author: &str| {
+            let opts = SemanticSearchOpts {
+                authors: Some(vec![author.to_string()]),
+                projects: None,
+                ..SemanticSearchOpts::default()
+            };
+            store
+                .search_semantic(&query_embedding, &opts)
+                .expect("search_semantic with authors")
+        };
+        assert_eq!(by_author("TEST@example.com").len(), 1);
+        assert!(by_author("someone@else.com").is_empty());
     }
 
     #[test]
@@ -1509,6 +2792,8 @@ mod tests {
             limit: Some(2),
             repos: None,
             since: None,
+            authors: None,
+            projects: None,
         };
         let results = store
             .search_semantic(&query_embedding, &opts)
@@ -1580,6 +2865,8 @@ mod tests {
             limit: Some(10),
             repos: Some(vec!["repo-one".to_string()]),
             since: None,
+            authors: None,
+            projects: None,
         };
         let results = store
             .search_semantic(&query_embedding, &opts)
@@ -1748,6 +3035,8 @@ mod tests {
             sha: full_sha.to_string(),
             patch_blob: b"diff --git a/foo.rs b/foo.rs\n+added line".to_vec(),
             patch_preview: "diff --git a/foo.rs".to_string(),
+            truncated: false,
+            original_size: 40,
         };
         store.upsert_patch(&patch).expect("upsert patch");
 
@@ -1,5 +1,7 @@
 /// All DDL for CommitMux SQLite schema.
 /// Run in order; all statements are idempotent (IF NOT EXISTS).
+/// Tables list every column; the `*_MIGRATIONS` below add the newer ones
+/// to databases created before them.
 pub const SCHEMA_SQL: &str = r#"
 PRAGMA journal_mode=WAL;
 PRAGMA foreign_keys=ON;
@@ -12,7 +14,21 @@ CREATE TABLE IF NOT EXISTS repos (
     default_branch   TEXT,
     fork_of          TEXT,
     author_filter    TEXT,
-    exclude_prefixes TEXT
+    exclude_prefixes TEXT,
+    embed_enabled    INTEGER NOT NULL DEFAULT 0,
+    clone_depth      INTEGER,
+    clone_since      INTEGER,
+    ssh_key          TEXT,
+    token_env        TEXT
+);
+
+-- Per-repo ingest settings. kind is one of 'email', 'name', 'regex'
+-- (author/committer matchers), 'include_path', 'ref_pattern' or 'project'.
+CREATE TABLE IF NOT EXISTS repo_filters (
+    repo_id INTEGER NOT NULL,
+    kind    TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    PRIMARY KEY (repo_id, kind, pattern)
 );
 
 CREATE TABLE IF NOT EXISTS commits (
@@ -22,21 +38,106 @@ CREATE TABLE IF NOT EXISTS commits (
     author_email    TEXT,
     committer_name  TEXT,
     committer_email TEXT,
+    canonical_author_name  TEXT,
+    canonical_author_email TEXT,
     author_time     INTEGER,
     commit_time     INTEGER,
     subject         TEXT,
     body            TEXT,
     parent_count    INTEGER,
     patch_preview   TEXT,
+    cc_type         TEXT,
+    cc_scope        TEXT,
+    breaking        INTEGER NOT NULL DEFAULT 0,
     PRIMARY KEY (repo_id, sha)
 );
 
+-- Global identity map. alias_email is stored lowercased.
+CREATE TABLE IF NOT EXISTS identities (
+    alias_email     TEXT PRIMARY KEY,
+    canonical_name  TEXT NOT NULL,
+    canonical_email TEXT NOT NULL
+);
+
+-- Commit message trailers. ident_name/ident_email are set when the value
+-- has the form `Name <email>`.
+CREATE TABLE IF NOT EXISTS commit_trailers (
+    repo_id     INTEGER NOT NULL,
+    sha         TEXT NOT NULL,
+    key         TEXT NOT NULL,
+    value       TEXT NOT NULL,
+    ident_name  TEXT,
+    ident_email TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_commit_trailers_repo_sha
+    ON commit_trailers (repo_id, sha);
+
+CREATE INDEX IF NOT EXISTS idx_commit_trailers_key
+    ON commit_trailers (key COLLATE NOCASE);
+
+-- Issue/PR references found in commit messages. project is the lowercased
+-- owner/repo path, or NULL when unknown; ref_id is the number or key.
+CREATE TABLE IF NOT EXISTS commit_refs (
+    repo_id INTEGER NOT NULL,
+    sha     TEXT NOT NULL,
+    kind    TEXT NOT NULL,
+    project TEXT,
+    ref_id  TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_commit_refs_repo_sha
+    ON commit_refs (repo_id, sha);
+
+CREATE INDEX IF NOT EXISTS idx_commit_refs_ref
+    ON commit_refs (ref_id COLLATE NOCASE, kind);
+
+-- Tags, lightweight and annotated. target_sha is the peeled commit;
+-- tag_time is the tagger date, or the commit time for lightweight tags.
+CREATE TABLE IF NOT EXISTS tags (
+    repo_id      INTEGER NOT NULL,
+    name         TEXT NOT NULL,
+    target_sha   TEXT NOT NULL,
+    annotated    INTEGER NOT NULL DEFAULT 0,
+    tagger_name  TEXT,
+    tagger_email TEXT,
+    tag_time     INTEGER,
+    message      TEXT,
+    PRIMARY KEY (repo_id, name)
+);
+
+-- Commits reachable from a tag but from none of the tags it contains.
+-- A commit is contained in a tag if the tag introduces it, or contains a
+-- tag that does.
+CREATE TABLE IF NOT EXISTS tag_commits (
+    repo_id INTEGER NOT NULL,
+    tag     TEXT NOT NULL,
+    sha     TEXT NOT NULL,
+    PRIMARY KEY (repo_id, sha, tag)
+);
+
+-- Pairs of tags where `tag` contains (is a descendant of) `ancestor`.
+CREATE TABLE IF NOT EXISTS tag_ancestors (
+    repo_id  INTEGER NOT NULL,
+    tag      TEXT NOT NULL,
+    ancestor TEXT NOT NULL,
+    PRIMARY KEY (repo_id, ancestor, tag)
+);
+
+-- kind is 'text', 'binary', 'lfs', 'oversized' or 'submodule'; size is the
+-- blob size, and lfs_oid/lfs_size describe the object an LFS pointer refers to.
+-- project is the repo project (longest matching prefix) the path is in.
 CREATE TABLE IF NOT EXISTS commit_files (
     repo_id  INTEGER NOT NULL,
     sha      TEXT NOT NULL,
     path     TEXT NOT NULL,
     status   TEXT,
-    old_path TEXT
+    old_path TEXT,
+    kind     TEXT,
+    size     INTEGER,
+    lfs_oid  TEXT,
+    lfs_size INTEGER,
+    project  TEXT
 );
 
 CREATE INDEX IF NOT EXISTS idx_commit_files_repo_sha
@@ -45,10 +146,55 @@ CREATE INDEX IF NOT EXISTS idx_commit_files_repo_sha
 CREATE INDEX IF NOT EXISTS idx_commit_files_path
     ON commit_files (path);
 
+-- The distinct projects of each commit's files.
+CREATE TABLE IF NOT EXISTS commit_projects (
+    repo_id INTEGER NOT NULL,
+    sha     TEXT NOT NULL,
+    project TEXT NOT NULL,
+    PRIMARY KEY (repo_id, sha, project)
+);
+
+CREATE INDEX IF NOT EXISTS idx_commit_projects_project
+    ON commit_projects (project);
+
+-- Functions and types whose lines each commit changed. name is the bare
+-- name and qualified includes enclosing types or modules.
+CREATE TABLE IF NOT EXISTS commit_symbols (
+    repo_id   INTEGER NOT NULL,
+    sha       TEXT NOT NULL,
+    path      TEXT NOT NULL,
+    name      TEXT NOT NULL,
+    qualified TEXT NOT NULL,
+    kind      TEXT NOT NULL,
+    excerpt   TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_commit_symbols_repo_sha
+    ON commit_symbols (repo_id, sha);
+
+CREATE INDEX IF NOT EXISTS idx_commit_symbols_name
+    ON commit_symbols (repo_id, name);
+
+-- Gitlinks each commit changed. old_sha is NULL for an added submodule and
+-- new_sha for a removed one; url comes from .gitmodules.
+CREATE TABLE IF NOT EXISTS commit_submodules (
+    repo_id INTEGER NOT NULL,
+    sha     TEXT NOT NULL,
+    path    TEXT NOT NULL,
+    url     TEXT,
+    old_sha TEXT,
+    new_sha TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_commit_submodules_repo_sha
+    ON commit_submodules (repo_id, sha);
+
 CREATE TABLE IF NOT EXISTS commit_patches (
-    repo_id    INTEGER NOT NULL,
-    sha        TEXT NOT NULL,
-    patch_blob BLOB,
+    repo_id       INTEGER NOT NULL,
+    sha           TEXT NOT NULL,
+    patch_blob    BLOB,
+    truncated     INTEGER NOT NULL DEFAULT 0,
+    original_size INTEGER,
     PRIMARY KEY (repo_id, sha)
 );
 
@@ -56,7 +202,10 @@ CREATE TABLE IF NOT EXISTS ingest_state (
     repo_id         INTEGER PRIMARY KEY,
     last_synced_at  INTEGER,
     last_synced_sha TEXT,
-    last_error      TEXT
+    last_error      TEXT,
+    checkpoint_sha  TEXT,
+    indexed_tips    TEXT,
+    config_hash     TEXT
 );
 
 CREATE VIRTUAL TABLE IF NOT EXISTS commits_fts
@@ -117,16 +266,66 @@ CREATE VIRTUAL TABLE IF NOT EXISTS memory_embeddings USING vec0(
 "#;
 
 /// Migration statements for new `repos` columns.
-/// Each is attempted individually; "duplicate column name" errors are ignored
-/// so that migrations are idempotent on databases that already have the column.
 pub const REPO_MIGRATIONS: &[&str] = &[
     "ALTER TABLE repos ADD COLUMN fork_of TEXT",
     "ALTER TABLE repos ADD COLUMN author_filter TEXT",
     "ALTER TABLE repos ADD COLUMN exclude_prefixes TEXT",
+    "ALTER TABLE repos ADD COLUMN clone_depth INTEGER",
+    "ALTER TABLE repos ADD COLUMN clone_since INTEGER",
+    "ALTER TABLE repos ADD COLUMN ssh_key TEXT",
+    "ALTER TABLE repos ADD COLUMN token_env TEXT",
 ];
 
+/// Moves the legacy single-email `repos.author_filter` column into
+/// `repo_filters`. Safe to run repeatedly: the column is cleared once copied.
+pub const AUTHOR_FILTER_MIGRATION: &str = "
+INSERT OR IGNORE INTO repo_filters (repo_id, kind, pattern)
+    SELECT repo_id, 'email', author_filter FROM repos
+    WHERE author_filter IS NOT NULL AND author_filter != '';
+UPDATE repos SET author_filter = NULL WHERE author_filter IS NOT NULL;
+";
+
+/// Migration statements for new `commits` columns.
+/// Commits indexed before a column existed read it as NULL, and queries fall
+/// back to the raw value.
+pub const COMMIT_MIGRATIONS: &[&str] = &[
+    "ALTER TABLE commits ADD COLUMN canonical_author_name TEXT",
+    "ALTER TABLE commits ADD COLUMN canonical_author_email TEXT",
+    "ALTER TABLE commits ADD COLUMN cc_type TEXT",
+    "ALTER TABLE commits ADD COLUMN cc_scope TEXT",
+    "ALTER TABLE commits ADD COLUMN breaking INTEGER NOT NULL DEFAULT 0",
+    "CREATE INDEX IF NOT EXISTS idx_commits_cc_type ON commits (cc_type)",
+];
+
+/// `config` key recording that Conventional Commit columns have been filled
+/// in for commits indexed before they existed.
+pub const CONVENTIONAL_BACKFILL_KEY: &str = "migration.conventional_commits";
+
 /// Migration statements for embedding support columns.
-/// Each is attempted individually; "duplicate column name" errors are ignored
-/// so that migrations are idempotent on databases that already have the column.
 pub const EMBED_MIGRATIONS: &[&str] =
     &["ALTER TABLE repos ADD COLUMN embed_enabled INTEGER NOT NULL DEFAULT 0"];
+
+/// Migration statements for new `commit_files` columns.
+/// Files indexed before these columns existed read `kind` as NULL, i.e. text.
+pub const COMMIT_FILE_MIGRATIONS: &[&str] = &[
+    "ALTER TABLE commit_files ADD COLUMN kind TEXT",
+    "ALTER TABLE commit_files ADD COLUMN size INTEGER",
+    "ALTER TABLE commit_files ADD COLUMN lfs_oid TEXT",
+    "ALTER TABLE commit_files ADD COLUMN lfs_size INTEGER",
+    "ALTER TABLE commit_files ADD COLUMN project TEXT",
+];
+
+/// Migration statements for new `commit_patches` columns.
+/// Patches stored before these columns existed read as not truncated, with no
+/// recorded size.
+pub const COMMIT_PATCH_MIGRATIONS: &[&str] = &[
+    "ALTER TABLE commit_patches ADD COLUMN truncated INTEGER NOT NULL DEFAULT 0",
+    "ALTER TABLE commit_patches ADD COLUMN original_size INTEGER",
+];
+
+/// Migration statements for new `ingest_state` columns.
+pub const INGEST_STATE_MIGRATIONS: &[&str] = &[
+    "ALTER TABLE ingest_state ADD COLUMN checkpoint_sha TEXT",
+    "ALTER TABLE ingest_state ADD COLUMN indexed_tips TEXT",
+    "ALTER TABLE ingest_state ADD COLUMN config_hash TEXT",
+];
@@ -1,4 +1,5 @@
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use thiserror::Error;
 
 // ── Error ─────────────────────────────────────────────────────────────────
@@ -34,9 +35,49 @@ pub struct Repo {
     pub default_branch: Option<String>,
     // NEW:
     pub fork_of: Option<String>,
-    pub author_filter: Option<String>,
+    /// Only commits whose author or committer matches one of these are indexed
+    /// (all commits when empty).
+    pub author_filters: Vec<AuthorMatcher>,
+    /// Only these path prefixes are indexed (everything when empty).
+    pub include_paths: Vec<String>,
+    /// Issue reference syntaxes extracted at ingest
+    /// ([`RefPattern::defaults`] when empty).
+    pub ref_patterns: Vec<RefPattern>,
+    /// Virtual sub-projects of a monorepo; each changed file is tagged with
+    /// the project whose prefix matches it.
+    pub projects: Vec<RepoProject>,
     pub exclude_prefixes: Vec<String>,
     pub embed_enabled: bool,
+    /// Managed clones only: number of commits fetched from each branch tip.
+    pub clone_depth: Option<u32>,
+    /// Managed clones only: unix time the fetched history must reach back
+    /// to. Older commits are not indexed.
+    pub clone_since: Option<i64>,
+    /// Managed clones only: private key file tried before the SSH agent.
+    pub ssh_key: Option<PathBuf>,
+    /// Managed clones only: environment variable holding an HTTPS token.
+    pub token_env: Option<String>,
+}
+
+/// The lowercased `owner/repo` (or `group/sub/project`) path of a remote
+/// URL, e.g. `git@github.com:Acme/api.git` → `acme/api`.
+pub fn remote_project(url: &str) -> Option<String> {
+    let rest = match url.split_once("://") {
+        // scheme://[user@]host[:port]/path; local file:// remotes have no host
+        Some((_, rest)) => match rest.split_once('/')? {
+            ("", _) => return None,
+            (_, path) => path,
+        },
+        // scp-like: [user@]host:path
+        None => url.split_once(':')?.1,
+    };
+    let path = rest.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if path.contains('/') {
+        Some(path.to_lowercase())
+    } else {
+        None
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -47,18 +88,325 @@ pub struct RepoInput {
     pub default_branch: Option<String>,
     // NEW:
     pub fork_of: Option<String>,
-    pub author_filter: Option<String>,
+    pub author_filters: Vec<AuthorMatcher>,
+    pub include_paths: Vec<String>,
+    pub ref_patterns: Vec<RefPattern>,
+    pub projects: Vec<RepoProject>,
     pub exclude_prefixes: Vec<String>,
     pub embed_enabled: bool,
+    pub clone_depth: Option<u32>,
+    pub clone_since: Option<i64>,
+    pub ssh_key: Option<PathBuf>,
+    pub token_env: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct RepoUpdate {
     pub fork_of: Option<Option<String>>,
-    pub author_filter: Option<Option<String>>,
+    /// Matchers added to the repo's author filter.
+    pub add_authors: Vec<AuthorMatcher>,
+    /// Matchers removed from the repo's author filter.
+    pub remove_authors: Vec<AuthorMatcher>,
+    pub include_paths: Option<Vec<String>>,
+    /// Replaces the repo's reference patterns (empty restores the defaults).
+    pub ref_patterns: Option<Vec<RefPattern>>,
+    /// Replaces the repo's projects; already indexed files are re-tagged.
+    pub projects: Option<Vec<RepoProject>>,
     pub exclude_prefixes: Option<Vec<String>>,
     pub default_branch: Option<Option<String>>,
     pub embed_enabled: Option<bool>,
+    pub clone_depth: Option<Option<u32>>,
+    pub clone_since: Option<Option<i64>>,
+    pub ssh_key: Option<Option<PathBuf>>,
+    pub token_env: Option<Option<String>>,
+}
+
+/// Credentials for cloning and fetching a managed clone, tried along with
+/// the SSH agent, git credential helpers and `GIT_ASKPASS`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemoteAuth {
+    /// Private key file for SSH remotes.
+    pub ssh_key: Option<PathBuf>,
+    /// Environment variable holding a token for HTTPS remotes.
+    pub token_env: Option<String>,
+}
+
+impl RemoteAuth {
+    /// `config` key naming the token variable for repos without their own.
+    pub const TOKEN_ENV_KEY: &'static str = "auth.token_env";
+}
+
+/// A global identity mapping, applied on top of each repo's `.mailmap`:
+/// commits whose author email is `alias_email` are attributed to
+/// `canonical_name <canonical_email>`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct IdentityAlias {
+    pub alias_email: String,
+    pub canonical_name: String,
+    pub canonical_email: String,
+}
+
+/// One entry in a repo's author filter, matched against both the author and
+/// the committer of each commit (raw and `.mailmap`-resolved).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AuthorMatcher {
+    /// Case-insensitive exact email match.
+    Email(String),
+    /// Case-insensitive exact name match.
+    Name(String),
+    /// Regex matched against `Name <email>`.
+    Regex(String),
+}
+
+impl AuthorMatcher {
+    /// Parses the CLI form: `name:<name>`, `re:<regex>`, `email:<email>`, or a
+    /// bare value (an email if it contains `@`, otherwise a name).
+    pub fn parse(s: &str) -> AuthorMatcher {
+        if let Some(v) = s.strip_prefix("name:") {
+            AuthorMatcher::Name(v.to_string())
+        } else if let Some(v) = s.strip_prefix("re:") {
+            AuthorMatcher::Regex(v.to_string())
+        } else if let Some(v) = s.strip_prefix("email:") {
+            AuthorMatcher::Email(v.to_string())
+        } else if s.contains('@') {
+            AuthorMatcher::Email(s.to_string())
+        } else {
+            AuthorMatcher::Name(s.to_string())
+        }
+    }
+
+    /// Rebuilds a matcher from its stored `(kind, pattern)` form.
+    pub fn from_parts(kind: &str, pattern: &str) -> Option<AuthorMatcher> {
+        match kind {
+            "email" => Some(AuthorMatcher::Email(pattern.to_string())),
+            "name" => Some(AuthorMatcher::Name(pattern.to_string())),
+            "regex" => Some(AuthorMatcher::Regex(pattern.to_string())),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuthorMatcher::Email(_) => "email",
+            AuthorMatcher::Name(_) => "name",
+            AuthorMatcher::Regex(_) => "regex",
+        }
+    }
+
+    pub fn pattern(&self) -> &str {
+        match self {
+            AuthorMatcher::Email(p) | AuthorMatcher::Name(p) | AuthorMatcher::Regex(p) => p,
+        }
+    }
+}
+
+impl std::fmt::Display for AuthorMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthorMatcher::Email(p) if p.contains('@') => write!(f, "{}", p),
+            AuthorMatcher::Email(p) => write!(f, "email:{}", p),
+            AuthorMatcher::Name(p) => write!(f, "name:{}", p),
+            AuthorMatcher::Regex(p) => write!(f, "re:{}", p),
+        }
+    }
+}
+
+/// An issue/PR reference syntax recognised in commit messages at ingest.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RefPattern {
+    /// `#123`, `owner/repo#123` and GitHub issue/pull request URLs.
+    Github,
+    /// `!45`, `group/project!45` and GitLab issue/merge request URLs.
+    Gitlab,
+    /// Jira keys such as `PROJ-881` in these projects. At least one key is
+    /// required: without them too many tokens look like Jira keys.
+    Jira(Vec<String>),
+    /// Custom regex; the first capture group (or the whole match) is the
+    /// reference.
+    Regex(String),
+}
+
+impl RefPattern {
+    /// Patterns used for repos that have none configured.
+    pub fn defaults() -> Vec<RefPattern> {
+        vec![RefPattern::Github, RefPattern::Gitlab]
+    }
+
+    /// Parses the CLI/stored form: `github`, `gitlab`, `jira`,
+    /// `jira:KEY1,KEY2` or `re:<regex>`.
+    pub fn parse(s: &str) -> Option<RefPattern> {
+        if let Some(re) = s.strip_prefix("re:") {
+            return Some(RefPattern::Regex(re.to_string()));
+        }
+        if let Some(keys) = s.strip_prefix("jira:") {
+            let keys = keys
+                .split(',')
+                .map(|k| k.trim().to_uppercase())
+                .filter(|k| !k.is_empty())
+                .collect();
+            return Some(RefPattern::Jira(keys));
+        }
+        match s.to_lowercase().as_str() {
+            "github" => Some(RefPattern::Github),
+            "gitlab" => Some(RefPattern::Gitlab),
+            "jira" => Some(RefPattern::Jira(Vec::new())),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for RefPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefPattern::Github => write!(f, "github"),
+            RefPattern::Gitlab => write!(f, "gitlab"),
+            RefPattern::Jira(keys) if keys.is_empty() => write!(f, "jira"),
+            RefPattern::Jira(keys) => write!(f, "jira:{}", keys.join(",")),
+            RefPattern::Regex(re) => write!(f, "re:{}", re),
+        }
+    }
+}
+
+/// A virtual sub-project of a monorepo: the files under `prefix`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct RepoProject {
+    pub name: String,
+    pub prefix: String,
+}
+
+impl RepoProject {
+    /// Parses the CLI/stored form `name=prefix`, e.g. `billing=services/billing/`.
+    pub fn parse(s: &str) -> Option<RepoProject> {
+        let (name, prefix) = s.split_once('=')?;
+        let (name, prefix) = (name.trim(), prefix.trim());
+        if name.is_empty() || prefix.is_empty() {
+            return None;
+        }
+        Some(RepoProject {
+            name: name.to_string(),
+            prefix: prefix.to_string(),
+        })
+    }
+
+    /// The project `path` belongs to: the one with the longest matching
+    /// prefix, so nested projects take their files from the enclosing one.
+    pub fn for_path<'a>(projects: &'a [RepoProject], path: &str) -> Option<&'a RepoProject> {
+        projects
+            .iter()
+            .filter(|p| path.starts_with(&p.prefix))
+            .max_by_key(|p| p.prefix.len())
+    }
+}
+
+impl std::fmt::Display for RepoProject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.name, self.prefix)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefKind {
+    /// GitHub issue or pull request, or GitLab issue (`#N`).
+    Issue,
+    /// GitLab merge request (`!N`).
+    MergeRequest,
+    Jira,
+    Custom,
+}
+
+impl RefKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RefKind::Issue => "issue",
+            RefKind::MergeRequest => "merge_request",
+            RefKind::Jira => "jira",
+            RefKind::Custom => "custom",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<RefKind> {
+        match s {
+            "issue" => Some(RefKind::Issue),
+            "merge_request" => Some(RefKind::MergeRequest),
+            "jira" => Some(RefKind::Jira),
+            "custom" => Some(RefKind::Custom),
+            _ => None,
+        }
+    }
+}
+
+/// An issue, PR or ticket reference found in a commit message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct CommitRef {
+    pub kind: RefKind,
+    /// Lowercased `owner/repo` path the issue or merge request belongs to;
+    /// `None` when the message does not say and the repo has no known remote.
+    pub project: Option<String>,
+    /// Issue/MR number, Jira key (`PROJ-881`) or custom match.
+    pub id: String,
+}
+
+impl CommitRef {
+    /// Parses a reference as a user would type it: `#123`, `owner/repo#123`,
+    /// `!45`, `group/project!45`, a bare number (an issue), a Jira key, or
+    /// anything else as a custom reference.
+    pub fn parse(s: &str) -> CommitRef {
+        let s = s.trim();
+        for (sep, kind) in [('#', RefKind::Issue), ('!', RefKind::MergeRequest)] {
+            if let Some((project, id)) = s.rsplit_once(sep) {
+                if !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) {
+                    let project = project.trim();
+                    return CommitRef {
+                        kind,
+                        project: (!project.is_empty()).then(|| project.to_lowercase()),
+                        id: id.to_string(),
+                    };
+                }
+            }
+        }
+        if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+            return CommitRef {
+                kind: RefKind::Issue,
+                project: None,
+                id: s.to_string(),
+            };
+        }
+        let is_jira = s.split_once('-').is_some_and(|(key, num)| {
+            key.len() > 1
+                && key.starts_with(|c: char| c.is_ascii_alphabetic())
+                && key.bytes().all(|b| b.is_ascii_alphanumeric())
+                && !num.is_empty()
+                && num.bytes().all(|b| b.is_ascii_digit())
+        });
+        if is_jira {
+            return CommitRef {
+                kind: RefKind::Jira,
+                project: None,
+                id: s.to_uppercase(),
+            };
+        }
+        CommitRef {
+            kind: RefKind::Custom,
+            project: None,
+            id: s.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for CommitRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sep = match self.kind {
+            RefKind::Issue => '#',
+            RefKind::MergeRequest => '!',
+            RefKind::Jira | RefKind::Custom => return write!(f, "{}", self.id),
+        };
+        match self.project {
+            Some(ref project) => write!(f, "{}{}{}", project, sep, self.id),
+            None => write!(f, "{}{}", sep, self.id),
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -76,11 +424,121 @@ pub struct Commit {
     pub author_email: String,
     pub committer_name: String,
     pub committer_email: String,
+    /// Author identity after `.mailmap` resolution (equal to the raw identity
+    /// when the repo has no mapping for it).
+    pub canonical_author_name: String,
+    pub canonical_author_email: String,
     pub author_time: i64,
     pub commit_time: i64,
     pub subject: String,
     pub body: Option<String>,
     pub parent_count: u32,
+    /// Trailers parsed from the end of the message, in order.
+    pub trailers: Vec<Trailer>,
+    /// Issue and PR references found in the message.
+    pub refs: Vec<CommitRef>,
+    /// Set when the subject follows Conventional Commits.
+    pub conventional: Option<ConventionalCommit>,
+}
+
+/// The `type(scope)!:` header of a Conventional Commits subject.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ConventionalCommit {
+    /// Lowercased type, e.g. `feat` or `fix`.
+    pub cc_type: String,
+    pub scope: Option<String>,
+    /// `!` after the type/scope, or a `BREAKING CHANGE:` footer.
+    pub breaking: bool,
+    /// Subject text after the header.
+    pub description: String,
+}
+
+impl ConventionalCommit {
+    /// Parses `type(scope)!: description`. Returns `None` for subjects that
+    /// do not follow the format.
+    pub fn parse(subject: &str, body: Option<&str>) -> Option<ConventionalCommit> {
+        let (header, description) = subject.split_once(':')?;
+        let description = description.strip_prefix(' ')?.trim();
+        let (header, bang) = match header.strip_suffix('!') {
+            Some(h) => (h, true),
+            None => (header, false),
+        };
+        let (cc_type, scope) = match header.split_once('(') {
+            Some((t, rest)) => {
+                let scope = rest.strip_suffix(')')?.trim();
+                if scope.is_empty() || scope.contains(['(', ')']) {
+                    return None;
+                }
+                (t, Some(scope.to_string()))
+            }
+            None => (header, None),
+        };
+        if cc_type.is_empty()
+            || description.is_empty()
+            || !cc_type
+                .bytes()
+                .all(|b| b.is_ascii_alphabetic() || b == b'-')
+        {
+            return None;
+        }
+        let footer = body.is_some_and(|b| {
+            b.lines()
+                .any(|l| l.starts_with("BREAKING CHANGE:") || l.starts_with("BREAKING-CHANGE:"))
+        });
+        Some(ConventionalCommit {
+            cc_type: cc_type.to_lowercase(),
+            scope,
+            breaking: bang || footer,
+            description: description.to_string(),
+        })
+    }
+}
+
+/// A git tag, peeled to the commit it points at.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Tag {
+    pub name: String,
+    pub target_sha: String,
+    pub annotated: bool,
+    pub tagger_name: Option<String>,
+    pub tagger_email: Option<String>,
+    /// Tagger date, or the target commit's time for lightweight tags.
+    pub date: i64,
+    pub message: Option<String>,
+}
+
+/// A repo's tags with precomputed commit containment. A commit is
+/// contained in a tag if the tag introduces it, or if the tag contains a
+/// tag that introduces it.
+#[derive(Debug, Clone, Default)]
+pub struct TagIndex {
+    pub tags: Vec<Tag>,
+    /// `(tag, sha)`: commits reachable from the tag but from none of the
+    /// tags it contains.
+    pub introduced: Vec<(String, String)>,
+    /// `(tag, ancestor)`: the tag's commit descends from the ancestor's.
+    pub ancestors: Vec<(String, String)>,
+}
+
+/// A `Key: value` trailer such as `Co-authored-by` or `Fixes`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+impl Trailer {
+    /// Splits a `Name <email>` value, as used by `Co-authored-by`,
+    /// `Signed-off-by` and `Reviewed-by`.
+    pub fn identity(&self) -> Option<(&str, &str)> {
+        let value = self.value.trim();
+        let open = value.rfind('<')?;
+        let email = value[open + 1..].strip_suffix('>')?.trim();
+        if email.is_empty() {
+            return None;
+        }
+        Some((value[..open].trim(), email))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -112,6 +570,61 @@ impl std::fmt::Display for FileStatus {
     }
 }
 
+/// How a changed file's content is stored; only `Text` files are diffed
+/// line by line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    #[default]
+    Text,
+    Binary,
+    /// A Git LFS pointer; the content lives in LFS storage.
+    Lfs,
+    /// Too large to diff (over `FileKind::MAX_TEXT_BYTES`).
+    Oversized,
+    /// A gitlink: the commit a submodule is pinned to.
+    Submodule,
+}
+
+impl FileKind {
+    pub const MAX_TEXT_BYTES: u64 = 1_048_576;
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileKind::Text => "text",
+            FileKind::Binary => "binary",
+            FileKind::Lfs => "lfs",
+            FileKind::Oversized => "oversized",
+            FileKind::Submodule => "submodule",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<FileKind> {
+        match s {
+            "text" => Some(FileKind::Text),
+            "binary" => Some(FileKind::Binary),
+            "lfs" => Some(FileKind::Lfs),
+            "oversized" => Some(FileKind::Oversized),
+            "submodule" => Some(FileKind::Submodule),
+            _ => None,
+        }
+    }
+
+    pub fn is_text(&self) -> bool {
+        *self == FileKind::Text
+    }
+}
+
+/// The object a Git LFS pointer file refers to.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LfsPointer {
+    /// `sha256:<hex>`.
+    pub oid: String,
+    /// Size of the real file in bytes.
+    pub size: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct CommitFile {
     pub repo_id: i64,
@@ -119,6 +632,47 @@ pub struct CommitFile {
     pub path: String,
     pub status: FileStatus,
     pub old_path: Option<String>,
+    pub kind: FileKind,
+    /// Blob size in bytes (the old blob for deletions).
+    pub size: Option<u64>,
+    pub lfs: Option<LfsPointer>,
+    /// The repo project the file belongs to, if any.
+    pub project: Option<String>,
+}
+
+/// A function, method or type definition whose lines a commit changed.
+/// Files are parsed on both sides of the diff, so symbols that were added,
+/// edited or removed are all recorded.
+#[derive(Debug, Clone)]
+pub struct CommitSymbol {
+    pub repo_id: i64,
+    pub sha: String,
+    pub path: String,
+    /// Bare name, e.g. `parse`.
+    pub name: String,
+    /// Name with its enclosing types or modules, e.g. `Parser::parse`.
+    pub qualified: String,
+    /// `function`, `method`, `struct`, `class`, ...
+    pub kind: String,
+    /// The changed lines of the hunks inside the definition, with their
+    /// `@@` headers.
+    pub excerpt: String,
+}
+
+/// A submodule whose pinned commit a commit changed: added, removed or
+/// moved to another commit.
+#[derive(Debug, Clone)]
+pub struct CommitSubmodule {
+    pub repo_id: i64,
+    pub sha: String,
+    pub path: String,
+    /// URL from `.gitmodules`; relative URLs are resolved against the
+    /// superproject's remote.
+    pub url: Option<String>,
+    /// Pinned commit before the change; `None` if the submodule was added.
+    pub old_sha: Option<String>,
+    /// Pinned commit after the change; `None` if the submodule was removed.
+    pub new_sha: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -127,14 +681,54 @@ pub struct CommitPatch {
     pub sha: String,
     pub patch_blob: Vec<u8>,
     pub patch_preview: String,
+    /// Whether `patch_blob` was cut at `IgnoreConfig::max_patch_bytes`.
+    pub truncated: bool,
+    /// Size in bytes of the whole patch before any cut.
+    pub original_size: u64,
 }
 
+/// Everything the ingester writes for one commit.
 #[derive(Debug, Clone)]
+pub struct BatchCommit {
+    pub commit: Commit,
+    pub files: Vec<CommitFile>,
+    pub patch: Option<CommitPatch>,
+    pub symbols: Vec<CommitSymbol>,
+    pub submodules: Vec<CommitSubmodule>,
+}
+
+/// A group of commits written by `Store::write_batch` in a single transaction.
+#[derive(Debug, Clone, Default)]
+pub struct WriteBatch {
+    pub commits: Vec<BatchCommit>,
+}
+
+impl WriteBatch {
+    pub fn len(&self) -> usize {
+        self.commits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commits.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct IngestState {
     pub repo_id: i64,
     pub last_synced_at: i64,
     pub last_synced_sha: Option<String>,
     pub last_error: Option<String>,
+    /// Last commit processed by a sync that has not finished yet. Every commit
+    /// reachable from it has been walked, so a resumed sync can hide it.
+    pub checkpoint_sha: Option<String>,
+    /// Tips of recent completed syncs, newest first. Any that are still
+    /// ancestors of the current tip are hidden in the next revwalk.
+    pub indexed_tips: Vec<String>,
+    /// Hash of the filters and diff settings the indexed history was walked
+    /// with. When it changes, the next sync walks the full history again so
+    /// commits the new settings admit are picked up.
+    pub config_hash: Option<String>,
 }
 
 // ── Query option types ────────────────────────────────────────────────────
@@ -144,12 +738,177 @@ pub struct SearchOpts {
     pub since: Option<i64>,
     pub repos: Option<Vec<String>>,
     pub paths: Option<Vec<String>>,
+    /// Author names or emails (raw, `.mailmap`-resolved or identity aliases);
+    /// matches every commit by the same canonical identity, including
+    /// commits where they appear as a `Co-authored-by` trailer.
+    pub authors: Option<Vec<String>>,
+    /// `Key` (commit has the trailer) or `Key:value` (value contains `value`),
+    /// both case-insensitive.
+    pub trailer: Option<String>,
+    /// Conventional Commit types, e.g. `["feat", "fix"]`.
+    pub cc_types: Option<Vec<String>>,
+    pub cc_scopes: Option<Vec<String>>,
+    /// Only breaking (`Some(true)`) or non-breaking (`Some(false)`) commits.
+    pub breaking: Option<bool>,
+    /// Only commits touching one of these repo projects.
+    pub projects: Option<Vec<String>>,
     pub limit: Option<usize>,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct ConventionalSummaryOpts {
+    pub repos: Option<Vec<String>>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub cc_types: Option<Vec<String>>,
+    pub cc_scopes: Option<Vec<String>>,
+    pub breaking: Option<bool>,
+    /// Maximum commits to summarise (default 500).
+    pub limit: Option<usize>,
+}
+
+/// How a changelog groups the commits in its range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChangelogGroupBy {
+    /// Conventional Commits type; other commits go under `other`.
+    #[default]
+    Type,
+    /// Top-level directory of each changed file.
+    Directory,
+    /// Canonical author name.
+    Author,
+}
+
+impl ChangelogGroupBy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangelogGroupBy::Type => "type",
+            ChangelogGroupBy::Directory => "directory",
+            ChangelogGroupBy::Author => "author",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<ChangelogGroupBy> {
+        match s {
+            "type" => Some(ChangelogGroupBy::Type),
+            "directory" | "dir" => Some(ChangelogGroupBy::Directory),
+            "author" => Some(ChangelogGroupBy::Author),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChangelogOpts {
+    pub repo: String,
+    /// Start of the range (exclusive); `None` means from the root commit.
+    pub from: Option<String>,
+    pub to: String,
+    /// Follow only the first parent of merges, listing each merge once.
+    pub first_parent: bool,
+    pub group_by: ChangelogGroupBy,
+}
+
+impl ChangelogOpts {
+    /// Splits `<from>..<to>` into its ends. `<to>` alone (or `..<to>`)
+    /// covers all history up to `<to>`.
+    pub fn parse_range(range: &str) -> Result<(Option<String>, String)> {
+        let (from, to) = match range.split_once("..") {
+            Some((from, to)) => (from.trim(), to.trim()),
+            None => ("", range.trim()),
+        };
+        if to.is_empty() || to.starts_with('.') {
+            return Err(CommitmuxError::Config(format!(
+                "invalid range '{}': expected <from>..<to>",
+                range
+            )));
+        }
+        let from = (!from.is_empty()).then(|| from.to_string());
+        Ok((from, to.to_string()))
+    }
+}
+
+/// Which part of a file to read. A line range and a byte offset cannot be
+/// combined; with neither, the file is read from the start.
+#[derive(Debug, Clone, Default)]
+pub struct FileOpts {
+    pub repo: String,
+    pub path: String,
+    /// Commit to read at: a SHA, or any branch or tag.
+    pub rev: String,
+    /// First line to return, 1-based.
+    pub start_line: Option<usize>,
+    /// Last line to return, inclusive (default: end of file).
+    pub end_line: Option<usize>,
+    /// Byte offset to start reading at.
+    pub offset: Option<usize>,
+    /// Most bytes of content to return (default: `FileContent::DEFAULT_MAX_BYTES`).
+    pub max_bytes: Option<usize>,
+}
+
+/// A live diff between two revisions.
+#[derive(Debug, Clone, Default)]
+pub struct DiffOpts {
+    pub repo: String,
+    /// Base revision: a SHA, or any branch or tag.
+    pub from: String,
+    pub to: String,
+    /// Only files at or under these paths (default: all files).
+    pub paths: Vec<String>,
+    /// Return per-file line counts without the patch text.
+    pub stat_only: bool,
+    /// Truncate the patch text to this many bytes.
+    pub max_bytes: Option<usize>,
+}
+
+/// Which part of a commit's tree to list.
+#[derive(Debug, Clone, Default)]
+pub struct TreeOpts {
+    pub repo: String,
+    /// Commit to list at: a SHA, or any branch or tag.
+    pub rev: String,
+    /// Directory to list, relative to the repo root (default: the root).
+    pub path: Option<String>,
+    /// Levels to descend; 1 lists only the directory's own entries
+    /// (default: `TreeListing::DEFAULT_DEPTH`).
+    pub depth: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BlameOpts {
+    pub repo: String,
+    pub path: String,
+    /// Revision to blame at (default: the repo's HEAD).
+    pub rev: Option<String>,
+    /// First line of the range, 1-based (default: 1).
+    pub start_line: Option<usize>,
+    /// Last line of the range, inclusive (default: end of file).
+    pub end_line: Option<usize>,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct TouchOpts {
     pub since: Option<i64>,
+    pub repos: Option<Vec<String>>,
+    pub authors: Option<Vec<String>>,
+    /// Only files in one of these repo projects.
+    pub projects: Option<Vec<String>>,
+    pub limit: Option<usize>,
+    /// Also return the history of matching files under their earlier
+    /// names, following recorded renames and copies back through `old_path`.
+    pub follow_renames: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymbolOpts {
+    /// Only changes in this file, or in files under this directory.
+    pub path: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RefOpts {
     pub repos: Option<Vec<String>>,
     pub limit: Option<usize>,
 }
@@ -175,9 +934,11 @@ pub struct EmbedCommit {
 /// Options for semantic (vector) search.
 #[derive(Debug, Clone, Default)]
 pub struct SemanticSearchOpts {
-    pub repos: Option<Vec<String>>, // filter by repo name
-    pub since: Option<i64>,         // unix timestamp lower bound
-    pub limit: Option<usize>,       // default 10
+    pub repos: Option<Vec<String>>,    // filter by repo name
+    pub since: Option<i64>,            // unix timestamp lower bound
+    pub authors: Option<Vec<String>>,  // filter by author name or email
+    pub projects: Option<Vec<String>>, // filter by repo project
+    pub limit: Option<usize>,          // default 10
 }
 
 // ── Memory types ──────────────────────────────────────────────────────────
@@ -290,6 +1051,304 @@ pub struct TouchResult {
     pub date: i64,
     pub path: String,
     pub status: String,
+    /// Previous path, for renames and copies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    #[serde(default, skip_serializing_if = "FileKind::is_text")]
+    pub kind: FileKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ConventionalEntry {
+    pub repo: String,
+    pub sha: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+    pub author: String,
+    pub date: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ConventionalSection {
+    pub cc_type: String,
+    pub commits: Vec<ConventionalEntry>,
+}
+
+/// Conventional Commits grouped changelog-style: breaking changes first,
+/// then one section per type.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ConventionalSummary {
+    pub total: usize,
+    /// True when more commits matched than the requested limit.
+    pub truncated: bool,
+    pub breaking: Vec<ConventionalEntry>,
+    pub sections: Vec<ConventionalSection>,
+}
+
+impl ConventionalSummary {
+    /// Section order for well-known types; others follow alphabetically.
+    pub const TYPE_ORDER: &'static [&'static str] = &[
+        "feat", "fix", "perf", "revert", "refactor", "docs", "style", "test", "build", "ci",
+        "chore",
+    ];
+
+    /// Groups `(type, entry)` pairs, keeping their order within each section.
+    pub fn group(entries: Vec<(String, ConventionalEntry)>, truncated: bool) -> Self {
+        let mut summary = ConventionalSummary {
+            total: entries.len(),
+            truncated,
+            ..Default::default()
+        };
+        for (cc_type, entry) in entries {
+            if entry.breaking {
+                summary.breaking.push(entry.clone());
+            }
+            match summary.sections.iter_mut().find(|s| s.cc_type == cc_type) {
+                Some(section) => section.commits.push(entry),
+                None => summary.sections.push(ConventionalSection {
+                    cc_type,
+                    commits: vec![entry],
+                }),
+            }
+        }
+        let rank = |t: &str| {
+            Self::TYPE_ORDER
+                .iter()
+                .position(|k| *k == t)
+                .unwrap_or(Self::TYPE_ORDER.len())
+        };
+        summary.sections.sort_by(|a, b| {
+            rank(&a.cc_type)
+                .cmp(&rank(&b.cc_type))
+                .then_with(|| a.cc_type.cmp(&b.cc_type))
+        });
+        summary
+    }
+}
+
+/// Commits reachable from `to` but not from `from`, newest first.
+#[derive(Debug, Clone, Default)]
+pub struct CommitRange {
+    pub from_sha: Option<String>,
+    pub to_sha: String,
+    pub shas: Vec<String>,
+}
+
+/// An indexed commit as needed to build a changelog.
+#[derive(Debug, Clone)]
+pub struct ChangelogCommit {
+    pub sha: String,
+    pub subject: String,
+    pub author: String,
+    pub date: i64,
+    pub conventional: Option<ConventionalCommit>,
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChangelogEntry {
+    pub sha: String,
+    pub subject: String,
+    pub author: String,
+    pub date: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cc_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    pub breaking: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChangelogGroup {
+    pub name: String,
+    pub commits: Vec<ChangelogEntry>,
+}
+
+/// Release notes for the commits between two refs of one repo.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Changelog {
+    pub repo: String,
+    pub from: Option<String>,
+    pub to: String,
+    pub from_sha: Option<String>,
+    pub to_sha: String,
+    pub group_by: String,
+    /// Indexed commits in the range.
+    pub total: usize,
+    /// Commits in the range that are not in the index (filtered out at
+    /// ingest, or not synced yet).
+    pub unindexed: usize,
+    pub breaking: Vec<ChangelogEntry>,
+    pub groups: Vec<ChangelogGroup>,
+}
+
+/// Consecutive lines that originate from the same commit.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BlameHunk {
+    pub sha: String,
+    /// First line of the hunk in the blamed revision, 1-based.
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Path in the originating commit, when the lines came from another file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orig_path: Option<String>,
+    /// First line of the hunk in the originating commit.
+    pub orig_start_line: usize,
+    pub author: String,
+    pub date: i64,
+    pub lines: Vec<String>,
+}
+
+/// Blame hunks for a line range as computed from git.
+#[derive(Debug, Clone, Default)]
+pub struct FileBlame {
+    pub rev_sha: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub hunks: Vec<BlameHunk>,
+}
+
+/// Blame for a line range, with the indexed details of each originating
+/// commit.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Blame {
+    pub repo: String,
+    pub path: String,
+    pub rev: String,
+    pub rev_sha: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub hunks: Vec<BlameHunk>,
+    /// Details by SHA. Commits that are not indexed (filtered out at ingest,
+    /// or not synced yet) are missing.
+    pub commits: std::collections::BTreeMap<String, CommitDetail>,
+}
+
+/// A file's contents, or part of them, at a commit.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct FileContent {
+    pub repo: String,
+    pub path: String,
+    pub rev: String,
+    /// The commit `rev` resolved to.
+    pub sha: String,
+    /// Size of the whole file in bytes.
+    pub size: usize,
+    pub binary: bool,
+    /// Lines in the whole file (0 for binary files).
+    pub total_lines: usize,
+    /// Lines returned, when read by line range.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<usize>,
+    /// Byte offset of `content` in the file.
+    pub offset: usize,
+    /// The requested part of the file as UTF-8 (invalid sequences are
+    /// replaced); `None` for binary files.
+    pub content: Option<String>,
+    /// Whether `max_bytes` cut the requested lines or bytes short.
+    pub truncated: bool,
+}
+
+impl FileContent {
+    pub const DEFAULT_MAX_BYTES: usize = 100_000;
+}
+
+/// One file's change in a `DiffResult`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DiffFileStat {
+    pub path: String,
+    /// `A`, `M`, `D`, `R` or `C`, as in `CommitFileDetail`.
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub additions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+}
+
+/// The changes between two revisions, computed from the local clone.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct DiffResult {
+    pub repo: String,
+    pub from: String,
+    pub to: String,
+    /// The commits `from` and `to` resolved to.
+    pub from_sha: String,
+    pub to_sha: String,
+    pub files: Vec<DiffFileStat>,
+    pub additions: usize,
+    pub deletions: usize,
+    /// Unified diff of every file in `files`; `None` in stat-only mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch_text: Option<String>,
+    /// Whether `patch_text` was cut at `max_bytes`.
+    pub truncated: bool,
+}
+
+/// One entry of a tree listing.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TreeEntry {
+    /// Path relative to the repo root.
+    pub path: String,
+    /// `blob`, `tree` or `commit` (a submodule).
+    pub kind: String,
+    /// Git file mode in octal, e.g. `100644`, `100755`, `120000`, `040000`.
+    pub mode: String,
+    /// Object id; for submodules, the pinned commit.
+    pub sha: String,
+    /// Blob size in bytes; `None` for trees and submodules.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// The entries of a directory at a commit, depth-first in path order.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct TreeListing {
+    pub repo: String,
+    pub rev: String,
+    /// The commit `rev` resolved to.
+    pub sha: String,
+    /// The listed directory (empty for the root).
+    pub path: String,
+    pub depth: usize,
+    pub entries: Vec<TreeEntry>,
+    /// Whether entries were dropped at `TreeListing::MAX_ENTRIES`.
+    pub truncated: bool,
+}
+
+impl TreeListing {
+    pub const DEFAULT_DEPTH: usize = 1;
+    pub const MAX_ENTRIES: usize = 5_000;
+}
+
+/// One commit's change to a symbol.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SymbolChange {
+    pub sha: String,
+    pub subject: String,
+    pub author: String,
+    pub date: i64,
+    pub path: String,
+    pub symbol: String,
+    pub kind: String,
+    pub excerpt: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RefResult {
+    pub repo: String,
+    pub sha: String,
+    pub subject: String,
+    pub author: String,
+    pub date: i64,
+    /// The matching reference as stored, e.g. `acme/api#1234`.
+    pub reference: String,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -301,6 +1360,65 @@ pub struct CommitDetail {
     pub author: String,
     pub date: String, // ISO 8601 UTC: "YYYY-MM-DDTHH:MM:SSZ"
     pub changed_files: Vec<CommitFileDetail>,
+    /// Trailer values by key, e.g. `{"Reviewed-by": ["Bob <bob@example.com>"]}`.
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub trailers: std::collections::BTreeMap<String, Vec<String>>,
+    /// Earliest tag (by tag date) that contains the commit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_release: Option<String>,
+    /// Every tag containing the commit, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained_in: Vec<String>,
+    /// Whether the stored patch was cut at ingest.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub patch_truncated: bool,
+    /// Size in bytes of the whole patch; `None` for commits without a patch
+    /// or indexed before sizes were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch_size: Option<u64>,
+    /// Submodule pointer changes, by path.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub submodules: Vec<CommitSubmoduleDetail>,
+    /// Repo projects the commit touches.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub projects: Vec<String>,
+}
+
+/// A submodule pointer change in `CommitDetail`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CommitSubmoduleDetail {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub old_sha: Option<String>,
+    pub new_sha: Option<String>,
+    /// The registered repo the submodule resolves to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Commits in `old_sha..new_sha` of `repo`, newest first, up to
+    /// `CommitSubmoduleDetail::MAX_COMMITS`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commits: Vec<SubmoduleCommit>,
+    /// Number of commits in the whole range.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_count: Option<usize>,
+    /// Why the range could not be listed, e.g. the submodule's clone does
+    /// not have `new_sha` yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_error: Option<String>,
+}
+
+impl CommitSubmoduleDetail {
+    pub const MAX_COMMITS: usize = 100;
+}
+
+/// A commit a submodule bump pulled in.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SubmoduleCommit {
+    pub sha: String,
+    /// `None` if the commit is not indexed (e.g. filtered out).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -308,6 +1426,12 @@ pub struct CommitFileDetail {
     pub path: String,
     pub status: String,
     pub old_path: Option<String>,
+    #[serde(default, skip_serializing_if = "FileKind::is_text")]
+    pub kind: FileKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lfs: Option<LfsPointer>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -315,14 +1439,120 @@ pub struct PatchResult {
     pub repo: String,
     pub sha: String,
     pub patch_text: String,
+    /// Whether the caller's `max_bytes` cut `patch_text` short.
+    #[serde(default)]
+    pub truncated: bool,
+    /// Whether the stored copy was cut at `max_patch_bytes` at ingest, as
+    /// `CommitDetail::patch_truncated`. False for a patch regenerated whole.
+    #[serde(default)]
+    pub stored_truncated: bool,
+    /// Size in bytes of the whole patch; `None` for patches indexed before
+    /// sizes were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchLineKind {
+    Context,
+    Add,
+    Delete,
+}
+
+/// One line of a hunk, with its line number on each side it appears on.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PatchLine {
+    pub kind: PatchLineKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_line: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_line: Option<usize>,
+    /// The line without its `+`/`-`/space marker or trailing newline.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PatchHunk {
+    /// The `@@ -a,b +c,d @@ context` line.
+    pub header: String,
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<PatchLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PatchFile {
+    pub path: String,
+    /// `A`, `M`, `D`, `R` or `C`, as in `CommitFileDetail`.
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub hunks: Vec<PatchHunk>,
+    /// Hunks left out because they did not fit in `max_bytes`.
+    pub omitted_hunks: usize,
+}
+
+/// A stored patch split into files, hunks and lines.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StructuredPatch {
+    pub repo: String,
+    pub sha: String,
+    pub files: Vec<PatchFile>,
+    /// Whether any file has omitted hunks, or the stored patch was cut at
+    /// ingest.
+    pub truncated: bool,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
 pub struct SyncSummary {
     pub commits_indexed: usize,
+    /// Walked commits that were already in the store. History hidden behind
+    /// a previously synced tip is not walked, so it is not counted.
     pub commits_already_indexed: usize,
     pub commits_filtered: usize,
     pub errors: Vec<String>,
+    /// True if the sync stopped early because cancellation was requested.
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+/// Snapshot of a running sync, passed to `SyncControl::on_progress`.
+#[derive(Debug, Clone, Default)]
+pub struct SyncProgress {
+    pub commits_walked: usize,
+    pub commits_total: usize,
+    pub commits_indexed: usize,
+    pub current_sha: Option<String>,
+    /// Estimated seconds remaining, once enough commits have been walked.
+    pub eta_secs: Option<u64>,
+}
+
+/// Caller-side hooks for a long-running sync: progress reporting,
+/// cooperative cancellation and full re-indexing. `SyncControl::default()`
+/// does none of these.
+#[derive(Clone, Copy, Default)]
+pub struct SyncControl<'a> {
+    pub on_progress: Option<&'a (dyn Fn(&SyncProgress) + Sync)>,
+    pub cancel: Option<&'a AtomicBool>,
+    /// Walk the full history and re-index every commit, rewriting what was
+    /// derived from it at ingest (files, patches, symbols, references,
+    /// submodule bumps) by an older version or with other settings.
+    pub full: bool,
+}
+
+impl SyncControl<'_> {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+
+    pub fn report(&self, progress: &SyncProgress) {
+        if let Some(f) = self.on_progress {
+            f(progress);
+        }
+    }
 }
 
 // ── Config ────────────────────────────────────────────────────────────────
@@ -330,7 +1560,38 @@ pub struct SyncSummary {
 #[derive(Debug, Clone)]
 pub struct IgnoreConfig {
     pub path_prefixes: Vec<String>,
+    /// If non-empty, only paths under one of these prefixes are indexed.
+    pub include_prefixes: Vec<String>,
     pub max_patch_bytes: usize,
+    /// Minimum similarity, in percent, for a deleted and an added file to be
+    /// recorded as a rename (or a modified and an added one as a copy).
+    /// 0 turns detection off.
+    pub rename_threshold: u16,
+    /// The repo's projects, used to tag changed files.
+    pub projects: Vec<RepoProject>,
+}
+
+impl IgnoreConfig {
+    /// `config` key overriding `rename_threshold` for every sync.
+    pub const RENAME_THRESHOLD_KEY: &'static str = "ingest.rename_threshold";
+
+    /// This config with the repo's persisted exclude prefixes and include
+    /// paths merged in.
+    pub fn for_repo(&self, repo: &Repo) -> IgnoreConfig {
+        let mut config = self.clone();
+        for p in &repo.exclude_prefixes {
+            if !config.path_prefixes.contains(p) {
+                config.path_prefixes.push(p.clone());
+            }
+        }
+        for p in &repo.include_paths {
+            if !config.include_prefixes.contains(p) {
+                config.include_prefixes.push(p.clone());
+            }
+        }
+        config.projects = repo.projects.clone();
+        config
+    }
 }
 
 impl Default for IgnoreConfig {
@@ -342,7 +1603,10 @@ impl Default for IgnoreConfig {
                 "dist/".into(),
                 ".git/".into(),
             ],
+            include_prefixes: vec![],
             max_patch_bytes: 1_048_576,
+            rename_threshold: 50,
+            projects: vec![],
         }
     }
 }
@@ -356,6 +1620,18 @@ pub struct RepoStats {
     pub last_synced_at: Option<i64>,
     pub last_synced_sha: Option<String>,
     pub last_error: Option<String>,
+    /// One entry per project defined on the repo.
+    pub projects: Vec<ProjectStats>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProjectStats {
+    pub name: String,
+    /// A project may span several prefixes.
+    pub prefixes: Vec<String>,
+    pub commit_count: usize,
+    /// Author time of the newest commit touching the project.
+    pub last_commit_at: Option<i64>,
 }
 
 // ── Core traits ───────────────────────────────────────────────────────────
@@ -373,13 +1649,45 @@ pub trait Store: Send + Sync {
     fn upsert_commit(&self, commit: &Commit) -> Result<()>;
     fn upsert_commit_files(&self, files: &[CommitFile]) -> Result<()>;
     fn upsert_patch(&self, patch: &CommitPatch) -> Result<()>;
+    /// Writes every commit in `batch` (with its files and patch) in one
+    /// transaction. Equivalent to the three upserts above, per commit.
+    fn write_batch(&self, batch: &WriteBatch) -> Result<()>;
     fn get_ingest_state(&self, repo_id: i64) -> Result<Option<IngestState>>;
     fn update_ingest_state(&self, state: &IngestState) -> Result<()>;
     fn commit_exists(&self, repo_id: i64, sha: &str) -> Result<bool>;
+    fn list_tags(&self, repo_id: i64) -> Result<Vec<Tag>>;
+    /// Replaces all of the repo's tags and their containment in one
+    /// transaction.
+    fn replace_tags(&self, repo_id: i64, index: &TagIndex) -> Result<()>;
+    /// Re-extracts the references of every indexed commit of the repo from
+    /// its stored message with `extract`, replacing the repo's `commit_refs`
+    /// rows in one transaction. Returns the number of commits with references.
+    fn reextract_refs(
+        &self,
+        repo_id: i64,
+        extract: &dyn Fn(&str) -> Vec<CommitRef>,
+    ) -> Result<usize>;
 
     // MCP queries
     fn search(&self, query: &str, opts: &SearchOpts) -> Result<Vec<SearchResult>>;
     fn touches(&self, path_glob: &str, opts: &TouchOpts) -> Result<Vec<TouchResult>>;
+    /// Commits in any repo whose message mentions `reference` (see
+    /// [`CommitRef::parse`]), newest first.
+    fn find_by_ref(&self, reference: &str, opts: &RefOpts) -> Result<Vec<RefResult>>;
+    /// Conventional Commits matching `opts`, newest first, grouped by type.
+    fn conventional_summary(&self, opts: &ConventionalSummaryOpts) -> Result<ConventionalSummary>;
+    /// Indexed commits among `shas`, in the given order; unknown SHAs are
+    /// skipped.
+    fn changelog_commits(&self, repo_id: i64, shas: &[String]) -> Result<Vec<ChangelogCommit>>;
+    /// Commits that changed a function or type named `symbol`, newest
+    /// first. `symbol` is a bare name or a qualified one such as
+    /// `Parser::parse` or `Parser.parse`.
+    fn symbol_history(
+        &self,
+        repo_id: i64,
+        symbol: &str,
+        opts: &SymbolOpts,
+    ) -> Result<Vec<SymbolChange>>;
     /// sha_prefix: exact SHA or a unique hex prefix (>=4 chars recommended)
     fn get_commit(&self, repo_name: &str, sha_prefix: &str) -> Result<Option<CommitDetail>>;
     fn get_patch(
@@ -395,6 +1703,13 @@ pub trait Store: Send + Sync {
     fn count_embeddings_for_repo(&self, repo_id: i64) -> Result<usize>;
     fn delete_embeddings_for_repo(&self, repo_id: i64) -> Result<()>;
 
+    // Identities
+    /// Adds or replaces the mapping for `alias.alias_email`.
+    fn add_identity(&self, alias: &IdentityAlias) -> Result<()>;
+    fn list_identities(&self) -> Result<Vec<IdentityAlias>>;
+    /// Errors with `NotFound` if no mapping exists for `alias_email`.
+    fn remove_identity(&self, alias_email: &str) -> Result<()>;
+
     // Embedding support
     fn get_config(&self, key: &str) -> Result<Option<String>>;
     fn set_config(&self, key: &str, value: &str) -> Result<()>;
@@ -441,7 +1756,47 @@ pub trait Ingester: Send + Sync {
         repo: &Repo,
         store: &dyn Store,
         config: &IgnoreConfig,
+    ) -> Result<SyncSummary> {
+        self.sync_repo_with(repo, store, config, &SyncControl::default())
+    }
+
+    /// Like `sync_repo`, but reports progress and stops early (after saving a
+    /// checkpoint in `ingest_state`) once `control` signals cancellation.
+    fn sync_repo_with(
+        &self,
+        repo: &Repo,
+        store: &dyn Store,
+        config: &IgnoreConfig,
+        control: &SyncControl<'_>,
     ) -> Result<SyncSummary>;
+
+    /// Resolves `from` and `to` (branches, tags or SHAs) in the repo and
+    /// lists the commits in `from..to`.
+    fn commit_range(
+        &self,
+        repo: &Repo,
+        from: Option<&str>,
+        to: &str,
+        first_parent: bool,
+    ) -> Result<CommitRange>;
+
+    /// Blames `opts.path` at `opts.rev` over the requested line range.
+    fn blame(&self, repo: &Repo, opts: &BlameOpts) -> Result<FileBlame>;
+
+    /// Reads `opts.path` as of `opts.rev` from the repo's local clone.
+    fn read_file(&self, repo: &Repo, opts: &FileOpts) -> Result<FileContent>;
+
+    /// Lists the tree at `opts.rev` under `opts.path` from the repo's local clone.
+    fn list_tree(&self, repo: &Repo, opts: &TreeOpts) -> Result<TreeListing>;
+
+    /// Diffs `opts.from` against `opts.to` in the repo's local clone,
+    /// leaving out paths `config` ignores.
+    fn diff(&self, repo: &Repo, opts: &DiffOpts, config: &IgnoreConfig) -> Result<DiffResult>;
+
+    /// Regenerates the whole patch of commit `sha` from the repo's local
+    /// clone, leaving out paths `config` ignores but not cutting it at
+    /// `config.max_patch_bytes`.
+    fn patch(&self, repo: &Repo, sha: &str, config: &IgnoreConfig) -> Result<PatchResult>;
 }
 
 // ── Tests ─────────────────────────────────────────────────────────────────
@@ -460,9 +1815,16 @@ mod tests {
             remote_url: Some("https://github.com/user/myrepo".into()),
             default_branch: Some("main".into()),
             fork_of: None,
-            author_filter: None,
+            author_filters: vec![],
+            include_paths: vec![],
+            ref_patterns: vec![],
+            projects: vec![],
             exclude_prefixes: vec![],
             embed_enabled: false,
+            clone_depth: None,
+            clone_since: None,
+            ssh_key: None,
+            token_env: None,
         };
         assert_eq!(repo.name, "myrepo");
 
@@ -473,11 +1835,16 @@ mod tests {
             author_email: "alice@example.com".into(),
             committer_name: "Alice".into(),
             committer_email: "alice@example.com".into(),
+            canonical_author_name: "Alice".into(),
+            canonical_author_email: "alice@example.com".into(),
             author_time: 1700000000,
             commit_time: 1700000000,
             subject: "Initial commit".into(),
             body: None,
             parent_count: 0,
+            trailers: vec![],
+            refs: vec![],
+            conventional: None,
         };
         assert_eq!(commit.sha, "abc123");
 
@@ -487,6 +1854,10 @@ mod tests {
             path: "src/main.rs".into(),
             status: FileStatus::Added,
             old_path: None,
+            kind: FileKind::Text,
+            size: None,
+            lfs: None,
+            project: None,
         };
         assert_eq!(file.status, FileStatus::Added);
         assert_eq!(file.status.as_str(), "A");
@@ -496,6 +1867,8 @@ mod tests {
             sha: "abc123".into(),
             patch_blob: vec![0u8; 10],
             patch_preview: "--- a/src/main.rs\n+++ b/src/main.rs".into(),
+            truncated: false,
+            original_size: 10,
         };
         assert!(!patch.patch_blob.is_empty());
 
@@ -504,6 +1877,9 @@ mod tests {
             last_synced_at: 1700000000,
             last_synced_sha: Some("abc123".into()),
             last_error: None,
+            checkpoint_sha: None,
+            indexed_tips: vec!["abc123".into()],
+            config_hash: None,
         };
         assert!(state.last_error.is_none());
 
@@ -514,6 +1890,7 @@ mod tests {
         let config = IgnoreConfig::default();
         assert!(config.path_prefixes.contains(&"node_modules/".to_string()));
         assert_eq!(config.max_patch_bytes, 1_048_576);
+        assert_eq!(config.rename_threshold, 50);
     }
 
     #[test]
@@ -533,12 +1910,20 @@ mod tests {
             remote_url: None,
             default_branch: None,
             fork_of: None,
-            author_filter: None,
+            author_filters: vec![],
+            include_paths: vec![],
+            ref_patterns: vec![],
+            projects: vec![],
             exclude_prefixes: vec![],
             embed_enabled: false,
+            clone_depth: None,
+            clone_since: None,
+            ssh_key: None,
+            token_env: None,
         };
         assert!(repo.fork_of.is_none());
-        assert!(repo.author_filter.is_none());
+        assert!(repo.author_filters.is_empty());
+        assert!(repo.include_paths.is_empty());
         assert!(repo.exclude_prefixes.is_empty());
     }
 
@@ -546,7 +1931,9 @@ mod tests {
     fn test_repo_update_type() {
         let default_update = RepoUpdate::default();
         assert!(default_update.fork_of.is_none());
-        assert!(default_update.author_filter.is_none());
+        assert!(default_update.add_authors.is_empty());
+        assert!(default_update.remove_authors.is_empty());
+        assert!(default_update.include_paths.is_none());
         assert!(default_update.exclude_prefixes.is_none());
         assert!(default_update.default_branch.is_none());
 
@@ -560,6 +1947,153 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_author_matcher_parse() {
+        assert_eq!(
+            AuthorMatcher::parse("alice@example.com"),
+            AuthorMatcher::Email("alice@example.com".into())
+        );
+        assert_eq!(
+            AuthorMatcher::parse("Alice Smith"),
+            AuthorMatcher::Name("Alice Smith".into())
+        );
+        assert_eq!(
+            AuthorMatcher::parse("name:a@b"),
+            AuthorMatcher::Name("a@b".into())
+        );
+        let re = AuthorMatcher::parse("re:alice.*@users\\.noreply");
+        assert_eq!(re.kind(), "regex");
+        assert_eq!(re.pattern(), "alice.*@users\\.noreply");
+        assert_eq!(
+            AuthorMatcher::Email("alice".into()).to_string(),
+            "email:alice"
+        );
+        for m in [
+            AuthorMatcher::parse("alice@example.com"),
+            AuthorMatcher::parse("name:Alice"),
+            AuthorMatcher::parse("email:alice"),
+            AuthorMatcher::parse("Alice"),
+            re,
+        ] {
+            assert_eq!(AuthorMatcher::parse(&m.to_string()), m);
+            assert_eq!(AuthorMatcher::from_parts(m.kind(), m.pattern()), Some(m));
+        }
+    }
+
+    #[test]
+    fn test_ref_pattern_parse() {
+        assert_eq!(RefPattern::parse("GitHub"), Some(RefPattern::Github));
+        assert_eq!(
+            RefPattern::parse("jira:proj, ops"),
+            Some(RefPattern::Jira(vec!["PROJ".into(), "OPS".into()]))
+        );
+        assert_eq!(RefPattern::parse("bitbucket"), None);
+        for p in [
+            RefPattern::Gitlab,
+            RefPattern::Jira(vec![]),
+            RefPattern::Jira(vec!["PROJ".into()]),
+            RefPattern::Regex(r"TICKET-(\d+)".into()),
+        ] {
+            assert_eq!(RefPattern::parse(&p.to_string()), Some(p));
+        }
+    }
+
+    #[test]
+    fn test_repo_project_parse_and_for_path() {
+        let project = RepoProject::parse(" web = apps/web/ ").expect("valid");
+        assert_eq!(project.name, "web");
+        assert_eq!(project.prefix, "apps/web/");
+        assert_eq!(RepoProject::parse(&project.to_string()), Some(project));
+        assert_eq!(RepoProject::parse("web"), None);
+        assert_eq!(RepoProject::parse("=apps/web/"), None);
+        assert_eq!(RepoProject::parse("web="), None);
+
+        let projects = vec![
+            RepoProject::parse("apps=apps/").unwrap(),
+            RepoProject::parse("web=apps/web/").unwrap(),
+        ];
+        let name = |path: &str| RepoProject::for_path(&projects, path).map(|p| p.name.as_str());
+        assert_eq!(name("apps/web/index.ts"), Some("web"));
+        assert_eq!(name("apps/cli/main.rs"), Some("apps"));
+        assert_eq!(name("docs/README.md"), None);
+    }
+
+    #[test]
+    fn test_commit_ref_parse() {
+        let r = CommitRef::parse("Acme/API#1234");
+        assert_eq!(r.kind, RefKind::Issue);
+        assert_eq!(r.project.as_deref(), Some("acme/api"));
+        assert_eq!(r.to_string(), "acme/api#1234");
+        assert_eq!(CommitRef::parse("#12").to_string(), "#12");
+        assert_eq!(CommitRef::parse("12").kind, RefKind::Issue);
+        assert_eq!(CommitRef::parse("!7").kind, RefKind::MergeRequest);
+        let jira = CommitRef::parse("proj-881");
+        assert_eq!(jira.kind, RefKind::Jira);
+        assert_eq!(jira.id, "PROJ-881");
+        assert_eq!(CommitRef::parse("wontfix").kind, RefKind::Custom);
+    }
+
+    #[test]
+    fn test_changelog_parse_range() {
+        assert_eq!(
+            ChangelogOpts::parse_range("v1.4.0..v1.5.0").unwrap(),
+            (Some("v1.4.0".to_string()), "v1.5.0".to_string())
+        );
+        assert_eq!(
+            ChangelogOpts::parse_range("..main").unwrap(),
+            (None, "main".to_string())
+        );
+        assert_eq!(ChangelogOpts::parse_range("HEAD").unwrap().1, "HEAD");
+        assert!(ChangelogOpts::parse_range("v1..").is_err());
+        assert!(ChangelogOpts::parse_range("v1...v2").is_err());
+    }
+
+    #[test]
+    fn test_conventional_commit_parse() {
+        let cc = ConventionalCommit::parse("feat(api)!: drop v1 endpoints", None).expect("cc");
+        assert_eq!(cc.cc_type, "feat");
+        assert_eq!(cc.scope.as_deref(), Some("api"));
+        assert!(cc.breaking);
+        assert_eq!(cc.description, "drop v1 endpoints");
+
+        let cc = ConventionalCommit::parse(
+            "Fix: handle empty input",
+            Some("Details.\n\nBREAKING CHANGE: errors are now returned"),
+        )
+        .expect("cc");
+        assert_eq!(cc.cc_type, "fix");
+        assert!(cc.scope.is_none());
+        assert!(cc.breaking);
+
+        for subject in [
+            "Merge branch 'main'",
+            "Revert \"feat: x\"",
+            "feat():x",
+            "feat: ",
+            "see http://example.com",
+            "wip(core: broken",
+        ] {
+            assert!(
+                ConventionalCommit::parse(subject, None).is_none(),
+                "{subject}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_trailer_identity() {
+        let t = Trailer {
+            key: "Co-authored-by".into(),
+            value: "Bob Jones <bob@example.com>".into(),
+        };
+        assert_eq!(t.identity(), Some(("Bob Jones", "bob@example.com")));
+        let t = Trailer {
+            key: "Fixes".into(),
+            value: "#123".into(),
+        };
+        assert_eq!(t.identity(), None);
+    }
+
     #[test]
     fn test_repo_list_entry_serializes() {
         let entry = RepoListEntry {
@@ -0,0 +1,205 @@
+//! Repo discovery for `add-repos --scan`: find every git repository under a
+//! directory and give each a unique name.
+//!
+//! The walk stops at the first repository on each path, so a repo's own
+//! submodules, vendored checkouts and `.git` directory are never visited.
+//! Symlinks are not followed.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// How a discovered repository is laid out on disk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepoKind {
+    /// A working tree with its own `.git` directory.
+    Normal,
+    /// A linked worktree (`git worktree add`), sharing another repo's history.
+    Worktree,
+    /// A bare repository.
+    Bare,
+}
+
+impl RepoKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            RepoKind::Normal => "repo",
+            RepoKind::Worktree => "worktree",
+            RepoKind::Bare => "bare repo",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FoundRepo {
+    /// Canonical path, as it would be registered.
+    pub path: PathBuf,
+    pub kind: RepoKind,
+    /// Shared git directory: the same for a repo and all its worktrees.
+    pub common_dir: PathBuf,
+}
+
+/// Finds the git repositories at most `max_depth` directory levels below
+/// `root` (0 checks only `root` itself), in path order.
+pub fn scan(root: &Path, max_depth: usize) -> Vec<FoundRepo> {
+    let mut found = Vec::new();
+    visit(root, max_depth, &mut found);
+    found
+}
+
+fn visit(dir: &Path, depth_left: usize, found: &mut Vec<FoundRepo>) {
+    if let Some(repo) = open_repo(dir) {
+        found.push(repo);
+        return;
+    }
+    if depth_left == 0 {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut subdirs: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|e| e.path())
+        .collect();
+    subdirs.sort();
+    for subdir in subdirs {
+        visit(&subdir, depth_left - 1, found);
+    }
+}
+
+/// Opens `dir` if it is the top of a working tree or a bare repository.
+/// Directories inside a working tree are not repos of their own.
+fn open_repo(dir: &Path) -> Option<FoundRepo> {
+    let repo = if dir.join(".git").exists() {
+        git2::Repository::open(dir).ok()?
+    } else if dir.join("HEAD").is_file() && dir.join("objects").is_dir() {
+        git2::Repository::open_bare(dir).ok()?
+    } else {
+        return None;
+    };
+    let kind = if repo.is_bare() {
+        RepoKind::Bare
+    } else if repo.is_worktree() {
+        RepoKind::Worktree
+    } else {
+        RepoKind::Normal
+    };
+    let git_dir = repo.path().canonicalize().ok()?;
+    Some(FoundRepo {
+        path: dir.canonicalize().ok()?,
+        kind,
+        common_dir: crate::watch::common_dir(&git_dir),
+    })
+}
+
+/// The name a repo gets on its own: its directory name, without the `.git`
+/// suffix bare repositories usually carry.
+fn base_name(path: &Path) -> String {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("repo");
+    name.strip_suffix(".git")
+        .filter(|n| !n.is_empty())
+        .unwrap_or(name)
+        .to_string()
+}
+
+/// Derives a name for each path, unique among themselves and not in `taken`.
+/// Paths sharing a directory name are qualified with their parent directory
+/// (`work-api`, `oss-api`); anything still clashing gets a numeric suffix.
+pub fn derive_names(paths: &[&Path], taken: &HashSet<String>) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for path in paths {
+        *counts.entry(base_name(path)).or_default() += 1;
+    }
+    let mut used = taken.clone();
+    paths
+        .iter()
+        .map(|path| {
+            let base = base_name(path);
+            let candidate = if counts[&base] > 1 || used.contains(&base) {
+                match path.parent().and_then(|p| p.file_name()) {
+                    Some(parent) => format!("{}-{}", parent.to_string_lossy(), base),
+                    None => base,
+                }
+            } else {
+                base
+            };
+            let mut name = candidate.clone();
+            let mut n = 2;
+            while used.contains(&name) {
+                name = format!("{}-{}", candidate, n);
+                n += 1;
+            }
+            used.insert(name.clone());
+            name
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init(path: &Path) -> git2::Repository {
+        let repo = git2::Repository::init(path).unwrap();
+        let sig = git2::Signature::now("Test User", "test@example.com").unwrap();
+        let tree = repo.treebuilder(None).unwrap().write().unwrap();
+        let tree = repo.find_tree(tree).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+            .unwrap();
+        drop(tree);
+        repo
+    }
+
+    #[test]
+    fn test_scan_finds_repos_worktrees_and_bare() {
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path().canonicalize().unwrap();
+        let app = init(&root_path.join("work/app"));
+        init(&root_path.join("work/app/vendor/lib"));
+        git2::Repository::init_bare(root_path.join("mirrors/tools.git")).unwrap();
+        app.worktree("app-feature", &root_path.join("work/app-feature"), None)
+            .unwrap();
+        init(&root_path.join("a/b/c/deep"));
+        std::fs::create_dir_all(root_path.join("notes")).unwrap();
+
+        let found = scan(&root_path, 3);
+        let paths: Vec<(PathBuf, RepoKind)> =
+            found.iter().map(|r| (r.path.clone(), r.kind)).collect();
+        assert_eq!(
+            paths,
+            vec![
+                (root_path.join("mirrors/tools.git"), RepoKind::Bare),
+                (root_path.join("work/app"), RepoKind::Normal),
+                (root_path.join("work/app-feature"), RepoKind::Worktree),
+            ]
+        );
+        // A worktree shares its main repository's git directory.
+        assert_eq!(found[1].common_dir, found[2].common_dir);
+
+        assert_eq!(scan(&root_path, 4).len(), 4);
+        assert_eq!(scan(&root_path.join("work/app"), 0).len(), 1);
+    }
+
+    #[test]
+    fn test_derive_names() {
+        let paths = [
+            Path::new("/src/work/api"),
+            Path::new("/src/oss/api"),
+            Path::new("/src/mirrors/tools.git"),
+            Path::new("/src/web"),
+            Path::new("/src/old/web"),
+        ];
+        let taken: HashSet<String> = ["tools".to_string(), "old-web".to_string()].into();
+        assert_eq!(
+            derive_names(&paths, &taken),
+            vec![
+                "work-api",
+                "oss-api",
+                "mirrors-tools",
+                "src-web",
+                "old-web-2"
+            ]
+        );
+    }
+}
@@ -1,13 +1,24 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use commitmux_embed::EmbedConfig;
-use commitmux_ingest::Git2Ingester;
+use commitmux_ingest::{AuthorFilter, Git2Ingester, RefExtractor};
+use commitmux_mcp::service;
 use commitmux_store::SqliteStore;
-use commitmux_types::{IgnoreConfig, Ingester, RepoInput, RepoUpdate, Store};
+use commitmux_types::{
+    AuthorMatcher, BlameOpts, ChangelogGroupBy, ChangelogOpts, DiffOpts, FileOpts, IdentityAlias,
+    IgnoreConfig, Ingester, RefOpts, RefPattern, RemoteAuth, RepoInput, RepoProject, RepoUpdate,
+    Store, SymbolOpts, SyncControl, SyncProgress, TreeOpts,
+};
+
+mod discover;
+mod watch;
 
 #[derive(Parser)]
 #[command(
@@ -62,14 +73,111 @@ enum Commands {
         fork_of: Option<String>,
         #[arg(
             long = "author",
-            help = "Only index commits by this author (email match)"
+            help = "Only index commits whose author or committer matches (repeatable): an email, name:<name> or re:<regex>"
+        )]
+        author: Vec<String>,
+        #[arg(
+            long = "include",
+            help = "Only index changes under this path prefix (repeatable)"
+        )]
+        include: Vec<String>,
+        #[arg(
+            long = "ref-pattern",
+            help = "Issue reference syntax to extract (repeatable): github, gitlab, jira:KEY1,KEY2 or re:<regex> (default: github, gitlab)"
+        )]
+        ref_pattern: Vec<String>,
+        #[arg(
+            long = "project",
+            help = "Monorepo sub-project as NAME=PREFIX (repeatable); changed files are tagged with the project of the longest matching prefix"
         )]
-        author: Option<String>,
+        project: Vec<String>,
         #[arg(
             long = "embed",
             help = "Enable semantic embeddings for this repo. Requires: 1) Ollama running, 2) embed.model configured (see: commitmux config --help)"
         )]
         embed: bool,
+        #[arg(
+            long,
+            requires = "url",
+            conflicts_with = "path",
+            help = "Shallow clone: fetch only this many commits from each branch tip"
+        )]
+        depth: Option<u32>,
+        #[arg(
+            long,
+            requires = "url",
+            conflicts_with = "path",
+            value_name = "DATE",
+            help = "Only fetch and index history since this date (YYYY-MM-DD or unix time)"
+        )]
+        since: Option<String>,
+        #[arg(
+            long = "ssh-key",
+            value_name = "PATH",
+            help = "Private key file for SSH remotes, tried before the SSH agent"
+        )]
+        ssh_key: Option<PathBuf>,
+        #[arg(
+            long = "token-env",
+            value_name = "VAR",
+            help = "Environment variable holding an access token for HTTPS remotes"
+        )]
+        token_env: Option<String>,
+    },
+    #[command(about = "Add every git repository found under a directory")]
+    AddRepos {
+        #[arg(
+            long,
+            value_name = "DIR",
+            help = "Directory to search for repositories, including worktrees and bare repos"
+        )]
+        scan: PathBuf,
+        #[arg(
+            long,
+            default_value_t = 3,
+            help = "How many directory levels below DIR to search"
+        )]
+        depth: usize,
+        #[arg(
+            long = "dry-run",
+            help = "Print what would be added and removed, and change nothing"
+        )]
+        dry_run: bool,
+        #[arg(
+            long,
+            help = "Also remove registered repos under DIR whose local path no longer exists"
+        )]
+        prune: bool,
+        #[arg(
+            long = "exclude",
+            help = "Path prefix to exclude from indexing, for every added repo (repeatable)"
+        )]
+        exclude: Vec<String>,
+        #[arg(
+            long = "include",
+            help = "Only index changes under this path prefix, for every added repo (repeatable)"
+        )]
+        include: Vec<String>,
+        #[arg(
+            long = "author",
+            help = "Only index commits whose author or committer matches (repeatable): an email, name:<name> or re:<regex>"
+        )]
+        author: Vec<String>,
+        #[arg(
+            long = "ref-pattern",
+            help = "Issue reference syntax to extract (repeatable): github, gitlab, jira:KEY1,KEY2 or re:<regex> (default: github, gitlab)"
+        )]
+        ref_pattern: Vec<String>,
+        #[arg(
+            long = "embed",
+            help = "Enable semantic embeddings for every added repo"
+        )]
+        embed: bool,
+        #[arg(
+            long,
+            help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
+        )]
+        db: Option<PathBuf>,
     },
     #[command(about = "Remove a repository and all its indexed commits")]
     RemoveRepo {
@@ -92,9 +200,29 @@ enum Commands {
         fork_of: Option<String>,
         #[arg(
             long = "author",
-            help = "Only index commits by this author (email match)"
+            help = "Add an author/committer matcher (repeatable): an email, name:<name> or re:<regex>"
+        )]
+        author: Vec<String>,
+        #[arg(
+            long = "remove-author",
+            help = "Remove a previously added author matcher (repeatable)"
+        )]
+        remove_author: Vec<String>,
+        #[arg(
+            long = "include",
+            help = "Only index changes under this path prefix (repeatable; replaces existing include paths)"
         )]
-        author: Option<String>,
+        include: Vec<String>,
+        #[arg(
+            long = "ref-pattern",
+            help = "Issue reference syntax to extract (repeatable; replaces existing patterns): github, gitlab, jira:KEY1,KEY2 or re:<regex>"
+        )]
+        ref_pattern: Vec<String>,
+        #[arg(
+            long = "project",
+            help = "Monorepo sub-project as NAME=PREFIX (repeatable; replaces existing projects and re-tags indexed commits)"
+        )]
+        project: Vec<String>,
         #[arg(
             long = "exclude",
             help = "Path prefix to exclude from indexing (repeatable)"
@@ -119,6 +247,45 @@ enum Commands {
             help = "Disable semantic embeddings for this repo"
         )]
         no_embed: bool,
+        #[arg(
+            long,
+            conflicts_with = "full_history",
+            help = "Managed clones: fetch only this many commits from each branch tip"
+        )]
+        depth: Option<u32>,
+        #[arg(
+            long,
+            conflicts_with = "full_history",
+            value_name = "DATE",
+            help = "Managed clones: only fetch and index history since this date (YYYY-MM-DD or unix time)"
+        )]
+        since: Option<String>,
+        #[arg(
+            long = "full-history",
+            help = "Managed clones: drop the depth and date limits and fetch the full history at the next sync"
+        )]
+        full_history: bool,
+        #[arg(
+            long = "ssh-key",
+            value_name = "PATH",
+            conflicts_with = "no_ssh_key",
+            help = "Private key file for SSH remotes, tried before the SSH agent"
+        )]
+        ssh_key: Option<PathBuf>,
+        #[arg(long = "no-ssh-key", help = "Stop using the repo's SSH key file")]
+        no_ssh_key: bool,
+        #[arg(
+            long = "token-env",
+            value_name = "VAR",
+            conflicts_with = "no_token_env",
+            help = "Environment variable holding an access token for HTTPS remotes"
+        )]
+        token_env: Option<String>,
+        #[arg(
+            long = "no-token-env",
+            help = "Stop using the repo's token variable (the auth.token_env config applies again)"
+        )]
+        no_token_env: bool,
     },
     #[command(about = "Index new commits from one or all repositories")]
     Sync {
@@ -134,6 +301,12 @@ enum Commands {
             help = "Generate embeddings for already-indexed commits; skip indexing new commits. Useful for backfilling when embeddings were enabled after initial sync."
         )]
         embed_only: bool,
+        #[arg(
+            long,
+            conflicts_with = "embed_only",
+            help = "Re-index every commit, rewriting files, patches, symbols, references and submodule bumps indexed by an older version"
+        )]
+        full: bool,
     },
     #[command(about = "Show full details for a specific commit (JSON output)")]
     Show {
@@ -147,8 +320,169 @@ enum Commands {
         )]
         db: Option<PathBuf>,
     },
+    #[command(about = "List commits in any repo that mention an issue or PR reference")]
+    Refs {
+        #[arg(help = "Reference, e.g. '#1234', 'acme/api#1234', '!45' or 'PROJ-881'")]
+        reference: String,
+        #[arg(long = "repo", help = "Only search this repo (repeatable)")]
+        repo: Vec<String>,
+        #[arg(long, help = "Maximum number of commits to list (default: 50)")]
+        limit: Option<usize>,
+        #[arg(long, help = "Print results as JSON")]
+        json: bool,
+        #[arg(
+            long,
+            help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
+        )]
+        db: Option<PathBuf>,
+    },
+    #[command(about = "List the commits that changed a function, method or type")]
+    SymbolHistory {
+        #[arg(help = "Name of the repo")]
+        repo: String,
+        #[arg(help = "Symbol name, e.g. 'parse' or 'Parser::parse'")]
+        symbol: String,
+        #[arg(
+            long,
+            help = "Only changes in this file, or in files under this directory"
+        )]
+        path: Option<String>,
+        #[arg(long, help = "Maximum number of changes to list (default: 50)")]
+        limit: Option<usize>,
+        #[arg(long, help = "Print results as JSON")]
+        json: bool,
+        #[arg(
+            long,
+            help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
+        )]
+        db: Option<PathBuf>,
+    },
+    #[command(about = "Show the commit that last changed each line of a file")]
+    Blame {
+        #[arg(help = "Name of the indexed repository")]
+        repo: String,
+        #[arg(help = "File path relative to the repository root")]
+        path: String,
+        #[arg(
+            short = 'L',
+            value_name = "START[,END]",
+            help = "Only blame this line range (1-based, inclusive)"
+        )]
+        lines: Option<String>,
+        #[arg(
+            long,
+            help = "Revision to blame at: branch, tag or SHA (default: HEAD)"
+        )]
+        rev: Option<String>,
+        #[arg(long, help = "Print the blame as JSON, with details of each commit")]
+        json: bool,
+        #[arg(
+            long,
+            help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
+        )]
+        db: Option<PathBuf>,
+    },
+    #[command(about = "Print a file as it was at a commit")]
+    Cat {
+        #[arg(help = "Name of the indexed repository")]
+        repo: String,
+        #[arg(help = "Commit to read at: a SHA, or any branch or tag")]
+        sha: String,
+        #[arg(help = "File path relative to the repository root")]
+        path: String,
+        #[arg(
+            short = 'L',
+            value_name = "START[,END]",
+            conflicts_with = "offset",
+            help = "Only print this line range (1-based, inclusive)"
+        )]
+        lines: Option<String>,
+        #[arg(long, help = "Byte offset to start printing at")]
+        offset: Option<usize>,
+        #[arg(long, help = "Print at most this many bytes (default: 100000)")]
+        max_bytes: Option<usize>,
+        #[arg(long, help = "Print the content and file metadata as JSON")]
+        json: bool,
+        #[arg(
+            long,
+            help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
+        )]
+        db: Option<PathBuf>,
+    },
+    #[command(about = "Show the changes between two revisions")]
+    Diff {
+        #[arg(help = "Name of the indexed repository")]
+        repo: String,
+        #[arg(help = "Base revision: branch, tag or SHA")]
+        from: String,
+        #[arg(help = "Target revision: branch, tag or SHA")]
+        to: String,
+        #[arg(help = "Only show files at or under these paths")]
+        paths: Vec<String>,
+        #[arg(long, help = "Show per-file line counts instead of the patch")]
+        stat: bool,
+        #[arg(long, help = "Truncate the patch to this many bytes")]
+        max_bytes: Option<usize>,
+        #[arg(long, help = "Print the diff as JSON")]
+        json: bool,
+        #[arg(
+            long,
+            help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
+        )]
+        db: Option<PathBuf>,
+    },
+    #[command(about = "List the files and directories of a repo at a commit")]
+    LsTree {
+        #[arg(help = "Name of the indexed repository")]
+        repo: String,
+        #[arg(help = "Commit to list at: a SHA, or any branch or tag")]
+        sha: String,
+        #[arg(help = "Directory to list, relative to the repository root (default: the root)")]
+        path: Option<String>,
+        #[arg(
+            long,
+            help = "Levels to descend; 1 lists only the directory's own entries (default: 1)"
+        )]
+        depth: Option<usize>,
+        #[arg(long, help = "Print the listing as JSON")]
+        json: bool,
+        #[arg(
+            long,
+            help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
+        )]
+        db: Option<PathBuf>,
+    },
+    #[command(about = "Generate release notes for the commits between two revisions")]
+    Changelog {
+        #[arg(help = "Name of the indexed repository")]
+        repo: String,
+        #[arg(
+            help = "Revision range '<from>..<to>' (tags, branches or SHAs); '<to>' alone covers all history"
+        )]
+        range: String,
+        #[arg(long, help = "Follow only the first parent of merge commits")]
+        first_parent: bool,
+        #[arg(
+            long,
+            default_value = "type",
+            help = "Group commits by 'type' (Conventional Commits), 'directory' or 'author'"
+        )]
+        group_by: String,
+        #[arg(long, help = "Print the changelog as JSON instead of Markdown")]
+        json: bool,
+        #[arg(
+            long,
+            help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
+        )]
+        db: Option<PathBuf>,
+    },
     #[command(about = "Show all indexed repositories with commit counts and sync times")]
     Status {
+        #[arg(
+            long = "project",
+            help = "Only show these repo projects (repeatable), skipping repos that define none of them"
+        )]
+        project: Vec<String>,
         #[arg(
             long,
             help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
@@ -162,9 +496,28 @@ enum Commands {
             help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
         )]
         db: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Keep the index fresh in the background while serving (see 'commitmux watch')"
+        )]
+        watch: bool,
+        #[command(flatten)]
+        watch_args: WatchArgs,
+    },
+    #[command(about = "Watch repositories and re-sync whenever their refs change")]
+    Watch {
+        #[arg(long, help = "Watch only this repo (default: watch all)")]
+        repo: Option<String>,
+        #[arg(
+            long,
+            help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
+        )]
+        db: Option<PathBuf>,
+        #[command(flatten)]
+        watch_args: WatchArgs,
     },
     #[command(
-        about = "Get or set global configuration values. For semantic search: set embed.model (e.g. nomic-embed-text) and embed.endpoint (default: http://localhost:11434/v1). Requires Ollama running."
+        about = "Get or set global configuration values. For semantic search: set embed.model (e.g. nomic-embed-text) and embed.endpoint (default: http://localhost:11434/v1). Requires Ollama running. ingest.rename_threshold sets the similarity (0-100, default 50, 0 = off) for detecting renames at sync. auth.token_env names the environment variable holding an HTTPS token for remotes without their own --token-env."
     )]
     Config {
         #[command(subcommand)]
@@ -226,6 +579,18 @@ enum Commands {
         )]
         claude_settings: Option<PathBuf>,
     },
+    #[command(
+        about = "Manage the global identity map used to merge authors across repos (applied on top of each repo's .mailmap)"
+    )]
+    Identity {
+        #[command(subcommand)]
+        action: IdentityAction,
+        #[arg(
+            long,
+            help = "Path to database file (default: ~/.commitmux/db.sqlite3, or $COMMITMUX_DB)"
+        )]
+        db: Option<PathBuf>,
+    },
     #[command(about = "Delete and rebuild embeddings for one or all repositories")]
     Reindex {
         #[arg(long, help = "Name of repo to reindex (omit to reindex all)")]
@@ -243,11 +608,61 @@ enum Commands {
     },
 }
 
+/// Timing of the watch loop, shared by `watch` and `serve --watch`.
+#[derive(Args)]
+struct WatchArgs {
+    #[arg(
+        long = "debounce-ms",
+        default_value_t = 2000,
+        help = "Quiet period after a ref change before syncing, in milliseconds"
+    )]
+    debounce_ms: u64,
+    #[arg(
+        long = "fetch-interval",
+        default_value_t = 300,
+        help = "Seconds between fetches of managed clones (0 disables)"
+    )]
+    fetch_interval: u64,
+}
+
+impl WatchArgs {
+    fn options(&self, follow_repos: bool) -> watch::WatchOptions {
+        watch::WatchOptions {
+            debounce: Duration::from_millis(self.debounce_ms),
+            fetch_interval: (self.fetch_interval > 0)
+                .then(|| Duration::from_secs(self.fetch_interval)),
+            follow_repos,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum IdentityAction {
+    #[command(about = "Attribute commits by an email address to a canonical identity")]
+    Add {
+        #[arg(help = "Email address to map (e.g. an old or noreply address)")]
+        alias: String,
+        #[arg(long, help = "Canonical display name")]
+        name: String,
+        #[arg(long, help = "Canonical email address")]
+        email: String,
+    },
+    #[command(about = "List identity mappings")]
+    List,
+    #[command(about = "Remove an identity mapping")]
+    Remove {
+        #[arg(help = "Email address whose mapping to remove")]
+        alias: String,
+    },
+}
+
 #[derive(Subcommand)]
 enum ConfigAction {
     #[command(about = "Set a configuration value")]
     Set {
-        #[arg(help = "Configuration key (e.g. embed.model, embed.endpoint)")]
+        #[arg(
+            help = "Configuration key (e.g. embed.model, embed.endpoint, ingest.rename_threshold, auth.token_env)"
+        )]
         key: String,
         #[arg(help = "Value to set")]
         value: String,
@@ -270,6 +685,12 @@ fn resolve_db_path(flag: Option<PathBuf>) -> PathBuf {
     PathBuf::from(home).join(".commitmux").join("db.sqlite3")
 }
 
+/// Directory holding clones created by `add-repo --url`.
+fn managed_clones_dir() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
+    PathBuf::from(home).join(".commitmux").join("clones")
+}
+
 fn format_timestamp(ts: i64) -> String {
     // Simple manual UTC formatting without chrono dependency
     // Using UNIX_EPOCH arithmetic
@@ -302,6 +723,17 @@ fn format_timestamp(ts: i64) -> String {
     )
 }
 
+/// Formats a duration in seconds compactly, e.g. "45s", "3m12s", "1h05m".
+fn format_duration(secs: u64) -> String {
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
 fn validate_git_url(url: &str) -> Result<()> {
     if !url.starts_with("https://")
         && !url.starts_with("http://")
@@ -317,6 +749,91 @@ fn validate_git_url(url: &str) -> Result<()> {
     Ok(())
 }
 
+/// Parses `--author` values and checks that any regexes compile.
+fn parse_author_matchers(values: &[String]) -> Result<Vec<AuthorMatcher>> {
+    let matchers: Vec<AuthorMatcher> = values.iter().map(|v| AuthorMatcher::parse(v)).collect();
+    AuthorFilter::compile(&matchers)?;
+    Ok(matchers)
+}
+
+fn parse_ref_patterns(values: &[String]) -> Result<Vec<RefPattern>> {
+    let patterns = values
+        .iter()
+        .map(|v| {
+            RefPattern::parse(v).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown ref pattern '{}': expected github, gitlab, jira:KEYS or re:<regex>",
+                    v
+                )
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    RefExtractor::compile(&patterns, None)?;
+    Ok(patterns)
+}
+
+fn parse_projects(values: &[String]) -> Result<Vec<RepoProject>> {
+    values
+        .iter()
+        .map(|v| {
+            RepoProject::parse(v)
+                .ok_or_else(|| anyhow::anyhow!("Invalid project '{}': expected NAME=PREFIX", v))
+        })
+        .collect()
+}
+
+/// Checks that an `--ssh-key` file exists and makes its path absolute, so
+/// syncs work from any directory.
+fn resolve_ssh_key(path: &std::path::Path) -> Result<PathBuf> {
+    path.canonicalize()
+        .with_context(|| format!("SSH key not found: {}", path.display()))
+}
+
+/// Parses a `--since` date: `YYYY-MM-DD` (midnight UTC) or a unix timestamp.
+fn parse_date(value: &str) -> Result<i64> {
+    let invalid = || anyhow::anyhow!("Invalid date '{}': expected YYYY-MM-DD or unix time", value);
+    if let Ok(ts) = value.trim().parse::<i64>() {
+        return Ok(ts);
+    }
+    let mut parts = value.trim().splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (Some(Some(y)), Some(Some(m)), Some(Some(d))) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return Err(invalid());
+    }
+    // Inverse of the calendar calculation in format_timestamp
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = if m > 2 { m - 3 } else { m + 9 };
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Ok((era * 146097 + doe - 719468) * 86400)
+}
+
+/// Parses a `-L START[,END]` line range.
+fn parse_line_range(value: &str) -> Result<(usize, Option<usize>)> {
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid line range '{}': expected START[,END]", value))
+    };
+    match value.split_once(',') {
+        Some((start, end)) => {
+            let (start, end) = (parse(start)?, parse(end)?);
+            if end < start {
+                anyhow::bail!("Invalid line range '{}': END is before START", value);
+            }
+            Ok((start, Some(end)))
+        }
+        None => Ok((parse(value)?, None)),
+    }
+}
+
 fn install_memory_hook(settings_path: &std::path::Path, command: &str) -> Result<()> {
     // Read existing settings or start fresh
     let mut value: serde_json::Value = if settings_path.exists() {
@@ -419,8 +936,17 @@ fn main() -> Result<()> {
             url,
             fork_of,
             author,
+            include,
+            ref_pattern,
+            project,
             embed,
+            depth,
+            since,
+            ssh_key,
+            token_env,
         } => {
+            let clone_since = since.as_deref().map(parse_date).transpose()?;
+            let ssh_key = ssh_key.map(|p| resolve_ssh_key(&p)).transpose()?;
             let db_path = resolve_db_path(db);
             if !db_path.exists() {
                 anyhow::bail!(
@@ -430,10 +956,20 @@ fn main() -> Result<()> {
             }
             let store = SqliteStore::open(&db_path)
                 .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+            let author_filters = parse_author_matchers(&author)?;
+            let ref_patterns = parse_ref_patterns(&ref_pattern)?;
+            let projects = parse_projects(&project)?;
 
             if let Some(remote_url) = url {
                 // Validate URL scheme before attempting clone
                 validate_git_url(&remote_url)?;
+                if (depth.is_some() || clone_since.is_some())
+                    && !commitmux_ingest::supports_shallow(&remote_url)
+                {
+                    anyhow::bail!(
+                        "--depth and --since need an http(s) URL: libgit2 cannot make shallow clones over ssh or git://"
+                    );
+                }
 
                 // URL-based ingestion: derive name from URL basename, clone repo
                 let derived_name = remote_url
@@ -445,11 +981,7 @@ fn main() -> Result<()> {
                     .to_string();
                 let repo_name = name.unwrap_or(derived_name);
 
-                let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
-                let clone_dir = PathBuf::from(home)
-                    .join(".commitmux")
-                    .join("clones")
-                    .join(&repo_name);
+                let clone_dir = managed_clones_dir().join(&repo_name);
 
                 println!("Cloning {} from {}...", repo_name, remote_url);
 
@@ -457,17 +989,16 @@ fn main() -> Result<()> {
                     format!("Failed to create clone directory: {}", clone_dir.display())
                 })?;
 
-                let mut callbacks = git2::RemoteCallbacks::new();
-                callbacks.credentials(|_url, username, _allowed| {
-                    git2::Cred::ssh_key_from_agent(username.unwrap_or("git"))
-                });
-                let mut fo = git2::FetchOptions::new();
-                fo.remote_callbacks(callbacks);
-                let mut builder = git2::build::RepoBuilder::new();
-                builder.fetch_options(fo);
-                builder.clone(&remote_url, &clone_dir).with_context(|| {
-                    format!("Failed to clone '{}' from '{}'", repo_name, remote_url)
-                })?;
+                let auth = RemoteAuth {
+                    ssh_key: ssh_key.clone(),
+                    token_env: token_env
+                        .clone()
+                        .or_else(|| store.get_config(RemoteAuth::TOKEN_ENV_KEY).ok().flatten()),
+                };
+                commitmux_ingest::clone_repo(&remote_url, &clone_dir, depth, clone_since, &auth)
+                    .with_context(|| {
+                        format!("Failed to clone '{}' from '{}'", repo_name, remote_url)
+                    })?;
 
                 store.add_repo(&RepoInput {
                     name: repo_name.clone(),
@@ -475,9 +1006,16 @@ fn main() -> Result<()> {
                     remote_url: Some(remote_url.clone()),
                     default_branch: None,
                     fork_of: fork_of.clone(),
-                    author_filter: author.clone(),
+                    author_filters: author_filters.clone(),
+                    include_paths: include.clone(),
+                    ref_patterns: ref_patterns.clone(),
+                    projects: projects.clone(),
                     exclude_prefixes: exclude.clone(),
                     embed_enabled: embed,
+                    clone_depth: depth,
+                    clone_since,
+                    ssh_key: ssh_key.clone(),
+                    token_env: token_env.clone(),
                 })
                 .map_err(|e| {
                     if e.to_string().contains("UNIQUE constraint") {
@@ -517,9 +1055,16 @@ fn main() -> Result<()> {
                     remote_url: None,
                     default_branch: None,
                     fork_of: fork_of.clone(),
-                    author_filter: author.clone(),
+                    author_filters: author_filters.clone(),
+                    include_paths: include.clone(),
+                    ref_patterns: ref_patterns.clone(),
+                    projects: projects.clone(),
                     exclude_prefixes: exclude.clone(),
                     embed_enabled: embed,
+                    clone_depth: None,
+                    clone_since: None,
+                    ssh_key: ssh_key.clone(),
+                    token_env: token_env.clone(),
                 })
                 .map_err(|e| {
                     if e.to_string().contains("UNIQUE constraint") {
@@ -538,6 +1083,160 @@ fn main() -> Result<()> {
             }
         }
 
+        Commands::AddRepos {
+            scan,
+            depth,
+            dry_run,
+            prune,
+            exclude,
+            include,
+            author,
+            ref_pattern,
+            embed,
+            db,
+        } => {
+            let db_path = resolve_db_path(db);
+            if !db_path.exists() {
+                anyhow::bail!(
+                    "Database not found at {}. Run 'commitmux init' first.",
+                    db_path.display()
+                );
+            }
+            let store = SqliteStore::open(&db_path)
+                .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+            let author_filters = parse_author_matchers(&author)?;
+            let ref_patterns = parse_ref_patterns(&ref_pattern)?;
+            let root = scan
+                .canonicalize()
+                .with_context(|| format!("Failed to canonicalize path: {}", scan.display()))?;
+            let existing = store.list_repos().context("Failed to list repos")?;
+
+            // Worktrees are skipped when their history is already indexed
+            // through another checkout, registered or found in this scan.
+            let mut owners: HashMap<PathBuf, String> = existing
+                .iter()
+                .filter_map(|r| {
+                    let git_repo = git2::Repository::open(&r.local_path).ok()?;
+                    let git_dir = git_repo.path().canonicalize().ok()?;
+                    Some((watch::common_dir(&git_dir), format!("'{}'", r.name)))
+                })
+                .collect();
+            let found = discover::scan(&root, depth);
+            for repo in found
+                .iter()
+                .filter(|r| r.kind != discover::RepoKind::Worktree)
+            {
+                owners
+                    .entry(repo.common_dir.clone())
+                    .or_insert_with(|| repo.path.display().to_string());
+            }
+
+            let mut skipped = 0;
+            let mut to_add = Vec::new();
+            for repo in &found {
+                if let Some(r) = existing.iter().find(|r| r.local_path == repo.path) {
+                    println!(
+                        "Skipped {}: already registered as '{}'",
+                        repo.path.display(),
+                        r.name
+                    );
+                    skipped += 1;
+                    continue;
+                }
+                if repo.kind == discover::RepoKind::Worktree {
+                    if let Some(owner) = owners.get(&repo.common_dir) {
+                        println!("Skipped {}: worktree of {}", repo.path.display(), owner);
+                        skipped += 1;
+                        continue;
+                    }
+                    owners.insert(repo.common_dir.clone(), repo.path.display().to_string());
+                }
+                to_add.push(repo);
+            }
+
+            let taken: HashSet<String> = existing.iter().map(|r| r.name.clone()).collect();
+            let paths: Vec<&std::path::Path> = to_add.iter().map(|r| r.path.as_path()).collect();
+            let names = discover::derive_names(&paths, &taken);
+            for (repo, name) in to_add.iter().zip(&names) {
+                if dry_run {
+                    println!(
+                        "Would add {} '{}' at {}",
+                        repo.kind.label(),
+                        name,
+                        repo.path.display()
+                    );
+                    continue;
+                }
+                store
+                    .add_repo(&RepoInput {
+                        name: name.clone(),
+                        local_path: repo.path.clone(),
+                        remote_url: None,
+                        default_branch: None,
+                        fork_of: None,
+                        author_filters: author_filters.clone(),
+                        include_paths: include.clone(),
+                        ref_patterns: ref_patterns.clone(),
+                        projects: vec![],
+                        exclude_prefixes: exclude.clone(),
+                        embed_enabled: embed,
+                        clone_depth: None,
+                        clone_since: None,
+                        ssh_key: None,
+                        token_env: None,
+                    })
+                    .with_context(|| format!("Failed to add repo '{}'", name))?;
+                println!(
+                    "Added {} '{}' at {}",
+                    repo.kind.label(),
+                    name,
+                    repo.path.display()
+                );
+            }
+
+            let mut removed = 0;
+            if prune {
+                for r in existing
+                    .iter()
+                    .filter(|r| r.local_path.starts_with(&root) && !r.local_path.exists())
+                {
+                    removed += 1;
+                    if dry_run {
+                        println!(
+                            "Would remove repo '{}': {} no longer exists",
+                            r.name,
+                            r.local_path.display()
+                        );
+                        continue;
+                    }
+                    store
+                        .remove_repo(&r.name)
+                        .with_context(|| format!("Failed to remove repo '{}'", r.name))?;
+                    println!(
+                        "Removed repo '{}': {} no longer exists",
+                        r.name,
+                        r.local_path.display()
+                    );
+                }
+            }
+
+            println!(
+                "{} {} repo(s), skipped {}{}",
+                if dry_run { "Would add" } else { "Added" },
+                names.len(),
+                skipped,
+                if prune {
+                    format!(
+                        ", {} {}",
+                        if dry_run { "would remove" } else { "removed" },
+                        removed
+                    )
+                } else {
+                    String::new()
+                }
+            );
+        }
+
         Commands::RemoveRepo { name, db } => {
             let db_path = resolve_db_path(db);
             if !db_path.exists() {
@@ -574,9 +1273,7 @@ fn main() -> Result<()> {
             }
 
             // Clean up managed clone if under ~/.commitmux/clones/
-            let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
-            let clones_dir = PathBuf::from(&home).join(".commitmux").join("clones");
-            if local_path.starts_with(&clones_dir) {
+            if local_path.starts_with(managed_clones_dir()) {
                 match std::fs::remove_dir_all(&local_path) {
                     Ok(_) => println!("Removed managed clone at {}", local_path.display()),
                     Err(e) => eprintln!(
@@ -592,11 +1289,22 @@ fn main() -> Result<()> {
             name,
             fork_of,
             author,
+            remove_author,
+            include,
+            ref_pattern,
+            project,
             exclude,
             default_branch,
             db,
             embed,
             no_embed,
+            depth,
+            since,
+            full_history,
+            ssh_key,
+            no_ssh_key,
+            token_env,
+            no_token_env,
         } => {
             let db_path = resolve_db_path(db);
             if !db_path.exists() {
@@ -621,9 +1329,60 @@ fn main() -> Result<()> {
             } else {
                 None
             };
+            if depth.is_some() || since.is_some() || full_history {
+                match repo.remote_url.as_deref() {
+                    None => anyhow::bail!(
+                        "'{}' is not a managed clone; --depth, --since and --full-history only apply to repos added with --url",
+                        name
+                    ),
+                    Some(url)
+                        if (depth.is_some() || since.is_some())
+                            && !commitmux_ingest::supports_shallow(url) =>
+                    {
+                        anyhow::bail!(
+                            "'{}' cannot be a shallow clone: shallow fetches need an http(s) URL",
+                            name
+                        )
+                    }
+                    Some(_) => {}
+                }
+            }
+            let (clone_depth, clone_since) = if full_history {
+                (Some(None), Some(None))
+            } else {
+                (
+                    depth.map(Some),
+                    since.as_deref().map(parse_date).transpose()?.map(Some),
+                )
+            };
+            let remove_authors: Vec<AuthorMatcher> = remove_author
+                .iter()
+                .map(|a| AuthorMatcher::parse(a))
+                .collect();
+            for m in &remove_authors {
+                if !repo.author_filters.contains(m) {
+                    eprintln!("Warning: '{}' is not an author filter on '{}'", m, name);
+                }
+            }
             let update = RepoUpdate {
                 fork_of: fork_of.map(Some),
-                author_filter: author.map(Some),
+                add_authors: parse_author_matchers(&author)?,
+                remove_authors,
+                include_paths: if include.is_empty() {
+                    None
+                } else {
+                    Some(include)
+                },
+                ref_patterns: if ref_pattern.is_empty() {
+                    None
+                } else {
+                    Some(parse_ref_patterns(&ref_pattern)?)
+                },
+                projects: if project.is_empty() {
+                    None
+                } else {
+                    Some(parse_projects(&project)?)
+                },
                 exclude_prefixes: if exclude.is_empty() {
                     None
                 } else {
@@ -631,15 +1390,35 @@ fn main() -> Result<()> {
                 },
                 default_branch: default_branch.map(Some),
                 embed_enabled,
+                clone_depth,
+                clone_since,
+                ssh_key: if no_ssh_key {
+                    Some(None)
+                } else {
+                    ssh_key.map(|p| resolve_ssh_key(&p)).transpose()?.map(Some)
+                },
+                token_env: if no_token_env {
+                    Some(None)
+                } else {
+                    token_env.map(Some)
+                },
             };
 
             let any_change = update.fork_of.is_some()
-                || update.author_filter.is_some()
+                || !update.add_authors.is_empty()
+                || !update.remove_authors.is_empty()
+                || update.include_paths.is_some()
+                || update.ref_patterns.is_some()
+                || update.projects.is_some()
                 || update.exclude_prefixes.is_some()
                 || update.default_branch.is_some()
-                || update.embed_enabled.is_some();
+                || update.embed_enabled.is_some()
+                || update.clone_depth.is_some()
+                || update.clone_since.is_some()
+                || update.ssh_key.is_some()
+                || update.token_env.is_some();
 
-            store
+            let updated = store
                 .update_repo(repo.repo_id, &update)
                 .with_context(|| format!("Failed to update repo '{}'", name))?;
 
@@ -648,12 +1427,21 @@ fn main() -> Result<()> {
             } else {
                 println!("Updated repo '{}' (no changes)", name);
             }
+            if update.ref_patterns.is_some() {
+                let with_refs = commitmux_ingest::reextract_refs(&updated, &store)
+                    .with_context(|| format!("Failed to re-extract references for '{}'", name))?;
+                println!(
+                    "Re-extracted references: {} indexed commit(s) mention one",
+                    with_refs
+                );
+            }
         }
 
         Commands::Sync {
             repo,
             db,
             embed_only,
+            full,
         } => {
             let db_path = resolve_db_path(db);
             if !db_path.exists() {
@@ -712,6 +1500,16 @@ fn main() -> Result<()> {
                                                 .as_ref()
                                                 .and_then(|s| s.last_synced_sha.clone()),
                                             last_error: None,
+                                            checkpoint_sha: prev_state
+                                                .as_ref()
+                                                .and_then(|s| s.checkpoint_sha.clone()),
+                                            indexed_tips: prev_state
+                                                .as_ref()
+                                                .map(|s| s.indexed_tips.clone())
+                                                .unwrap_or_default(),
+                                            config_hash: prev_state
+                                                .as_ref()
+                                                .and_then(|s| s.config_hash.clone()),
                                         };
                                         let _ = store.update_ingest_state(&ingest_state);
                                     }
@@ -728,14 +1526,64 @@ fn main() -> Result<()> {
                     }
                 }
             } else {
+                // First Ctrl+C asks the ingester to stop at the next commit and
+                // save a checkpoint; a second one exits immediately.
+                let cancel = Arc::new(AtomicBool::new(false));
+                {
+                    let cancel = Arc::clone(&cancel);
+                    ctrlc::set_handler(move || {
+                        if cancel.swap(true, Ordering::SeqCst) {
+                            std::process::exit(130);
+                        }
+                        eprintln!("\nInterrupted; saving sync progress (Ctrl+C again to abort)...");
+                    })
+                    .context("Failed to install Ctrl+C handler")?;
+                }
+                let show_progress = std::io::stderr().is_terminal();
+
                 for r in &repos {
                     let ingester = Git2Ingester::new();
                     let config = IgnoreConfig::default();
-                    match ingester.sync_repo(r, &store, &config) {
+                    let on_progress = |p: &SyncProgress| {
+                        if show_progress && p.commits_total > 0 {
+                            let eta = p
+                                .eta_secs
+                                .map(|s| format!(", ETA {}", format_duration(s)))
+                                .unwrap_or_default();
+                            eprint!(
+                                "\r\x1b[2KSyncing '{}'... {}/{} commits walked{}",
+                                r.name, p.commits_walked, p.commits_total, eta
+                            );
+                        }
+                    };
+                    let control = SyncControl {
+                        on_progress: Some(&on_progress),
+                        cancel: Some(&cancel),
+                        full,
+                    };
+                    let result = ingester.sync_repo_with(r, &store, &config, &control);
+                    if show_progress {
+                        eprint!("\r\x1b[2K");
+                    }
+                    match result {
+                        Ok(summary) if summary.cancelled => {
+                            if full {
+                                println!(
+                                    "Sync of '{}' interrupted after {} commits; run 'commitmux sync --full' again to start over.",
+                                    r.name, summary.commits_indexed
+                                );
+                            } else {
+                                println!(
+                                    "Sync of '{}' interrupted after {} new commits; run 'commitmux sync' again to resume.",
+                                    r.name, summary.commits_indexed
+                                );
+                            }
+                            std::process::exit(130);
+                        }
                         Ok(summary) => {
                             if summary.commits_filtered > 0 {
                                 println!(
-                                    "Syncing '{}'... {} indexed, {} already indexed, {} filtered by author",
+                                    "Syncing '{}'... {} indexed, {} already indexed, {} filtered",
                                     r.name,
                                     summary.commits_indexed,
                                     summary.commits_already_indexed,
@@ -752,8 +1600,9 @@ fn main() -> Result<()> {
                             for err in &summary.errors {
                                 eprintln!("  warning: {}", err);
                             }
-                            total_in_index +=
-                                summary.commits_indexed + summary.commits_already_indexed;
+                            // Hidden history is not counted as already indexed,
+                            // so ask the store.
+                            total_in_index += store.count_commits_for_repo(r.repo_id).unwrap_or(0);
                         }
                         Err(e) => {
                             eprintln!("Error syncing '{}': {}", r.name, e);
@@ -794,19 +1643,400 @@ fn main() -> Result<()> {
                     }
                 }
 
-                if any_error {
-                    std::process::exit(1);
-                }
+                if any_error {
+                    std::process::exit(1);
+                }
+
+                if total_in_index > 0 {
+                    println!(
+                        "Tip: run 'commitmux serve' to expose this index via MCP to AI agents."
+                    );
+                }
+            }
+        }
+
+        Commands::Show { repo, sha, db } => {
+            let db_path = resolve_db_path(db);
+            if !db_path.exists() {
+                anyhow::bail!(
+                    "Database not found at {}. Run 'commitmux init' first.",
+                    db_path.display()
+                );
+            }
+            let store = SqliteStore::open(&db_path)
+                .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+
+            match service::commit_detail(&store, &Git2Ingester::new(), &repo, &sha)
+                .context("Failed to get commit")?
+            {
+                None => {
+                    eprintln!("Error: Commit '{}' not found in repo '{}'", sha, repo);
+                    std::process::exit(1);
+                }
+                Some(detail) => {
+                    let json = serde_json::to_string_pretty(&detail)
+                        .context("Failed to serialize commit to JSON")?;
+                    println!("{}", json);
+                }
+            }
+        }
+
+        Commands::Refs {
+            reference,
+            repo,
+            limit,
+            json,
+            db,
+        } => {
+            let db_path = resolve_db_path(db);
+            if !db_path.exists() {
+                anyhow::bail!(
+                    "Database not found at {}. Run 'commitmux init' first.",
+                    db_path.display()
+                );
+            }
+            let store = SqliteStore::open(&db_path)
+                .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+
+            let opts = RefOpts {
+                repos: if repo.is_empty() { None } else { Some(repo) },
+                limit,
+            };
+            let results = store
+                .find_by_ref(&reference, &opts)
+                .with_context(|| format!("Failed to look up reference '{}'", reference))?;
+
+            if json {
+                let json = serde_json::to_string_pretty(&results)
+                    .context("Failed to serialize results to JSON")?;
+                println!("{}", json);
+            } else if results.is_empty() {
+                println!("No commits mention '{}'", reference);
+            } else {
+                for r in results {
+                    println!(
+                        "{:<20} {}  {}  {:<16} {}",
+                        r.repo,
+                        &r.sha[..r.sha.len().min(12)],
+                        format_timestamp(r.date),
+                        r.reference,
+                        r.subject
+                    );
+                }
+            }
+        }
+
+        Commands::SymbolHistory {
+            repo,
+            symbol,
+            path,
+            limit,
+            json,
+            db,
+        } => {
+            let db_path = resolve_db_path(db);
+            if !db_path.exists() {
+                anyhow::bail!(
+                    "Database not found at {}. Run 'commitmux init' first.",
+                    db_path.display()
+                );
+            }
+            let store = SqliteStore::open(&db_path)
+                .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+
+            let r = store
+                .get_repo_by_name(&repo)
+                .with_context(|| format!("Failed to look up repo '{}'", repo))?
+                .ok_or_else(|| anyhow::anyhow!("Repo '{}' not found", repo))?;
+            let opts = SymbolOpts { path, limit };
+            let changes = store
+                .symbol_history(r.repo_id, &symbol, &opts)
+                .with_context(|| format!("Failed to look up history of '{}'", symbol))?;
+
+            if json {
+                let json = serde_json::to_string_pretty(&changes)
+                    .context("Failed to serialize results to JSON")?;
+                println!("{}", json);
+            } else if changes.is_empty() {
+                println!("No indexed commits changed '{}' in {}", symbol, repo);
+            } else {
+                for (i, c) in changes.iter().enumerate() {
+                    if i > 0 {
+                        println!();
+                    }
+                    println!(
+                        "{}  {}  {:<16} {}",
+                        &c.sha[..c.sha.len().min(12)],
+                        format_timestamp(c.date),
+                        c.author,
+                        c.subject
+                    );
+                    println!("{} {} in {}", c.kind, c.symbol, c.path);
+                    for line in c.excerpt.lines() {
+                        println!("    {}", line);
+                    }
+                }
+            }
+        }
+
+        Commands::Blame {
+            repo,
+            path,
+            lines,
+            rev,
+            json,
+            db,
+        } => {
+            let db_path = resolve_db_path(db);
+            if !db_path.exists() {
+                anyhow::bail!(
+                    "Database not found at {}. Run 'commitmux init' first.",
+                    db_path.display()
+                );
+            }
+            let store = SqliteStore::open(&db_path)
+                .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+
+            let (start_line, end_line) = match lines.as_deref() {
+                Some(l) => {
+                    let (start, end) = parse_line_range(l)?;
+                    (Some(start), end)
+                }
+                None => (None, None),
+            };
+            let opts = BlameOpts {
+                repo,
+                path,
+                rev,
+                start_line,
+                end_line,
+            };
+            let blame = service::blame(&store, &Git2Ingester::new(), &opts)
+                .with_context(|| format!("Failed to blame '{}'", opts.path))?;
+
+            if json {
+                let json = serde_json::to_string_pretty(&blame)
+                    .context("Failed to serialize blame to JSON")?;
+                println!("{}", json);
+            } else {
+                for hunk in &blame.hunks {
+                    let date = format_timestamp(hunk.date);
+                    for (i, line) in hunk.lines.iter().enumerate() {
+                        println!(
+                            "{} ({:<16} {}) {:>5}  {}",
+                            &hunk.sha[..hunk.sha.len().min(12)],
+                            hunk.author,
+                            &date[..date.len().min(10)],
+                            hunk.start_line + i,
+                            line
+                        );
+                    }
+                }
+                // One line per originating commit, in order of appearance.
+                let mut seen = HashSet::new();
+                let shas: Vec<&String> = blame
+                    .hunks
+                    .iter()
+                    .map(|h| &h.sha)
+                    .filter(|sha| seen.insert(*sha))
+                    .collect();
+                if !shas.is_empty() {
+                    println!();
+                }
+                for sha in shas {
+                    let subject = blame
+                        .commits
+                        .get(sha)
+                        .map(|c| c.subject.as_str())
+                        .unwrap_or("(not indexed)");
+                    println!("{}  {}", &sha[..sha.len().min(12)], subject);
+                }
+            }
+        }
+
+        Commands::Cat {
+            repo,
+            sha,
+            path,
+            lines,
+            offset,
+            max_bytes,
+            json,
+            db,
+        } => {
+            let db_path = resolve_db_path(db);
+            if !db_path.exists() {
+                anyhow::bail!(
+                    "Database not found at {}. Run 'commitmux init' first.",
+                    db_path.display()
+                );
+            }
+            let store = SqliteStore::open(&db_path)
+                .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+
+            let (start_line, end_line) = match lines.as_deref() {
+                Some(l) => {
+                    let (start, end) = parse_line_range(l)?;
+                    (Some(start), end)
+                }
+                None => (None, None),
+            };
+            let opts = FileOpts {
+                repo,
+                path,
+                rev: sha,
+                start_line,
+                end_line,
+                offset,
+                max_bytes,
+            };
+            let file = service::read_file(&store, &Git2Ingester::new(), &opts)
+                .with_context(|| format!("Failed to read '{}' at '{}'", opts.path, opts.rev))?;
+
+            if json {
+                let json = serde_json::to_string_pretty(&file)
+                    .context("Failed to serialize file to JSON")?;
+                println!("{}", json);
+            } else {
+                match &file.content {
+                    Some(content) => print!("{}", content),
+                    None => eprintln!("{}: binary file, {} bytes", file.path, file.size),
+                }
+                if file.truncated {
+                    eprintln!(
+                        "(truncated: showing {} of {} bytes; use -L or --offset to read more)",
+                        file.content.as_ref().map_or(0, |c| c.len()),
+                        file.size
+                    );
+                }
+            }
+        }
+
+        Commands::Diff {
+            repo,
+            from,
+            to,
+            paths,
+            stat,
+            max_bytes,
+            json,
+            db,
+        } => {
+            let db_path = resolve_db_path(db);
+            if !db_path.exists() {
+                anyhow::bail!(
+                    "Database not found at {}. Run 'commitmux init' first.",
+                    db_path.display()
+                );
+            }
+            let store = SqliteStore::open(&db_path)
+                .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+
+            let opts = DiffOpts {
+                repo,
+                from,
+                to,
+                paths,
+                stat_only: stat,
+                max_bytes,
+            };
+            let diff = service::diff(&store, &Git2Ingester::new(), &opts)
+                .with_context(|| format!("Failed to diff '{}'..'{}'", opts.from, opts.to))?;
+
+            if json {
+                let json = serde_json::to_string_pretty(&diff)
+                    .context("Failed to serialize diff to JSON")?;
+                println!("{}", json);
+            } else if let Some(patch_text) = &diff.patch_text {
+                print!("{}", patch_text);
+                if diff.truncated {
+                    eprintln!("(truncated at {} bytes)", patch_text.len());
+                }
+            } else {
+                for file in &diff.files {
+                    let path = match &file.old_path {
+                        Some(old) => format!("{} -> {}", old, file.path),
+                        None => file.path.clone(),
+                    };
+                    if file.binary {
+                        println!("{} {}  (binary)", file.status, path);
+                    } else {
+                        println!(
+                            "{} {}  +{} -{}",
+                            file.status, path, file.additions, file.deletions
+                        );
+                    }
+                }
+                println!(
+                    "{} file(s) changed, {} insertion(s), {} deletion(s)",
+                    diff.files.len(),
+                    diff.additions,
+                    diff.deletions
+                );
+            }
+        }
+
+        Commands::LsTree {
+            repo,
+            sha,
+            path,
+            depth,
+            json,
+            db,
+        } => {
+            let db_path = resolve_db_path(db);
+            if !db_path.exists() {
+                anyhow::bail!(
+                    "Database not found at {}. Run 'commitmux init' first.",
+                    db_path.display()
+                );
+            }
+            let store = SqliteStore::open(&db_path)
+                .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+
+            let opts = TreeOpts {
+                repo,
+                rev: sha,
+                path,
+                depth,
+            };
+            let listing = service::list_tree(&store, &Git2Ingester::new(), &opts)
+                .with_context(|| format!("Failed to list tree at '{}'", opts.rev))?;
 
-                if total_in_index > 0 {
+            if json {
+                let json = serde_json::to_string_pretty(&listing)
+                    .context("Failed to serialize tree listing to JSON")?;
+                println!("{}", json);
+            } else {
+                // Like `git ls-tree -l`.
+                for entry in &listing.entries {
+                    let size = entry.size.map_or("-".to_string(), |s| s.to_string());
                     println!(
-                        "Tip: run 'commitmux serve' to expose this index via MCP to AI agents."
+                        "{} {:<6} {} {:>9}\t{}",
+                        entry.mode,
+                        entry.kind,
+                        &entry.sha[..entry.sha.len().min(12)],
+                        size,
+                        entry.path
+                    );
+                }
+                if listing.truncated {
+                    eprintln!(
+                        "(truncated at {} entries; list a subdirectory or lower --depth)",
+                        listing.entries.len()
                     );
                 }
             }
         }
 
-        Commands::Show { repo, sha, db } => {
+        Commands::Changelog {
+            repo,
+            range,
+            first_parent,
+            group_by,
+            json,
+            db,
+        } => {
             let db_path = resolve_db_path(db);
             if !db_path.exists() {
                 anyhow::bail!(
@@ -817,23 +2047,33 @@ fn main() -> Result<()> {
             let store = SqliteStore::open(&db_path)
                 .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
 
-            match store
-                .get_commit(&repo, &sha)
-                .context("Failed to get commit")?
-            {
-                None => {
-                    eprintln!("Error: Commit '{}' not found in repo '{}'", sha, repo);
-                    std::process::exit(1);
-                }
-                Some(detail) => {
-                    let json = serde_json::to_string_pretty(&detail)
-                        .context("Failed to serialize commit to JSON")?;
-                    println!("{}", json);
-                }
+            let group_by = ChangelogGroupBy::from_str(&group_by).with_context(|| {
+                format!(
+                    "Unknown --group-by '{}': expected type, directory or author",
+                    group_by
+                )
+            })?;
+            let (from, to) = ChangelogOpts::parse_range(&range)?;
+            let opts = ChangelogOpts {
+                repo,
+                from,
+                to,
+                first_parent,
+                group_by,
+            };
+            let changelog = service::changelog(&store, &Git2Ingester::new(), &opts)
+                .with_context(|| format!("Failed to build changelog for '{}'", range))?;
+
+            if json {
+                let json = serde_json::to_string_pretty(&changelog)
+                    .context("Failed to serialize changelog to JSON")?;
+                println!("{}", json);
+            } else {
+                print!("{}", service::changelog_markdown(&changelog));
             }
         }
 
-        Commands::Status { db } => {
+        Commands::Status { project, db } => {
             let db_path = resolve_db_path(db);
             if !db_path.exists() {
                 anyhow::bail!(
@@ -844,13 +2084,20 @@ fn main() -> Result<()> {
             let store = SqliteStore::open(&db_path)
                 .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
 
-            let repos = store.list_repos().context("Failed to list repos")?;
+            let mut repos = store.list_repos().context("Failed to list repos")?;
 
             if repos.is_empty() {
                 println!("No repositories indexed.");
                 println!("Run: commitmux add-repo <path>");
                 return Ok(());
             }
+            if !project.is_empty() {
+                repos.retain(|r| r.projects.iter().any(|p| project.contains(&p.name)));
+                if repos.is_empty() {
+                    println!("No repository defines project {}.", project.join(", "));
+                    return Ok(());
+                }
+            }
 
             let any_embed = repos.iter().any(|r| r.embed_enabled);
 
@@ -910,6 +2157,22 @@ fn main() -> Result<()> {
                                 r.name, stats.commit_count, source, last_synced
                             );
                         }
+                        for p in &stats.projects {
+                            if !project.is_empty() && !project.contains(&p.name) {
+                                continue;
+                            }
+                            let last_commit = p
+                                .last_commit_at
+                                .map(format_timestamp)
+                                .unwrap_or_else(|| "never".to_string());
+                            println!(
+                                "  {:<18} {:>8}  {:<45}  last commit {}",
+                                p.name,
+                                p.commit_count,
+                                p.prefixes.join(", "),
+                                last_commit
+                            );
+                        }
                     }
                     Err(e) => {
                         eprintln!("Error fetching stats for '{}': {}", r.name, e);
@@ -917,16 +2180,51 @@ fn main() -> Result<()> {
                 }
 
                 // Show active filters if any
-                if r.author_filter.is_some() || !r.exclude_prefixes.is_empty() {
+                if !r.author_filters.is_empty()
+                    || !r.include_paths.is_empty()
+                    || !r.ref_patterns.is_empty()
+                    || !r.exclude_prefixes.is_empty()
+                {
                     let mut parts = Vec::new();
-                    if let Some(ref author) = r.author_filter {
-                        parts.push(format!("author={}", author));
+                    if !r.author_filters.is_empty() {
+                        let authors: Vec<String> =
+                            r.author_filters.iter().map(|m| m.to_string()).collect();
+                        parts.push(format!("author=[{}]", authors.join(", ")));
+                    }
+                    if !r.include_paths.is_empty() {
+                        parts.push(format!("include=[{}]", r.include_paths.join(", ")));
+                    }
+                    if !r.ref_patterns.is_empty() {
+                        let patterns: Vec<String> =
+                            r.ref_patterns.iter().map(|p| p.to_string()).collect();
+                        parts.push(format!("refs=[{}]", patterns.join(", ")));
                     }
                     if !r.exclude_prefixes.is_empty() {
                         parts.push(format!("exclude=[{}]", r.exclude_prefixes.join(", ")));
                     }
                     println!("  filters: {}", parts.join(", "));
                 }
+                if r.clone_depth.is_some() || r.clone_since.is_some() {
+                    let mut parts = Vec::new();
+                    if let Some(depth) = r.clone_depth {
+                        parts.push(format!("depth={}", depth));
+                    }
+                    if let Some(since) = r.clone_since {
+                        let date = format_timestamp(since);
+                        parts.push(format!("since={}", date.split(' ').next().unwrap_or("")));
+                    }
+                    println!("  shallow clone: {}", parts.join(", "));
+                }
+                if r.ssh_key.is_some() || r.token_env.is_some() {
+                    let mut parts = Vec::new();
+                    if let Some(key) = &r.ssh_key {
+                        parts.push(format!("ssh_key={}", key.display()));
+                    }
+                    if let Some(var) = &r.token_env {
+                        parts.push(format!("token_env={}", var));
+                    }
+                    println!("  auth: {}", parts.join(", "));
+                }
             }
 
             if any_embed {
@@ -947,7 +2245,11 @@ fn main() -> Result<()> {
             }
         }
 
-        Commands::Serve { db } => {
+        Commands::Serve {
+            db,
+            watch,
+            watch_args,
+        } => {
             let db_path = resolve_db_path(db);
             if !db_path.exists() {
                 anyhow::bail!(
@@ -1008,9 +2310,71 @@ fn main() -> Result<()> {
                 }
             }
 
-            let store: Arc<dyn commitmux_types::Store + 'static> = Arc::new(store);
+            let store = Arc::new(store);
+            if watch {
+                let watch_store = Arc::clone(&store);
+                std::thread::spawn(move || {
+                    let repos = match watch_store.list_repos() {
+                        Ok(repos) => repos,
+                        Err(e) => {
+                            eprintln!("commitmux: warning: failed to list repos for watch: {}", e);
+                            return;
+                        }
+                    };
+                    let opts = watch_args.options(true);
+                    let watched = watch::embed_runtime()
+                        .and_then(|rt| watch::run_watch(&*watch_store, &repos, &opts, &rt));
+                    if let Err(e) = watched {
+                        eprintln!("commitmux: warning: watch stopped: {:#}", e);
+                    }
+                });
+            }
+
+            let store: Arc<dyn commitmux_types::Store + 'static> = store;
             eprintln!("commitmux MCP server ready (JSON-RPC over stdio). Press Ctrl+C to stop.");
-            commitmux_mcp::run_mcp_server(store).context("MCP server error")?;
+            let ingester: Arc<dyn commitmux_types::Ingester + 'static> =
+                Arc::new(Git2Ingester::new());
+            commitmux_mcp::run_mcp_server(store, ingester).context("MCP server error")?;
+        }
+
+        Commands::Watch {
+            repo,
+            db,
+            watch_args,
+        } => {
+            let db_path = resolve_db_path(db);
+            if !db_path.exists() {
+                anyhow::bail!(
+                    "Database not found at {}. Run 'commitmux init' first.",
+                    db_path.display()
+                );
+            }
+            let store = SqliteStore::open(&db_path)
+                .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+
+            let repos = if let Some(ref repo_name) = repo {
+                let r = store
+                    .get_repo_by_name(repo_name)
+                    .with_context(|| format!("Failed to look up repo '{}'", repo_name))?
+                    .ok_or_else(|| anyhow::anyhow!("Repo '{}' not found", repo_name))?;
+                vec![r]
+            } else {
+                store.list_repos().context("Failed to list repos")?
+            };
+            if repos.is_empty() {
+                anyhow::bail!("No repositories indexed. Run: commitmux add-repo <path>");
+            }
+
+            // Catch up first so the watcher only has to react to new changes.
+            let rt = watch::embed_runtime()?;
+            for r in &repos {
+                watch::sync_and_embed(&store, r, &rt);
+            }
+
+            // Watching every repo follows repos added or removed later.
+            let opts = watch_args.options(repo.is_none());
+            eprintln!("Press Ctrl+C to stop.");
+            watch::run_watch(&store, &repos, &opts, &rt)?;
         }
 
         Commands::Config { action, db } => {
@@ -1025,7 +2389,12 @@ fn main() -> Result<()> {
                 .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
             match action {
                 ConfigAction::Set { key, value } => {
-                    const VALID_CONFIG_KEYS: &[&str] = &["embed.model", "embed.endpoint"];
+                    const VALID_CONFIG_KEYS: &[&str] = &[
+                        "embed.model",
+                        "embed.endpoint",
+                        IgnoreConfig::RENAME_THRESHOLD_KEY,
+                        RemoteAuth::TOKEN_ENV_KEY,
+                    ];
                     if !VALID_CONFIG_KEYS.contains(&key.as_str()) {
                         anyhow::bail!(
                             "Unknown config key '{}'. Valid keys: {}",
@@ -1036,6 +2405,14 @@ fn main() -> Result<()> {
                     if value.trim().is_empty() {
                         anyhow::bail!("Value for '{}' cannot be empty", key);
                     }
+                    if key == IgnoreConfig::RENAME_THRESHOLD_KEY
+                        && !matches!(value.parse::<u16>(), Ok(0..=100))
+                    {
+                        anyhow::bail!(
+                            "Value for '{}' must be a percentage from 0 (off) to 100",
+                            key
+                        );
+                    }
                     store
                         .set_config(&key, &value)
                         .context("Failed to set config")?;
@@ -1050,6 +2427,53 @@ fn main() -> Result<()> {
             }
         }
 
+        Commands::Identity { action, db } => {
+            let db_path = resolve_db_path(db);
+            if !db_path.exists() {
+                anyhow::bail!(
+                    "Database not found at {}. Run 'commitmux init' first.",
+                    db_path.display()
+                );
+            }
+            let store = SqliteStore::open(&db_path)
+                .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+            match action {
+                IdentityAction::Add { alias, name, email } => {
+                    if !alias.contains('@') || !email.contains('@') {
+                        anyhow::bail!("Both the alias and --email must be email addresses");
+                    }
+                    store
+                        .add_identity(&IdentityAlias {
+                            alias_email: alias.clone(),
+                            canonical_name: name.clone(),
+                            canonical_email: email.clone(),
+                        })
+                        .context("Failed to add identity")?;
+                    println!("Mapped {} to {} <{}>", alias, name, email);
+                }
+                IdentityAction::List => {
+                    let identities = store
+                        .list_identities()
+                        .context("Failed to list identities")?;
+                    if identities.is_empty() {
+                        println!("No identity mappings. Add one with 'commitmux identity add'.");
+                    }
+                    for i in identities {
+                        println!(
+                            "{} -> {} <{}>",
+                            i.alias_email, i.canonical_name, i.canonical_email
+                        );
+                    }
+                }
+                IdentityAction::Remove { alias } => {
+                    store
+                        .remove_identity(&alias)
+                        .with_context(|| format!("Failed to remove identity '{}'", alias))?;
+                    println!("Removed identity mapping for {}", alias);
+                }
+            }
+        }
+
         Commands::IngestMemory { claude_home, db } => {
             let db_path = resolve_db_path(db);
             if !db_path.exists() {
@@ -1392,7 +2816,7 @@ fn main() -> Result<()> {
 mod tests {
     use super::*;
     use commitmux_store::SqliteStore;
-    use commitmux_types::{RepoInput, Store};
+    use commitmux_types::{AuthorMatcher, RepoInput, Store};
 
     fn temp_store() -> (SqliteStore, tempfile::TempDir) {
         let dir = tempfile::tempdir().expect("tempdir");
@@ -1401,7 +2825,7 @@ mod tests {
     }
 
     #[test]
-    fn test_add_repo_persists_author_filter() {
+    fn test_add_repo_persists_author_filters() {
         let (store, _dir) = temp_store();
         store
             .add_repo(&RepoInput {
@@ -1410,9 +2834,19 @@ mod tests {
                 remote_url: None,
                 default_branch: None,
                 fork_of: None,
-                author_filter: Some("alice@example.com".into()),
+                author_filters: vec![
+                    AuthorMatcher::parse("alice@example.com"),
+                    AuthorMatcher::parse("re:^Alice "),
+                ],
+                include_paths: vec!["src/".into()],
+                ref_patterns: vec![],
+                projects: vec![],
                 exclude_prefixes: vec![],
                 embed_enabled: false,
+                clone_depth: None,
+                clone_since: None,
+                ssh_key: None,
+                token_env: None,
             })
             .expect("add_repo");
 
@@ -1420,7 +2854,14 @@ mod tests {
             .get_repo_by_name("myrepo")
             .expect("get")
             .expect("some");
-        assert_eq!(repo.author_filter, Some("alice@example.com".to_string()));
+        assert_eq!(
+            repo.author_filters,
+            vec![
+                AuthorMatcher::Email("alice@example.com".into()),
+                AuthorMatcher::Regex("^Alice ".into()),
+            ]
+        );
+        assert_eq!(repo.include_paths, vec!["src/"]);
     }
 
     #[test]
@@ -1433,9 +2874,16 @@ mod tests {
                 remote_url: None,
                 default_branch: None,
                 fork_of: None,
-                author_filter: None,
+                author_filters: vec![],
+                include_paths: vec![],
+                ref_patterns: vec![],
+                projects: vec![],
                 exclude_prefixes: vec!["dist/".into(), "vendor/".into()],
                 embed_enabled: false,
+                clone_depth: None,
+                clone_since: None,
+                ssh_key: None,
+                token_env: None,
             })
             .expect("add_repo");
 
@@ -1463,6 +2911,13 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(192), "3m12s");
+        assert_eq!(format_duration(3900), "1h05m");
+    }
+
     #[test]
     fn test_url_validation_rejects_bare_string() {
         let result = validate_git_url("not-a-url");
@@ -1744,4 +3199,425 @@ mod tests {
         ]);
         assert!(cli.is_ok(), "reindex should parse with all flags");
     }
+
+    #[test]
+    fn test_update_repo_author_flags_parse() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from([
+            "commitmux",
+            "update-repo",
+            "myrepo",
+            "--author",
+            "alice@work.com",
+            "--author",
+            "re:@users\\.noreply",
+            "--remove-author",
+            "alice@old.com",
+        ])
+        .expect("update-repo should parse");
+        match cli.command {
+            Commands::UpdateRepo {
+                author,
+                remove_author,
+                ..
+            } => {
+                assert_eq!(author, vec!["alice@work.com", "re:@users\\.noreply"]);
+                assert_eq!(remove_author, vec!["alice@old.com"]);
+            }
+            _ => panic!("expected UpdateRepo command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_author_matchers_rejects_bad_regex() {
+        let ok = parse_author_matchers(&["bob".into(), "re:^b.b".into()]).expect("valid");
+        assert_eq!(ok[0], AuthorMatcher::Name("bob".into()));
+        assert!(parse_author_matchers(&["re:(".into()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_ref_patterns() {
+        let patterns =
+            parse_ref_patterns(&["github".into(), "jira:PROJ".into()]).expect("valid patterns");
+        assert_eq!(
+            patterns,
+            vec![RefPattern::Github, RefPattern::Jira(vec!["PROJ".into()])]
+        );
+        assert!(parse_ref_patterns(&["bitbucket".into()]).is_err());
+        assert!(parse_ref_patterns(&["re:(".into()]).is_err());
+        assert!(
+            parse_ref_patterns(&["jira".into()]).is_err(),
+            "jira needs project keys"
+        );
+    }
+
+    #[test]
+    fn test_parse_projects() {
+        let projects = parse_projects(&["web=apps/web/".into()]).expect("valid projects");
+        assert_eq!(projects[0].name, "web");
+        assert_eq!(projects[0].prefix, "apps/web/");
+        assert!(parse_projects(&["apps/web/".into()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-01").unwrap(), 0);
+        assert_eq!(parse_date("2024-03-01").unwrap(), 1709251200);
+        assert_eq!(parse_date("1969-12-31").unwrap(), -86400);
+        assert_eq!(parse_date("1709251200").unwrap(), 1709251200);
+        assert_eq!(
+            format_timestamp(parse_date("2000-02-29").unwrap()),
+            "2000-02-29 00:00:00 UTC"
+        );
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("last week").is_err());
+    }
+
+    #[test]
+    fn test_add_repo_clone_window_needs_url() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from([
+            "commitmux",
+            "add-repo",
+            "--url",
+            "https://example.com/big.git",
+            "--depth",
+            "50",
+            "--since",
+            "2024-01-01",
+        ]);
+        assert!(cli.is_ok(), "--depth and --since should parse with --url");
+        let cli = Cli::try_parse_from(["commitmux", "add-repo", ".", "--depth", "50"]);
+        assert!(cli.is_err(), "--depth should require --url");
+    }
+
+    #[test]
+    fn test_add_repos_scan_defaults() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["commitmux", "add-repos", "--scan", "~/src"]).unwrap();
+        match cli.command {
+            Commands::AddRepos {
+                depth,
+                dry_run,
+                prune,
+                ..
+            } => {
+                assert_eq!(depth, 3);
+                assert!(!dry_run);
+                assert!(!prune);
+            }
+            _ => panic!("expected add-repos"),
+        }
+        assert!(
+            Cli::try_parse_from(["commitmux", "add-repos"]).is_err(),
+            "--scan should be required"
+        );
+    }
+
+    #[test]
+    fn test_parse_line_range() {
+        assert_eq!(parse_line_range("10,20").unwrap(), (10, Some(20)));
+        assert_eq!(parse_line_range("7").unwrap(), (7, None));
+        assert!(parse_line_range("0,3").is_err());
+        assert!(parse_line_range("20,10").is_err());
+        assert!(parse_line_range("a,b").is_err());
+    }
+
+    #[test]
+    fn test_cat_command_parses() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from([
+            "commitmux",
+            "cat",
+            "api",
+            "v1.2.0",
+            "src/lib.rs",
+            "-L",
+            "10,20",
+        ])
+        .expect("cat should parse");
+        match cli.command {
+            Commands::Cat {
+                repo,
+                sha,
+                path,
+                lines,
+                offset,
+                ..
+            } => {
+                assert_eq!(repo, "api");
+                assert_eq!(sha, "v1.2.0");
+                assert_eq!(path, "src/lib.rs");
+                assert_eq!(lines.as_deref(), Some("10,20"));
+                assert_eq!(offset, None);
+            }
+            _ => panic!("expected Cat command"),
+        }
+
+        let both = Cli::try_parse_from([
+            "commitmux",
+            "cat",
+            "api",
+            "HEAD",
+            "README.md",
+            "-L",
+            "1",
+            "--offset",
+            "5",
+        ]);
+        assert!(both.is_err(), "-L and --offset must conflict");
+    }
+
+    #[test]
+    fn test_diff_command_parses() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from([
+            "commitmux",
+            "diff",
+            "api",
+            "v1.2.0",
+            "v1.3.0",
+            "src/auth",
+            "docs",
+            "--stat",
+        ])
+        .expect("diff should parse");
+        match cli.command {
+            Commands::Diff {
+                repo,
+                from,
+                to,
+                paths,
+                stat,
+                max_bytes,
+                ..
+            } => {
+                assert_eq!(repo, "api");
+                assert_eq!((from.as_str(), to.as_str()), ("v1.2.0", "v1.3.0"));
+                assert_eq!(paths, vec!["src/auth", "docs"]);
+                assert!(stat);
+                assert_eq!(max_bytes, None);
+            }
+            _ => panic!("expected Diff command"),
+        }
+    }
+
+    #[test]
+    fn test_ls_tree_command_parses() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from([
+            "commitmux",
+            "ls-tree",
+            "api",
+            "v1.2.0",
+            "src",
+            "--depth",
+            "3",
+        ])
+        .expect("ls-tree should parse");
+        match cli.command {
+            Commands::LsTree {
+                repo,
+                sha,
+                path,
+                depth,
+                ..
+            } => {
+                assert_eq!(repo, "api");
+                assert_eq!(sha, "v1.2.0");
+                assert_eq!(path.as_deref(), Some("src"));
+                assert_eq!(depth, Some(3));
+            }
+            _ => panic!("expected LsTree command"),
+        }
+
+        let cli =
+            Cli::try_parse_from(["commitmux", "ls-tree", "api", "HEAD"]).expect("root listing");
+        assert!(matches!(cli.command, Commands::LsTree { path: None, .. }));
+    }
+
+    #[test]
+    fn test_symbol_history_command_parses() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from([
+            "commitmux",
+            "symbol-history",
+            "api",
+            "Parser::parse",
+            "--path",
+            "src/",
+            "--limit",
+            "5",
+        ])
+        .expect("symbol-history should parse");
+        match cli.command {
+            Commands::SymbolHistory {
+                repo,
+                symbol,
+                path,
+                limit,
+                json,
+                ..
+            } => {
+                assert_eq!(repo, "api");
+                assert_eq!(symbol, "Parser::parse");
+                assert_eq!(path.as_deref(), Some("src/"));
+                assert_eq!(limit, Some(5));
+                assert!(!json);
+            }
+            _ => panic!("expected SymbolHistory command"),
+        }
+    }
+
+    #[test]
+    fn test_refs_command_parses() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from([
+            "commitmux",
+            "refs",
+            "acme/api#1234",
+            "--repo",
+            "api",
+            "--repo",
+            "web",
+            "--json",
+        ])
+        .expect("refs should parse");
+        match cli.command {
+            Commands::Refs {
+                reference,
+                repo,
+                json,
+                ..
+            } => {
+                assert_eq!(reference, "acme/api#1234");
+                assert_eq!(repo, vec!["api", "web"]);
+                assert!(json);
+            }
+            _ => panic!("expected Refs command"),
+        }
+    }
+
+    #[test]
+    fn test_changelog_command_parses() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from([
+            "commitmux",
+            "changelog",
+            "api",
+            "v1.4.0..v1.5.0",
+            "--first-parent",
+            "--group-by",
+            "author",
+        ])
+        .expect("changelog should parse");
+        match cli.command {
+            Commands::Changelog {
+                repo,
+                range,
+                first_parent,
+                group_by,
+                json,
+                ..
+            } => {
+                assert_eq!(repo, "api");
+                assert_eq!(range, "v1.4.0..v1.5.0");
+                assert!(first_parent);
+                assert_eq!(group_by, "author");
+                assert!(!json);
+            }
+            _ => panic!("expected Changelog command"),
+        }
+    }
+
+    #[test]
+    fn test_identity_command_parses() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from([
+            "commitmux",
+            "identity",
+            "add",
+            "1+alice@users.noreply.github.com",
+            "--name",
+            "Alice",
+            "--email",
+            "alice@work.com",
+        ])
+        .expect("identity add should parse");
+        match cli.command {
+            Commands::Identity {
+                action: IdentityAction::Add { alias, name, email },
+                ..
+            } => {
+                assert_eq!(alias, "1+alice@users.noreply.github.com");
+                assert_eq!(name, "Alice");
+                assert_eq!(email, "alice@work.com");
+            }
+            _ => panic!("expected identity add"),
+        }
+        assert!(Cli::try_parse_from(["commitmux", "identity", "list"]).is_ok());
+    }
+
+    #[test]
+    fn test_watch_command_parses() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["commitmux", "watch"]).expect("watch should parse");
+        match cli.command {
+            Commands::Watch {
+                repo, watch_args, ..
+            } => {
+                assert!(repo.is_none());
+                assert_eq!(watch_args.debounce_ms, 2000);
+                assert_eq!(watch_args.fetch_interval, 300);
+            }
+            _ => panic!("expected Watch command"),
+        }
+
+        let cli = Cli::try_parse_from([
+            "commitmux",
+            "watch",
+            "--repo",
+            "myrepo",
+            "--debounce-ms",
+            "500",
+            "--fetch-interval",
+            "0",
+        ])
+        .expect("watch should parse with flags");
+        match cli.command {
+            Commands::Watch {
+                repo, watch_args, ..
+            } => {
+                assert_eq!(repo.as_deref(), Some("myrepo"));
+                assert_eq!(watch_args.debounce_ms, 500);
+                assert_eq!(watch_args.fetch_interval, 0);
+                assert!(watch_args.options(false).fetch_interval.is_none());
+            }
+            _ => panic!("expected Watch command"),
+        }
+
+        let cli = Cli::try_parse_from(["commitmux", "serve", "--watch", "--debounce-ms", "250"])
+            .expect("serve --watch");
+        match cli.command {
+            Commands::Serve {
+                watch, watch_args, ..
+            } => {
+                assert!(watch);
+                assert_eq!(watch_args.debounce_ms, 250);
+                assert_eq!(watch_args.fetch_interval, 300);
+            }
+            _ => panic!("expected Serve command"),
+        }
+    }
 }
@@ -0,0 +1,541 @@
+//! Background sync: re-index repos when their refs move.
+//!
+//! Each repo's git directory is watched for changes to `HEAD`, `packed-refs`
+//! and anything under `refs/`. Events are debounced per repo so that a rebase
+//! or a fetch that rewrites many refs triggers a single sync. Managed clones
+//! (under `~/.commitmux/clones/`) have no local commits to react to, so they
+//! are additionally re-synced (which fetches) on a fixed interval. When
+//! following the store, its repo list is re-read periodically so repos added
+//! or removed while watching are picked up.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use notify::{RecursiveMode, Watcher};
+
+use commitmux_embed::EmbedConfig;
+use commitmux_ingest::Git2Ingester;
+use commitmux_types::{IgnoreConfig, Ingester, Repo, Store};
+
+/// Tuning knobs for [`run_watch`].
+pub struct WatchOptions {
+    /// Quiet period after the last ref change before a repo is synced.
+    pub debounce: Duration,
+    /// How often managed clones are fetched and synced; `None` disables polling.
+    pub fetch_interval: Option<Duration>,
+    /// Re-read the store's repo list while watching, so repos added or
+    /// removed after start are picked up.
+    pub follow_repos: bool,
+}
+
+struct WatchTarget {
+    name: String,
+    local_path: PathBuf,
+    /// Per-worktree git directory (holds `HEAD`).
+    git_dir: PathBuf,
+    /// Shared git directory (holds `refs/` and `packed-refs`).
+    common_dir: PathBuf,
+    /// Directories registered with the watcher.
+    paths: Vec<PathBuf>,
+    managed: bool,
+    /// [`ref_state`] right after the last sync, so that the ref events a
+    /// sync's own fetch causes do not trigger another.
+    synced_refs: Option<u64>,
+}
+
+/// Returns true if `path` (inside a watched git directory) is a ref that can
+/// change what `sync_repo` would index. Lock files are ignored: git writes
+/// `<ref>.lock` and renames it into place, and the rename is what we act on.
+fn is_ref_change(git_dir: &Path, common_dir: &Path, path: &Path) -> bool {
+    if path.extension().is_some_and(|ext| ext == "lock") {
+        return false;
+    }
+    if path == git_dir.join("HEAD") || path == common_dir.join("packed-refs") {
+        return true;
+    }
+    path.starts_with(common_dir.join("refs"))
+}
+
+/// Resolves the shared git directory. Linked worktrees keep their own `HEAD`
+/// but point at the main repository's refs through a `commondir` file.
+pub(crate) fn common_dir(git_dir: &Path) -> PathBuf {
+    match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(contents) => {
+            let dir = git_dir.join(contents.trim());
+            dir.canonicalize().unwrap_or(dir)
+        }
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+/// Fingerprint of where `HEAD` and every ref of the repo at `path` point.
+fn ref_state(path: &Path) -> Option<u64> {
+    let git_repo = git2::Repository::open(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    let refs = git_repo
+        .find_reference("HEAD")
+        .into_iter()
+        .chain(git_repo.references().ok()?.filter_map(|r| r.ok()));
+    for r in refs {
+        r.name_bytes().hash(&mut hasher);
+        r.target().hash(&mut hasher);
+        r.symbolic_target_bytes().hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+/// The runtime embedding runs on, built once per watcher.
+pub fn embed_runtime() -> Result<tokio::runtime::Runtime> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to create async runtime")
+}
+
+/// Returns true if the repo lives under the managed clones directory.
+fn is_managed_clone(repo: &Repo) -> bool {
+    repo.local_path.starts_with(crate::managed_clones_dir())
+}
+
+/// Syncs one repo and, when enabled, embeds its new commits on `rt`. All
+/// output goes to stderr so this is safe to call while the MCP server owns
+/// stdout.
+pub fn sync_and_embed(store: &dyn Store, repo: &Repo, rt: &tokio::runtime::Runtime) {
+    let ingester = Git2Ingester::new();
+    let config = IgnoreConfig::default();
+    match ingester.sync_repo(repo, store, &config) {
+        Ok(summary) => {
+            if summary.commits_indexed > 0 {
+                eprintln!(
+                    "commitmux: sync '{}': {} indexed, {} already indexed",
+                    repo.name, summary.commits_indexed, summary.commits_already_indexed
+                );
+            }
+            for err in &summary.errors {
+                eprintln!("commitmux: warning: {}", err);
+            }
+        }
+        Err(e) => {
+            eprintln!("commitmux: warning: sync failed for '{}': {}", repo.name, e);
+            return;
+        }
+    }
+
+    if repo.embed_enabled {
+        match EmbedConfig::from_store(store) {
+            Ok(config) => {
+                let embedder = commitmux_embed::Embedder::new(&config);
+                match rt.block_on(commitmux_embed::embed_pending(
+                    store,
+                    &embedder,
+                    repo.repo_id,
+                    50,
+                )) {
+                    Ok(esummary) => {
+                        if esummary.embedded > 0 || esummary.failed > 0 {
+                            eprintln!(
+                                "commitmux: embedded {} commits for '{}' ({} failed)",
+                                esummary.embedded, repo.name, esummary.failed
+                            );
+                        }
+                    }
+                    Err(e) => eprintln!(
+                        "commitmux: warning: embedding failed for '{}': {e}",
+                        repo.name
+                    ),
+                }
+            }
+            Err(e) => eprintln!(
+                "commitmux: warning: embed config error for '{}': {e}",
+                repo.name
+            ),
+        }
+    }
+}
+
+/// How often the store's repo list is re-read when following it.
+const REPO_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The file watcher and the repos it covers.
+struct Watched {
+    watcher: notify::RecommendedWatcher,
+    targets: Vec<WatchTarget>,
+    /// Repos that could not be watched, by name and path, so that following
+    /// the store warns about each only once.
+    skipped: Vec<(String, PathBuf)>,
+}
+
+impl Watched {
+    /// Starts watching `repo`'s git directories. A repo that is not a git
+    /// repository or cannot be watched is warned about and skipped, so it
+    /// does not stop the others from being watched; returns false then.
+    fn add(&mut self, repo: &Repo) -> bool {
+        let git_repo = match git2::Repository::open(&repo.local_path) {
+            Ok(r) => r,
+            Err(_) => {
+                eprintln!(
+                    "commitmux: warning: '{}' is not a git repository, not watching '{}'",
+                    repo.local_path.display(),
+                    repo.name
+                );
+                self.skipped
+                    .push((repo.name.clone(), repo.local_path.clone()));
+                return false;
+            }
+        };
+        let git_dir = git_repo.path().to_path_buf();
+        let common_dir = common_dir(&git_dir);
+
+        // The git directories are watched non-recursively so that HEAD and
+        // packed-refs are seen even when git replaces them via rename.
+        let mut watch_paths = vec![
+            (git_dir.clone(), RecursiveMode::NonRecursive),
+            (common_dir.join("refs"), RecursiveMode::Recursive),
+        ];
+        if common_dir != git_dir {
+            watch_paths.push((common_dir.clone(), RecursiveMode::NonRecursive));
+        }
+        for (i, (path, mode)) in watch_paths.iter().enumerate() {
+            if let Err(e) = self.watcher.watch(path, *mode) {
+                eprintln!(
+                    "commitmux: warning: failed to watch {}, not watching '{}': {}",
+                    path.display(),
+                    repo.name,
+                    e
+                );
+                for (watched, _) in &watch_paths[..i] {
+                    if !self.targets.iter().any(|t| t.paths.contains(watched)) {
+                        let _ = self.watcher.unwatch(watched);
+                    }
+                }
+                self.skipped
+                    .push((repo.name.clone(), repo.local_path.clone()));
+                return false;
+            }
+        }
+
+        self.targets.push(WatchTarget {
+            name: repo.name.clone(),
+            local_path: repo.local_path.clone(),
+            git_dir,
+            common_dir,
+            paths: watch_paths.into_iter().map(|(path, _)| path).collect(),
+            managed: is_managed_clone(repo),
+            synced_refs: None,
+        });
+        true
+    }
+
+    /// Syncs `repo` and records its refs afterwards.
+    fn sync(&mut self, store: &dyn Store, repo: &Repo, rt: &tokio::runtime::Runtime) {
+        sync_and_embed(store, repo, rt);
+        if let Some(target) = self.targets.iter_mut().find(|t| t.name == repo.name) {
+            target.synced_refs = ref_state(&target.local_path);
+        }
+    }
+
+    /// Re-reads the repo from the store (so `update-repo` changes made while
+    /// watching take effect) and syncs it.
+    fn resync(&mut self, store: &dyn Store, name: &str, rt: &tokio::runtime::Runtime) {
+        match store.get_repo_by_name(name) {
+            Ok(Some(repo)) => self.sync(store, &repo, rt),
+            Ok(None) => eprintln!("commitmux: warning: repo '{}' was removed", name),
+            Err(e) => eprintln!("commitmux: warning: failed to look up '{}': {}", name, e),
+        }
+    }
+
+    /// True if the refs of the watched repo `name` are where its last sync
+    /// left them, as after the ref events of that sync's own fetch.
+    fn refs_unchanged(&self, name: &str) -> bool {
+        self.targets.iter().any(|t| {
+            t.name == name && t.synced_refs.is_some() && t.synced_refs == ref_state(&t.local_path)
+        })
+    }
+
+    /// Brings the watched set in line with `repos`: repos removed from the
+    /// store (or moved to another path) stop being watched, and new ones are
+    /// synced once and then watched.
+    fn refresh(&mut self, store: &dyn Store, repos: &[Repo], rt: &tokio::runtime::Runtime) {
+        let listed =
+            |name: &str, path: &Path| repos.iter().any(|r| r.name == name && r.local_path == path);
+        self.skipped.retain(|(name, path)| listed(name, path));
+        let (kept, removed): (Vec<_>, Vec<_>) = std::mem::take(&mut self.targets)
+            .into_iter()
+            .partition(|t| listed(&t.name, &t.local_path));
+        self.targets = kept;
+        for target in removed {
+            eprintln!("commitmux: no longer watching '{}'", target.name);
+            // Worktrees of one repository share their refs directory.
+            for path in &target.paths {
+                if !self.targets.iter().any(|t| t.paths.contains(path)) {
+                    let _ = self.watcher.unwatch(path);
+                }
+            }
+        }
+
+        for repo in repos {
+            let known = self.targets.iter().any(|t| t.name == repo.name)
+                || self.skipped.iter().any(|(name, _)| *name == repo.name);
+            if known {
+                continue;
+            }
+            if self.add(repo) {
+                eprintln!("commitmux: watching new repo '{}'", repo.name);
+                self.sync(store, repo, rt);
+            }
+        }
+    }
+}
+
+/// Watches `repos` and keeps the index up to date. With
+/// `opts.follow_repos`, repos added to or removed from the store while
+/// watching are picked up. Runs until the watcher channel closes, which in
+/// practice means until the process exits.
+pub fn run_watch(
+    store: &dyn Store,
+    repos: &[Repo],
+    opts: &WatchOptions,
+    rt: &tokio::runtime::Runtime,
+) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watched = Watched {
+        watcher: notify::recommended_watcher(tx).context("Failed to create file watcher")?,
+        targets: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for repo in repos {
+        watched.add(repo);
+    }
+
+    eprintln!(
+        "commitmux: watching {} repo(s) for ref changes",
+        watched.targets.len()
+    );
+
+    let mut pending: HashMap<String, Instant> = HashMap::new();
+    let mut last_fetch = Instant::now();
+    let mut last_poll = Instant::now();
+
+    loop {
+        match rx.recv_timeout(Duration::from_millis(250)) {
+            Ok(Ok(event)) => {
+                for path in &event.paths {
+                    for t in &watched.targets {
+                        if is_ref_change(&t.git_dir, &t.common_dir, path) {
+                            pending.insert(t.name.clone(), Instant::now());
+                        }
+                    }
+                }
+            }
+            Ok(Err(e)) => eprintln!("commitmux: warning: watch error: {}", e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let due: Vec<String> = pending
+            .iter()
+            .filter(|(_, changed_at)| changed_at.elapsed() >= opts.debounce)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in due {
+            pending.remove(&name);
+            if !watched.refs_unchanged(&name) {
+                watched.resync(store, &name, rt);
+            }
+        }
+
+        if let Some(interval) = opts.fetch_interval {
+            if last_fetch.elapsed() >= interval {
+                let managed: Vec<String> = watched
+                    .targets
+                    .iter()
+                    .filter(|t| t.managed)
+                    .map(|t| t.name.clone())
+                    .collect();
+                for name in managed {
+                    watched.resync(store, &name, rt);
+                }
+                last_fetch = Instant::now();
+            }
+        }
+
+        if opts.follow_repos && last_poll.elapsed() >= REPO_POLL_INTERVAL {
+            match store.list_repos() {
+                Ok(repos) => {
+                    watched.refresh(store, &repos, rt);
+                    pending.retain(|name, _| watched.targets.iter().any(|t| t.name == *name));
+                }
+                Err(e) => eprintln!("commitmux: warning: failed to list repos: {}", e),
+            }
+            last_poll = Instant::now();
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ref_change() {
+        let git_dir = Path::new("/repo/.git");
+        assert!(is_ref_change(
+            git_dir,
+            git_dir,
+            Path::new("/repo/.git/HEAD")
+        ));
+        assert!(is_ref_change(
+            git_dir,
+            git_dir,
+            Path::new("/repo/.git/packed-refs")
+        ));
+        assert!(is_ref_change(
+            git_dir,
+            git_dir,
+            Path::new("/repo/.git/refs/heads/main")
+        ));
+        assert!(!is_ref_change(
+            git_dir,
+            git_dir,
+            Path::new("/repo/.git/refs/heads/main.lock")
+        ));
+        assert!(!is_ref_change(
+            git_dir,
+            git_dir,
+            Path::new("/repo/.git/index")
+        ));
+        assert!(!is_ref_change(
+            git_dir,
+            git_dir,
+            Path::new("/repo/.git/FETCH_HEAD")
+        ));
+    }
+
+    #[test]
+    fn test_is_ref_change_worktree() {
+        let git_dir = Path::new("/repo/.git/worktrees/wt");
+        let common_dir = Path::new("/repo/.git");
+        assert!(is_ref_change(
+            git_dir,
+            common_dir,
+            Path::new("/repo/.git/worktrees/wt/HEAD")
+        ));
+        assert!(is_ref_change(
+            git_dir,
+            common_dir,
+            Path::new("/repo/.git/refs/heads/feature")
+        ));
+        // The main worktree's HEAD does not affect this worktree's tip.
+        assert!(!is_ref_change(
+            git_dir,
+            common_dir,
+            Path::new("/repo/.git/HEAD")
+        ));
+    }
+
+    #[test]
+    fn test_refresh_follows_store() {
+        let store = commitmux_store::SqliteStore::open_in_memory().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().canonicalize().unwrap();
+        git2::Repository::init(&path).unwrap();
+        let mut input = commitmux_types::RepoInput {
+            name: "app".to_string(),
+            local_path: path.clone(),
+            remote_url: None,
+            default_branch: None,
+            fork_of: None,
+            author_filters: vec![],
+            include_paths: vec![],
+            ref_patterns: vec![],
+            projects: vec![],
+            exclude_prefixes: vec![],
+            embed_enabled: false,
+            clone_depth: None,
+            clone_since: None,
+            ssh_key: None,
+            token_env: None,
+        };
+        store.add_repo(&input).unwrap();
+        input.name = "notes".to_string();
+        input.local_path = path.join("missing");
+        store.add_repo(&input).unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let mut watched = Watched {
+            watcher: notify::recommended_watcher(tx).unwrap(),
+            targets: Vec::new(),
+            skipped: Vec::new(),
+        };
+        let rt = embed_runtime().unwrap();
+        watched.refresh(&store, &store.list_repos().unwrap(), &rt);
+        let names: Vec<&str> = watched.targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["app"]);
+        assert_eq!(watched.skipped.len(), 1);
+
+        store.remove_repo("app").unwrap();
+        store.remove_repo("notes").unwrap();
+        watched.refresh(&store, &store.list_repos().unwrap(), &rt);
+        assert!(watched.targets.is_empty());
+        assert!(watched.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_refs_unchanged_after_own_sync() {
+        let store = commitmux_store::SqliteStore::open_in_memory().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().canonicalize().unwrap();
+        let git_repo = git2::Repository::init(&path).unwrap();
+        let sig = git2::Signature::now("Test User", "test@example.com").unwrap();
+        let tree = git_repo.treebuilder(None).unwrap().write().unwrap();
+        let tree = git_repo.find_tree(tree).unwrap();
+        let head = git_repo
+            .commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+            .unwrap();
+        store
+            .add_repo(&commitmux_types::RepoInput {
+                name: "app".to_string(),
+                local_path: path.clone(),
+                remote_url: None,
+                default_branch: None,
+                fork_of: None,
+                author_filters: vec![],
+                include_paths: vec![],
+                ref_patterns: vec![],
+                projects: vec![],
+                exclude_prefixes: vec![],
+                embed_enabled: false,
+                clone_depth: None,
+                clone_since: None,
+                ssh_key: None,
+                token_env: None,
+            })
+            .unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let mut watched = Watched {
+            watcher: notify::recommended_watcher(tx).unwrap(),
+            targets: Vec::new(),
+            skipped: Vec::new(),
+        };
+        let rt = embed_runtime().unwrap();
+        // Nothing is known about a repo's refs before its first sync.
+        watched.add(&store.get_repo_by_name("app").unwrap().unwrap());
+        assert!(!watched.refs_unchanged("app"));
+
+        watched.resync(&store, "app", &rt);
+        assert!(watched.refs_unchanged("app"));
+
+        git_repo
+            .branch("feature", &git_repo.find_commit(head).unwrap(), false)
+            .unwrap();
+        assert!(!watched.refs_unchanged("app"));
+    }
+}
@@ -1,6 +1,10 @@
 use commitmux_ingest::Git2Ingester;
+use commitmux_mcp::service;
 use commitmux_store::SqliteStore;
-use commitmux_types::{IgnoreConfig, Ingester, RepoInput, SearchOpts, Store, TouchOpts};
+use commitmux_types::{
+    AuthorMatcher, ChangelogGroupBy, ChangelogOpts, FileKind, IgnoreConfig, Ingester,
+    PatchLineKind, RefPattern, RemoteAuth, RepoInput, SearchOpts, Store, SymbolOpts, TouchOpts,
+};
 
 #[test]
 fn test_end_to_end() {
@@ -56,9 +60,16 @@ fn test_end_to_end() {
         remote_url: None,
         default_branch: None,
         fork_of: None,
-        author_filter: None,
+        author_filters: vec![],
+        include_paths: vec![],
+        ref_patterns: vec![],
+        projects: vec![],
         exclude_prefixes: vec![],
         embed_enabled: false,
+        clone_depth: None,
+        clone_since: None,
+        ssh_key: None,
+        token_env: None,
     };
     let repo = store.add_repo(&repo_input).unwrap();
 
@@ -78,6 +89,12 @@ fn test_end_to_end() {
         since: None,
         repos: None,
         paths: None,
+        authors: None,
+        trailer: None,
+        cc_types: None,
+        cc_scopes: None,
+        breaking: None,
+        projects: None,
         limit: Some(10),
     };
     let results = store.search("initial commit", &opts).unwrap();
@@ -98,4 +115,691 @@ fn test_end_to_end() {
     let detail = store.get_commit("test-repo", sha).unwrap();
     assert!(detail.is_some());
     assert_eq!(detail.unwrap().repo, "test-repo");
+
+    // 7. Patch text round-trips through ingest and storage uncompressed
+    let patch = store
+        .get_patch("test-repo", sha, None)
+        .unwrap()
+        .expect("patch for indexed commit");
+    assert!(
+        patch.patch_text.contains("README.md"),
+        "Expected readable patch text, got: {:?}",
+        patch.patch_text
+    );
+    let head = git_repo.head().unwrap().target().unwrap().to_string();
+    let patch = store
+        .get_patch("test-repo", &head, None)
+        .unwrap()
+        .expect("patch for HEAD");
+    let structured = commitmux_ingest::structured_patch(&patch, &[], None).unwrap();
+    assert_eq!(structured.files.len(), 1);
+    assert_eq!(structured.files[0].path, "src/main.rs");
+    assert_eq!(structured.files[0].status, "A");
+    let line = &structured.files[0].hunks[0].lines[0];
+    assert_eq!(line.kind, PatchLineKind::Add);
+    assert_eq!(
+        (line.new_line, line.text.as_str()),
+        (Some(1), "fn main() {}")
+    );
+
+    // 8. A no-op re-sync walks nothing new
+    let summary = ingester.sync_repo(&repo, &store, &config).unwrap();
+    assert_eq!(summary.commits_indexed, 0);
+    assert_eq!(summary.commits_already_indexed, 0);
+
+    // 9. Changelog over the whole history, grouped by top-level directory
+    let changelog = service::changelog(
+        &store,
+        &ingester,
+        &ChangelogOpts {
+            repo: "test-repo".to_string(),
+            from: None,
+            to: "HEAD".to_string(),
+            first_parent: true,
+            group_by: ChangelogGroupBy::Directory,
+        },
+    )
+    .unwrap();
+    assert_eq!((changelog.total, changelog.unindexed), (2, 0));
+    let groups: Vec<&str> = changelog.groups.iter().map(|g| g.name.as_str()).collect();
+    assert_eq!(groups, vec!["/", "src"]);
+    assert_eq!(changelog.groups[1].commits[0].subject, "add main.rs");
+
+    // 10. A tag added after the last sync is picked up without new commits
+    let head = git_repo.head().unwrap().peel_to_commit().unwrap();
+    git_repo
+        .tag("v0.1.0", head.as_object(), &sig, "First release", false)
+        .unwrap();
+    ingester.sync_repo(&repo, &store, &config).unwrap();
+    let tagged = store
+        .get_commit("test-repo", &head.id().to_string())
+        .unwrap()
+        .unwrap();
+    assert_eq!(tagged.first_release.as_deref(), Some("v0.1.0"));
+    assert_eq!(tagged.contained_in, vec!["v0.1.0"]);
+
+    // 11. Symbol history finds the commit that added main()
+    let changes = store
+        .symbol_history(repo.repo_id, "main", &SymbolOpts::default())
+        .unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].subject, "add main.rs");
+    assert_eq!(
+        (changes[0].path.as_str(), changes[0].kind.as_str()),
+        ("src/main.rs", "function")
+    );
+    assert!(changes[0].excerpt.contains("+fn main() {}"));
+}
+
+#[test]
+fn test_submodule_bump_linked_to_submodule_history() {
+    let db_dir = tempfile::tempdir().unwrap();
+    let store = SqliteStore::open(&db_dir.path().join("test.sqlite3")).unwrap();
+    let ingester = Git2Ingester::new();
+    let config = IgnoreConfig::default();
+    let sig = git2::Signature::now("Test User", "test@example.com").unwrap();
+    let repo_input = |name: &str, path: &std::path::Path| RepoInput {
+        name: name.to_string(),
+        local_path: path.to_path_buf(),
+        remote_url: None,
+        default_branch: None,
+        fork_of: None,
+        author_filters: vec![],
+        include_paths: vec![],
+        ref_patterns: vec![],
+        projects: vec![],
+        exclude_prefixes: vec![],
+        embed_enabled: false,
+        clone_depth: None,
+        clone_since: None,
+        ssh_key: None,
+        token_env: None,
+    };
+    // Commits `tree` on HEAD and returns the new commit's id.
+    let commit = |repo: &git2::Repository, tree: git2::Oid, message: &str| {
+        let tree = repo.find_tree(tree).unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap()
+    };
+
+    // The library that gets vendored as a submodule.
+    let lib_dir = tempfile::tempdir().unwrap();
+    let lib_git = git2::Repository::init(lib_dir.path()).unwrap();
+    let mut lib_commits = Vec::new();
+    for i in 1..=3 {
+        let blob = lib_git.blob(format!("v{}\n", i).as_bytes()).unwrap();
+        let mut tree = lib_git.treebuilder(None).unwrap();
+        tree.insert("lib.txt", blob, 0o100644).unwrap();
+        let tree = tree.write().unwrap();
+        lib_commits.push(commit(&lib_git, tree, &format!("lib release {}", i)));
+    }
+
+    // The superproject pins the library at its first commit, then bumps it.
+    let app_dir = tempfile::tempdir().unwrap();
+    let app_git = git2::Repository::init(app_dir.path()).unwrap();
+    let gitmodules = format!(
+        "[submodule \"vendor-lib\"]\n\tpath = lib\n\turl = {}\n",
+        lib_dir.path().display()
+    );
+    let gitmodules = app_git.blob(gitmodules.as_bytes()).unwrap();
+    let mut app_commits = Vec::new();
+    for (pin, message) in [(lib_commits[0], "add lib"), (lib_commits[2], "bump lib")] {
+        let mut tree = app_git.treebuilder(None).unwrap();
+        tree.insert(".gitmodules", gitmodules, 0o100644).unwrap();
+        tree.insert("lib", pin, 0o160000).unwrap();
+        let tree = tree.write().unwrap();
+        app_commits.push(commit(&app_git, tree, message));
+    }
+
+    for (name, dir) in [("lib", lib_dir.path()), ("app", app_dir.path())] {
+        let repo = store.add_repo(&repo_input(name, dir)).unwrap();
+        ingester.sync_repo(&repo, &store, &config).unwrap();
+    }
+
+    let bump = service::commit_detail(&store, &ingester, "app", &app_commits[1].to_string())
+        .unwrap()
+        .unwrap();
+    assert_eq!(bump.changed_files.len(), 1);
+    assert_eq!(bump.changed_files[0].path, "lib");
+    assert_eq!(bump.changed_files[0].kind, FileKind::Submodule);
+    let submodule = &bump.submodules[0];
+    assert_eq!(submodule.path, "lib");
+    assert_eq!(submodule.old_sha, Some(lib_commits[0].to_string()));
+    assert_eq!(submodule.new_sha, Some(lib_commits[2].to_string()));
+    assert_eq!(submodule.repo.as_deref(), Some("lib"));
+    assert_eq!(submodule.commit_count, Some(2));
+    let pulled: Vec<(String, Option<&str>)> = submodule
+        .commits
+        .iter()
+        .map(|c| (c.sha.clone(), c.subject.as_deref()))
+        .collect();
+    assert_eq!(
+        pulled,
+        vec![
+            (lib_commits[2].to_string(), Some("lib release 3")),
+            (lib_commits[1].to_string(), Some("lib release 2")),
+        ]
+    );
+
+    // Adding the submodule pulls in its history up to the pinned commit.
+    let added = service::commit_detail(&store, &ingester, "app", &app_commits[0].to_string())
+        .unwrap()
+        .unwrap();
+    assert_eq!(added.submodules[0].old_sha, None);
+    assert_eq!(added.submodules[0].commit_count, Some(1));
+
+    // The store alone records the bump without linking it.
+    let stored = store
+        .get_commit("app", &app_commits[1].to_string())
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.submodules[0].repo, None);
+    assert!(stored.submodules[0].commits.is_empty());
+}
+
+/// Serves the repos under `root` over smart HTTP, through `git http-backend`,
+/// and returns the port. Shallow fetches need a remote: libgit2 does not
+/// support them over its local transport. With `authorization`, requests
+/// without that exact `Authorization` header get a Basic challenge. None if
+/// `git` is not installed.
+fn serve_git_http(root: &std::path::Path, authorization: Option<&'static str>) -> Option<u16> {
+    use std::io::{BufRead, Read, Write};
+
+    std::process::Command::new("git")
+        .arg("--version")
+        .output()
+        .ok()?;
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").ok()?;
+    let port = listener.local_addr().ok()?.port();
+    let root = root.to_path_buf();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = std::io::BufReader::new(&stream);
+            let mut request_line = String::new();
+            let mut content_type = String::new();
+            let mut content_length = 0;
+            let mut authorized = authorization.is_none();
+            reader.read_line(&mut request_line).unwrap();
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                let Some((name, value)) = header.trim_end().split_once(": ") else {
+                    break;
+                };
+                match name.to_ascii_lowercase().as_str() {
+                    "content-type" => content_type = value.to_string(),
+                    "content-length" => content_length = value.parse().unwrap(),
+                    "authorization" => authorized |= Some(value) == authorization,
+                    _ => {}
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            if !authorized {
+                let _ = (&stream).write_all(
+                    b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"git\"\r\n\
+                      Content-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+                continue;
+            }
+
+            let mut parts = request_line.split_whitespace();
+            let method = parts.next().unwrap_or_default();
+            let target = parts.next().unwrap_or_default();
+            let (path, query) = target.split_once('?').unwrap_or((target, ""));
+            let mut backend = std::process::Command::new("git")
+                .arg("http-backend")
+                .env("GIT_PROJECT_ROOT", &root)
+                .env("GIT_HTTP_EXPORT_ALL", "1")
+                .env("REQUEST_METHOD", method)
+                .env("PATH_INFO", path)
+                .env("QUERY_STRING", query)
+                .env("CONTENT_TYPE", &content_type)
+                .env("CONTENT_LENGTH", content_length.to_string())
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::null())
+                .spawn()
+                .unwrap();
+            backend.stdin.take().unwrap().write_all(&body).unwrap();
+            let output = backend.wait_with_output().unwrap().stdout;
+
+            // Turn the CGI response into an HTTP one.
+            let split = output
+                .windows(4)
+                .position(|w| w == b"\r\n\r\n")
+                .unwrap_or(output.len());
+            let headers = String::from_utf8_lossy(&output[..split]);
+            let body = output.get(split + 4..).unwrap_or_default();
+            let mut status = "200 OK".to_string();
+            let mut response = String::new();
+            for header in headers.lines() {
+                match header.strip_prefix("Status: ") {
+                    Some(s) => status = s.to_string(),
+                    None => response.push_str(&format!("{}\r\n", header)),
+                }
+            }
+            let mut stream = &stream;
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                response,
+                body.len()
+            );
+            let _ = stream.write_all(body);
+        }
+    });
+    Some(port)
+}
+
+#[test]
+fn test_shallow_clone_indexes_within_depth_and_window() {
+    let served = tempfile::tempdir().unwrap();
+    let source_dir = served.path().join("source");
+    let source = git2::Repository::init(&source_dir).unwrap();
+    let day = 86_400;
+    let commit = |i: i64| {
+        let sig = git2::Signature::new(
+            "Test User",
+            "test@example.com",
+            &git2::Time::new(i * day, 0),
+        )
+        .unwrap();
+        let blob = source.blob(format!("v{}\n", i).as_bytes()).unwrap();
+        let mut tree = source.treebuilder(None).unwrap();
+        tree.insert("file.txt", blob, 0o100644).unwrap();
+        let tree = source.find_tree(tree.write().unwrap()).unwrap();
+        let parent = source.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        let oid = source
+            .commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                &format!("change {}", i),
+                &tree,
+                &parents,
+            )
+            .unwrap();
+        oid.to_string()
+    };
+    let mut shas: Vec<String> = (1..=100).map(commit).collect();
+
+    let Some(port) = serve_git_http(served.path(), None) else {
+        eprintln!("skipping: git is not installed");
+        return;
+    };
+    let url = format!("http://127.0.0.1:{}/source", port);
+    let db_dir = tempfile::tempdir().unwrap();
+    let store = SqliteStore::open(&db_dir.path().join("test.sqlite3")).unwrap();
+    let ingester = Git2Ingester::new();
+    let config = IgnoreConfig::default();
+    let clones = tempfile::tempdir().unwrap();
+    // Syncs follow the fetched branch rather than the clone's own HEAD.
+    let branch = format!("origin/{}", source.head().unwrap().shorthand().unwrap());
+    let repo_input = |name: &str, depth: Option<u32>, since: Option<i64>| RepoInput {
+        name: name.to_string(),
+        local_path: clones.path().join(name),
+        remote_url: Some(url.clone()),
+        default_branch: Some(branch.clone()),
+        fork_of: None,
+        author_filters: vec![],
+        include_paths: vec![],
+        ref_patterns: vec![],
+        projects: vec![],
+        exclude_prefixes: vec![],
+        embed_enabled: false,
+        clone_depth: depth,
+        clone_since: since,
+        ssh_key: None,
+        token_env: None,
+    };
+    // Indices of the source commits indexed for `repo_id`.
+    let indexed = |repo_id: i64, shas: &[String]| -> Vec<usize> {
+        (0..shas.len())
+            .filter(|&i| store.commit_exists(repo_id, &shas[i]).unwrap())
+            .collect()
+    };
+
+    // --depth 3 indexes the three newest commits; the boundary commit they
+    // are diffed against is fetched but not indexed.
+    let input = repo_input("by-depth", Some(3), None);
+    let no_auth = RemoteAuth::default();
+    let git_repo =
+        commitmux_ingest::clone_repo(&url, &input.local_path, Some(3), None, &no_auth).unwrap();
+    assert!(git_repo.is_shallow());
+    let by_depth = store.add_repo(&input).unwrap();
+    let summary = ingester.sync_repo(&by_depth, &store, &config).unwrap();
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    assert_eq!(indexed(by_depth.repo_id, &shas), vec![97, 98, 99]);
+
+    // --since deepens the clone until it reaches back past the date.
+    let input = repo_input("by-date", None, Some(50 * day));
+    let git_repo =
+        commitmux_ingest::clone_repo(&url, &input.local_path, None, Some(50 * day), &no_auth)
+            .unwrap();
+    assert!(git_repo.is_shallow());
+    let by_date = store.add_repo(&input).unwrap();
+    ingester.sync_repo(&by_date, &store, &config).unwrap();
+    assert_eq!(
+        indexed(by_date.repo_id, &shas),
+        (49..100).collect::<Vec<_>>()
+    );
+
+    // New upstream commits are fetched within the depth.
+    shas.push(commit(101));
+    ingester.sync_repo(&by_depth, &store, &config).unwrap();
+    assert_eq!(indexed(by_depth.repo_id, &shas), vec![97, 98, 99, 100]);
+
+    // Dropping the limits fetches and indexes the rest of the history.
+    let full = store
+        .update_repo(
+            by_depth.repo_id,
+            &commitmux_types::RepoUpdate {
+                clone_depth: Some(None),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let summary = ingester.sync_repo(&full, &store, &config).unwrap();
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    assert!(!git2::Repository::open(&full.local_path)
+        .unwrap()
+        .is_shallow());
+    assert_eq!(
+        indexed(by_depth.repo_id, &shas),
+        (0..=100).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_remote_credentials_chain() {
+    let served = tempfile::tempdir().unwrap();
+    let source = git2::Repository::init(served.path().join("source")).unwrap();
+    let mut next = 0;
+    let mut commit = || {
+        next += 1;
+        let sig = git2::Signature::now("Test User", "test@example.com").unwrap();
+        let blob = source.blob(format!("v{}\n", next).as_bytes()).unwrap();
+        let mut tree = source.treebuilder(None).unwrap();
+        tree.insert("file.txt", blob, 0o100644).unwrap();
+        let tree = source.find_tree(tree.write().unwrap()).unwrap();
+        let parent = source.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        source
+            .commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                &format!("change {}", next),
+                &tree,
+                &parents,
+            )
+            .unwrap()
+            .to_string()
+    };
+    commit();
+
+    // base64("x-access-token:s3cret")
+    let Some(port) = serve_git_http(served.path(), Some("Basic eC1hY2Nlc3MtdG9rZW46czNjcmV0"))
+    else {
+        eprintln!("skipping: git is not installed");
+        return;
+    };
+    let url = format!("http://127.0.0.1:{}/source", port);
+    let clones = tempfile::tempdir().unwrap();
+    let dest = clones.path().join("private");
+
+    // Without credentials the clone is refused, with a hint.
+    let Err(err) = commitmux_ingest::clone_repo(&url, &dest, None, None, &RemoteAuth::default())
+    else {
+        panic!("cloned without credentials");
+    };
+    let err = err.to_string();
+    assert!(err.contains("--token-env"), "{}", err);
+
+    // A token from the environment is sent as the password.
+    let token_env = format!("COMMITMUX_TEST_TOKEN_{}", std::process::id());
+    std::env::set_var(&token_env, "s3cret\n");
+    let auth = RemoteAuth {
+        ssh_key: None,
+        token_env: Some(token_env.clone()),
+    };
+    commitmux_ingest::clone_repo(&url, &dest, None, None, &auth).unwrap();
+
+    let db_dir = tempfile::tempdir().unwrap();
+    let store = SqliteStore::open(&db_dir.path().join("test.sqlite3")).unwrap();
+    let ingester = Git2Ingester::new();
+    let config = IgnoreConfig::default();
+    let repo = store
+        .add_repo(&RepoInput {
+            name: "private".into(),
+            local_path: dest.clone(),
+            remote_url: Some(url.clone()),
+            default_branch: Some(format!(
+                "origin/{}",
+                source.head().unwrap().shorthand().unwrap()
+            )),
+            fork_of: None,
+            author_filters: vec![],
+            include_paths: vec![],
+            ref_patterns: vec![],
+            projects: vec![],
+            exclude_prefixes: vec![],
+            embed_enabled: false,
+            clone_depth: None,
+            clone_since: None,
+            ssh_key: None,
+            token_env: Some(token_env.clone()),
+        })
+        .unwrap();
+    let sha = commit();
+    let summary = ingester.sync_repo(&repo, &store, &config).unwrap();
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    assert!(store.commit_exists(repo.repo_id, &sha).unwrap());
+
+    // Without the token the fetch fails, and the sync reports it.
+    let repo = store
+        .update_repo(
+            repo.repo_id,
+            &commitmux_types::RepoUpdate {
+                token_env: Some(None),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let sha = commit();
+    let summary = ingester.sync_repo(&repo, &store, &config).unwrap();
+    assert!(
+        summary.errors.iter().any(|e| e.contains("failed to fetch")),
+        "{:?}",
+        summary.errors
+    );
+    assert!(!store.commit_exists(repo.repo_id, &sha).unwrap());
+
+    // A credential helper configured for the clone answers instead.
+    let mut clone_config = git2::Repository::open(&dest).unwrap().config().unwrap();
+    clone_config
+        .set_str(
+            "credential.helper",
+            "!f() { echo username=x-access-token; echo password=s3cret; }; f",
+        )
+        .unwrap();
+    let summary = ingester.sync_repo(&repo, &store, &config).unwrap();
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    assert!(store.commit_exists(repo.repo_id, &sha).unwrap());
+
+    // So does an askpass program, asked once per prompt.
+    clone_config.remove("credential.helper").unwrap();
+    let askpass = clones.path().join("askpass.sh");
+    std::fs::write(
+        &askpass,
+        "#!/bin/sh\ncase \"$1\" in Username*) echo x-access-token ;; *) echo s3cret ;; esac\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(
+        &askpass,
+        std::os::unix::fs::PermissionsExt::from_mode(0o755),
+    )
+    .unwrap();
+    clone_config
+        .set_str("core.askPass", askpass.to_str().unwrap())
+        .unwrap();
+    let sha = commit();
+    let summary = ingester.sync_repo(&repo, &store, &config).unwrap();
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    assert!(store.commit_exists(repo.repo_id, &sha).unwrap());
+}
+
+#[test]
+fn test_changed_filters_backfill_history() {
+    let repo_dir = tempfile::tempdir().unwrap();
+    let git_repo = git2::Repository::init(repo_dir.path()).unwrap();
+    let mut shas = Vec::new();
+    for (i, author) in ["a", "b", "a"].iter().enumerate() {
+        let sig = git2::Signature::now(author, &format!("{}@example.com", author)).unwrap();
+        let blob = git_repo.blob(format!("v{}\n", i).as_bytes()).unwrap();
+        let mut tree = git_repo.treebuilder(None).unwrap();
+        tree.insert("file.txt", blob, 0o100644).unwrap();
+        let tree = git_repo.find_tree(tree.write().unwrap()).unwrap();
+        let parent = git_repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        let oid = git_repo
+            .commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                &format!("change {}", i),
+                &tree,
+                &parents,
+            )
+            .unwrap();
+        shas.push(oid.to_string());
+    }
+
+    let db_dir = tempfile::tempdir().unwrap();
+    let store = SqliteStore::open(&db_dir.path().join("test.sqlite3")).unwrap();
+    let ingester = Git2Ingester::new();
+    let config = IgnoreConfig::default();
+    let repo = store
+        .add_repo(&RepoInput {
+            name: "filtered".into(),
+            local_path: repo_dir.path().to_path_buf(),
+            remote_url: None,
+            default_branch: None,
+            fork_of: None,
+            author_filters: vec![AuthorMatcher::Email("a@example.com".into())],
+            include_paths: vec![],
+            ref_patterns: vec![],
+            projects: vec![],
+            exclude_prefixes: vec![],
+            embed_enabled: false,
+            clone_depth: None,
+            clone_since: None,
+            ssh_key: None,
+            token_env: None,
+        })
+        .unwrap();
+    let indexed = |repo_id: i64| -> Vec<usize> {
+        (0..shas.len())
+            .filter(|&i| store.commit_exists(repo_id, &shas[i]).unwrap())
+            .collect()
+    };
+    ingester.sync_repo(&repo, &store, &config).unwrap();
+    assert_eq!(indexed(repo.repo_id), vec![0, 2]);
+
+    // An unchanged repo is not walked again.
+    let summary = ingester.sync_repo(&repo, &store, &config).unwrap();
+    assert_eq!(summary.commits_indexed, 0);
+
+    // Widening the filter walks the full history again and picks up the
+    // commit the old filter skipped, without re-indexing the others.
+    let repo = store
+        .update_repo(
+            repo.repo_id,
+            &commitmux_types::RepoUpdate {
+                add_authors: vec![AuthorMatcher::Email("b@example.com".into())],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let summary = ingester.sync_repo(&repo, &store, &config).unwrap();
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    assert_eq!(summary.commits_indexed, 1);
+    assert_eq!(indexed(repo.repo_id), vec![0, 1, 2]);
+
+    // The next sync is incremental again.
+    let summary = ingester.sync_repo(&repo, &store, &config).unwrap();
+    assert_eq!(summary.commits_indexed, 0);
+    assert_eq!(summary.commits_filtered, 0);
+}
+
+#[test]
+fn test_changed_ref_patterns_apply_to_indexed_commits() {
+    let repo_dir = tempfile::tempdir().unwrap();
+    let git_repo = git2::Repository::init(repo_dir.path()).unwrap();
+    let sig = git2::Signature::now("Test User", "test@example.com").unwrap();
+    let tree = git_repo.treebuilder(None).unwrap().write().unwrap();
+    let tree = git_repo.find_tree(tree).unwrap();
+    let sha = git_repo
+        .commit(
+            Some("HEAD"),
+            &sig,
+            &sig,
+            "Fix crash on empty config\n\nPROJ-12",
+            &tree,
+            &[],
+        )
+        .unwrap()
+        .to_string();
+
+    let db_dir = tempfile::tempdir().unwrap();
+    let store = SqliteStore::open(&db_dir.path().join("test.sqlite3")).unwrap();
+    let repo = store
+        .add_repo(&RepoInput {
+            name: "app".into(),
+            local_path: repo_dir.path().to_path_buf(),
+            remote_url: None,
+            default_branch: None,
+            fork_of: None,
+            author_filters: vec![],
+            include_paths: vec![],
+            ref_patterns: vec![],
+            projects: vec![],
+            exclude_prefixes: vec![],
+            embed_enabled: false,
+            clone_depth: None,
+            clone_since: None,
+            ssh_key: None,
+            token_env: None,
+        })
+        .unwrap();
+    Git2Ingester::new()
+        .sync_repo(&repo, &store, &IgnoreConfig::default())
+        .unwrap();
+    let mentions = |reference: &str| -> Vec<String> {
+        store
+            .find_by_ref(reference, &Default::default())
+            .unwrap()
+            .into_iter()
+            .map(|r| r.sha)
+            .collect()
+    };
+    // The default patterns do not extract Jira keys.
+    assert!(mentions("PROJ-12").is_empty());
+
+    let repo = store
+        .update_repo(
+            repo.repo_id,
+            &commitmux_types::RepoUpdate {
+                ref_patterns: Some(vec![RefPattern::Jira(vec!["PROJ".into()])]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(commitmux_ingest::reextract_refs(&repo, &store).unwrap(), 1);
+    assert_eq!(mentions("PROJ-12"), vec![sha]);
 }